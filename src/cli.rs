@@ -1,64 +1,746 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use inquire::Confirm;
 use inquire::InquireError;
-use inquire::Select;
-use nix::sys::signal;
+use inquire::MultiSelect;
+#[cfg(unix)]
+use nix::sys::signal::{self, Signal};
+#[cfg(unix)]
 use nix::unistd::Pid;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::string::String;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::completions::Shell;
+use crate::config::{self, Config};
+use crate::connections;
+use crate::schemas::AddressFamily;
+use crate::schemas::AddressFilter;
+use crate::schemas::BorderStyle;
+use crate::schemas::ColorMode;
 use crate::schemas::Connection;
+use crate::schemas::EnvFilter;
+use crate::schemas::Exposure;
+use crate::schemas::GroupField;
+use crate::schemas::PidFilter;
+use crate::schemas::PortFilter;
+use crate::schemas::ProgramMatcher;
+use crate::schemas::SortField;
+use crate::schemas::StateCategory;
+use crate::schemas::Theme;
+use crate::schemas::TimestampFormat;
+use crate::table;
+use crate::table::ColumnSelection;
 use crate::utils;
 
 /// Used for parsing all the flags values provided by the user in the CLI.
 #[derive(Debug)]
 pub struct Flags {
     pub kill: bool,
-    pub proto: Option<String>,
-    pub ip: Option<String>,
-    pub remote_port: Option<String>,
-    pub port: Option<String>,
-    pub program: Option<String>,
-    pub pid: Option<String>,
+    pub kill_pid: Vec<i32>,
+    pub kill_delay: Option<Duration>,
+    pub tree: bool,
+    pub all: bool,
+    pub signal: Signal,
+    pub yes: bool,
+    pub dry_run: bool,
+    pub force: bool,
+    pub proto: Vec<String>,
+    pub ip: Option<AddressFilter>,
+    pub local_ip: Option<AddressFilter>,
+    pub remote_port: Option<PortFilter>,
+    pub port: Option<PortFilter>,
+    pub holding: Option<u16>,
+    pub program: Option<ProgramMatcher>,
+    pub match_path: bool,
+    pub pid: Option<PidFilter>,
+    pub ppid: Option<i32>,
+    pub user: Option<String>,
+    pub env: Vec<EnvFilter>,
+    pub state: Option<String>,
     pub open: bool,
     pub listen: bool,
-    pub exclude_ipv6: bool,
+    pub include: Vec<StateCategory>,
+    pub unresolved_only: bool,
+    pub local: Option<bool>,
+    pub privileged: Option<bool>,
+    pub raw_addresses: bool,
+    pub family: AddressFamily,
+    pub show_exposure: bool,
+    pub exposure: Option<Exposure>,
+    pub exclude_program: Option<String>,
+    pub exclude_self: bool,
+    pub exclude_ip: Option<AddressFilter>,
+    pub exclude_port: Option<PortFilter>,
+    pub resolve: bool,
+    pub services: bool,
+    pub numeric: bool,
+    pub long: bool,
+    pub show_exe: bool,
+    pub show_inode: bool,
+    pub queues: bool,
+    pub no_summary: bool,
+    pub show_host: bool,
+    pub hostname: Option<String>,
+    pub highlight_suspicious: bool,
+    pub highlight: Vec<String>,
+    pub stats: bool,
+    pub show_age: bool,
+    pub since: Option<u64>,
+    pub include_unknown_age: bool,
+    pub timestamps: Option<TimestampFormat>,
+    pub columns: Option<ColumnSelection>,
+    pub full: bool,
+    pub width: Option<u16>,
+    pub theme: Theme,
+    pub geoip: Option<PathBuf>,
+    pub containers: bool,
+    pub container: Option<String>,
+    pub units: bool,
+    pub unit: Option<String>,
+    pub all_netns: bool,
+    pub netns: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: bool,
+    pub unique: bool,
+    pub count_dupes: bool,
+    pub diff: Option<PathBuf>,
+    pub limit: usize,
+    pub group_by: Option<GroupField>,
+    pub watch: Option<u64>,
+    pub iterations: Option<u64>,
+    pub duration: Option<u64>,
+    pub interactive: bool,
+    pub json: bool,
+    pub yaml: bool,
+    pub csv: bool,
+    pub markdown: bool,
+    pub prometheus: bool,
+    pub oneline: bool,
+    pub ndjson: bool,
+    pub output: Option<PathBuf>,
+    pub append: bool,
+    pub count: bool,
+    pub top_remotes: bool,
+    pub exit_nonzero_on_empty: bool,
+    pub strict: bool,
+    pub timeout: Option<u64>,
+    pub print_schema: bool,
+    pub list_fields: bool,
+    pub completions: Option<Shell>,
+    pub man: bool,
+}
+
+/// Contains the options for killing processes.
+#[derive(Debug, Clone, Copy)]
+pub struct KillOptions {
+    pub signal: Signal,
+    pub yes: bool,
+    pub dry_run: bool,
+    pub force: bool,
+    /// The time to wait between two kills, set with `--kill-delay`.
+    pub delay: Option<Duration>,
+    /// Whether the children of the processes are killed too, set with `--tree`.
+    pub tree: bool,
+}
+
+/// A process which is killed as part of a process tree, see `--tree`.
+struct KillTarget<'a> {
+    pid: i32,
+    /// A connection of the process, descendants without connections only know their program.
+    connection: Cow<'a, Connection>,
+    /// The PID of the parent in the tree, `None` for the selected process itself.
+    parent: Option<i32>,
+    /// How deep the process is in the tree, 0 for the selected process.
+    depth: usize,
+}
+
+/// The final disposition of a process after it was sent a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillOutcome {
+    /// The process exited after the signal.
+    Terminated,
+    /// The signal was sent, but it doesn't terminate processes so its effect isn't verified, e.g `SIGHUP`.
+    Signaled,
+    /// The process survived the grace period, and `SIGKILL` too if `--force` was given.
+    StillRunning,
+    /// The process had already exited before the signal was sent.
+    AlreadyGone,
+    /// The signal couldn't be sent, e.g because of missing permissions.
+    Failed,
 }
 
 /// Represents all possible flags which can be provided by the user in the CLI.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("format").multiple(false)))]
+#[command(group(ArgGroup::new("program_filter").multiple(false)))]
+#[command(group(ArgGroup::new("killing").multiple(true).args(["kill", "kill_pid"])))]
 struct Args {
     #[arg(short = 'k', long, default_value = None)]
     kill: bool,
 
-    #[arg(long, default_value = None)]
-    proto: Option<String>,
+    #[arg(long, value_name = "PID", value_parser = clap::value_parser!(i32).range(1..), conflicts_with_all = ["kill", "interactive", "watch"])]
+    kill_pid: Vec<i32>,
+
+    #[arg(short = 'a', long, default_value_t = false, requires = "kill")]
+    all: bool,
+
+    #[arg(long, default_value = "TERM")]
+    signal: String,
+
+    #[arg(short = 'y', long, default_value_t = false, requires = "kill")]
+    yes: bool,
+
+    #[arg(long, default_value_t = false, requires = "killing")]
+    dry_run: bool,
+
+    #[arg(long, default_value_t = false, requires = "killing")]
+    force: bool,
+
+    #[arg(long, value_name = "MS", default_value = None, requires = "killing")]
+    kill_delay: Option<u64>,
+
+    #[arg(long, default_value_t = false, requires = "killing")]
+    tree: bool,
+
+    #[arg(long, value_delimiter = ',')]
+    proto: Vec<String>,
 
     #[arg(long, default_value = None)]
     ip: Option<String>,
 
+    #[arg(long, default_value = None, value_name = "LOCAL_IP|any")]
+    local_ip: Option<String>,
+
     #[arg(long, default_value = None)]
     remote_port: Option<String>,
 
     #[arg(short = 'p', long, default_value = None)]
     port: Option<String>,
 
-    #[arg(long, default_value = None)]
+    #[arg(long, value_name = "PORT", default_value = None, conflicts_with_all = ["port", "format", "ndjson", "watch", "interactive", "group_by", "top_remotes", "count", "diff"])]
+    holding: Option<u16>,
+
+    #[arg(long, default_value = None, group = "program_filter")]
     program: Option<String>,
 
-    #[arg(long, default_value = None)]
+    #[arg(long, value_name = "NAME", default_value = None, group = "program_filter")]
+    program_exact: Option<String>,
+
+    #[arg(long, value_name = "PATTERN", default_value = None, group = "program_filter")]
+    program_glob: Option<String>,
+
+    #[arg(long, value_name = "REGEX", default_value = None, group = "program_filter")]
+    program_regex: Option<String>,
+
+    #[arg(long, default_value_t = false, requires = "program_filter")]
+    match_path: bool,
+
+    #[arg(long, value_name = "PID|PID,PID|START-END", default_value = None)]
     pid: Option<String>,
 
+    #[arg(long, value_name = "PPID", default_value = None)]
+    ppid: Option<i32>,
+
+    #[arg(short = 'u', long, default_value = None)]
+    user: Option<String>,
+
+    #[arg(long, value_name = "KEY=VALUE")]
+    env: Vec<String>,
+
+    #[arg(long, default_value = None)]
+    state: Option<String>,
+
     #[arg(short = 'o', long, default_value_t = false)]
     open: bool,
 
     #[arg(short = 'l', long, default_value_t = false)]
     listen: bool,
 
+    #[arg(long, value_delimiter = ',', value_name = "CATEGORIES")]
+    include: Vec<String>,
+
+    #[arg(long, default_value_t = false, conflicts_with_all = ["program_filter", "pid", "ppid", "user", "env", "kill"])]
+    unresolved_only: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with = "no_local")]
+    local_only: bool,
+
+    #[arg(long, default_value_t = false)]
+    no_local: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with = "unprivileged_ports")]
+    privileged_ports: bool,
+
+    #[arg(long, default_value_t = false)]
+    unprivileged_ports: bool,
+
+    #[arg(long, value_enum, default_value = None)]
+    family: Option<AddressFamily>,
+
+    #[arg(long, value_enum, value_name = "EXPOSURE", num_args = 0..=1)]
+    exposure: Option<Option<Exposure>>,
+
+    #[arg(long, default_value_t = false)]
+    exclude_ipv4: bool,
+
     #[arg(long, default_value_t = false)]
     exclude_ipv6: bool,
+
+    #[arg(long, default_value_t = false)]
+    raw_addresses: bool,
+
+    #[arg(long, default_value = None)]
+    exclude_program: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    exclude_self: bool,
+
+    #[arg(long, default_value = None)]
+    exclude_ip: Option<String>,
+
+    #[arg(long, default_value = None)]
+    exclude_port: Option<String>,
+
+    #[arg(short = 'r', long, default_value_t = false)]
+    resolve: bool,
+
+    #[arg(long, default_value_t = false)]
+    services: bool,
+
+    #[arg(short = 'n', long, default_value_t = false)]
+    numeric: bool,
+
+    #[arg(short = 'L', long, default_value_t = false)]
+    long: bool,
+
+    #[arg(long, default_value_t = false)]
+    show_exe: bool,
+
+    #[arg(long, default_value_t = false)]
+    show_inode: bool,
+
+    #[arg(long, default_value_t = false)]
+    queues: bool,
+
+    #[arg(long, default_value_t = false)]
+    no_summary: bool,
+
+    #[arg(long, default_value_t = false)]
+    show_host: bool,
+
+    #[arg(long, value_name = "NAME", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    hostname: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    highlight_suspicious: bool,
+
+    #[arg(long, value_name = "TERM")]
+    highlight: Vec<String>,
+
+    #[arg(long, default_value_t = false)]
+    stats: bool,
+
+    #[arg(long, default_value_t = false)]
+    show_age: bool,
+
+    #[arg(long, value_name = "DURATION", default_value = None)]
+    since: Option<String>,
+
+    #[arg(long, default_value_t = false, requires = "since")]
+    include_unknown_age: bool,
+
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = None)]
+    timestamps: Option<TimestampFormat>,
+
+    #[arg(long, value_name = "COLUMNS", allow_hyphen_values = true, default_value = None)]
+    columns: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    full: bool,
+
+    #[arg(long, value_name = "N", default_value = None, value_parser = clap::value_parser!(u16).range(20..))]
+    width: Option<u16>,
+
+    #[arg(long, value_name = "MMDB_PATH", default_value = None)]
+    geoip: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false)]
+    containers: bool,
+
+    #[arg(long, value_name = "NAME", default_value = None)]
+    container: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    units: bool,
+
+    #[arg(long, value_name = "NAME", default_value = None)]
+    unit: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    all_netns: bool,
+
+    #[arg(long, value_name = "ID|NAME", default_value = None)]
+    netns: Option<String>,
+
+    #[arg(long, value_enum, default_value = None)]
+    sort: Option<SortField>,
+
+    #[arg(long, default_value_t = false, requires = "sort")]
+    reverse: bool,
+
+    #[arg(long, default_value_t = false)]
+    unique: bool,
+
+    #[arg(long, default_value_t = false, requires = "unique")]
+    count_dupes: bool,
+
+    #[arg(long, value_name = "FILE", default_value = None, conflicts_with_all = ["kill", "watch", "interactive", "group_by", "top_remotes"])]
+    diff: Option<PathBuf>,
+
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with_all = ["group_by", "prometheus"])]
+    limit: usize,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = None,
+        conflicts_with_all = ["kill", "watch", "format", "ndjson"]
+    )]
+    group_by: Option<GroupField>,
+
+    #[arg(
+        short = 'w',
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        default_missing_value = "2",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["kill", "format"]
+    )]
+    watch: Option<u64>,
+
+    #[arg(long, value_name = "N", default_value = None, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    iterations: Option<u64>,
+
+    #[arg(long, value_name = "SECONDS", default_value = None, requires = "watch", value_parser = clap::value_parser!(u64).range(1..))]
+    duration: Option<u64>,
+
+    #[arg(short = 'i', long, default_value_t = false, conflicts_with_all = ["kill", "watch", "format", "ndjson", "count", "top_remotes", "group_by"])]
+    interactive: bool,
+
+    #[arg(long, default_value_t = false, group = "format")]
+    json: bool,
+
+    #[arg(long, default_value_t = false, group = "format")]
+    yaml: bool,
+
+    #[arg(long, default_value_t = false, group = "format")]
+    csv: bool,
+
+    #[arg(long, default_value_t = false, group = "format")]
+    markdown: bool,
+
+    #[arg(long, default_value_t = false, group = "format")]
+    prometheus: bool,
+
+    #[arg(
+        long,
+        visible_alias = "short",
+        default_value_t = false,
+        group = "format"
+    )]
+    oneline: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with_all = ["kill", "format"])]
+    ndjson: bool,
+
+    #[arg(long, value_name = "PATH", default_value = None, conflicts_with_all = ["interactive", "kill"])]
+    output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false, requires = "output")]
+    append: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with_all = ["kill", "watch", "limit", "format", "ndjson"])]
+    count: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with_all = ["group_by", "count", "kill", "watch", "format", "ndjson"])]
+    top_remotes: bool,
+
+    #[arg(long, default_value_t = false, conflicts_with = "watch")]
+    exit_nonzero_on_empty: bool,
+
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    #[arg(long, value_name = "SECONDS", default_value = None, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    #[arg(long, default_value_t = false, exclusive = true)]
+    print_schema: bool,
+
+    #[arg(long, default_value_t = false, exclusive = true)]
+    list_fields: bool,
+
+    #[arg(long, value_enum, value_name = "SHELL", exclusive = true)]
+    completions: Option<Shell>,
+
+    #[arg(long, default_value_t = false, exclusive = true, hide = true)]
+    man: bool,
+
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+
+    #[arg(long, value_enum, default_value_t = Theme::Default)]
+    theme: Theme,
+
+    #[arg(long, value_enum, default_value = None)]
+    border: Option<BorderStyle>,
+
+    #[arg(long, value_name = "PATH", default_value = None)]
+    config: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false, conflicts_with = "config")]
+    no_config: bool,
 }
 
-/// Gets all flag values provided by the user in the CLI using the "clap" crate.
+/// Enables or disables colors for all output based on `--color`, `--theme` and the terminal.
+/// The mono theme and `--output` disable colors entirely, even with `--color always`.
+///
+/// # Arguments
+/// * `args`: The parsed flag values.
+///
+/// # Returns
+/// None
+fn set_color(args: &Args) {
+    utils::set_color_enabled(
+        args.theme != Theme::Mono
+            && args.output.is_none()
+            && utils::should_use_color(
+                args.color,
+                std::io::stdout().is_terminal(),
+                std::env::var_os("NO_COLOR"),
+            ),
+    );
+}
+
+/// Merges the values of the config file under the flag values provided by the user.
+/// Flags provided in the CLI always win, the config file only fills in the flags which weren't provided.
+///
+/// # Arguments
+/// * `args`: The parsed flag values to merge the config into.
+/// * `config`: The values of the config file.
+/// * `matches`: The raw matches of the CLI, used to check which flags were provided explicitly.
+///
+/// # Returns
+/// None
+fn merge_config(args: &mut Args, config: Config, matches: &ArgMatches) {
+    if matches.value_source("signal") != Some(ValueSource::CommandLine) {
+        if let Some(signal) = config.signal {
+            args.signal = signal;
+        }
+    }
+    if matches.value_source("color") != Some(ValueSource::CommandLine) {
+        if let Some(color) = config.color {
+            args.color = color;
+        }
+    }
+    if args.border.is_none() {
+        args.border = config.border;
+    }
+    if matches.value_source("theme") != Some(ValueSource::CommandLine) {
+        if let Some(theme) = config.theme {
+            args.theme = theme;
+        }
+    }
+
+    if args.proto.is_empty() {
+        if let Some(proto) = config.proto {
+            args.proto = proto
+                .split(',')
+                .map(|proto| proto.trim().to_string())
+                .collect();
+        }
+    }
+    if args.include.is_empty() {
+        if let Some(include) = config.include {
+            args.include = include
+                .split(',')
+                .map(|category| category.trim().to_string())
+                .collect();
+        }
+    }
+    args.ip = args.ip.take().or(config.ip);
+    args.local_ip = args.local_ip.take().or(config.local_ip);
+    args.remote_port = args.remote_port.take().or(config.remote_port);
+    args.port = args.port.take().or(config.port);
+    // the program of the config file is only matched as a substring, so any program filter of the CLI replaces it
+    if args.program_exact.is_none() && args.program_glob.is_none() && args.program_regex.is_none() {
+        args.program = args.program.take().or(config.program);
+    }
+    args.user = args.user.take().or(config.user);
+    args.state = args.state.take().or(config.state);
+    args.exclude_program = args.exclude_program.take().or(config.exclude_program);
+    args.exclude_ip = args.exclude_ip.take().or(config.exclude_ip);
+    args.exclude_port = args.exclude_port.take().or(config.exclude_port);
+    args.geoip = args.geoip.take().or(config.geoip);
+    args.columns = args.columns.take().or(config.columns);
+    args.sort = args.sort.or(config.sort);
+
+    // flags can only be enabled in the CLI, so an enabled flag always wins over the config file
+    args.open |= config.open.unwrap_or(false);
+    args.listen |= config.listen.unwrap_or(false);
+    // the address family of the CLI replaces the one of the config file, including the deprecated exclude flags
+    if args.family.is_none() && !args.exclude_ipv4 && !args.exclude_ipv6 {
+        args.family = config.family;
+        args.exclude_ipv4 = config.exclude_ipv4.unwrap_or(false);
+        args.exclude_ipv6 = config.exclude_ipv6.unwrap_or(false);
+    }
+    args.resolve |= config.resolve.unwrap_or(false);
+    args.long |= config.long.unwrap_or(false);
+    args.reverse |= config.reverse.unwrap_or(false);
+}
+
+/// Turns off the flags which look up names if `--numeric` is set, since only raw addresses and ports should be shown.
+///
+/// # Arguments
+/// * `args`: The flag values provided by the user.
+///
+/// # Returns
+/// The flags which were turned off, to warn about them.
+fn disable_lookups(args: &mut Args) -> Vec<&'static str> {
+    let mut disabled = Vec::new();
+    if !args.numeric {
+        return disabled;
+    }
+    if std::mem::take(&mut args.resolve) {
+        disabled.push("--resolve");
+    }
+    if std::mem::take(&mut args.services) {
+        disabled.push("--services");
+    }
+    disabled
+}
+
+/// Parses an optional flag value into its typed representation and exits with an error message if it is invalid.
+///
+/// # Arguments
+/// * `flag`: The name of the flag, used in the error message.
+/// * `value`: The raw flag value provided by the user.
+///
+/// # Returns
+/// The parsed flag value or `None` if the flag wasn't provided.
+fn parse_flag<T: FromStr<Err = String>>(flag: &str, value: Option<String>) -> Option<T> {
+    value.map(|value| match value.parse::<T>() {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            utils::pretty_print_error(&format!("Invalid value for --{}: {}.", flag, error));
+            std::process::exit(1);
+        }
+    })
+}
+
+/// Builds the program filter of `--program`, `--program-exact`, `--program-glob` or `--program-regex`, of which at most one is given.
+///
+/// # Arguments
+/// * `args`: The flag values provided by the user.
+///
+/// # Returns
+/// The matcher, `None` if no program filter was given or an error message if the pattern is invalid.
+fn get_program_matcher(args: &Args) -> Result<Option<ProgramMatcher>, String> {
+    if let Some(program) = &args.program {
+        Ok(Some(ProgramMatcher::Substring(program.to_string())))
+    } else if let Some(program) = &args.program_exact {
+        Ok(Some(ProgramMatcher::Exact(program.to_string())))
+    } else if let Some(pattern) = &args.program_glob {
+        ProgramMatcher::glob(pattern)
+            .map(Some)
+            .map_err(|error| format!("Invalid value for --program-glob: {}", error))
+    } else if let Some(pattern) = &args.program_regex {
+        ProgramMatcher::regex(pattern)
+            .map(Some)
+            .map_err(|error| format!("Invalid value for --program-regex: {}", error))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Combines `--family` with the deprecated `--exclude-ipv4` and `--exclude-ipv6` flags into one address family.
+///
+/// # Arguments
+/// * `family`: The family provided with `--family`, if any.
+/// * `exclude_ipv4`: Whether `--exclude-ipv4` was given.
+/// * `exclude_ipv6`: Whether `--exclude-ipv6` was given.
+///
+/// # Example
+/// ```
+/// assert_eq!(resolve_family(None, false, true), Ok(AddressFamily::Inet));
+/// assert!(resolve_family(Some(AddressFamily::Inet), true, false).is_err());
+/// ```
+///
+/// # Returns
+/// The address family or an error message if the flags contradict each other.
+fn resolve_family(
+    family: Option<AddressFamily>,
+    exclude_ipv4: bool,
+    exclude_ipv6: bool,
+) -> Result<AddressFamily, String> {
+    let excluded = match (exclude_ipv4, exclude_ipv6) {
+        (false, false) => return Ok(family.unwrap_or_default()),
+        (true, true) => {
+            return Err(
+                "--exclude-ipv4 together with --exclude-ipv6 excludes every connection, use --family instead".to_string(),
+            )
+        }
+        (true, false) => AddressFamily::Inet6,
+        (false, true) => AddressFamily::Inet,
+    };
+    match family {
+        Some(family) if family != excluded => Err(format!(
+            "--family {} contradicts --exclude-ipv{}",
+            family
+                .to_possible_value()
+                .map_or_else(String::new, |value| value.get_name().to_string()),
+            if exclude_ipv4 { 4 } else { 6 }
+        )),
+        _ => Ok(excluded),
+    }
+}
+
+/// Parses the state categories of the `--include` flag, ignoring the case.
+///
+/// # Arguments
+/// * `categories`: The categories provided by the user, e.g ["listening", "established"].
+///
+/// # Returns
+/// The parsed categories or the first unknown category as an error.
+fn parse_categories(categories: &[String]) -> Result<Vec<StateCategory>, String> {
+    categories
+        .iter()
+        .map(|category| StateCategory::from_str(category, true).map_err(|_| category.clone()))
+        .collect()
+}
+
+/// Gets the definition of all flags, e.g to generate the completion scripts.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The clap command of somo.
+pub fn command() -> clap::Command {
+    Args::command()
+}
+
+/// Gets all flag values provided by the user in the CLI using the "clap" crate, merged with the config file.
 ///
 /// # Arguments
 /// None
@@ -66,85 +748,1020 @@ struct Args {
 /// # Returns
 /// A struct containing all the flag values.
 pub fn cli() -> Flags {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    set_color(&args);
+
+    if !args.no_config {
+        match config::load_config(args.config.as_deref()) {
+            Ok(config) => {
+                merge_config(&mut args, config, &matches);
+                set_color(&args);
+            }
+            Err(error) => {
+                utils::pretty_print_error(&error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|locale| !locale.is_empty());
+    table::set_border_style(
+        args.border
+            .unwrap_or_else(|| table::default_border_style(locale.as_deref())),
+    );
+
+    let program = get_program_matcher(&args).unwrap_or_else(|error| {
+        utils::pretty_print_error(&format!("{}.", error));
+        std::process::exit(1);
+    });
+
+    let family =
+        resolve_family(args.family, args.exclude_ipv4, args.exclude_ipv6).unwrap_or_else(|error| {
+            utils::pretty_print_error(&format!("{}.", error));
+            std::process::exit(1);
+        });
+
+    let signal = match parse_signal(&args.signal) {
+        Some(signal) => signal,
+        None => {
+            utils::pretty_print_error(&format!(
+                "Unknown signal '{}', use a name like TERM, KILL, INT or HUP or a signal number.",
+                args.signal
+            ));
+            std::process::exit(1);
+        }
+    };
 
-    return Flags {
+    if let Some(proto) = args
+        .proto
+        .iter()
+        .find(|proto| !connections::is_valid_protocol(proto))
+    {
+        utils::pretty_print_error(&format!(
+            "Unknown protocol '{}', valid protocols are: {}.",
+            proto,
+            connections::VALID_PROTOCOLS.join(", ")
+        ));
+        std::process::exit(1);
+    }
+
+    let include = match parse_categories(&args.include) {
+        Ok(include) => include,
+        Err(category) => {
+            let valid_categories: Vec<String> = StateCategory::value_variants()
+                .iter()
+                .filter_map(|category| category.to_possible_value())
+                .map(|value| value.get_name().to_string())
+                .collect();
+            utils::pretty_print_error(&format!(
+                "Unknown category '{}', valid categories are: {}.",
+                category,
+                valid_categories.join(", ")
+            ));
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(state) = &args.state {
+        if !connections::is_valid_state(state) {
+            utils::pretty_print_error(&format!(
+                "Unknown state '{}', valid states are: {}.",
+                state,
+                connections::VALID_STATES.join(", ")
+            ));
+            std::process::exit(1);
+        }
+    }
+
+    let disabled = disable_lookups(&mut args);
+    if !disabled.is_empty() {
+        utils::pretty_print_info(&format!(
+            "--numeric turns off {}, no names are looked up.",
+            disabled.join(" and ")
+        ));
+    }
+
+    Flags {
         kill: args.kill,
+        kill_pid: args.kill_pid,
+        kill_delay: args.kill_delay.map(Duration::from_millis),
+        tree: args.tree,
+        all: args.all,
+        signal,
+        yes: args.yes,
+        dry_run: args.dry_run,
+        force: args.force,
         proto: args.proto,
-        ip: args.ip,
-        program: args.program,
-        remote_port: args.remote_port,
-        port: args.port,
-        pid: args.pid,
+        ip: parse_flag("ip", args.ip),
+        local_ip: parse_flag("local-ip", args.local_ip),
+        program,
+        match_path: args.match_path,
+        remote_port: parse_flag("remote-port", args.remote_port),
+        // everything on the port is shown, no matter its state
+        port: args
+            .holding
+            .map(PortFilter::Port)
+            .or_else(|| parse_flag("port", args.port)),
+        holding: args.holding,
+        pid: parse_flag("pid", args.pid),
+        ppid: args.ppid,
+        user: args.user,
+        env: args
+            .env
+            .into_iter()
+            .filter_map(|variable| parse_flag("env", Some(variable)))
+            .collect(),
+        state: args.state,
         open: args.open,
         listen: args.listen,
-        exclude_ipv6: args.exclude_ipv6,
+        include,
+        unresolved_only: args.unresolved_only,
+        local: match (args.local_only, args.no_local) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        raw_addresses: args.raw_addresses,
+        privileged: match (args.privileged_ports, args.unprivileged_ports) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        family,
+        show_exposure: args.exposure.is_some(),
+        exposure: args.exposure.flatten(),
+        exclude_program: args.exclude_program,
+        exclude_self: args.exclude_self,
+        exclude_ip: parse_flag("exclude-ip", args.exclude_ip),
+        exclude_port: parse_flag("exclude-port", args.exclude_port),
+        resolve: args.resolve,
+        services: args.services,
+        numeric: args.numeric,
+        long: args.long,
+        show_exe: args.show_exe,
+        show_inode: args.show_inode,
+        queues: args.queues,
+        no_summary: args.no_summary,
+        show_host: args.show_host,
+        hostname: args.hostname,
+        highlight_suspicious: args.highlight_suspicious,
+        highlight: args.highlight,
+        stats: args.stats,
+        show_age: args.show_age,
+        since: args.since.map(|since| {
+            utils::parse_duration(&since).unwrap_or_else(|error| {
+                utils::pretty_print_error(&format!("Invalid value for --since: {}.", error));
+                std::process::exit(1);
+            })
+        }),
+        include_unknown_age: args.include_unknown_age,
+        timestamps: args.timestamps,
+        columns: parse_flag("columns", args.columns),
+        full: args.full,
+        width: args.width,
+        theme: args.theme,
+        geoip: args.geoip,
+        containers: args.containers,
+        container: args.container,
+        units: args.units,
+        unit: args.unit,
+        all_netns: args.all_netns,
+        netns: args.netns,
+        sort: args.sort,
+        reverse: args.reverse,
+        unique: args.unique,
+        count_dupes: args.count_dupes,
+        diff: args.diff,
+        limit: args.limit,
+        group_by: args.group_by,
+        watch: args.watch,
+        iterations: args.iterations,
+        duration: args.duration,
+        interactive: args.interactive,
+        json: args.json,
+        yaml: args.yaml,
+        csv: args.csv,
+        markdown: args.markdown,
+        prometheus: args.prometheus,
+        oneline: args.oneline,
+        ndjson: args.ndjson,
+        output: args.output,
+        append: args.append,
+        count: args.count,
+        top_remotes: args.top_remotes,
+        exit_nonzero_on_empty: args.exit_nonzero_on_empty,
+        strict: args.strict,
+        timeout: args.timeout,
+        print_schema: args.print_schema,
+        list_fields: args.list_fields,
+        completions: args.completions,
+        man: args.man,
+    }
+}
+
+/// Parses a signal provided by the user either by its name, with or without the "SIG" prefix, or by its number.
+///
+/// # Arguments
+/// * `signal_name`: The signal name or number, e.g "TERM", "sigkill" or "9".
+///
+/// # Example
+/// ```
+/// assert_eq!(parse_signal("kill"), Some(Signal::SIGKILL));
+/// assert_eq!(parse_signal("15"), Some(Signal::SIGTERM));
+/// ```
+///
+/// # Returns
+/// The parsed signal or `None` if the signal doesn't exist.
+#[cfg(unix)]
+pub fn parse_signal(signal_name: &str) -> Option<Signal> {
+    if let Ok(signal_number) = signal_name.parse::<i32>() {
+        return Signal::try_from(signal_number).ok();
+    }
+
+    let signal_name = signal_name.to_ascii_uppercase();
+    if signal_name.starts_with("SIG") {
+        Signal::from_str(&signal_name).ok()
+    } else {
+        Signal::from_str(&format!("SIG{}", signal_name)).ok()
+    }
+}
+
+/// Windows has no signals, every process is stopped with `TerminateProcess` no matter which signal was requested.
+/// The signal number is only kept so that `--signal` is validated the same way as on Unix.
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal(i32);
+
+/// The names and numbers of the signals which are accepted on Windows.
+#[cfg(windows)]
+const SIGNALS: [(&str, i32); 5] = [
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("KILL", 9),
+    ("TERM", 15),
+];
+
+#[cfg(windows)]
+impl std::fmt::Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match SIGNALS.iter().find(|(_, number)| *number == self.0) {
+            Some((name, _)) => write!(f, "SIG{}", name),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+/// Parses a signal provided by the user either by its name, with or without the "SIG" prefix, or by its number.
+/// Only the common termination signals are known on Windows.
+///
+/// # Arguments
+/// * `signal_name`: The signal name or number, e.g "TERM", "sigkill" or "9".
+///
+/// # Returns
+/// The parsed signal or `None` if the signal doesn't exist.
+#[cfg(windows)]
+pub fn parse_signal(signal_name: &str) -> Option<Signal> {
+    if let Ok(signal_number) = signal_name.parse::<i32>() {
+        return SIGNALS
+            .iter()
+            .any(|(_, number)| *number == signal_number)
+            .then_some(Signal(signal_number));
+    }
+
+    let signal_name = signal_name.to_ascii_uppercase();
+    let signal_name = signal_name.strip_prefix("SIG").unwrap_or(&signal_name);
+    SIGNALS
+        .iter()
+        .find(|(name, _)| *name == signal_name)
+        .map(|(_, number)| Signal(*number))
+}
+
+/// Sends a signal to a process.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+/// * `signal`: The signal to send to the process.
+///
+/// # Returns
+/// `true` if the signal was sent successfully, `false` if not.
+#[cfg(unix)]
+pub fn send_signal(pid_num: i32, signal: Signal) -> bool {
+    signal::kill(Pid::from_raw(pid_num), signal).is_ok()
+}
+
+/// Terminates a process using `TerminateProcess`, since Windows has no signals.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+/// * `_signal`: The requested signal, which is ignored.
+///
+/// # Returns
+/// `true` if the process was terminated successfully, `false` if not.
+#[cfg(windows)]
+pub fn send_signal(pid_num: i32, _signal: Signal) -> bool {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+
+    // SAFETY: the process handle is only used while it is open
+    unsafe {
+        match OpenProcess(PROCESS_TERMINATE, false, pid_num as u32) {
+            Ok(handle) => {
+                let terminated = TerminateProcess(handle, 1).is_ok();
+                let _ = CloseHandle(handle);
+                terminated
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// The signal sent with `--force` to processes which survive the requested signal.
+#[cfg(unix)]
+const FORCE_SIGNAL: Signal = Signal::SIGKILL;
+#[cfg(windows)]
+const FORCE_SIGNAL: Signal = Signal(9);
+
+/// How long a process has to exit after it was sent a terminating signal.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// How often a killed process is checked for during the grace period.
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Checks whether a process is still running.
+/// Zombies have already exited and only wait for their parent to reap them, so they don't count as running.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+///
+/// # Returns
+/// `true` if the process exists and hasn't exited, `false` if not.
+#[cfg(unix)]
+pub fn is_process_alive(pid_num: i32) -> bool {
+    #[cfg(target_os = "linux")]
+    if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid_num)) {
+        // the state follows the program name, which itself can contain spaces and parentheses
+        let state = stat
+            .rsplit_once(')')
+            .and_then(|(_, fields)| fields.trim_start().chars().next());
+        return state != Some('Z');
+    }
+
+    match signal::kill(Pid::from_raw(pid_num), None) {
+        Ok(()) => true,
+        // the process exists but belongs to another user
+        Err(errno) => errno == nix::errno::Errno::EPERM,
+    }
+}
+
+/// Checks whether a process is still running by waiting for its handle without a timeout.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+///
+/// # Returns
+/// `true` if the process exists and hasn't exited, `false` if not.
+#[cfg(windows)]
+pub fn is_process_alive(pid_num: i32) -> bool {
+    use windows::Win32::Foundation::{CloseHandle, WAIT_TIMEOUT};
+    use windows::Win32::System::Threading::{
+        OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE,
     };
+
+    // SAFETY: the process handle is only used while it is open
+    unsafe {
+        match OpenProcess(PROCESS_SYNCHRONIZE, false, pid_num as u32) {
+            Ok(handle) => {
+                // the handle of a process is signaled once it exited
+                let alive = WaitForSingleObject(handle, 0) == WAIT_TIMEOUT;
+                let _ = CloseHandle(handle);
+                alive
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Checks whether a signal terminates processes by default, only then a kill is verified.
+/// Other signals, e.g `SIGHUP` which makes many daemons reload their configuration, are expected to leave the process running.
+///
+/// # Argument
+/// * `signal`: The signal sent to the process.
+///
+/// # Returns
+/// `true` if the process is expected to exit, `false` if not.
+#[cfg(unix)]
+fn is_terminating_signal(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::SIGTERM | Signal::SIGINT | Signal::SIGQUIT | Signal::SIGKILL
+    )
+}
+
+/// Every process is stopped with `TerminateProcess` on Windows, no matter which signal was requested.
+#[cfg(windows)]
+fn is_terminating_signal(_signal: Signal) -> bool {
+    true
+}
+
+/// Polls a process until it exited or the grace period is over.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+/// * `grace_period`: How long to wait for the process.
+///
+/// # Returns
+/// `true` if the process exited in time, `false` if it is still running.
+fn wait_for_exit(pid_num: i32, grace_period: Duration) -> bool {
+    let deadline = Instant::now() + grace_period;
+    loop {
+        if !is_process_alive(pid_num) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(KILL_POLL_INTERVAL);
+    }
+}
+
+/// Names the process targeted by a kill, so that processes with similar PIDs can be told apart.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+/// * `connection`: A connection of the process, used for its program.
+///
+/// # Returns
+/// The program followed by the PID, e.g "nginx (PID 1234)".
+fn describe_target(pid: i32, connection: &Connection) -> String {
+    format!("{} (PID {})", connection.program, pid)
+}
+
+/// Kills a process by its PID, reports which program was sent which signal and verifies that it exited.
+/// A process which survives the grace period is sent `SIGKILL` if `--force` was given.
+///
+/// # Argument
+/// * `pid_num`: The PID value as a number.
+/// * `connection`: A connection of the process, used for its program.
+/// * `options`: The signal to send and whether to escalate it.
+///
+/// # Returns
+/// The final disposition of the process.
+pub fn kill_process(pid_num: i32, connection: &Connection, options: &KillOptions) -> KillOutcome {
+    let target = describe_target(pid_num, connection);
+    let signal = options.signal;
+
+    if !is_process_alive(pid_num) {
+        utils::pretty_print_info(&format!(
+            "{} was already gone before sending {}.",
+            target, signal
+        ));
+        return KillOutcome::AlreadyGone;
+    }
+
+    if !send_signal(pid_num, signal) {
+        // the process may have exited between the check and the signal
+        if !is_process_alive(pid_num) {
+            utils::pretty_print_info(&format!(
+                "{} was already gone before sending {}.",
+                target, signal
+            ));
+            return KillOutcome::AlreadyGone;
+        }
+        utils::pretty_print_error(&format!("Failed to send {} to {}", signal, target));
+        return KillOutcome::Failed;
+    }
+    utils::pretty_print_info(&format!("Sent {} to {}.", signal, target));
+
+    if !is_terminating_signal(signal) {
+        return KillOutcome::Signaled;
+    }
+    if wait_for_exit(pid_num, KILL_GRACE_PERIOD) {
+        utils::pretty_print_info(&format!("{} terminated.", target));
+        return KillOutcome::Terminated;
+    }
+
+    if !options.force || signal == FORCE_SIGNAL {
+        let hint = if signal == FORCE_SIGNAL {
+            ""
+        } else {
+            ", use --force to send SIGKILL"
+        };
+        utils::pretty_print_error(&format!(
+            "{} is still alive after {}{}",
+            target, signal, hint
+        ));
+        return KillOutcome::StillRunning;
+    }
+
+    utils::pretty_print_info(&format!(
+        "{} is still alive after {}, sending {}.",
+        target, signal, FORCE_SIGNAL
+    ));
+    // a failed SIGKILL is detected by the process still running
+    send_signal(pid_num, FORCE_SIGNAL);
+    if wait_for_exit(pid_num, KILL_GRACE_PERIOD) {
+        utils::pretty_print_info(&format!("{} terminated.", target));
+        KillOutcome::Terminated
+    } else {
+        utils::pretty_print_error(&format!("{} is still alive after {}", target, FORCE_SIGNAL));
+        KillOutcome::StillRunning
+    }
+}
+
+/// Collects the processes of all connections without duplicates, since many connections can belong to the same process.
+///
+/// # Arguments
+/// * `connections`: The connections to collect the processes from.
+///
+/// # Returns
+/// All known PIDs together with their first connection, in the order in which they first appear.
+fn get_unique_processes<'a>(
+    connections: impl IntoIterator<Item = &'a Connection>,
+) -> Vec<(i32, &'a Connection)> {
+    let mut processes: Vec<(i32, &Connection)> = Vec::new();
+    for connection in connections {
+        if let Ok(pid) = connection.pid.parse::<i32>() {
+            if !processes.iter().any(|(known_pid, _)| *known_pid == pid) {
+                processes.push((pid, connection));
+            }
+        }
+    }
+    processes
+}
+
+/// Groups every process with its descendants for `--tree`, so that the children are killed together with their parent.
+/// A group starts with the selected process followed by its descendants in pre-order, every process is only part of one group.
+/// PID 1 and somo itself are never part of a tree, PID 1 also isn't expanded since every process descends from it.
+///
+/// # Arguments
+/// * `processes`: The selected processes together with one of their connections.
+/// * `parent_pids`: The PID of the parent keyed by the PID of every running process, `None` to kill the processes alone.
+/// * `own_pid`: The PID of somo.
+///
+/// # Returns
+/// One group for every selected process, in the order of the processes.
+fn build_process_trees<'a>(
+    processes: &[(i32, &'a Connection)],
+    parent_pids: Option<&HashMap<i32, i32>>,
+    own_pid: i32,
+) -> Vec<Vec<KillTarget<'a>>> {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (pid, parent) in parent_pids.into_iter().flatten() {
+        if *pid > 1 && *pid != own_pid {
+            children.entry(*parent).or_default().push(*pid);
+        }
+    }
+    for pids in children.values_mut() {
+        pids.sort_unstable();
+    }
+
+    // the selected processes stay in their own group even if they descend from another one
+    let mut seen: HashSet<i32> = processes.iter().map(|(pid, _)| *pid).collect();
+    let mut trees = Vec::new();
+    for (pid, connection) in processes {
+        let mut tree = vec![KillTarget {
+            pid: *pid,
+            connection: Cow::Borrowed(*connection),
+            parent: None,
+            depth: 0,
+        }];
+        if *pid == 1 && parent_pids.is_some() {
+            utils::pretty_print_info("PID 1 is killed without its children.");
+        } else if parent_pids.is_some() {
+            // a stack in reverse order visits the children by ascending PID
+            let mut stack: Vec<(i32, i32, usize)> = children
+                .get(pid)
+                .into_iter()
+                .flatten()
+                .rev()
+                .map(|child| (*child, *pid, 1))
+                .collect();
+            while let Some((child, parent, depth)) = stack.pop() {
+                if !seen.insert(child) {
+                    continue;
+                }
+                tree.push(KillTarget {
+                    pid: child,
+                    connection: Cow::Owned(Connection {
+                        pid: child.to_string(),
+                        program: get_program_name(child),
+                        ..Default::default()
+                    }),
+                    parent: Some(parent),
+                    depth,
+                });
+                stack.extend(
+                    children
+                        .get(&child)
+                        .into_iter()
+                        .flatten()
+                        .rev()
+                        .map(|grandchild| (*grandchild, child, depth + 1)),
+                );
+            }
+        }
+        trees.push(tree);
+    }
+    trees
+}
+
+/// Describes a process of a tree for the confirmation and the dry-run.
+///
+/// # Arguments
+/// * `target`: The process in the tree.
+///
+/// # Returns
+/// The description together with the parent, e.g "PID 43 (worker), child of PID 42".
+fn describe_tree_member(target: &KillTarget) -> String {
+    let description = describe_process(target.pid, &target.connection);
+    match target.parent {
+        Some(parent) => format!("{}, child of PID {}", description, parent),
+        None => description,
+    }
+}
+
+/// Describes a process for the kill confirmation and the dry-run.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+/// * `connection`: A connection of the process, used for its program and command line.
+///
+/// # Returns
+/// The PID, the program and the command line if it is known.
+fn describe_process(pid: i32, connection: &Connection) -> String {
+    match &connection.command {
+        Some(command) => format!("PID {} ({}): {}", pid, connection.program, command),
+        None => format!("PID {} ({})", pid, connection.program),
+    }
+}
+
+/// Asks the user whether a process should really be killed, anything but a "y" keeps the process alive.
+/// With `--tree` the whole tree is shown and confirmed at once.
+///
+/// # Arguments
+/// * `tree`: The selected process followed by the descendants which are killed with it.
+///
+/// # Returns
+/// `true` if the user confirmed the kill, `false` if not.
+fn confirm_kill(tree: &[KillTarget]) -> bool {
+    for target in tree {
+        utils::pretty_print_info(&format!(
+            "{}{}",
+            "  ".repeat(target.depth),
+            describe_tree_member(target)
+        ));
+    }
+    let question = if tree.len() > 1 {
+        "Kill this process tree?"
+    } else {
+        "Kill this?"
+    };
+    Confirm::new(question)
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false)
+}
+
+/// Cleared by the Ctrl-C handler while a batch of delayed kills runs, set again at the start of every batch.
+static KILLS_RUNNING: AtomicBool = AtomicBool::new(true);
+
+/// Whether the Ctrl-C handler of the kills was registered, a process can only register one handler.
+static INTERRUPT_HANDLER: OnceLock<bool> = OnceLock::new();
+
+/// Registers a Ctrl-C handler which stops a batch of delayed kills, so that the kills sent until then can still be reported.
+/// The handler is only registered by the first batch, later batches (e.g from the interactive mode) reuse it.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The flag which is cleared once Ctrl-C is pressed.
+fn stop_on_interrupt() -> &'static AtomicBool {
+    INTERRUPT_HANDLER.get_or_init(|| {
+        let registered =
+            ctrlc::set_handler(|| KILLS_RUNNING.store(false, Ordering::SeqCst)).is_ok();
+        if !registered {
+            // Ctrl-C still stops somo, just without the report
+            utils::pretty_print_error("Couldn't register the Ctrl-C handler.");
+        }
+        registered
+    });
+    KILLS_RUNNING.store(true, Ordering::SeqCst);
+    &KILLS_RUNNING
+}
+
+/// Waits before the next kill of `--kill-delay`, returning early once Ctrl-C is pressed.
+///
+/// # Arguments
+/// * `delay`: The time to wait.
+/// * `running`: Cleared by the Ctrl-C handler.
+///
+/// # Returns
+/// `true` if the batch should go on, `false` if it was interrupted.
+fn wait_while_running(delay: Duration, running: &AtomicBool) -> bool {
+    let deadline = Instant::now() + delay;
+    while running.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        thread::sleep(remaining.min(KILL_POLL_INTERVAL));
+    }
+    false
+}
+
+/// Kills every process once, asking for a confirmation first unless `--yes` was given, and reports which of them couldn't be killed.
+/// In a dry-run the processes are only listed and nothing is killed.
+/// With `--kill-delay` the kills are spaced out and their progress is reported, Ctrl-C stops the remaining kills.
+/// With `--tree` the descendants of every process are killed before it, unless they were replaced by an unrelated process.
+///
+/// # Argument
+/// * `processes`: The unique PIDs of the processes to kill together with one of their connections.
+/// * `options`: The signal and how to confirm the kills.
+///
+/// # Returns
+/// `true` if all confirmed processes were killed successfully, `false` if at least one kill failed.
+pub fn kill_processes(processes: &[(i32, &Connection)], options: &KillOptions) -> bool {
+    let parent_pids = if options.tree {
+        match connections::get_parent_pids() {
+            Ok(parent_pids) => Some(parent_pids),
+            Err(err) => {
+                utils::pretty_print_error(&format!("Couldn't read the process tree: {}", err));
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+    let trees = build_process_trees(processes, parent_pids.as_ref(), std::process::id() as i32);
+    let total: usize = trees.iter().map(Vec::len).sum();
+
+    if options.dry_run {
+        for target in trees.iter().flatten() {
+            let escalation = if options.force && options.signal != FORCE_SIGNAL {
+                format!(" and {} if it survives", FORCE_SIGNAL)
+            } else {
+                String::new()
+            };
+            utils::pretty_print_info(&format!(
+                "{}Would send {} to {}{}.",
+                "  ".repeat(target.depth),
+                options.signal,
+                describe_tree_member(target),
+                escalation
+            ));
+        }
+        return true;
+    }
+
+    let running = options.delay.map(|_| stop_on_interrupt());
+    let mut index: usize = 0;
+    let mut sent: usize = 0;
+    let mut interrupted = false;
+    let mut killed: usize = 0;
+    let mut skipped: usize = 0;
+    let mut failed: Vec<String> = Vec::new();
+    'trees: for tree in &trees {
+        if !options.yes && !confirm_kill(tree) {
+            index += tree.len();
+            continue;
+        }
+        // the children are killed before their parents, so that they can't be restarted by them
+        for target in tree.iter().rev() {
+            if let (Some(delay), Some(running)) = (options.delay, running) {
+                if sent > 0 && !wait_while_running(delay, running) {
+                    utils::pretty_print_info(&format!(
+                        "Interrupted, sent {} to {} of {} processes.",
+                        options.signal, sent, total
+                    ));
+                    interrupted = true;
+                    break 'trees;
+                }
+            }
+            index += 1;
+            // a PID which was recycled since the tree was read belongs to an unrelated process
+            if let Some(parent) = target.parent {
+                let current_parent = connections::get_parent_pid(target.pid);
+                if current_parent.is_some_and(|current_parent| current_parent != parent) {
+                    utils::pretty_print_info(&format!(
+                        "PID {} is no longer a child of PID {}, skipping it.",
+                        target.pid, parent
+                    ));
+                    skipped += 1;
+                    continue;
+                }
+            }
+            sent += 1;
+            if options.delay.is_some() {
+                utils::pretty_print_info(&format!(
+                    "[{}/{}] {}",
+                    index,
+                    total,
+                    describe_target(target.pid, &target.connection)
+                ));
+            }
+            match kill_process(target.pid, &target.connection, options) {
+                KillOutcome::Terminated | KillOutcome::Signaled | KillOutcome::AlreadyGone => {
+                    killed += 1
+                }
+                KillOutcome::StillRunning | KillOutcome::Failed => {
+                    failed.push(describe_target(target.pid, &target.connection))
+                }
+            }
+        }
+    }
+
+    utils::pretty_print_info(&format!(
+        "Killed {} of {} processes.",
+        killed,
+        total - skipped
+    ));
+    if !failed.is_empty() {
+        utils::pretty_print_error(&format!("Failed: {}", failed.join(", ")));
+    }
+    failed.is_empty() && !interrupted
+}
+
+/// Kills the processes of all connections without selecting them, every process is only killed once.
+///
+/// # Argument
+/// * `connections`: A vector containing all connections which themselves contain a PID value.
+/// * `options`: The signal and how to confirm the kills.
+///
+/// # Returns
+/// `true` if all confirmed processes were killed successfully, `false` if at least one kill failed.
+pub fn kill_all_processes(connections: &[Connection], options: &KillOptions) -> bool {
+    if connections.is_empty() {
+        utils::pretty_print_info("No connections to kill.");
+        return true;
+    }
+    kill_processes(&get_unique_processes(connections), options)
+}
+
+/// Reads the program name of a process for the messages of `--kill-pid`, which doesn't read any connection.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+///
+/// # Returns
+/// The program name, or "process" if it can't be read.
+fn get_program_name(pid: i32) -> String {
+    #[cfg(target_os = "linux")]
+    if let Ok(comm) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        return comm.trim_end().to_string();
+    }
+    let _ = pid;
+    "process".to_string()
 }
 
-/// Kills a process by its PID.
+/// Kills processes by their PID without reading any connection or asking for a confirmation, e.g for `--kill-pid`.
+/// PIDs which aren't running are reported and count as failed kills, the others are still killed.
 ///
 /// # Argument
-/// * `pid`: The PID value as a string.
+/// * `pids`: The PIDs provided by the user, a PID given twice is only killed once.
+/// * `options`: The signal to send and whether it is a dry-run.
 ///
 /// # Returns
-/// None
-pub fn kill_process(pid_num: i32) {
-    let pid = Pid::from_raw(pid_num);
+/// `true` if all processes were running and were killed successfully, `false` if not.
+pub fn kill_pids(pids: &[i32], options: &KillOptions) -> bool {
+    let mut all_running = true;
+    let mut connections: Vec<Connection> = Vec::new();
+    for pid in pids {
+        if connections
+            .iter()
+            .any(|connection| connection.pid == pid.to_string())
+        {
+            continue;
+        }
+        if !is_process_alive(*pid) {
+            utils::pretty_print_error(&format!("No process with PID {} is running.", pid));
+            all_running = false;
+            continue;
+        }
+        connections.push(Connection {
+            pid: pid.to_string(),
+            program: get_program_name(*pid),
+            ..Default::default()
+        });
+    }
+
+    let processes = get_unique_processes(&connections);
+    let options = KillOptions {
+        yes: true,
+        ..*options
+    };
+    let killed = processes.is_empty() || kill_processes(&processes, &options);
+    all_running && killed
+}
+
+/// One selectable connection in the interactive kill prompt.
+struct KillChoice<'a> {
+    index: usize,
+    connection: &'a Connection,
+}
 
-    match signal::kill(pid, signal::Signal::SIGTERM) {
-        Ok(_) => utils::pretty_print_info(&format!("Killed process with PID {}.", pid)),
-        Err(_) => utils::pretty_print_error(&format!("Failed to kill process with PID {}", pid)),
+impl std::fmt::Display for KillChoice<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} (PID {})",
+            self.index, self.connection.program, self.connection.pid
+        )
     }
 }
 
-/// Starts an interactive selection process in the console for choosing a process to kill using the "inquire" crate.
+/// Starts an interactive selection process in the console for choosing the processes to kill using the "inquire" crate.
+/// Several connections can be selected, a process with multiple selected connections is only killed once.
 ///
 /// # Argument
 /// * `connections`: A vector containing all connections which themselves contain a PID value.
+/// * `options`: The signal and how to confirm the kills.
 ///
 /// # Returns
-/// None
-pub fn interactve_process_kill(connections: &Vec<Connection>) {
-    let selection: Result<u32, InquireError> = Select::new(
-        "Which process to kill (search or type index)?",
-        (1..=connections.len() as u32).collect(),
+/// `true` if all selected processes were killed successfully or the selection was cancelled, `false` if at least one kill failed.
+pub fn interactve_process_kill(connections: &[Connection], options: &KillOptions) -> bool {
+    // an empty selection prompt can't be answered
+    if connections.is_empty() {
+        utils::pretty_print_info("No connections to kill.");
+        return true;
+    }
+
+    let choices: Vec<KillChoice> = connections
+        .iter()
+        .enumerate()
+        .map(|(idx, connection)| KillChoice {
+            index: idx + 1,
+            connection,
+        })
+        .collect();
+
+    let selection: Result<Vec<KillChoice>, InquireError> = MultiSelect::new(
+        "Which processes to kill (search or type index, space to select)?",
+        choices,
     )
     .prompt();
 
     match selection {
-        Ok(choice) => {
-            let pid_str = &connections[choice as usize - 1].pid;
-            let pid_num = match pid_str.parse::<i32>() {
-                Ok(pid) => pid,
-                Err(_) => {
-                    utils::pretty_print_error("Couldn't find PID.");
-                    return;
-                }
-            };
-            kill_process(pid_num)
+        Ok(choices) => {
+            let processes = get_unique_processes(choices.iter().map(|choice| choice.connection));
+            if processes.is_empty() {
+                utils::pretty_print_error("Couldn't find PID.");
+                return true;
+            }
+            kill_processes(&processes, options)
         }
         Err(_) => {
             utils::pretty_print_error("Process selection cancelled.");
-            return;
+            true
         }
-    };
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Args;
-    use clap::Parser;
+    use super::{
+        build_process_trees, describe_process, describe_target, describe_tree_member,
+        disable_lookups, get_program_matcher, get_unique_processes, interactve_process_kill,
+        kill_all_processes, merge_config, parse_categories, parse_signal, resolve_family,
+        stop_on_interrupt, wait_while_running, Args, KillChoice, KillOptions, INTERRUPT_HANDLER,
+    };
+    #[cfg(unix)]
+    use super::{is_process_alive, kill_pids, kill_process, KillOutcome};
+    use crate::completions::Shell;
+    use crate::config::Config;
+    use crate::schemas::{
+        AddressFamily, BorderStyle, ColorMode, Connection, Exposure, GroupField, ProgramMatcher,
+        SortField, StateCategory, Theme, TimestampFormat,
+    };
+    use clap::{CommandFactory, FromArgMatches, Parser};
+    #[cfg(unix)]
+    use nix::sys::signal::Signal;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    #[cfg(unix)]
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_all_flags_parsing() {
-        let args = Args::parse_from(&[
+        let args = Args::parse_from([
             "test-bin",
             "-k",
+            "--all",
+            "--signal",
+            "KILL",
             "--proto",
             "udp",
             "--ip",
             "192.168.0.1",
+            "--local-ip",
+            "any",
             "--remote-port",
             "53",
             "-p",
@@ -153,43 +1770,148 @@ mod tests {
             "nginx",
             "--pid",
             "1234",
+            "--user",
+            "root",
+            "--state",
+            "time-wait",
             "-o",
             "-l",
+            "--exclude-ipv4",
             "--exclude-ipv6",
+            "--exclude-program",
+            "chrome",
+            "--exclude-self",
+            "--exclude-ip",
+            "10.0.0.0/8",
+            "--exclude-port",
+            "22",
+            "--resolve",
+            "--long",
+            "--geoip",
+            "GeoLite2-Country.mmdb",
+            "--sort",
+            "local_port",
+            "--reverse",
+            "--json",
         ]);
 
         assert!(args.kill);
-        assert_eq!(args.proto.as_deref(), Some("udp"));
+        assert!(args.all);
+        assert_eq!(args.signal, "KILL");
+        assert_eq!(args.proto, vec!["udp"]);
         assert_eq!(args.ip.as_deref(), Some("192.168.0.1"));
+        assert_eq!(args.local_ip.as_deref(), Some("any"));
         assert_eq!(args.remote_port.as_deref(), Some("53"));
         assert_eq!(args.port.as_deref(), Some("8080"));
         assert_eq!(args.program.as_deref(), Some("nginx"));
         assert_eq!(args.pid.as_deref(), Some("1234"));
+        assert_eq!(args.user.as_deref(), Some("root"));
+        assert_eq!(args.state.as_deref(), Some("time-wait"));
         assert!(args.open);
         assert!(args.listen);
+        assert!(args.exclude_ipv4);
         assert!(args.exclude_ipv6);
+        assert_eq!(args.exclude_program.as_deref(), Some("chrome"));
+        assert!(args.exclude_self);
+        assert_eq!(args.exclude_ip.as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(args.exclude_port.as_deref(), Some("22"));
+        assert!(args.resolve);
+        assert!(args.long);
+        assert_eq!(args.geoip, Some(PathBuf::from("GeoLite2-Country.mmdb")));
+        assert_eq!(args.sort, Some(SortField::LocalPort));
+        assert!(args.reverse);
+        assert!(args.json);
     }
 
     #[test]
     fn test_default_values() {
-        let args = Args::parse_from(&["test-bin"]);
+        let args = Args::parse_from(["test-bin"]);
 
         assert!(!args.kill);
-        assert!(args.proto.is_none());
+        assert!(!args.all);
+        assert!(!args.tree);
+        assert_eq!(args.signal, "TERM");
+        assert!(args.proto.is_empty());
         assert!(args.ip.is_none());
+        assert!(args.local_ip.is_none());
         assert!(args.remote_port.is_none());
         assert!(args.port.is_none());
         assert!(args.program.is_none());
         assert!(args.pid.is_none());
+        assert!(args.user.is_none());
+        assert!(args.state.is_none());
         assert!(!args.open);
         assert!(!args.listen);
+        assert!(args.include.is_empty());
+        assert!(args.columns.is_none());
+        assert!(!args.full);
+        assert!(!args.unresolved_only);
+        assert!(!args.exclude_ipv4);
+        assert!(args.exposure.is_none());
         assert!(!args.exclude_ipv6);
+        assert!(args.exclude_program.is_none());
+        assert!(!args.exclude_self);
+        assert!(args.exclude_ip.is_none());
+        assert!(args.exclude_port.is_none());
+        assert!(!args.resolve);
+        assert!(!args.services);
+        assert!(!args.long);
+        assert!(!args.no_summary);
+        assert!(!args.show_host);
+        assert!(args.hostname.is_none());
+        assert!(args.output.is_none());
+        assert!(!args.strict);
+        assert!(!args.show_inode);
+        assert!(!args.queues);
+        assert!(!args.append);
+        assert!(!args.local_only);
+        assert!(!args.no_local);
+        assert!(!args.privileged_ports);
+        assert!(!args.unprivileged_ports);
+        assert!(!args.raw_addresses);
+        assert!(!args.highlight_suspicious);
+        assert!(args.highlight.is_empty());
+        assert!(!args.stats);
+        assert!(!args.show_age);
+        assert!(args.since.is_none());
+        assert!(!args.include_unknown_age);
+        assert!(args.timestamps.is_none());
+        assert!(!args.unique);
+        assert!(!args.count_dupes);
+        assert!(args.diff.is_none());
+        assert!(args.geoip.is_none());
+        assert!(!args.containers);
+        assert!(args.container.is_none());
+        assert!(!args.units);
+        assert!(args.unit.is_none());
+        assert!(!args.all_netns);
+        assert!(args.netns.is_none());
+        assert!(args.sort.is_none());
+        assert!(!args.reverse);
+        assert_eq!(args.limit, 0);
+        assert!(args.group_by.is_none());
+        assert_eq!(args.color, ColorMode::Auto);
+        assert_eq!(args.theme, Theme::Default);
+        assert!(args.border.is_none());
+        assert!(args.watch.is_none());
+        assert!(!args.interactive);
+        assert!(!args.json);
+        assert!(!args.yaml);
+        assert!(!args.csv);
+        assert!(!args.markdown);
+        assert!(!args.prometheus);
+        assert!(!args.oneline);
+        assert!(!args.ndjson);
+        assert!(!args.count);
+        assert!(!args.top_remotes);
+        assert!(!args.exit_nonzero_on_empty);
+        assert!(args.timeout.is_none());
     }
 
     #[test]
     fn test_flag_short_and_long_equivalence() {
-        let short = Args::parse_from(&["test-bin", "-k", "-p", "80", "-o", "-l"]);
-        let long = Args::parse_from(&["test-bin", "--kill", "--port", "80", "--open", "--listen"]);
+        let short = Args::parse_from(["test-bin", "-k", "-p", "80", "-o", "-l"]);
+        let long = Args::parse_from(["test-bin", "--kill", "--port", "80", "--open", "--listen"]);
 
         assert_eq!(short.kill, long.kill);
         assert_eq!(short.port, long.port);
@@ -197,4 +1919,941 @@ mod tests {
         assert_eq!(short.listen, long.listen);
         assert_eq!(short.exclude_ipv6, long.exclude_ipv6);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("TERM"), Some(Signal::SIGTERM));
+        assert_eq!(parse_signal("kill"), Some(Signal::SIGKILL));
+        assert_eq!(parse_signal("SIGINT"), Some(Signal::SIGINT));
+        assert_eq!(parse_signal("HUP"), Some(Signal::SIGHUP));
+        assert_eq!(parse_signal("9"), Some(Signal::SIGKILL));
+        assert_eq!(parse_signal("FOO"), None);
+        assert_eq!(parse_signal("999"), None);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("TERM"), parse_signal("15"));
+        assert_eq!(parse_signal("sigkill"), parse_signal("9"));
+        assert!(parse_signal("NOPE").is_none());
+        assert!(parse_signal("99").is_none());
+    }
+
+    #[test]
+    fn test_kill_all_requires_kill() {
+        assert!(Args::try_parse_from(["test-bin", "--all"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "-k", "-a"]).unwrap().all);
+    }
+
+    #[test]
+    fn test_get_unique_processes() {
+        let connections: Vec<Connection> = ["12", "-", "7", "12", "7", "3"]
+            .iter()
+            .map(|pid| Connection {
+                pid: pid.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        let pids: Vec<i32> = get_unique_processes(&connections)
+            .iter()
+            .map(|(pid, _)| *pid)
+            .collect();
+        assert_eq!(pids, vec![12, 7, 3]);
+    }
+
+    #[test]
+    fn test_kill_confirmation_flags() {
+        let args = Args::try_parse_from(["test-bin", "-k", "--all", "--yes", "--dry-run"]).unwrap();
+        assert!(args.yes);
+        assert!(args.dry_run);
+
+        assert!(Args::try_parse_from(["test-bin", "--yes"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn test_force_requires_kill() {
+        let args = Args::try_parse_from(["test-bin", "-k", "--force"]).unwrap();
+        assert!(args.force);
+        assert!(Args::try_parse_from(["test-bin", "--force"]).is_err());
+    }
+
+    #[test]
+    fn test_kill_delay() {
+        let args =
+            Args::try_parse_from(["test-bin", "-k", "--all", "--kill-delay", "250"]).unwrap();
+        assert_eq!(args.kill_delay, Some(250));
+        assert!(
+            Args::try_parse_from(["test-bin", "--kill-pid", "1", "--kill-delay", "10"]).is_ok()
+        );
+        assert!(Args::try_parse_from(["test-bin", "--kill-delay", "250"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "-k", "--kill-delay", "soon"]).is_err());
+
+        let running = AtomicBool::new(true);
+        let start = Instant::now();
+        assert!(wait_while_running(Duration::from_millis(50), &running));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // Ctrl-C stops the wait right away
+        running.store(false, Ordering::SeqCst);
+        let start = Instant::now();
+        assert!(!wait_while_running(Duration::from_secs(10), &running));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_stop_on_interrupt_per_batch() {
+        let first = stop_on_interrupt();
+        // Ctrl-C during the first batch
+        first.store(false, Ordering::SeqCst);
+
+        // the next batch reuses the handler, but isn't interrupted by the earlier Ctrl-C
+        let second = stop_on_interrupt();
+        assert!(std::ptr::eq(first, second));
+        assert!(second.load(Ordering::SeqCst));
+        assert!(INTERRUPT_HANDLER.get().is_some());
+    }
+
+    #[test]
+    fn test_host_flags() {
+        let args =
+            Args::try_parse_from(["test-bin", "--json", "--hostname", "web-1", "--show-host"])
+                .unwrap();
+        assert_eq!(args.hostname.as_deref(), Some("web-1"));
+        assert!(args.show_host);
+        assert!(Args::try_parse_from(["test-bin", "--hostname", ""]).is_err());
+    }
+
+    #[test]
+    fn test_tree_flag() {
+        assert!(
+            Args::try_parse_from(["test-bin", "-k", "--tree"])
+                .unwrap()
+                .tree
+        );
+        assert!(
+            Args::try_parse_from(["test-bin", "--kill-pid", "12", "--tree"])
+                .unwrap()
+                .tree
+        );
+        assert!(Args::try_parse_from(["test-bin", "--tree"]).is_err());
+    }
+
+    #[test]
+    fn test_build_process_trees() {
+        // PIDs above the maximum PID of Linux, so that no program name can be read for them
+        let connection = Connection {
+            program: "nginx".to_string(),
+            ..Default::default()
+        };
+        let other = Connection {
+            program: "sshd".to_string(),
+            ..Default::default()
+        };
+        let parent_pids: HashMap<i32, i32> = HashMap::from([
+            (5_000_001, 1),
+            (5_000_003, 5_000_001),
+            (5_000_002, 5_000_001),
+            (5_000_004, 5_000_002),
+            (5_000_005, 5_000_001),
+            (5_000_009, 5_000_008),
+            (5_000_010, 1),
+        ]);
+        let processes = [(5_000_001, &connection), (5_000_005, &other), (1, &other)];
+
+        let trees = build_process_trees(&processes, Some(&parent_pids), 5_000_003);
+        let pids: Vec<Vec<(i32, Option<i32>, usize)>> = trees
+            .iter()
+            .map(|tree| {
+                tree.iter()
+                    .map(|target| (target.pid, target.parent, target.depth))
+                    .collect()
+            })
+            .collect();
+        // somo itself is left out, the selected 5_000_005 keeps its own group and PID 1 isn't expanded
+        assert_eq!(
+            pids,
+            vec![
+                vec![
+                    (5_000_001, None, 0),
+                    (5_000_002, Some(5_000_001), 1),
+                    (5_000_004, Some(5_000_002), 2),
+                ],
+                vec![(5_000_005, None, 0)],
+                vec![(1, None, 0)],
+            ]
+        );
+        assert_eq!(trees[0][0].connection.program, "nginx");
+        assert_eq!(trees[0][1].connection.program, "process");
+        assert_eq!(
+            describe_tree_member(&trees[0][2]),
+            "PID 5000004 (process), child of PID 5000002"
+        );
+        assert_eq!(describe_tree_member(&trees[0][0]), "PID 5000001 (nginx)");
+
+        // without the parents every process is killed alone
+        let trees = build_process_trees(&processes, None, 5_000_003);
+        assert!(trees.iter().all(|tree| tree.len() == 1));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kill_pids_tree() {
+        let options = KillOptions {
+            signal: parse_signal("TERM").unwrap(),
+            yes: true,
+            dry_run: false,
+            force: false,
+            delay: None,
+            tree: true,
+        };
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "sleep 30 & wait"])
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        let start = Instant::now();
+        let grandchild = loop {
+            let parent_pids = crate::connections::get_parent_pids().unwrap();
+            if let Some((grandchild, _)) = parent_pids.iter().find(|(_, parent)| **parent == pid) {
+                break *grandchild;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        assert!(kill_pids(&[pid], &options));
+        child.wait().unwrap();
+        let start = Instant::now();
+        while is_process_alive(grandchild) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_kill_pid_flag() {
+        let args = Args::try_parse_from([
+            "test-bin",
+            "--kill-pid",
+            "12",
+            "--kill-pid",
+            "34",
+            "--signal",
+            "HUP",
+            "--dry-run",
+            "--force",
+        ])
+        .unwrap();
+        assert_eq!(args.kill_pid, vec![12, 34]);
+        assert!(args.dry_run);
+        assert!(args.force);
+
+        assert!(Args::try_parse_from(["test-bin", "--kill-pid", "nginx"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--kill-pid", "0"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--kill-pid", "12", "-k"]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_pids() {
+        let options = KillOptions {
+            signal: parse_signal("TERM").unwrap(),
+            yes: false,
+            dry_run: false,
+            force: false,
+            delay: None,
+            tree: false,
+        };
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+
+        let dry_run = KillOptions {
+            dry_run: true,
+            ..options
+        };
+        assert!(kill_pids(&[pid], &dry_run));
+        assert!(is_process_alive(pid));
+
+        // the PID given twice is only killed once, without a confirmation
+        assert!(kill_pids(&[pid, pid], &options));
+        child.wait().unwrap();
+        assert!(!kill_pids(&[pid], &options));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_process_outcome() {
+        let options = KillOptions {
+            signal: parse_signal("TERM").unwrap(),
+            yes: true,
+            dry_run: false,
+            force: false,
+            delay: None,
+            tree: false,
+        };
+        let connection = Connection {
+            program: "sleep".to_string(),
+            ..Default::default()
+        };
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+        assert!(is_process_alive(pid));
+        assert_eq!(
+            kill_process(pid, &connection, &options),
+            KillOutcome::Terminated
+        );
+        child.wait().unwrap();
+
+        // the reaped child is gone before the signal is sent
+        assert!(!is_process_alive(pid));
+        assert_eq!(
+            kill_process(pid, &connection, &options),
+            KillOutcome::AlreadyGone
+        );
+    }
+
+    #[test]
+    fn test_describe_process() {
+        let mut connection = Connection {
+            program: "nginx".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(describe_process(123, &connection), "PID 123 (nginx)");
+
+        connection.command = Some("nginx -g daemon off;".to_string());
+        assert_eq!(
+            describe_process(123, &connection),
+            "PID 123 (nginx): nginx -g daemon off;"
+        );
+    }
+
+    #[test]
+    fn test_describe_target() {
+        let connection = Connection {
+            pid: "1234".to_string(),
+            program: "nginx".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(describe_target(1234, &connection), "nginx (PID 1234)");
+    }
+
+    #[test]
+    fn test_kill_without_connections() {
+        let options = KillOptions {
+            signal: parse_signal("TERM").unwrap(),
+            yes: false,
+            dry_run: false,
+            force: false,
+            delay: None,
+            tree: false,
+        };
+        // returns before prompting, so the test doesn't wait for input
+        assert!(interactve_process_kill(&[], &options));
+        assert!(kill_all_processes(&[], &options));
+    }
+
+    #[test]
+    fn test_kill_choice_display() {
+        let connection = Connection {
+            program: "nginx".to_string(),
+            pid: "123".to_string(),
+            ..Default::default()
+        };
+        let choice = KillChoice {
+            index: 4,
+            connection: &connection,
+        };
+        assert_eq!(choice.to_string(), "4: nginx (PID 123)");
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        let default_interval = Args::parse_from(["test-bin", "--watch"]);
+        assert_eq!(default_interval.watch, Some(2));
+
+        let custom_interval = Args::parse_from(["test-bin", "-w", "5"]);
+        assert_eq!(custom_interval.watch, Some(5));
+
+        assert!(Args::try_parse_from(["test-bin", "--watch", "-k"]).is_err());
+        // an interval of 0 would refresh without ever sleeping
+        assert!(Args::try_parse_from(["test-bin", "--watch", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_print_schema() {
+        assert!(Args::parse_from(["test-bin", "--print-schema"]).print_schema);
+        assert!(Args::try_parse_from(["test-bin", "--print-schema", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_list_fields_flag() {
+        assert!(Args::parse_from(["test-bin", "--list-fields"]).list_fields);
+        assert!(Args::try_parse_from(["test-bin", "--list-fields", "-l"]).is_err());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let args = Args::try_parse_from(["test-bin", "--timestamps", "absolute"]).unwrap();
+        assert_eq!(args.timestamps, Some(TimestampFormat::Absolute));
+        assert!(Args::try_parse_from(["test-bin", "--timestamps", "iso"]).is_err());
+    }
+
+    #[test]
+    fn test_output() {
+        let args = Args::try_parse_from(["test-bin", "--output", "somo.txt", "--append", "--json"])
+            .unwrap();
+        assert_eq!(args.output, Some(PathBuf::from("somo.txt")));
+        assert!(args.append);
+        assert!(Args::try_parse_from(["test-bin", "--append"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--output", "somo.txt", "-i"]).is_err());
+    }
+
+    #[test]
+    fn test_completions() {
+        let args = Args::parse_from(["test-bin", "--completions", "powershell"]);
+        assert_eq!(args.completions, Some(Shell::Powershell));
+        assert!(Args::try_parse_from(["test-bin", "--completions", "tcsh"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--completions", "zsh", "-l"]).is_err());
+    }
+
+    #[test]
+    fn test_man() {
+        assert!(Args::parse_from(["test-bin", "--man"]).man);
+        assert!(Args::try_parse_from(["test-bin", "--man", "--json"]).is_err());
+        // the packaging flag isn't shown in the help
+        let command = Args::command();
+        let man = command.get_arguments().find(|arg| arg.get_id() == "man");
+        assert!(man.is_some_and(|arg| arg.is_hide_set()));
+    }
+
+    #[test]
+    fn test_width() {
+        let args = Args::try_parse_from(["test-bin", "--width", "100"]).unwrap();
+        assert_eq!(args.width, Some(100));
+        assert!(Args::try_parse_from(["test-bin", "--width", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_program_matchers() {
+        let matcher = |cli_args: &[&str]| get_program_matcher(&Args::parse_from(cli_args));
+
+        assert!(matches!(
+            matcher(&["test-bin", "--program", "nginx"]),
+            Ok(Some(ProgramMatcher::Substring(_)))
+        ));
+        assert!(matches!(
+            matcher(&["test-bin", "--program-exact", "nginx"]),
+            Ok(Some(ProgramMatcher::Exact(_)))
+        ));
+        assert!(matches!(
+            matcher(&["test-bin", "--program-glob", "python3*"]),
+            Ok(Some(ProgramMatcher::Glob(_)))
+        ));
+        assert!(matches!(
+            matcher(&["test-bin", "--program-regex", "^nginx$", "--match-path"]),
+            Ok(Some(ProgramMatcher::Regex(_)))
+        ));
+        assert!(matches!(matcher(&["test-bin"]), Ok(None)));
+
+        let error = matcher(&["test-bin", "--program-regex", "(nginx"]).unwrap_err();
+        assert!(error.starts_with("Invalid value for --program-regex:"));
+        assert!(matcher(&["test-bin", "--program-glob", "[python"]).is_err());
+
+        // only one program filter can be given
+        assert!(
+            Args::try_parse_from(["test-bin", "--program", "a", "--program-exact", "b"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_exe_flags() {
+        let args = Args::try_parse_from([
+            "test-bin",
+            "--show-exe",
+            "--program",
+            "venv",
+            "--match-path",
+        ])
+        .unwrap();
+        assert!(args.show_exe);
+        assert!(args.match_path);
+        assert!(Args::try_parse_from(["test-bin", "--match-path"]).is_err());
+    }
+
+    #[test]
+    fn test_local() {
+        let args = Args::try_parse_from(["test-bin", "--local-only"]).unwrap();
+        assert!(args.local_only);
+        let args = Args::try_parse_from(["test-bin", "--no-local"]).unwrap();
+        assert!(args.no_local);
+        assert!(Args::try_parse_from(["test-bin", "--local-only", "--no-local"]).is_err());
+    }
+
+    #[test]
+    fn test_privileged_ports() {
+        let args = Args::try_parse_from(["test-bin", "--privileged-ports", "-l"]).unwrap();
+        assert!(args.privileged_ports);
+        assert!(args.listen);
+        let args = Args::try_parse_from(["test-bin", "--unprivileged-ports"]).unwrap();
+        assert!(args.unprivileged_ports);
+        assert!(
+            Args::try_parse_from(["test-bin", "--privileged-ports", "--unprivileged-ports"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_exposure() {
+        let args = Args::try_parse_from(["test-bin", "--exposure", "-l"]).unwrap();
+        assert_eq!(args.exposure, Some(None));
+        assert!(args.listen);
+
+        let args = Args::try_parse_from(["test-bin", "--exposure", "all-interfaces"]).unwrap();
+        assert_eq!(args.exposure, Some(Some(Exposure::AllInterfaces)));
+        assert!(Args::try_parse_from(["test-bin", "--exposure", "internet"]).is_err());
+    }
+
+    #[test]
+    fn test_holding() {
+        let args = Args::try_parse_from(["test-bin", "--holding", "8080", "-k"]).unwrap();
+        assert_eq!(args.holding, Some(8080));
+
+        assert!(Args::try_parse_from(["test-bin", "--holding", "http"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--holding", "80", "--port", "443"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--holding", "80", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_highlight_terms() {
+        let args = Args::try_parse_from(["test-bin", "--highlight", "nginx", "--highlight", "443"])
+            .unwrap();
+        assert_eq!(args.highlight, vec!["nginx", "443"]);
+    }
+
+    #[test]
+    fn test_watch_limits() {
+        let args = Args::try_parse_from([
+            "test-bin",
+            "--watch",
+            "--iterations",
+            "3",
+            "--duration",
+            "60",
+        ])
+        .unwrap();
+        assert_eq!(args.iterations, Some(3));
+        assert_eq!(args.duration, Some(60));
+
+        assert!(Args::try_parse_from(["test-bin", "--iterations", "3"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--duration", "60"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--watch", "--iterations", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_since() {
+        let args =
+            Args::try_parse_from(["test-bin", "--since", "5m", "--include-unknown-age"]).unwrap();
+        assert_eq!(args.since.as_deref(), Some("5m"));
+        assert!(args.include_unknown_age);
+        assert!(Args::try_parse_from(["test-bin", "--include-unknown-age"]).is_err());
+    }
+
+    #[test]
+    fn test_pid_and_ppid() {
+        let args = Args::try_parse_from(["test-bin", "--pid", "1234,5678", "--ppid", "1"]).unwrap();
+        assert_eq!(args.pid.as_deref(), Some("1234,5678"));
+        assert_eq!(args.ppid, Some(1));
+        assert!(Args::try_parse_from(["test-bin", "--ppid", "nginx"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--ppid", "1", "--unresolved-only"]).is_err());
+    }
+
+    #[test]
+    fn test_env() {
+        let args =
+            Args::try_parse_from(["test-bin", "--env", "DEPLOY_ID=42", "--env", "REGION=eu"])
+                .unwrap();
+        assert_eq!(args.env, vec!["DEPLOY_ID=42", "REGION=eu"]);
+        assert!(Args::try_parse_from(["test-bin", "--env", "A=1", "--unresolved-only"]).is_err());
+    }
+
+    #[test]
+    fn test_interactive() {
+        let args = Args::try_parse_from(["test-bin", "-i", "--proto", "tcp"]).unwrap();
+        assert!(args.interactive);
+        assert!(Args::try_parse_from(["test-bin", "--interactive", "--signal", "KILL"]).is_ok());
+
+        assert!(Args::try_parse_from(["test-bin", "-i", "--watch"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "-i", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "-i", "--kill"]).is_err());
+    }
+
+    #[test]
+    fn test_count() {
+        let args = Args::try_parse_from(["test-bin", "--listen", "--count"]).unwrap();
+        assert!(args.count);
+        assert!(args.listen);
+        assert!(Args::try_parse_from(["test-bin", "--count", "--group-by", "state"]).is_ok());
+
+        assert!(Args::try_parse_from(["test-bin", "--count", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--count", "--kill"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--count", "--watch"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--count", "--limit", "5"]).is_err());
+
+        let args =
+            Args::try_parse_from(["test-bin", "--count", "--exit-nonzero-on-empty"]).unwrap();
+        assert!(args.exit_nonzero_on_empty);
+        assert!(Args::try_parse_from(["test-bin", "--exit-nonzero-on-empty", "--watch"]).is_err());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        assert!(
+            Args::try_parse_from(["test-bin", "--ndjson"])
+                .unwrap()
+                .ndjson
+        );
+        let args = Args::try_parse_from(["test-bin", "--ndjson", "--watch", "5"]).unwrap();
+        assert!(args.ndjson);
+        assert_eq!(args.watch, Some(5));
+
+        assert!(Args::try_parse_from(["test-bin", "--ndjson", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--ndjson", "--count"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--ndjson", "--group-by", "state"]).is_err());
+    }
+
+    #[test]
+    fn test_output_formats_conflict() {
+        assert!(Args::try_parse_from(["test-bin", "--csv"]).unwrap().csv);
+        assert!(Args::try_parse_from(["test-bin", "--json", "--csv"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--yaml"]).unwrap().yaml);
+        assert!(Args::try_parse_from(["test-bin", "--yaml", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--yaml", "--watch"]).is_err());
+        assert!(
+            Args::try_parse_from(["test-bin", "--markdown"])
+                .unwrap()
+                .markdown
+        );
+        assert!(Args::try_parse_from(["test-bin", "--markdown", "--csv"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--markdown", "--group-by", "pid"]).is_err());
+        assert!(
+            Args::try_parse_from(["test-bin", "--prometheus"])
+                .unwrap()
+                .prometheus
+        );
+        assert!(Args::try_parse_from(["test-bin", "--prometheus", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--prometheus", "--watch"]).is_err());
+        assert!(
+            Args::try_parse_from(["test-bin", "--oneline"])
+                .unwrap()
+                .oneline
+        );
+        assert!(
+            Args::try_parse_from(["test-bin", "--short"])
+                .unwrap()
+                .oneline
+        );
+        assert!(Args::try_parse_from(["test-bin", "--oneline", "--csv"]).is_err());
+    }
+
+    fn parse_with_config(cli_args: &[&str], config: Config) -> Args {
+        let matches = Args::command().get_matches_from(cli_args);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        merge_config(&mut args, config, &matches);
+        args
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let config = Config {
+            signal: Some("KILL".to_string()),
+            proto: Some("tcp".to_string()),
+            exclude_ipv6: Some(true),
+            sort: Some(SortField::Pid),
+            ..Default::default()
+        };
+        let args = parse_with_config(&["test-bin"], config);
+
+        assert_eq!(args.signal, "KILL");
+        assert_eq!(args.proto, vec!["tcp"]);
+        assert!(args.exclude_ipv6);
+        assert!(!args.exclude_ipv4);
+        assert_eq!(args.sort, Some(SortField::Pid));
+    }
+
+    #[test]
+    fn test_resolve_family() {
+        assert_eq!(resolve_family(None, false, false), Ok(AddressFamily::Any));
+        assert_eq!(
+            resolve_family(Some(AddressFamily::Inet6), false, false),
+            Ok(AddressFamily::Inet6)
+        );
+        assert_eq!(resolve_family(None, true, false), Ok(AddressFamily::Inet6));
+        assert_eq!(resolve_family(None, false, true), Ok(AddressFamily::Inet));
+        assert_eq!(
+            resolve_family(Some(AddressFamily::Inet), false, true),
+            Ok(AddressFamily::Inet)
+        );
+
+        assert!(resolve_family(None, true, true).is_err());
+        assert!(resolve_family(Some(AddressFamily::Any), false, true).is_err());
+        let error = resolve_family(Some(AddressFamily::Inet), true, false).unwrap_err();
+        assert_eq!(error, "--family inet contradicts --exclude-ipv4");
+    }
+
+    #[test]
+    fn test_merge_config_family() {
+        let config = Config {
+            exclude_ipv6: Some(true),
+            ..Default::default()
+        };
+        // the family of the CLI replaces the excluded family of the config file
+        let args = parse_with_config(&["test-bin", "--family", "inet6"], config);
+        assert_eq!(args.family, Some(AddressFamily::Inet6));
+        assert!(!args.exclude_ipv6);
+
+        let config = Config {
+            family: Some(AddressFamily::Inet),
+            ..Default::default()
+        };
+        let args = parse_with_config(&["test-bin"], config);
+        assert_eq!(args.family, Some(AddressFamily::Inet));
+    }
+
+    #[test]
+    fn test_merge_config_cli_wins() {
+        let config = Config {
+            signal: Some("KILL".to_string()),
+            proto: Some("tcp".to_string()),
+            sort: Some(SortField::Pid),
+            ..Default::default()
+        };
+        let args = parse_with_config(
+            &[
+                "test-bin", "--signal", "TERM", "--proto", "udp", "--sort", "program",
+            ],
+            config,
+        );
+
+        assert_eq!(args.signal, "TERM");
+        assert_eq!(args.proto, vec!["udp"]);
+        assert_eq!(args.sort, Some(SortField::Program));
+    }
+
+    #[test]
+    fn test_multiple_protocols() {
+        let args = Args::parse_from(["test-bin", "--proto", "tcp,udp6"]);
+        assert_eq!(args.proto, vec!["tcp", "udp6"]);
+
+        let args = Args::parse_from(["test-bin", "--proto", "tcp6", "--proto", "udp"]);
+        assert_eq!(args.proto, vec!["tcp6", "udp"]);
+
+        let args = parse_with_config(
+            &["test-bin"],
+            Config {
+                proto: Some("tcp, udp".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(args.proto, vec!["tcp", "udp"]);
+    }
+
+    #[test]
+    fn test_include_categories() {
+        let args = Args::parse_from(["test-bin", "--include", "listening,Established"]);
+        assert_eq!(
+            parse_categories(&args.include),
+            Ok(vec![StateCategory::Listening, StateCategory::Established])
+        );
+        assert_eq!(
+            parse_categories(&["waiting".to_string(), "active".to_string()]),
+            Err("active".to_string())
+        );
+
+        let args = parse_with_config(
+            &["test-bin"],
+            Config {
+                include: Some("closing, waiting".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(args.include, vec!["closing", "waiting"]);
+    }
+
+    #[test]
+    fn test_columns() {
+        let args = Args::parse_from(["test-bin", "--columns", "-proto,+command"]);
+        assert_eq!(args.columns.as_deref(), Some("-proto,+command"));
+
+        let args = parse_with_config(
+            &["test-bin"],
+            Config {
+                columns: Some("local_port,program".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(args.columns.as_deref(), Some("local_port,program"));
+    }
+
+    #[test]
+    fn test_top_remotes() {
+        let args = Args::try_parse_from(["test-bin", "--top-remotes", "--limit", "10"]).unwrap();
+        assert!(args.top_remotes);
+        assert_eq!(args.limit, 10);
+
+        assert!(Args::try_parse_from(["test-bin", "--top-remotes", "--group-by", "pid"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--top-remotes", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--top-remotes", "-k"]).is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let args = Args::try_parse_from(["test-bin", "--timeout", "5"]).unwrap();
+        assert_eq!(args.timeout, Some(5));
+        assert!(Args::try_parse_from(["test-bin", "--timeout", "0"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--timeout", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let args = Args::try_parse_from(["test-bin", "--group-by", "program"]).unwrap();
+        assert_eq!(args.group_by, Some(GroupField::Program));
+
+        assert!(Args::try_parse_from(["test-bin", "--group-by", "port"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--group-by", "pid", "--json"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--group-by", "pid", "-k"]).is_err());
+    }
+
+    #[test]
+    fn test_disable_lookups() {
+        let mut args = Args::parse_from(["test-bin", "-n", "--resolve", "--services"]);
+        assert_eq!(disable_lookups(&mut args), vec!["--resolve", "--services"]);
+        assert!(args.numeric);
+        assert!(!args.resolve);
+        assert!(!args.services);
+
+        let mut args = Args::parse_from(["test-bin", "--numeric"]);
+        assert!(disable_lookups(&mut args).is_empty());
+
+        // without --numeric the lookups stay enabled
+        let mut args = Args::parse_from(["test-bin", "--resolve"]);
+        assert!(disable_lookups(&mut args).is_empty());
+        assert!(args.resolve);
+    }
+
+    #[test]
+    fn test_unresolved_only() {
+        assert!(
+            Args::try_parse_from(["test-bin", "--unresolved-only"])
+                .unwrap()
+                .unresolved_only
+        );
+        assert!(Args::try_parse_from(["test-bin", "--unresolved-only", "--pid", "1"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--unresolved-only", "-k"]).is_err());
+    }
+
+    #[test]
+    fn test_limit() {
+        let args = Args::try_parse_from(["test-bin", "--limit", "20", "--json"]).unwrap();
+        assert_eq!(args.limit, 20);
+
+        assert!(Args::try_parse_from(["test-bin", "--limit", "-1"]).is_err());
+        assert!(Args::try_parse_from(["test-bin", "--limit", "5", "--prometheus"]).is_err());
+    }
+
+    #[test]
+    fn test_unique() {
+        let args = Args::try_parse_from(["test-bin", "--unique", "--count-dupes"]).unwrap();
+        assert!(args.unique);
+        assert!(args.count_dupes);
+        assert!(Args::try_parse_from(["test-bin", "--count-dupes"]).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let args = Args::try_parse_from(["test-bin", "--diff", "before.json", "--json"]).unwrap();
+        assert_eq!(args.diff, Some(PathBuf::from("before.json")));
+        assert!(
+            Args::try_parse_from(["test-bin", "--diff", "before.json", "--watch", "5"]).is_err()
+        );
+        assert!(Args::try_parse_from(["test-bin", "--diff", "before.json", "-k"]).is_err());
+    }
+
+    #[test]
+    fn test_color_flag() {
+        let args = Args::try_parse_from(["test-bin", "--color", "never"]).unwrap();
+        assert_eq!(args.color, ColorMode::Never);
+        assert!(Args::try_parse_from(["test-bin", "--color", "sometimes"]).is_err());
+
+        let config = Config {
+            color: Some(ColorMode::Never),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_config(&["test-bin"], config).color,
+            ColorMode::Never
+        );
+
+        let config = Config {
+            color: Some(ColorMode::Never),
+            ..Default::default()
+        };
+        let args = parse_with_config(&["test-bin", "--color", "always"], config);
+        assert_eq!(args.color, ColorMode::Always);
+    }
+
+    #[test]
+    fn test_theme_flag() {
+        let args = Args::try_parse_from(["test-bin", "--theme", "high-contrast"]).unwrap();
+        assert_eq!(args.theme, Theme::HighContrast);
+        assert!(Args::try_parse_from(["test-bin", "--theme", "neon"]).is_err());
+
+        let args = Args::try_parse_from(["test-bin", "--border", "ascii"]).unwrap();
+        assert_eq!(args.border, Some(BorderStyle::Ascii));
+        assert!(Args::try_parse_from(["test-bin", "--border", "double"]).is_err());
+        let config = || Config {
+            border: Some(BorderStyle::None),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_config(&["test-bin", "--border", "ascii"], config()).border,
+            Some(BorderStyle::Ascii)
+        );
+        assert_eq!(
+            parse_with_config(&["test-bin"], config()).border,
+            Some(BorderStyle::None)
+        );
+
+        let config = Config {
+            theme: Some(Theme::Mono),
+            ..Default::default()
+        };
+        assert_eq!(parse_with_config(&["test-bin"], config).theme, Theme::Mono);
+    }
+
+    #[test]
+    fn test_config_flags() {
+        let args = Args::try_parse_from(["test-bin", "--config", "somo.toml"]).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("somo.toml")));
+        assert!(!args.no_config);
+
+        assert!(
+            Args::try_parse_from(["test-bin", "--no-config"])
+                .unwrap()
+                .no_config
+        );
+        assert!(
+            Args::try_parse_from(["test-bin", "--config", "somo.toml", "--no-config"]).is_err()
+        );
+    }
 }
@@ -0,0 +1,121 @@
+use clap::{Command, ValueEnum};
+use clap_complete::shells;
+
+use crate::connections;
+
+/// Represents the shells for which `--completions` generates a completion script.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Lets the completion scripts suggest a fixed set of values for a flag which accepts any text, e.g the protocols of `--proto`.
+/// The values only change the command the scripts are generated from, parsing the flag stays as lenient as before.
+///
+/// # Arguments
+/// * `command`: The clap command of the CLI.
+/// * `long`: The long name of the flag, flags which don't exist are skipped.
+/// * `values`: The values to suggest.
+///
+/// # Returns
+/// The command with the values set.
+fn suggest_values(command: Command, long: &str, values: &'static [&'static str]) -> Command {
+    if !command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some(long))
+    {
+        return command;
+    }
+    command.mut_arg(long, |arg| arg.value_parser(values.to_vec()))
+}
+
+/// Generates the completion script of a shell from the definition of the CLI using the "clap_complete" crate, so that new flags are completed without extra work.
+/// Besides the values of the enums, `--proto` and `--state` suggest the protocols and states somo knows.
+///
+/// # Arguments
+/// * `shell`: The shell provided with `--completions`.
+/// * `command`: The clap command of the CLI.
+///
+/// # Returns
+/// The completion script, to be sourced by the shell.
+pub fn generate(shell: Shell, command: Command) -> String {
+    let mut command = suggest_values(command, "proto", &connections::VALID_PROTOCOLS);
+    command = suggest_values(command, "state", &connections::VALID_STATES);
+    let name = command.get_name().to_string();
+
+    let mut script = Vec::new();
+    match shell {
+        Shell::Bash => clap_complete::generate(shells::Bash, &mut command, name, &mut script),
+        Shell::Zsh => clap_complete::generate(shells::Zsh, &mut command, name, &mut script),
+        Shell::Fish => clap_complete::generate(shells::Fish, &mut command, name, &mut script),
+        Shell::Powershell => {
+            clap_complete::generate(shells::PowerShell, &mut command, name, &mut script)
+        }
+    }
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli;
+
+    fn possible_values(command: &Command, long: &str) -> Vec<String> {
+        command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long))
+            .unwrap()
+            .get_possible_values()
+            .iter()
+            .map(|value| value.get_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_suggest_values() {
+        let command = suggest_values(cli::command(), "proto", &connections::VALID_PROTOCOLS);
+        assert_eq!(
+            possible_values(&command, "proto"),
+            connections::VALID_PROTOCOLS
+        );
+
+        // flags which don't exist are skipped instead of panicking
+        let command = suggest_values(command, "unknown", &connections::VALID_STATES);
+        assert!(!command
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some("unknown")));
+    }
+
+    #[test]
+    fn test_generate() {
+        let states = connections::VALID_STATES.join(" ");
+
+        let bash = generate(Shell::Bash, cli::command());
+        for flag in [
+            "--kill", "--proto", "--state", "--port", "--watch", "--json",
+        ] {
+            assert!(bash.contains(flag), "{flag} is missing");
+        }
+        assert!(bash.contains("tcp udp tcp4 udp4 tcp6 udp6"));
+        assert!(bash.contains(&states));
+
+        let zsh = generate(Shell::Zsh, cli::command());
+        assert!(zsh.starts_with("#compdef somo\n"));
+        assert!(zsh.contains("--proto"));
+
+        let fish = generate(Shell::Fish, cli::command());
+        assert!(fish.contains("complete -c somo -s k -l kill"));
+        assert!(fish.contains("-l proto"));
+        assert!(fish.contains("-l state"));
+        assert!(fish.contains("tcp6"));
+        assert!(fish.contains("established"));
+
+        let powershell = generate(Shell::Powershell, cli::command());
+        assert!(powershell.contains("Register-ArgumentCompleter"));
+        assert!(powershell.contains("'--proto'"));
+        assert!(powershell.contains("'--state'"));
+    }
+}
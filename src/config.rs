@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::schemas::{AddressFamily, BorderStyle, ColorMode, SortField, Theme};
+
+/// Represents the default flag values which can be set in the config file.
+/// Every field is optional and flags provided in the CLI always override the values of the config file.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub signal: Option<String>,
+    pub proto: Option<String>,
+    pub ip: Option<String>,
+    pub local_ip: Option<String>,
+    pub remote_port: Option<String>,
+    pub port: Option<String>,
+    pub program: Option<String>,
+    pub user: Option<String>,
+    pub state: Option<String>,
+    pub open: Option<bool>,
+    pub listen: Option<bool>,
+    pub include: Option<String>,
+    pub family: Option<AddressFamily>,
+    pub exclude_ipv4: Option<bool>,
+    pub exclude_ipv6: Option<bool>,
+    pub exclude_program: Option<String>,
+    pub exclude_ip: Option<String>,
+    pub exclude_port: Option<String>,
+    pub resolve: Option<bool>,
+    pub long: Option<bool>,
+    pub geoip: Option<PathBuf>,
+    pub columns: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: Option<bool>,
+    pub color: Option<ColorMode>,
+    pub theme: Option<Theme>,
+    pub border: Option<BorderStyle>,
+}
+
+/// Gets the path of the default config file, ie. `~/.config/somo/config.toml` or `$XDG_CONFIG_HOME/somo/config.toml`.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The path of the config file or `None` if the home directory is unknown.
+fn get_default_config_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("somo").join("config.toml"))
+}
+
+/// Parses the content of a config file using the "toml" crate.
+///
+/// # Arguments
+/// * `content`: The TOML content of the config file.
+///
+/// # Example
+/// ```
+/// let config = parse_config("exclude_ipv6 = true").unwrap();
+/// assert_eq!(config.exclude_ipv6, Some(true));
+/// ```
+///
+/// # Returns
+/// The parsed config or an error message pointing to the offending line.
+fn parse_config(content: &str) -> Result<Config, String> {
+    toml::from_str(content).map_err(|error| error.to_string())
+}
+
+/// Loads the config file, a missing config file is treated like an empty one.
+///
+/// # Arguments
+/// * `path`: The path of the config file provided by the user, `None` to use the default path.
+///
+/// # Returns
+/// The config or an error message if the file is unreadable or malformed.
+pub fn load_config(path: Option<&Path>) -> Result<Config, String> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => match get_default_config_path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        },
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+        Err(error) => {
+            return Err(format!(
+                "Couldn't read config file {}: {}",
+                path.display(),
+                error
+            ))
+        }
+    };
+
+    parse_config(&content)
+        .map_err(|error| format!("Invalid config file {}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(
+            r#"
+            exclude_ipv6 = true
+            sort = "local_port"
+            exclude_port = "22,8000-9000"
+            border = "ascii"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.exclude_ipv6, Some(true));
+        assert_eq!(config.sort, Some(SortField::LocalPort));
+        assert_eq!(config.exclude_port.as_deref(), Some("22,8000-9000"));
+        assert_eq!(config.border, Some(BorderStyle::Ascii));
+        assert!(config.proto.is_none());
+    }
+
+    #[test]
+    fn test_parse_config_empty() {
+        assert_eq!(parse_config("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_config_malformed() {
+        let error = parse_config("exclude_ipv6 = true\nsort = \n").unwrap_err();
+        assert!(error.contains("line 2"), "{}", error);
+
+        let error = parse_config("exclude_ipv7 = true").unwrap_err();
+        assert!(error.contains("exclude_ipv7"), "{}", error);
+    }
+
+    #[test]
+    fn test_load_config_missing() {
+        let config = load_config(Some(Path::new("/nonexistent/somo/config.toml"))).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -1,36 +1,392 @@
-use procfs::process::FDTarget;
-use procfs::process::Stat;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
+use crate::error::SomoError;
+use crate::schemas::AddressFamily;
 use crate::schemas::AddressType;
 use crate::schemas::Connection;
+use crate::schemas::ConnectionGroup;
+use crate::schemas::DuplicateKey;
+use crate::schemas::Exposure;
 use crate::schemas::FilterOptions;
+use crate::schemas::GroupField;
 use crate::schemas::NetEntry;
+use crate::schemas::PidFilter;
+use crate::schemas::ProcessInfo;
+use crate::schemas::ProgramMatcher;
+use crate::schemas::SortField;
+use crate::schemas::StateCategory;
+use crate::schemas::TimestampFormat;
 use crate::utils;
 
-/// Gets all running processes on the system using the "procfs" crate.
-/// This code is taken from the "procfs" crate documentation.
+#[cfg(unix)]
+use nix::unistd::{Uid, User};
+
+#[cfg(target_os = "linux")]
+mod cache;
+#[cfg(target_os = "linux")]
+mod containers;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "linux")]
+mod netns;
+#[cfg(target_os = "linux")]
+mod stats;
+#[cfg(target_os = "linux")]
+mod units;
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "linux")]
+use self::linux::Linux as CurrentPlatform;
+#[cfg(target_os = "macos")]
+use self::macos::MacOs as CurrentPlatform;
+#[cfg(target_os = "windows")]
+use self::windows::Windows as CurrentPlatform;
+
+#[cfg(target_os = "linux")]
+pub use self::containers::{annotate_containers, parse_container_id};
+
+/// The share of connections without a known process from which on a normal user is told to run somo with sudo.
+#[cfg(unix)]
+const PRIVILEGE_HINT_THRESHOLD: f64 = 0.25;
+
+/// Whether the hint about unreadable environments was already shown, so that the refreshes of the watch mode don't repeat it.
+static ENV_HINT_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Containers are only detected on Linux, on all other platforms no connection has a container.
 ///
 /// # Arguments
+/// * `_connections`: The connections in which the `container` stays unset.
+///
+/// # Returns
 /// None
+#[cfg(not(target_os = "linux"))]
+pub fn annotate_containers(_connections: &mut [Connection]) {}
+
+#[cfg(target_os = "linux")]
+pub use self::units::{annotate_units, parse_unit};
+
+/// Systemd units are only detected on Linux, on all other platforms no connection has a unit.
+///
+/// # Arguments
+/// * `_connections`: The connections in which the `unit` stays unset.
 ///
 /// # Returns
-/// A map of all current processes.
-fn get_processes() -> HashMap<u64, Stat> {
-    let all_procs = procfs::process::all_processes().unwrap();
+/// None
+#[cfg(not(target_os = "linux"))]
+pub fn annotate_units(_connections: &mut [Connection]) {}
 
-    let mut map: HashMap<u64, Stat> = HashMap::new();
-    for p in all_procs {
-        let process = p.unwrap();
-        if let (Ok(stat), Ok(fds)) = (process.stat(), process.fd()) {
-            for fd in fds {
-                if let FDTarget::Socket(inode) = fd.unwrap().target {
-                    map.insert(inode, stat.clone());
-                }
-            }
+#[cfg(target_os = "linux")]
+pub use self::stats::annotate_stats;
+
+/// The byte counters of sockets are only read on Linux, on all other platforms they are shown as unavailable.
+///
+/// # Arguments
+/// * `_connections`: The connections in which `rx_bytes` and `tx_bytes` stay unset.
+///
+/// # Returns
+/// None
+#[cfg(not(target_os = "linux"))]
+pub fn annotate_stats(_connections: &mut [Connection]) {}
+
+/// Keeps only the connections of a container, matching either its name or the beginning of its ID, both ignoring the case.
+///
+/// # Arguments
+/// * `connections`: The connections annotated by `annotate_containers`.
+/// * `container`: The container name or ID provided by the user.
+///
+/// # Returns
+/// None
+pub fn filter_by_container(connections: &mut Vec<Connection>, container: &str) {
+    let prefix = container.to_ascii_lowercase();
+    connections.retain(|connection| {
+        connection
+            .container
+            .as_ref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(container))
+            || connection
+                .container_id
+                .as_ref()
+                .is_some_and(|id| id.to_ascii_lowercase().starts_with(&prefix))
+    });
+}
+
+/// Keeps only the connections of a systemd unit, matching its name with or without the ".service" suffix.
+///
+/// # Arguments
+/// * `connections`: The connections annotated by `annotate_units`.
+/// * `unit`: The unit name provided by the user, e.g "nginx" or "nginx.service".
+///
+/// # Returns
+/// None
+pub fn filter_by_unit(connections: &mut Vec<Connection>, unit: &str) {
+    connections.retain(|connection| {
+        connection.unit.as_ref().is_some_and(|name| {
+            name.eq_ignore_ascii_case(unit)
+                || name
+                    .strip_suffix(".service")
+                    .is_some_and(|name| name.eq_ignore_ascii_case(unit))
+        })
+    });
+}
+
+/// The operating system specific part of collecting connections, ie. reading the socket tables and the processes which own the sockets.
+/// Processing and filtering the sockets is shared by all platforms.
+pub trait Platform {
+    /// Gets all running processes which own sockets, keyed by the `owner` of the socket entries.
+    /// Processes which can't be accessed are left out, their sockets are shown without a process.
+    /// If `numeric_users` is set the users are their UID, their names aren't looked up.
+    fn get_processes(&self, numeric_users: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError>;
+
+    /// Gets the processes like `get_processes`, but only the process with this PID, e.g for `--pid 4321`.
+    /// By default all processes are read and the others are dropped, Linux only reads the sockets of this one process.
+    fn get_process(
+        &self,
+        pid: i32,
+        numeric_users: bool,
+    ) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+        let mut processes = self.get_processes(numeric_users)?;
+        processes.retain(|_, process| process.pid == pid);
+        Ok(processes)
+    }
+
+    /// Gets all IPv4 and IPv6 TCP sockets.
+    fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError>;
+
+    /// Gets all IPv4 and IPv6 UDP sockets.
+    fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError>;
+
+    /// Gets the parent of every running process, keyed by the PID of the process.
+    /// Only Linux reads the parents, on the other platforms no process has a known parent.
+    fn get_parent_pids(&self) -> Result<HashMap<i32, i32>, SomoError> {
+        Ok(HashMap::new())
+    }
+
+    /// Gets the environment variables a process was started with, `None` if they can't be read, e.g because the process belongs to another user.
+    /// Only Linux reads the environment, on the other platforms it is never known.
+    fn get_environment(&self, _pid: i32) -> Option<HashMap<String, String>> {
+        None
+    }
+}
+
+/// All connection states which can be used with the `--state` filter.
+pub const VALID_STATES: [&str; 11] = [
+    "established",
+    "syn-sent",
+    "syn-recv",
+    "fin-wait1",
+    "fin-wait2",
+    "time-wait",
+    "close",
+    "close-wait",
+    "last-ack",
+    "listen",
+    "closing",
+];
+
+/// All protocols which can be used with the `--proto` filter, the "4" and "6" variants only match IPv4 or IPv6 sockets.
+pub const VALID_PROTOCOLS: [&str; 6] = ["tcp", "udp", "tcp4", "udp4", "tcp6", "udp6"];
+
+/// Normalizes a protocol so that the user input and the protocol of a socket can be compared.
+///
+/// # Arguments
+/// * `proto`: The protocol to normalize, e.g "TCP" or " udp6".
+///
+/// # Example
+/// ```
+/// # use somo::connections::normalize_protocol;
+/// assert_eq!(normalize_protocol("TCP"), "tcp");
+/// assert_eq!(normalize_protocol(" Udp6"), "udp6");
+/// ```
+///
+/// # Returns
+/// The trimmed lowercase protocol.
+pub fn normalize_protocol(proto: &str) -> String {
+    proto.trim().to_ascii_lowercase()
+}
+
+/// Checks if a protocol provided by the user is one of the protocols of the `--proto` filter, ignoring its case.
+///
+/// # Arguments
+/// * `proto`: The protocol provided by the user.
+///
+/// # Returns
+/// `true` if the protocol is known, `false` if not.
+pub fn is_valid_protocol(proto: &str) -> bool {
+    VALID_PROTOCOLS.contains(&normalize_protocol(proto).as_str())
+}
+
+/// Normalizes a connection state so that the user input and the state read from the kernel can be compared.
+///
+/// # Arguments
+/// * `state`: The state to normalize, e.g "TIME_WAIT", "time-wait" or "timewait".
+///
+/// # Example
+/// ```
+/// # use somo::connections::normalize_state;
+/// assert_eq!(normalize_state("TIME_WAIT"), "timewait");
+/// assert_eq!(normalize_state("time-wait"), "timewait");
+/// ```
+///
+/// # Returns
+/// The lowercase state without any "-" or "_" separators.
+pub fn normalize_state(state: &str) -> String {
+    state
+        .chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Checks if a state provided by the user is one of the known connection states.
+///
+/// # Arguments
+/// * `state`: The state provided by the user.
+///
+/// # Returns
+/// `true` if the state is known, `false` if not.
+pub fn is_valid_state(state: &str) -> bool {
+    let state = normalize_state(state);
+    VALID_STATES
+        .iter()
+        .any(|valid_state| normalize_state(valid_state) == state)
+}
+
+/// Maps a connection state to its high-level category used by the `--include` filter.
+///
+/// # Arguments
+/// * `state`: The state of a connection, e.g "time-wait" or "timewait".
+///
+/// # Example
+/// ```
+/// # use somo::connections::state_category;
+/// # use somo::schemas::StateCategory;
+/// assert_eq!(state_category("TIME_WAIT"), Some(StateCategory::Waiting));
+/// assert_eq!(state_category("close"), None);
+/// ```
+///
+/// # Returns
+/// The category or `None` for closed sockets (e.g UDP sockets) and unknown states.
+pub fn state_category(state: &str) -> Option<StateCategory> {
+    match normalize_state(state).as_str() {
+        "listen" => Some(StateCategory::Listening),
+        "established" => Some(StateCategory::Established),
+        "synsent" | "synrecv" | "timewait" => Some(StateCategory::Waiting),
+        "finwait1" | "finwait2" | "closewait" | "lastack" | "closing" => {
+            Some(StateCategory::Closing)
         }
+        _ => None,
+    }
+}
+
+/// Brings the user of the `--user` filter into the form in which the users of the connections are shown, so that both a name and a UID match.
+/// With `numeric_users` the users are their UID, so a name is turned into its UID, otherwise a UID is turned into the name of its user.
+/// Unknown users and UIDs without a name are kept as they are, on Windows no user is known.
+///
+/// # Arguments
+/// * `user`: The user provided by the user, e.g "root" or "0".
+/// * `numeric_users`: Whether the users are shown by their UID, e.g for `--numeric`.
+///
+/// # Example
+/// ```
+/// # use somo::connections::resolve_user_filter;
+/// # #[cfg(unix)]
+/// assert_eq!(resolve_user_filter("root", true), "0");
+/// ```
+///
+/// # Returns
+/// The user to compare with the users of the connections.
+pub fn resolve_user_filter(user: &str, numeric_users: bool) -> String {
+    #[cfg(unix)]
+    {
+        let uid = user.parse::<u32>().ok();
+        let resolved = match (numeric_users, uid) {
+            (true, None) => User::from_name(user)
+                .ok()
+                .flatten()
+                .map(|found| found.uid.to_string()),
+            (false, Some(uid)) => User::from_uid(Uid::from_raw(uid))
+                .ok()
+                .flatten()
+                .map(|found| found.name),
+            _ => None,
+        };
+        resolved.unwrap_or_else(|| user.to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = numeric_users;
+        user.to_string()
+    }
+}
+
+/// Gets the name of a user by its UID, falling back to the UID itself if the user has no name or the users are numeric.
+///
+/// # Arguments
+/// * `uid`: The UID of the user.
+/// * `user_names`: A cache of already looked up user names.
+/// * `numeric_users`: Whether to skip the lookup and use the UID, e.g for `--numeric`.
+///
+/// # Returns
+/// The user name.
+#[cfg(unix)]
+fn get_user_name(uid: u32, user_names: &mut HashMap<u32, String>, numeric_users: bool) -> String {
+    if numeric_users {
+        return uid.to_string();
     }
-    map
+    user_names
+        .entry(uid)
+        .or_insert_with(|| match User::from_uid(Uid::from_raw(uid)) {
+            Ok(Some(user)) => user.name,
+            _ => uid.to_string(),
+        })
+        .to_string()
+}
+
+/// Checks if a program name contains a filter value, ignoring the case.
+///
+/// # Arguments
+/// * `program`: The program name of a connection.
+/// * `filter_program`: The program name provided by the user.
+///
+/// # Example
+/// ```ignore
+/// assert!(matches_program("Chrome", "chrome"));
+/// assert!(matches_program("postgres", "gres"));
+/// ```
+///
+/// # Returns
+/// `true` if the program name matches, `false` if not.
+fn matches_program(program: &str, filter_program: &str) -> bool {
+    program
+        .to_lowercase()
+        .contains(&filter_program.to_lowercase())
+}
+
+/// Checks if the executable of a connection's process matches the program filter, only if `--match-path` was given.
+///
+/// # Arguments
+/// * `connection`: The connection to check.
+/// * `filter_program`: The program filter provided by the user.
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// `true` if the path is matched and matches the filter, `false` if not or if the path is unknown.
+fn matches_exe_path(
+    connection: &Connection,
+    filter_program: &ProgramMatcher,
+    filter_options: &FilterOptions,
+) -> bool {
+    filter_options.match_path
+        && connection
+            .exe_path
+            .as_ref()
+            .is_some_and(|path| filter_program.matches(path))
 }
 
 /// Checks if a connection should be filtered out based on options provided by the user.
@@ -38,36 +394,68 @@ fn get_processes() -> HashMap<u64, Stat> {
 /// # Arguments
 /// * `connection_details`: The connection to check for filtering.
 /// * `filter_options`: The filter options provided by the user.
+/// * `now`: The current time as a Unix timestamp in seconds, read once per read of the sockets so that `--since` uses the same cutoff for all connections.
 ///
 /// # Returns
 /// `true` if the connection should be filtered out, `false` if not.
-fn filter_out_connection(connection_details: &Connection, filter_options: &FilterOptions) -> bool {
+fn filter_out_connection(
+    connection_details: &Connection,
+    filter_options: &FilterOptions,
+    now: u64,
+) -> bool {
     match &filter_options.by_remote_port {
-        Some(filter_remote_port) if &connection_details.remote_port != filter_remote_port => {
+        Some(filter_remote_port)
+            if !filter_remote_port.matches(&connection_details.remote_port) =>
+        {
             return true
         }
         _ => {}
     }
     match &filter_options.by_local_port {
-        Some(filter_local_port) if &connection_details.local_port != filter_local_port => {
+        Some(filter_local_port) if !filter_local_port.matches(&connection_details.local_port) => {
             return true
         }
         _ => {}
     }
     match &filter_options.by_remote_address {
         Some(filter_remote_address)
-            if &connection_details.remote_address != filter_remote_address =>
+            if !filter_remote_address.matches(&connection_details.remote_address) =>
+        {
+            return true
+        }
+        _ => {}
+    }
+    match &filter_options.by_local_address {
+        Some(filter_local_address)
+            if !filter_local_address.matches(&connection_details.local_address) =>
         {
             return true
         }
         _ => {}
     }
     match &filter_options.by_program {
-        Some(filter_program) if &connection_details.program != filter_program => return true,
+        Some(filter_program)
+            if !filter_program.matches(&connection_details.program)
+                && !matches_exe_path(connection_details, filter_program, filter_options) =>
+        {
+            return true
+        }
         _ => {}
     }
     match &filter_options.by_pid {
-        Some(filter_pid) if &connection_details.pid != filter_pid => return true,
+        Some(filter_pid) if !filter_pid.matches(&connection_details.pid) => return true,
+        _ => {}
+    }
+    match &filter_options.by_user {
+        Some(filter_user) if connection_details.user.as_ref() != Some(filter_user) => return true,
+        _ => {}
+    }
+    match &filter_options.by_state {
+        Some(filter_state)
+            if normalize_state(&connection_details.state) != normalize_state(filter_state) =>
+        {
+            return true
+        }
         _ => {}
     }
     if filter_options.by_listen && connection_details.state != "listen" {
@@ -76,183 +464,1604 @@ fn filter_out_connection(connection_details: &Connection, filter_options: &Filte
     if filter_options.by_open && connection_details.state == "close" {
         return true;
     }
+    if !filter_options.by_include.is_empty()
+        && !state_category(&connection_details.state)
+            .is_some_and(|category| filter_options.by_include.contains(&category))
+    {
+        return true;
+    }
+    if filter_options.by_unresolved && connection_details.pid != "-" {
+        return true;
+    }
+    if let Some(since) = filter_options.by_since {
+        if !is_recent(
+            connection_details.created,
+            now,
+            since,
+            filter_options.include_unknown_age,
+        ) {
+            return true;
+        }
+    }
+    match filter_options.by_privileged {
+        Some(privileged) if is_privileged_port(&connection_details.local_port) != privileged => {
+            return true
+        }
+        _ => {}
+    }
+    match filter_options.by_exposure {
+        Some(filter_exposure)
+            if get_exposure(&connection_details.local_address) != Some(filter_exposure) =>
+        {
+            return true
+        }
+        _ => {}
+    }
+
+    // exclude filters win over all the filters above
+    if filter_options.exclude_self && connection_details.pid == std::process::id().to_string() {
+        return true;
+    }
+    match &filter_options.exclude_program {
+        Some(exclude_program) if matches_program(&connection_details.program, exclude_program) => {
+            return true
+        }
+        _ => {}
+    }
+    match &filter_options.exclude_remote_address {
+        Some(exclude_remote_address)
+            if exclude_remote_address.matches(&connection_details.remote_address) =>
+        {
+            return true
+        }
+        _ => {}
+    }
+    match &filter_options.exclude_local_port {
+        Some(exclude_local_port) if exclude_local_port.matches(&connection_details.local_port) => {
+            return true
+        }
+        _ => {}
+    }
 
-    return false;
+    false
 }
 
-/// Checks if a given IP address is either "unspecified", localhost or an extern address.
-///
-/// * `0.0.0.0` or `[::]` -> unspecified
-/// * `127.0.0.1` or `[::1]` -> localhost
-/// * else -> extern address
+/// Checks if a port is privileged, ie. below 1024, so that only root can bind it on most systems.
+/// Port 0 means that the socket isn't bound to a port yet and isn't privileged.
 ///
 /// # Arguments
-/// * `remote_address`: The address to be checked.
+/// * `port`: The port of a connection.
+///
+/// # Example
+/// ```
+/// # use somo::connections::is_privileged_port;
+/// assert!(is_privileged_port("443"));
+/// assert!(!is_privileged_port("8080"));
+/// assert!(!is_privileged_port("-"));
+/// ```
 ///
 /// # Returns
-/// The address-type as an AddressType enum.
-fn get_address_type(remote_address: &str) -> AddressType {
-    if remote_address == "127.0.0.1" || remote_address == "[::1]" {
-        return AddressType::Localhost;
-    } else if remote_address == "0.0.0.0" || remote_address == "[::]" {
-        return AddressType::Unspecified;
-    }
-    AddressType::Extern
+/// `true` if the port is privileged, `false` if not or if it isn't a port number.
+pub fn is_privileged_port(port: &str) -> bool {
+    port.parse::<u16>()
+        .is_ok_and(|port| (1..1024).contains(&port))
 }
 
-fn get_connection_data(net_entry: NetEntry, all_processes: &HashMap<u64, Stat>) -> Connection {
-    // process the remote-address and remote-port by spliting them at ":"
-    let (_, local_port) = utils::get_address_parts(&format!("{}", net_entry.local_address));
-    let (remote_address, remote_port) =
-        utils::get_address_parts(&format!("{}", net_entry.remote_address));
-    let state = net_entry.state;
+/// Finds filters which contradict each other, so that no connection can ever match all of them.
+/// Only combinations which are impossible regardless of the sockets are reported, unusual but valid ones, e.g `--open --listen`, are not.
+///
+/// # Arguments
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Example
+/// ```
+/// # use somo::connections::find_contradictions;
+/// # use somo::schemas::FilterOptions;
+/// let filter_options = FilterOptions {
+///     by_state: Some("established".to_string()),
+///     by_listen: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(find_contradictions(&filter_options), vec!["--state established contradicts --listen"]);
+/// ```
+///
+/// # Returns
+/// A description of every contradiction, e.g "--state established contradicts --listen", empty if there is none.
+pub fn find_contradictions(filter_options: &FilterOptions) -> Vec<String> {
+    let mut contradictions = Vec::new();
+    let protocols: Vec<String> = filter_options
+        .by_proto
+        .iter()
+        .map(|proto| normalize_protocol(proto))
+        .collect();
+    let proto_flag = format!("--proto {}", protocols.join(","));
+    let only_udp = !protocols.is_empty() && protocols.iter().all(|proto| proto.starts_with("udp"));
 
-    // check if there is no program/pid information
-    let (program, pid) = all_processes
-        .get(&net_entry.inode)
-        .map(|stat| (stat.comm.to_string(), stat.pid.to_string()))
-        .unwrap_or(("-".to_string(), "-".to_string()));
+    if let Some(state) = &filter_options.by_state {
+        let state_flag = format!("--state {}", state);
+        let category = state_category(state);
+        if filter_options.by_listen && category != Some(StateCategory::Listening) {
+            contradictions.push(format!("{} contradicts --listen", state_flag));
+        }
+        if filter_options.by_open && normalize_state(state) == "close" {
+            contradictions.push(format!("{} contradicts --open", state_flag));
+        }
+        if !filter_options.by_include.is_empty()
+            && !category.is_some_and(|category| filter_options.by_include.contains(&category))
+        {
+            contradictions.push(format!("{} contradicts --include", state_flag));
+        }
+        // UDP sockets are either unconnected ("close") or connected ("established")
+        let is_udp_state = ["close", "established"].contains(&normalize_state(state).as_str());
+        if only_udp && !is_udp_state {
+            contradictions.push(format!(
+                "{} contradicts {}, UDP sockets have no such state",
+                state_flag, proto_flag
+            ));
+        }
+    }
 
-    let address_type: AddressType = get_address_type(&remote_address);
+    if filter_options.by_listen {
+        if !filter_options.by_include.is_empty()
+            && !filter_options
+                .by_include
+                .contains(&StateCategory::Listening)
+        {
+            contradictions.push("--listen contradicts --include".to_string());
+        }
+        if only_udp {
+            contradictions.push(format!(
+                "--listen contradicts {}, UDP sockets never listen",
+                proto_flag
+            ));
+        }
+        // listening sockets have no peer, their remote port is 0
+        if filter_options
+            .by_remote_port
+            .as_ref()
+            .is_some_and(|remote_port| !remote_port.matches("0"))
+        {
+            contradictions.push(
+                "--remote-port contradicts --listen, listening sockets have no remote port"
+                    .to_string(),
+            );
+        }
+    }
 
-    let connection: Connection = Connection {
-        proto: net_entry.protocol,
-        local_port,
-        remote_address: remote_address.to_string(),
-        remote_port,
-        program,
-        pid,
-        state,
-        address_type,
+    let family_suffix = match filter_options.by_family {
+        AddressFamily::Inet => Some(("6", "inet")),
+        AddressFamily::Inet6 => Some(("4", "inet6")),
+        AddressFamily::Any => None,
     };
+    if let Some((suffix, family)) = family_suffix {
+        if !protocols.is_empty() && protocols.iter().all(|proto| proto.ends_with(suffix)) {
+            contradictions.push(format!("{} contradicts --family {}", proto_flag, family));
+        }
+    }
 
-    return connection;
+    contradictions
 }
 
-/// Gets all currently open TCP connections using the "procfs" crate and processes them.
+/// Turns an IPv4-mapped IPv6 address, e.g `[::ffff:192.168.1.1]:80`, into its IPv4 form, since it is IPv4 traffic over an IPv6 socket.
+/// All other addresses are kept as they are.
 ///
 /// # Arguments
-/// * `all_processes`: A map of all running processes on the system.
-/// * `filter_options`: The filter options provided by the user.
+/// * `address`: The address of a socket.
+///
+/// # Example
+/// ```ignore
+/// let mapped: SocketAddr = "[::ffff:192.168.1.1]:80".parse().unwrap();
+/// assert_eq!(unmap_ipv4_address(mapped), "192.168.1.1:80".parse().unwrap());
+/// ```
 ///
 /// # Returns
-/// All processed and filtered TCP connections as a `Connection` struct in a vector.
-fn get_tcp_connections(
-    all_processes: &HashMap<u64, Stat>,
-    filter_options: &FilterOptions,
-) -> Vec<Connection> {
-    let mut tcp_entries = procfs::net::tcp().unwrap();
-    if !filter_options.exclude_ipv6 {
-        tcp_entries.extend(procfs::net::tcp6().unwrap());
+/// The IPv4 address if the address is IPv4-mapped, otherwise the address itself.
+fn unmap_ipv4_address(address: SocketAddr) -> SocketAddr {
+    match address {
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(ipv4) => SocketAddr::new(ipv4.into(), v6.port()),
+            None => address,
+        },
+        SocketAddr::V4(_) => address,
     }
-
-    return tcp_entries
-        .iter()
-        .filter_map(|entry| {
-            let tcp_entry: NetEntry = NetEntry {
-                protocol: "tcp".to_string(),
-                local_address: entry.local_address,
-                remote_address: entry.remote_address,
-                state: format!("{:?}", entry.state).to_ascii_lowercase(),
-                inode: entry.inode,
-            };
-            let connection = get_connection_data(tcp_entry, all_processes);
-
-            let filter_connection: bool = filter_out_connection(&connection, filter_options);
-            if !filter_connection {
-                Some(connection)
-            } else {
-                None
-            }
-        })
-        .collect();
 }
 
-/// Gets all currently open UDP connections using the "procfs" crate and processes them.
-/// ###### TODO: combine with the `get_tcp_connections` function if possible.
+/// Checks if a socket should be filtered out because its address family (IPv4 or IPv6) isn't the one selected with `--family`.
 ///
 /// # Arguments
-/// * `all_processes`: A map of all running processes on the system.
+/// * `address`: The local address of the socket.
 /// * `filter_options`: The filter options provided by the user.
 ///
 /// # Returns
-/// All processed and filtered UDP connections as a `Connection` struct in a vector.
-fn get_udp_connections(
-    all_processes: &HashMap<u64, Stat>,
-    filter_options: &FilterOptions,
-) -> Vec<Connection> {
-    let mut udp_entries = procfs::net::udp().unwrap();
-    if !filter_options.exclude_ipv6 {
-        udp_entries.extend(procfs::net::udp6().unwrap());
+/// `true` if the socket should be filtered out, `false` if not.
+fn filter_out_address_family(address: &SocketAddr, filter_options: &FilterOptions) -> bool {
+    match (address, filter_options.by_family) {
+        (_, AddressFamily::Any) => false,
+        (SocketAddr::V4(_), family) => family != AddressFamily::Inet,
+        (SocketAddr::V6(_), family) => family != AddressFamily::Inet6,
     }
-
-    return udp_entries
-        .iter()
-        .filter_map(|entry| {
-            let udp_entry: NetEntry = NetEntry {
-                protocol: "udp".to_string(),
-                local_address: entry.local_address,
-                remote_address: entry.remote_address,
-                state: format!("{:?}", entry.state).to_ascii_lowercase(),
-                inode: entry.inode,
-            };
-            let connection: Connection = get_connection_data(udp_entry, all_processes);
-
-            let filter_connection: bool = filter_out_connection(&connection, filter_options);
-            if !filter_connection {
-                Some(connection)
-            } else {
-                None
-            }
-        })
-        .collect();
 }
 
-/// Gets both TCP and UDP connections and combines them based on the `proto` filter option.
+/// Checks if a socket should be filtered out because its protocol isn't one of the protocols provided by the user.
 ///
 /// # Arguments
+/// * `entry`: The socket entry to check.
 /// * `filter_options`: The filter options provided by the user.
 ///
 /// # Returns
-/// All processed and filtered TCP/UDP connections as a `Connection` struct in a vector.
-pub fn get_all_connections(filter_options: &FilterOptions) -> Vec<Connection> {
-    let all_processes = get_processes();
-
-    let mut connections = Vec::new();
-
-    match filter_options.by_proto.as_deref() {
-        Some("tcp") => connections.extend(get_tcp_connections(&all_processes, filter_options)),
-        Some("udp") => connections.extend(get_udp_connections(&all_processes, filter_options)),
-        _ => {
-            connections.extend(get_tcp_connections(&all_processes, filter_options));
-            connections.extend(get_udp_connections(&all_processes, filter_options));
-        }
+/// `true` if the socket should be filtered out, `false` if not.
+fn filter_out_protocol(entry: &NetEntry, filter_options: &FilterOptions) -> bool {
+    if filter_options.by_proto.is_empty() {
+        return false;
     }
 
-    return connections;
+    let protocol = normalize_protocol(&entry.protocol);
+    !filter_options.by_proto.iter().any(|proto| {
+        match normalize_protocol(proto).strip_prefix(protocol.as_str()) {
+            Some("") => true,
+            Some("4") => entry.local_address.is_ipv4(),
+            Some("6") => entry.local_address.is_ipv6(),
+            _ => false,
+        }
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_get_address_type() {
-        use crate::schemas::AddressType;
+/// Checks if a given IP address is either "unspecified", localhost or an extern address.
+///
+/// * `0.0.0.0` or `[::]` -> unspecified
+/// * `127.0.0.1` or `[::1]` -> localhost
+/// * else -> extern address
+///
+/// # Arguments
+/// * `remote_address`: The address to be checked.
+///
+/// # Returns
+/// The address-type as an AddressType enum.
+pub fn get_address_type(remote_address: &str) -> AddressType {
+    if remote_address == "127.0.0.1" || remote_address == "[::1]" {
+        return AddressType::Localhost;
+    } else if remote_address == "0.0.0.0" || remote_address == "[::]" {
+        return AddressType::Unspecified;
+    }
+    AddressType::Extern
+}
+
+/// Classifies from where a socket can be reached by its local address.
+///
+/// * `127.0.0.1` or `[::1]` -> loopback
+/// * `0.0.0.0` or `[::]` -> all interfaces
+/// * private and link-local addresses, e.g `10.0.0.5` or `[fe80::1%eth0]` -> private
+/// * else -> public
+///
+/// # Arguments
+/// * `local_address`: The local address of the socket.
+///
+/// # Example
+/// ```
+/// # use somo::connections::get_exposure;
+/// # use somo::schemas::Exposure;
+/// assert_eq!(get_exposure("0.0.0.0"), Some(Exposure::AllInterfaces));
+/// assert_eq!(get_exposure("192.168.0.10"), Some(Exposure::Private));
+/// ```
+///
+/// # Returns
+/// The exposure of the socket or `None` if the local address isn't a valid IP.
+pub fn get_exposure(local_address: &str) -> Option<Exposure> {
+    let ip = utils::parse_ip(local_address)?;
+    let exposure = if ip.is_loopback() {
+        Exposure::Loopback
+    } else if ip.is_unspecified() {
+        Exposure::AllInterfaces
+    } else if utils::is_public_ip(&ip) {
+        Exposure::Public
+    } else {
+        Exposure::Private
+    };
+    Some(exposure)
+}
+
+/// Sets the exposure of all connections, see `get_exposure`.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `exposure`.
+///
+/// # Returns
+/// None
+pub fn annotate_exposure(connections: &mut [Connection]) {
+    for connection in connections.iter_mut() {
+        connection.exposure = get_exposure(&connection.local_address);
+    }
+}
+
+/// Gets the addresses of the network interfaces of this host, e.g to tell whether a remote address is this host.
+///
+/// # Returns
+/// The IPv4 and IPv6 addresses of all interfaces, empty if they can't be read.
+#[cfg(unix)]
+pub fn get_interface_addresses() -> HashSet<IpAddr> {
+    let Ok(interfaces) = nix::ifaddrs::getifaddrs() else {
+        return HashSet::new();
+    };
+    interfaces
+        .filter_map(|interface| {
+            let address = interface.address?;
+            address
+                .as_sockaddr_in()
+                .map(|address| IpAddr::V4(address.ip()))
+                .or_else(|| {
+                    address
+                        .as_sockaddr_in6()
+                        .map(|address| IpAddr::V6(address.ip()))
+                })
+        })
+        .collect()
+}
+
+/// The interface addresses aren't read on Windows, only loopback connections are detected as local there.
+///
+/// # Returns
+/// An empty set.
+#[cfg(not(unix))]
+pub fn get_interface_addresses() -> HashSet<IpAddr> {
+    HashSet::new()
+}
+
+/// Checks whether both endpoints of a connection are on this host, ie. loopback addresses or addresses of its interfaces.
+/// Sockets without a peer, e.g listening sockets, are never local.
+///
+/// # Arguments
+/// * `connection`: The connection to check.
+/// * `interface_addresses`: The addresses of the interfaces of this host, see `get_interface_addresses`.
+///
+/// # Example
+/// ```
+/// # use somo::connections::is_local_connection;
+/// # use somo::schemas::Connection;
+/// # use std::collections::HashSet;
+/// let connection = Connection {
+///     local_address: "127.0.0.1".to_string(),
+///     remote_address: "127.0.0.1".to_string(),
+///     ..Default::default()
+/// };
+/// assert!(is_local_connection(&connection, &HashSet::new()));
+/// ```
+///
+/// # Returns
+/// `true` if both endpoints are on this host, `false` if not.
+pub fn is_local_connection(connection: &Connection, interface_addresses: &HashSet<IpAddr>) -> bool {
+    let (Some(local), Some(remote)) = (
+        utils::parse_ip(&connection.local_address),
+        utils::parse_ip(&connection.remote_address),
+    ) else {
+        return false;
+    };
+    let is_own = |ip: &IpAddr| ip.is_loopback() || interface_addresses.contains(ip);
+    !remote.is_unspecified() && is_own(&local) && is_own(&remote)
+}
+
+/// Marks all connections whose endpoints are both on this host, see `is_local_connection`.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set `is_local`.
+///
+/// # Returns
+/// None
+pub fn annotate_local(connections: &mut [Connection]) {
+    let interface_addresses = get_interface_addresses();
+    for connection in connections.iter_mut() {
+        connection.is_local = is_local_connection(connection, &interface_addresses);
+    }
+}
+
+/// Checks if a connection was created recently enough for `--since`.
+///
+/// # Arguments
+/// * `created`: The creation time of the connection as a Unix timestamp in seconds, if known.
+/// * `now`: The current time as a Unix timestamp in seconds.
+/// * `since`: The maximum age in seconds.
+/// * `include_unknown_age`: Whether a connection without a known creation time counts as recent, see `--include-unknown-age`.
+///
+/// # Returns
+/// `true` if the connection is younger than `since` seconds.
+fn is_recent(created: Option<u64>, now: u64, since: u64, include_unknown_age: bool) -> bool {
+    match created {
+        Some(created) => now.saturating_sub(created) < since,
+        None => include_unknown_age,
+    }
+}
+
+/// Gets the current time, e.g to compare it with the creation time of the connections.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The current time as a Unix timestamp in seconds, 0 if the clock is before 1970.
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats the creation time of all connections whose creation time is known, see `utils::format_timestamp`.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set `created_formatted`.
+/// * `format`: How to show the time, selected with `--timestamps`.
+///
+/// # Returns
+/// None
+pub fn annotate_timestamps(connections: &mut [Connection], format: TimestampFormat) {
+    let now = unix_now();
+    for connection in connections.iter_mut() {
+        connection.created_formatted = connection
+            .created
+            .map(|created| utils::format_timestamp(created, now, format));
+    }
+}
+
+/// Keeps only the local connections or only the ones with another host, see `--local-only` and `--no-local`.
+///
+/// # Arguments
+/// * `connections`: The connections annotated by `annotate_local`.
+/// * `local`: Whether to keep the local connections, `false` keeps all others.
+///
+/// # Returns
+/// None
+pub fn filter_by_local(connections: &mut Vec<Connection>, local: bool) {
+    connections.retain(|connection| connection.is_local == local);
+}
+
+/// Gets the name of a network interface, e.g "eth0".
+///
+/// # Arguments
+/// * `index`: The index of the interface.
+///
+/// # Returns
+/// The name of the interface or its index if the name can't be found.
+fn get_interface_name(index: u32) -> String {
+    #[cfg(unix)]
+    if let Ok(name) = nix::net::if_::if_indextoname(index) {
+        return name.to_string_lossy().into_owned();
+    }
+    index.to_string()
+}
+
+/// Splits a socket address into the address and the port, link-local IPv6 addresses get the name of their interface as zone, e.g "[fe80::1%eth0]".
+///
+/// # Arguments
+/// * `address`: The address of a socket.
+///
+/// # Returns
+/// The address and the port.
+fn get_socket_address_parts(address: &SocketAddr) -> (String, String) {
+    match address {
+        SocketAddr::V6(address) if address.scope_id() != 0 => (
+            format!(
+                "[{}%{}]",
+                address.ip(),
+                get_interface_name(address.scope_id())
+            ),
+            address.port().to_string(),
+        ),
+        _ => utils::get_address_parts(&format!("{}", address)),
+    }
+}
+
+fn get_connection_data(
+    net_entry: NetEntry,
+    all_processes: &HashMap<u64, ProcessInfo>,
+) -> Connection {
+    // process the remote-address and remote-port by spliting them at ":"
+    let (local_address, local_port) = get_socket_address_parts(&net_entry.local_address);
+    let (remote_address, remote_port) = get_socket_address_parts(&net_entry.remote_address);
+    let state = net_entry.state;
+
+    // check if there is no program/pid information
+    let process = all_processes.get(&net_entry.owner);
+    let (program, pid) = process
+        .map(|process| (process.program.to_string(), process.pid.to_string()))
+        .unwrap_or(("-".to_string(), "-".to_string()));
+    let user = process.and_then(|process| process.user.clone());
+    let command = process.map(|process| process.command.clone());
+    let created = process.and_then(|process| process.created);
+    let exe_path = process.and_then(|process| process.exe_path.clone());
+    let exe_deleted = process.is_some_and(|process| process.exe_deleted);
+
+    let address_type: AddressType = get_address_type(&remote_address);
+    // only the owner of a Linux socket entry is its inode
+    let inode = cfg!(target_os = "linux").then_some(net_entry.owner);
+    let queues = net_entry.queues;
+    let family = match net_entry.local_address {
+        SocketAddr::V4(_) => "inet",
+        SocketAddr::V6(_) => "inet6",
+    };
+
+    let connection: Connection = Connection {
+        proto: net_entry.protocol,
+        family: family.to_string(),
+        local_address,
+        local_port,
+        service: None,
+        remote_address: remote_address.to_string(),
+        remote_port,
+        remote_service: None,
+        remote_hostname: None,
+        country: None,
+        container: None,
+        container_id: None,
+        unit: None,
+        exposure: None,
+        is_local: false,
+        netns: None,
+        rx_bytes: None,
+        tx_bytes: None,
+        rx_queue: queues.map(|queues| queues.rx_queue),
+        tx_queue: queues.map(|queues| queues.tx_queue),
+        retransmits: queues.and_then(|queues| queues.retransmits),
+        created,
+        created_formatted: None,
+        inode,
+        program,
+        exe_path,
+        exe_deleted,
+        pid,
+        user,
+        command,
+        state,
+        duplicates: None,
+        diff: None,
+        address_type,
+    };
+
+    connection
+}
+
+/// Processes the socket entries of one protocol into connections and filters them.
+///
+/// # Arguments
+/// * `entries`: The socket entries read by the platform.
+/// * `all_processes`: A map of all running processes on the system.
+/// * `filter_options`: The filter options provided by the user.
+/// * `now`: The current time as a Unix timestamp in seconds, see `filter_out_connection`.
+///
+/// # Returns
+/// All processed and filtered connections as a `Connection` struct in a vector.
+fn get_connections(
+    entries: Vec<NetEntry>,
+    all_processes: &HashMap<u64, ProcessInfo>,
+    filter_options: &FilterOptions,
+    now: u64,
+) -> Vec<Connection> {
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            if !filter_options.raw_addresses {
+                entry.local_address = unmap_ipv4_address(entry.local_address);
+                entry.remote_address = unmap_ipv4_address(entry.remote_address);
+            }
+            entry
+        })
+        .filter(|entry| !filter_out_address_family(&entry.local_address, filter_options))
+        .filter(|entry| !filter_out_protocol(entry, filter_options))
+        .map(|entry| get_connection_data(entry, all_processes))
+        .filter(|connection| !filter_out_connection(connection, filter_options, now))
+        .collect()
+}
+
+/// Finds all descendants of a process, ie. its children, their children and so on.
+///
+/// # Arguments
+/// * `parent_pids`: The parent of every running process.
+/// * `ancestor`: The PID of the process whose descendants are searched.
+///
+/// # Returns
+/// The PIDs of all descendants, the process itself isn't included.
+fn get_descendants(parent_pids: &HashMap<i32, i32>, ancestor: i32) -> HashSet<i32> {
+    parent_pids
+        .keys()
+        .copied()
+        .filter(|pid| {
+            // the depth is bounded, so that a cycle (e.g from a PID reused while reading) can't loop forever
+            let mut current = *pid;
+            for _ in 0..parent_pids.len() {
+                match parent_pids.get(&current) {
+                    Some(parent) if *parent == ancestor => return true,
+                    Some(parent) if *parent != current => current = *parent,
+                    _ => return false,
+                }
+            }
+            false
+        })
+        .collect()
+}
+
+/// Removes the connections whose process doesn't descend from the `--ppid` process.
+///
+/// # Arguments
+/// * `connections`: The connections to filter.
+/// * `platform`: The platform to read the parents of the processes from.
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// The error if the parents of the processes couldn't be read.
+fn filter_by_ppid(
+    connections: &mut Vec<Connection>,
+    platform: &impl Platform,
+    filter_options: &FilterOptions,
+) -> Result<(), SomoError> {
+    let Some(ppid) = filter_options.by_ppid else {
+        return Ok(());
+    };
+
+    let descendants = get_descendants(&platform.get_parent_pids()?, ppid);
+    connections.retain(|connection| {
+        connection
+            .pid
+            .parse::<i32>()
+            .is_ok_and(|pid| descendants.contains(&pid))
+    });
+    Ok(())
+}
+
+/// Removes the connections whose process wasn't started with all `--env` variables.
+/// The environment of a process is read once for all its connections. Processes whose environment can't be read are left out, the user is told once how many there were.
+///
+/// # Arguments
+/// * `connections`: The connections to filter.
+/// * `platform`: The platform to read the environments of the processes from.
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// The number of processes whose environment couldn't be read.
+fn filter_by_env(
+    connections: &mut Vec<Connection>,
+    platform: &impl Platform,
+    filter_options: &FilterOptions,
+) -> usize {
+    if filter_options.by_env.is_empty() {
+        return 0;
+    }
+
+    let mut matching: HashMap<i32, Option<bool>> = HashMap::new();
+    connections.retain(|connection| {
+        let Ok(pid) = connection.pid.parse::<i32>() else {
+            return false;
+        };
+        let matches = matching.entry(pid).or_insert_with(|| {
+            platform.get_environment(pid).map(|environment| {
+                filter_options
+                    .by_env
+                    .iter()
+                    .all(|filter| filter.matches(&environment))
+            })
+        });
+        matches.unwrap_or(false)
+    });
+
+    let unreadable = matching
+        .values()
+        .filter(|matches| matches.is_none())
+        .count();
+    if unreadable > 0 && !ENV_HINT_SHOWN.swap(true, AtomicOrdering::SeqCst) {
+        utils::pretty_print_info(&format!(
+            "The environment of {} process(es) couldn't be read, their connections are left out by --env. Run 'sudo somo' to read the environment of all processes.",
+            unreadable
+        ));
+    }
+    unreadable
+}
+
+/// Gets the processes which own sockets, only reading the one process if `--pid` selects a single PID.
+/// The sockets of other processes are then shown without a process and removed by the PID filter, so the result is the same as with all processes.
+///
+/// # Arguments
+/// * `platform`: The platform to read the processes from.
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// The processes keyed by the `owner` of the socket entries or the error which prevented reading them.
+fn get_owning_processes(
+    platform: &impl Platform,
+    filter_options: &FilterOptions,
+) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+    match filter_options.by_pid {
+        Some(PidFilter::Pid(pid)) => match i32::try_from(pid) {
+            Ok(pid) => platform.get_process(pid, filter_options.numeric_users),
+            Err(_) => Ok(HashMap::new()),
+        },
+        _ => platform.get_processes(filter_options.numeric_users),
+    }
+}
+
+/// Gets both TCP and UDP connections from a platform and combines them based on the `proto` filter option.
+///
+/// # Arguments
+/// * `platform`: The platform to read the sockets and processes from.
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// All processed and filtered TCP/UDP connections as a `Connection` struct in a vector or the error which prevented reading them.
+fn get_platform_connections(
+    platform: &impl Platform,
+    filter_options: &FilterOptions,
+) -> Result<Vec<Connection>, SomoError> {
+    let all_processes = get_owning_processes(platform, filter_options)?;
+    let now = unix_now();
+
+    let mut connections = Vec::new();
+
+    // only read the socket tables of the protocols which can match the filter
+    let includes = |protocol: &str| {
+        filter_options.by_proto.is_empty()
+            || filter_options
+                .by_proto
+                .iter()
+                .any(|proto| normalize_protocol(proto).starts_with(protocol))
+    };
+    if includes("tcp") {
+        connections.extend(get_connections(
+            platform.get_tcp_entries()?,
+            &all_processes,
+            filter_options,
+            now,
+        ));
+    }
+    if includes("udp") {
+        connections.extend(get_connections(
+            platform.get_udp_entries()?,
+            &all_processes,
+            filter_options,
+            now,
+        ));
+    }
+    filter_by_ppid(&mut connections, platform, filter_options)?;
+    filter_by_env(&mut connections, platform, filter_options);
+
+    Ok(connections)
+}
+
+/// Guesses from the share of connections without a known process whether running as root would show more processes.
+/// This is only a heuristic, it doesn't know why a process couldn't be read, e.g a process which exited in between looks the same as one which denied access.
+/// Sockets in the "time-wait" state are ignored, since they never belong to a process.
+///
+/// # Arguments
+/// * `connections`: The connections which were read.
+/// * `is_root`: Whether somo runs as root, in which case all processes can be read.
+///
+/// # Returns
+/// `true` if at least `PRIVILEGE_HINT_THRESHOLD` of the connections have no known process and somo isn't running as root.
+#[cfg(unix)]
+fn needs_privilege_hint(connections: &[Connection], is_root: bool) -> bool {
+    if is_root {
+        return false;
+    }
+
+    let owned: Vec<&Connection> = connections
+        .iter()
+        .filter(|connection| normalize_state(&connection.state) != "timewait")
+        .collect();
+    let unresolved = owned
+        .iter()
+        .filter(|connection| connection.pid == "-")
+        .count();
+    unresolved > 0 && unresolved as f64 >= owned.len() as f64 * PRIVILEGE_HINT_THRESHOLD
+}
+
+/// Checks whether running as root would likely show the processes of more connections, e.g to tell the user to run somo with sudo.
+/// Nothing is printed, it is up to the caller how to surface the hint.
+///
+/// # Arguments
+/// * `connections`: The connections returned by `get_all_connections`.
+///
+/// # Returns
+/// `true` if many connections have no known process and the current user isn't root.
+#[cfg(unix)]
+pub fn lacks_privileges(connections: &[Connection]) -> bool {
+    needs_privilege_hint(connections, Uid::effective().is_root())
+}
+
+/// Gets the parent of every running process of the current platform, e.g to find the children which `--tree` kills too.
+/// Only Linux reads the parents, on the other platforms no process has a known parent.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The PID of the parent keyed by the PID of every process or the error which prevented reading them.
+// the platform is only a unit struct on Windows, Linux and macOS hold caches
+#[cfg_attr(target_os = "windows", allow(clippy::default_constructed_unit_structs))]
+pub fn get_parent_pids() -> Result<HashMap<i32, i32>, SomoError> {
+    CurrentPlatform::default().get_parent_pids()
+}
+
+/// Reads the current parent of a single process, e.g to check that a PID wasn't recycled by an unrelated process.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+///
+/// # Returns
+/// The PID of the parent, `None` if the process isn't running or its parent isn't known on this platform.
+pub fn get_parent_pid(pid: i32) -> Option<i32> {
+    #[cfg(target_os = "linux")]
+    return linux::read_parent_pid(pid);
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Gets both TCP and UDP connections of the current platform and combines them based on the `proto` filter option.
+/// Processes which can't be read leave their connections without a known process, see `lacks_privileges`.
+///
+/// # Arguments
+/// * `filter_options`: The filter options provided by the user.
+///
+/// # Returns
+/// All processed and filtered TCP/UDP connections as a `Connection` struct in a vector or the error which prevented reading them.
+// the platform is only a unit struct on Windows, Linux and macOS hold caches
+#[cfg_attr(target_os = "windows", allow(clippy::default_constructed_unit_structs))]
+pub fn get_all_connections(filter_options: &FilterOptions) -> Result<Vec<Connection>, SomoError> {
+    get_platform_connections(&CurrentPlatform::default(), filter_options)
+}
+
+/// Gets the TCP and UDP connections of all network namespaces, every connection is tagged with its namespace.
+/// Reading other namespaces requires root or `CAP_SYS_ADMIN`, without them a permission error is returned.
+///
+/// # Arguments
+/// * `filter_options`: The filter options provided by the user.
+/// * `netns`: Only read the namespace with this name or ID, all namespaces are read if it is `None`.
+///
+/// # Returns
+/// All processed and filtered TCP/UDP connections of the namespaces or the error which prevented reading them.
+#[cfg(target_os = "linux")]
+pub fn get_netns_connections(
+    filter_options: &FilterOptions,
+    netns: Option<&str>,
+) -> Result<Vec<Connection>, SomoError> {
+    // socket inodes are unique across all namespaces, so the processes only have to be read once
+    let platform = CurrentPlatform::default();
+    let all_processes = get_owning_processes(&platform, filter_options)?;
+    let own_namespace = netns::get_own_namespace_id()?;
+    let now = unix_now();
+
+    let mut connections = Vec::new();
+    for namespace in netns::get_net_namespaces()? {
+        if netns.is_some_and(|netns| !namespace.matches(netns)) {
+            continue;
+        }
+
+        let (tcp_entries, udp_entries) =
+            netns::read_namespace_entries(&namespace, namespace.id != own_namespace)?;
+        for entries in [tcp_entries, udp_entries] {
+            connections.extend(
+                get_connections(entries, &all_processes, filter_options, now)
+                    .into_iter()
+                    .map(|connection| Connection {
+                        netns: Some(namespace.label()),
+                        ..connection
+                    }),
+            );
+        }
+    }
+
+    filter_by_ppid(&mut connections, &platform, filter_options)?;
+    filter_by_env(&mut connections, &platform, filter_options);
+
+    Ok(connections)
+}
+
+/// Network namespaces only exist on Linux.
+///
+/// # Arguments
+/// * `_filter_options`: The filter options provided by the user.
+/// * `_netns`: The namespace provided by the user.
+///
+/// # Returns
+/// Always an error.
+#[cfg(not(target_os = "linux"))]
+pub fn get_netns_connections(
+    _filter_options: &FilterOptions,
+    _netns: Option<&str>,
+) -> Result<Vec<Connection>, SomoError> {
+    Err(SomoError::ProcRead(
+        "network namespaces are only supported on Linux".to_string(),
+    ))
+}
+
+/// Compares two optional sort keys so that missing keys always end up last, regardless of the sort direction.
+///
+/// # Arguments
+/// * `a`: The first key, `None` if the value is unknown.
+/// * `b`: The second key, `None` if the value is unknown.
+/// * `reverse`: Whether known keys should be compared in descending order.
+///
+/// # Returns
+/// The ordering of both keys.
+fn compare_keys<T: Ord>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if reverse => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Collapses identical connections into their first row, e.g sockets which are listed in multiple socket tables.
+///
+/// # Arguments
+/// * `connections`: The filtered connections to deduplicate in place.
+/// * `count_duplicates`: Whether to set the number of identical rows as `duplicates` of every kept row.
+///
+/// # Returns
+/// None
+pub fn deduplicate_connections(connections: &mut Vec<Connection>, count_duplicates: bool) {
+    let mut counts: HashMap<DuplicateKey, usize> = HashMap::new();
+    for connection in connections.iter() {
+        *counts.entry(connection.duplicate_key()).or_insert(0) += 1;
+    }
+
+    let mut seen: HashSet<DuplicateKey> = HashSet::new();
+    connections.retain(|connection| seen.insert(connection.duplicate_key()));
+    if count_duplicates {
+        for connection in connections.iter_mut() {
+            connection.duplicates = counts.get(&connection.duplicate_key()).copied();
+        }
+    }
+}
+
+/// Sorts connections by a column, comparing ports and PIDs numerically, the age from oldest to newest and all other columns lexically.
+/// Unknown values ("-") are always sorted last so that the resolved rows stay together.
+///
+/// # Arguments
+/// * `connections`: The connections to sort in place.
+/// * `sort_field`: The column to sort by.
+/// * `reverse`: Whether to sort in descending order.
+///
+/// # Returns
+/// None
+pub fn sort_connections(connections: &mut [Connection], sort_field: SortField, reverse: bool) {
+    let numeric_key = |value: &str| value.parse::<u64>().ok();
+    let lexical_key = |value: &str| (value != "-").then(|| value.to_string());
+
+    connections.sort_by(|a, b| match sort_field {
+        SortField::LocalPort => compare_keys(
+            numeric_key(&a.local_port),
+            numeric_key(&b.local_port),
+            reverse,
+        ),
+        SortField::RemotePort => compare_keys(
+            numeric_key(&a.remote_port),
+            numeric_key(&b.remote_port),
+            reverse,
+        ),
+        SortField::Pid => compare_keys(numeric_key(&a.pid), numeric_key(&b.pid), reverse),
+        SortField::Proto => compare_keys(lexical_key(&a.proto), lexical_key(&b.proto), reverse),
+        SortField::RemoteAddress => compare_keys(
+            lexical_key(&a.remote_address),
+            lexical_key(&b.remote_address),
+            reverse,
+        ),
+        SortField::Program => {
+            compare_keys(lexical_key(&a.program), lexical_key(&b.program), reverse)
+        }
+        SortField::State => compare_keys(lexical_key(&a.state), lexical_key(&b.state), reverse),
+        // the oldest connections, ie. the ones created first, are listed first
+        SortField::Age => compare_keys(a.created, b.created, reverse),
+    });
+}
+
+/// Aggregates connections into groups which share the same value in a column and counts the states in every group.
+///
+/// # Arguments
+/// * `connections`: The connections to aggregate.
+/// * `group_field`: The column to group by.
+///
+/// # Returns
+/// The groups ordered by their number of connections (largest first) and then by their key.
+pub fn group_connections(
+    connections: &[Connection],
+    group_field: GroupField,
+) -> Vec<ConnectionGroup> {
+    group_connections_by(connections, |connection| {
+        Some(match group_field {
+            GroupField::Program => connection.program.to_string(),
+            GroupField::Pid => format!("{} {}", connection.pid, connection.program),
+            GroupField::State => connection.state.to_string(),
+            GroupField::Proto => connection.proto.to_string(),
+        })
+    })
+}
+
+/// Aggregates connections into groups by their remote peer, showing who the local programs are talking to.
+/// Sockets without a peer, e.g listening sockets, are skipped. Loopback peers can be excluded with `exclude_remote_address`.
+///
+/// # Arguments
+/// * `connections`: The connections to aggregate.
+///
+/// # Returns
+/// One group per remote address (or hostname if it was resolved), ordered by their number of connections (largest first).
+pub fn group_remote_addresses(connections: &[Connection]) -> Vec<ConnectionGroup> {
+    group_connections_by(connections, |connection| {
+        if connection.address_type == AddressType::Unspecified {
+            return None;
+        }
+        Some(
+            connection
+                .remote_hostname
+                .as_ref()
+                .unwrap_or(&connection.remote_address)
+                .to_string(),
+        )
+    })
+}
+
+/// Aggregates connections into groups which share the same key and collects the states, programs and local ports of every group.
+///
+/// # Arguments
+/// * `connections`: The connections to aggregate.
+/// * `get_key`: Gets the key of the group of a connection, connections without a key are skipped.
+///
+/// # Returns
+/// The groups ordered by their number of connections (largest first) and then by their key.
+fn group_connections_by(
+    connections: &[Connection],
+    get_key: impl Fn(&Connection) -> Option<String>,
+) -> Vec<ConnectionGroup> {
+    let mut groups: BTreeMap<String, ConnectionGroup> = BTreeMap::new();
+
+    for connection in connections {
+        let Some(key) = get_key(connection) else {
+            continue;
+        };
+
+        let group = groups
+            .entry(key.clone())
+            .or_insert_with(|| ConnectionGroup {
+                key,
+                ..Default::default()
+            });
+        group.count += 1;
+        *group
+            .states
+            .entry(connection.state.to_string())
+            .or_insert(0) += 1;
+        group.programs.insert(connection.program.to_string());
+        group.local_ports.insert(connection.local_port.to_string());
+    }
+
+    let mut groups: Vec<ConnectionGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::PidFilter;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_get_address_type() {
+        use crate::schemas::AddressType;
+
+        assert_eq!(get_address_type("127.0.0.1"), AddressType::Localhost);
+        assert_eq!(get_address_type("[::1]"), AddressType::Localhost);
+        assert_eq!(get_address_type("0.0.0.0"), AddressType::Unspecified);
+        assert_eq!(get_address_type("[::]"), AddressType::Unspecified);
+        assert_eq!(get_address_type("8.8.8.8"), AddressType::Extern);
+    }
+
+    #[test]
+    fn test_is_local_connection() {
+        let conn = |local_address: &str, remote_address: &str| Connection {
+            local_address: local_address.to_string(),
+            remote_address: remote_address.to_string(),
+            ..Default::default()
+        };
+        let interface_addresses = HashSet::from(["192.168.0.10".parse().unwrap()]);
+        assert!(is_local_connection(
+            &conn("127.0.0.1", "127.0.0.1"),
+            &interface_addresses
+        ));
+        assert!(is_local_connection(
+            &conn("[::1]", "[::1]"),
+            &interface_addresses
+        ));
+        assert!(is_local_connection(
+            &conn("192.168.0.10", "192.168.0.10"),
+            &interface_addresses
+        ));
+        assert!(!is_local_connection(
+            &conn("192.168.0.10", "192.168.0.20"),
+            &interface_addresses
+        ));
+        assert!(!is_local_connection(
+            &conn("127.0.0.1", "0.0.0.0"),
+            &interface_addresses
+        ));
+        assert!(!is_local_connection(
+            &conn("-", "127.0.0.1"),
+            &interface_addresses
+        ));
+    }
+
+    #[test]
+    fn test_filter_by_local() {
+        let conn = |remote_address: &str, is_local: bool| Connection {
+            remote_address: remote_address.to_string(),
+            is_local,
+            ..Default::default()
+        };
+        let all = vec![conn("127.0.0.1", true), conn("8.8.8.8", false)];
+
+        let mut local = all.clone();
+        filter_by_local(&mut local, true);
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].remote_address, "127.0.0.1");
+
+        let mut other = all;
+        filter_by_local(&mut other, false);
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].remote_address, "8.8.8.8");
+    }
+
+    #[test]
+    fn test_get_exposure() {
+        assert_eq!(get_exposure("127.0.0.1"), Some(Exposure::Loopback));
+        assert_eq!(get_exposure("127.0.0.53"), Some(Exposure::Loopback));
+        assert_eq!(get_exposure("[::1]"), Some(Exposure::Loopback));
+        assert_eq!(get_exposure("0.0.0.0"), Some(Exposure::AllInterfaces));
+        assert_eq!(get_exposure("[::]"), Some(Exposure::AllInterfaces));
+        assert_eq!(get_exposure("10.1.2.3"), Some(Exposure::Private));
+        assert_eq!(get_exposure("[fe80::1%eth0]"), Some(Exposure::Private));
+        assert_eq!(get_exposure("[fd00::5]"), Some(Exposure::Private));
+        assert_eq!(get_exposure("203.0.113.7"), Some(Exposure::Public));
+        assert_eq!(get_exposure("[2001:db8::1]"), Some(Exposure::Public));
+        assert_eq!(get_exposure("-"), None);
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_exe_path() {
+        let conn = Connection {
+            program: "python3".to_string(),
+            exe_path: Some("/opt/venv/bin/python3".to_string()),
+            ..Default::default()
+        };
+        let by_program = FilterOptions {
+            by_program: Some(ProgramMatcher::Substring("venv".to_string())),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &by_program, 0));
+
+        let by_path = FilterOptions {
+            match_path: true,
+            ..by_program
+        };
+        assert!(!filter_out_connection(&conn, &by_path, 0));
+        // the program name still matches on its own
+        let by_name = FilterOptions {
+            by_program: Some(ProgramMatcher::Substring("python".to_string())),
+            ..by_path.clone()
+        };
+        assert!(!filter_out_connection(&conn, &by_name, 0));
+        assert!(filter_out_connection(&Connection::default(), &by_path, 0));
+    }
+
+    #[test]
+    fn test_find_contradictions() {
+        use crate::schemas::PortFilter;
+
+        let state = |state: &str| Some(state.to_string());
+        assert!(find_contradictions(&FilterOptions::default()).is_empty());
+
+        // unusual but valid combinations aren't reported
+        let valid = [
+            FilterOptions {
+                by_open: true,
+                by_listen: true,
+                ..Default::default()
+            },
+            FilterOptions {
+                by_state: state("established"),
+                by_proto: vec!["udp".to_string()],
+                by_include: vec![StateCategory::Established],
+                ..Default::default()
+            },
+            FilterOptions {
+                by_proto: vec!["tcp".to_string(), "udp6".to_string()],
+                by_family: AddressFamily::Inet,
+                by_listen: true,
+                by_remote_port: Some(PortFilter::Range(0, 1023)),
+                ..Default::default()
+            },
+        ];
+        for filter_options in valid {
+            assert!(find_contradictions(&filter_options).is_empty());
+        }
+
+        let contradictions = find_contradictions(&FilterOptions {
+            by_state: state("time-wait"),
+            by_proto: vec!["UDP".to_string()],
+            by_listen: true,
+            by_include: vec![StateCategory::Established],
+            ..Default::default()
+        });
+        assert_eq!(
+            contradictions,
+            vec![
+                "--state time-wait contradicts --listen",
+                "--state time-wait contradicts --include",
+                "--state time-wait contradicts --proto udp, UDP sockets have no such state",
+                "--listen contradicts --include",
+                "--listen contradicts --proto udp, UDP sockets never listen",
+            ]
+        );
+
+        let contradictions = find_contradictions(&FilterOptions {
+            by_state: state("close"),
+            by_open: true,
+            by_proto: vec!["tcp6".to_string()],
+            by_family: AddressFamily::Inet,
+            ..Default::default()
+        });
+        assert_eq!(
+            contradictions,
+            vec![
+                "--state close contradicts --open",
+                "--proto tcp6 contradicts --family inet",
+            ]
+        );
+
+        let contradictions = find_contradictions(&FilterOptions {
+            by_listen: true,
+            by_remote_port: Some(PortFilter::Port(443)),
+            ..Default::default()
+        });
+        assert_eq!(contradictions.len(), 1);
+        assert!(contradictions[0].starts_with("--remote-port contradicts --listen"));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_exposure() {
+        let public = FilterOptions {
+            by_exposure: Some(Exposure::Public),
+            ..Default::default()
+        };
+        let conn = |local_address: &str| Connection {
+            local_address: local_address.to_string(),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn("203.0.113.7"), &public, 0));
+        assert!(filter_out_connection(&conn("0.0.0.0"), &public, 0));
+        assert!(filter_out_connection(&conn("127.0.0.1"), &public, 0));
+        assert!(!filter_out_connection(
+            &conn("127.0.0.1"),
+            &FilterOptions::default(),
+            0
+        ));
+    }
+
+    #[test]
+    fn test_filter_out_address_family() {
+        let ipv4: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let ipv6: SocketAddr = "[::1]:80".parse().unwrap();
+
+        let any_family = FilterOptions::default();
+        assert!(!filter_out_address_family(&ipv4, &any_family));
+        assert!(!filter_out_address_family(&ipv6, &any_family));
+
+        let only_ipv6 = FilterOptions {
+            by_family: AddressFamily::Inet6,
+            ..Default::default()
+        };
+        assert!(filter_out_address_family(&ipv4, &only_ipv6));
+        assert!(!filter_out_address_family(&ipv6, &only_ipv6));
+
+        let only_ipv4 = FilterOptions {
+            by_family: AddressFamily::Inet,
+            ..Default::default()
+        };
+        assert!(!filter_out_address_family(&ipv4, &only_ipv4));
+        assert!(filter_out_address_family(&ipv6, &only_ipv4));
+    }
+
+    #[test]
+    fn test_ipv4_mapped_addresses() {
+        let entry = NetEntry {
+            protocol: "tcp6".to_string(),
+            local_address: "[::ffff:192.168.1.1]:8080".parse().unwrap(),
+            remote_address: "[::ffff:10.0.0.2]:51000".parse().unwrap(),
+            state: "established".to_string(),
+            owner: 1,
+            queues: None,
+        };
+        let connections = |filter_options: FilterOptions| {
+            get_connections(vec![entry.clone()], &HashMap::new(), &filter_options, 0)
+        };
+
+        let all = connections(FilterOptions::default());
+        assert_eq!(all[0].local_address, "192.168.1.1");
+        assert_eq!(all[0].remote_address, "10.0.0.2");
+        assert_eq!(all[0].remote_port, "51000");
+
+        // the mapped address is IPv4 traffic, like with --exclude-ipv4 and --exclude-ipv6
+        let only_ipv6 = FilterOptions {
+            by_family: AddressFamily::Inet6,
+            ..Default::default()
+        };
+        assert!(connections(only_ipv6).is_empty());
+        let only_ipv4 = FilterOptions {
+            by_family: AddressFamily::Inet,
+            ..Default::default()
+        };
+        assert_eq!(connections(only_ipv4).len(), 1);
+        let excluded = FilterOptions {
+            exclude_remote_address: Some("10.0.0.2".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(connections(excluded).is_empty());
+
+        let raw = connections(FilterOptions {
+            raw_addresses: true,
+            ..Default::default()
+        });
+        assert_eq!(raw[0].local_address, "[::ffff:192.168.1.1]");
+        let raw_ipv6 = FilterOptions {
+            raw_addresses: true,
+            by_family: AddressFamily::Inet6,
+            ..Default::default()
+        };
+        assert_eq!(connections(raw_ipv6).len(), 1);
+
+        // other IPv6 addresses aren't changed
+        let loopback: SocketAddr = "[::1]:22".parse().unwrap();
+        assert_eq!(unmap_ipv4_address(loopback), loopback);
+    }
+
+    struct FakePlatform;
+
+    impl Platform for FakePlatform {
+        fn get_processes(&self, _: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+            Ok(HashMap::from([(
+                1,
+                ProcessInfo {
+                    pid: 42,
+                    program: "nginx".to_string(),
+                    user: Some("www-data".to_string()),
+                    command: "nginx -g daemon off;".to_string(),
+                    exe_path: Some("/usr/sbin/nginx".to_string()),
+                    exe_deleted: false,
+                    created: None,
+                },
+            )]))
+        }
+
+        fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+            Ok(vec![
+                NetEntry {
+                    protocol: "tcp".to_string(),
+                    local_address: "0.0.0.0:80".parse().unwrap(),
+                    remote_address: "0.0.0.0:0".parse().unwrap(),
+                    state: "listen".to_string(),
+                    owner: 1,
+                    queues: None,
+                },
+                NetEntry {
+                    protocol: "tcp".to_string(),
+                    local_address: "[::1]:5432".parse().unwrap(),
+                    remote_address: "[::1]:41000".parse().unwrap(),
+                    state: "established".to_string(),
+                    owner: 2,
+                    queues: None,
+                },
+            ])
+        }
+
+        fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+            Ok(vec![NetEntry {
+                protocol: "udp".to_string(),
+                local_address: "127.0.0.53:53".parse().unwrap(),
+                remote_address: "0.0.0.0:0".parse().unwrap(),
+                state: "close".to_string(),
+                owner: 3,
+                queues: None,
+            }])
+        }
+
+        fn get_parent_pids(&self) -> Result<HashMap<i32, i32>, SomoError> {
+            Ok(HashMap::from([(42, 7), (7, 1), (1, 0)]))
+        }
+    }
+
+    #[test]
+    fn test_get_descendants() {
+        let parent_pids = HashMap::from([(1, 0), (10, 1), (11, 10), (12, 10), (20, 1), (30, 30)]);
+        assert_eq!(get_descendants(&parent_pids, 10), HashSet::from([11, 12]));
+        assert_eq!(
+            get_descendants(&parent_pids, 1),
+            HashSet::from([10, 11, 12, 20])
+        );
+        assert!(get_descendants(&parent_pids, 11).is_empty());
+        // a process which is its own parent doesn't loop forever
+        assert!(get_descendants(&parent_pids, 99).is_empty());
+    }
+
+    #[test]
+    fn test_get_platform_connections_by_ppid() {
+        let by_ppid = |ppid: i32| {
+            get_platform_connections(
+                &FakePlatform,
+                &FilterOptions {
+                    by_ppid: Some(ppid),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        assert_eq!(by_ppid(7).len(), 1);
+        assert_eq!(by_ppid(1)[0].pid, "42");
+        assert!(by_ppid(42).is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_env_unreadable() {
+        let filter_options = FilterOptions {
+            by_env: vec!["DEPLOY_ID=42".parse().unwrap()],
+            ..Default::default()
+        };
+        let mut connections =
+            get_platform_connections(&FakePlatform, &FilterOptions::default()).unwrap();
+        assert!(!connections.is_empty());
+        // the fake platform can't read environments, the process of all connections is counted once
+        assert_eq!(
+            filter_by_env(&mut connections, &FakePlatform, &filter_options),
+            1
+        );
+        assert!(connections.is_empty());
+    }
+
+    #[test]
+    fn test_get_platform_connections() {
+        let connections =
+            get_platform_connections(&FakePlatform, &FilterOptions::default()).unwrap();
+        assert_eq!(connections.len(), 3);
+
+        assert_eq!(connections[0].proto, "tcp");
+        assert_eq!(connections[0].local_port, "80");
+        assert_eq!(connections[0].pid, "42");
+        assert_eq!(connections[0].program, "nginx");
+        assert_eq!(connections[0].user.as_deref(), Some("www-data"));
+        #[cfg(target_os = "linux")]
+        assert_eq!(connections[0].inode, Some(1));
+        assert_eq!(connections[0].address_type, AddressType::Unspecified);
+
+        assert_eq!(connections[1].remote_address, "[::1]");
+        assert_eq!(connections[1].remote_port, "41000");
+        assert_eq!(connections[1].pid, "-");
+        assert_eq!(connections[1].program, "-");
+        assert!(connections[1].command.is_none());
+
+        assert_eq!(connections[2].proto, "udp");
+    }
+
+    #[test]
+    fn test_protocol_and_family() {
+        let connections =
+            get_platform_connections(&FakePlatform, &FilterOptions::default()).unwrap();
+        assert_eq!(connections[0].family, "inet");
+
+        // an IPv6 TCP socket keeps "tcp" as its protocol, the family tells it apart from IPv4
+        let ipv6 = serde_json::to_value(&connections[1]).unwrap();
+        assert_eq!(ipv6["proto"], "tcp");
+        assert_eq!(ipv6["family"], "inet6");
+        assert_eq!(connections[2].family, "inet");
+    }
+
+    #[test]
+    fn test_get_platform_connections_filtered() {
+        let udp_only = FilterOptions {
+            by_proto: vec!["udp".to_string()],
+            ..Default::default()
+        };
+        let connections = get_platform_connections(&FakePlatform, &udp_only).unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].local_port, "53");
+
+        let ipv4_listening = FilterOptions {
+            by_family: AddressFamily::Inet,
+            by_listen: true,
+            ..Default::default()
+        };
+        let connections = get_platform_connections(&FakePlatform, &ipv4_listening).unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].local_port, "80");
+    }
+
+    struct DeniedPlatform;
+
+    impl Platform for DeniedPlatform {
+        fn get_processes(&self, _: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+            Ok(HashMap::new())
+        }
+
+        fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+            Err(SomoError::PermissionDenied("/proc/net/tcp".to_string()))
+        }
+
+        fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_get_platform_connections_error() {
+        let error =
+            get_platform_connections(&DeniedPlatform, &FilterOptions::default()).unwrap_err();
+        assert!(error.is_permission_denied());
+
+        // the TCP table isn't read at all if only UDP sockets are requested
+        let udp_only = FilterOptions {
+            by_proto: vec!["udp".to_string()],
+            ..Default::default()
+        };
+        assert!(get_platform_connections(&DeniedPlatform, &udp_only).is_ok());
+    }
+
+    #[test]
+    fn test_get_platform_connections_by_protocols() {
+        let tcp6 = FilterOptions {
+            by_proto: vec!["tcp6".to_string()],
+            ..Default::default()
+        };
+        let connections = get_platform_connections(&FakePlatform, &tcp6).unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].local_port, "5432");
+
+        let tcp6_and_udp = FilterOptions {
+            by_proto: vec!["tcp6".to_string(), "udp".to_string()],
+            ..Default::default()
+        };
+        let connections = get_platform_connections(&FakePlatform, &tcp6_and_udp).unwrap();
+        let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["5432", "53"]);
+
+        let tcp4 = FilterOptions {
+            by_proto: vec!["tcp4".to_string()],
+            ..Default::default()
+        };
+        let connections = get_platform_connections(&FakePlatform, &tcp4).unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].local_port, "80");
+    }
+
+    #[test]
+    fn test_get_platform_connections_by_protocol_case() {
+        for proto in ["TCP", "Tcp", "tcp"] {
+            let filter_options = FilterOptions {
+                by_proto: vec![proto.to_string()],
+                ..Default::default()
+            };
+            let connections = get_platform_connections(&FakePlatform, &filter_options).unwrap();
+            let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+            assert_eq!(ports, vec!["80", "5432"], "{}", proto);
+        }
 
-        assert_eq!(get_address_type("127.0.0.1"), AddressType::Localhost);
-        assert_eq!(get_address_type("[::1]"), AddressType::Localhost);
-        assert_eq!(get_address_type("0.0.0.0"), AddressType::Unspecified);
-        assert_eq!(get_address_type("[::]"), AddressType::Unspecified);
-        assert_eq!(get_address_type("8.8.8.8"), AddressType::Extern);
+        assert!(is_valid_protocol("UDP6"));
+        assert!(is_valid_protocol("tcp4"));
+        assert!(!is_valid_protocol("sctp"));
     }
 
     #[test]
     fn test_filter_out_connection_by_port() {
-        use crate::schemas::{AddressType, Connection, FilterOptions};
+        use crate::schemas::{AddressType, Connection, FilterOptions, PortFilter};
 
         let conn = Connection {
             proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
             local_port: "8080".to_string(),
             remote_port: "443".to_string(),
             remote_address: "8.8.8.8".to_string(),
@@ -260,25 +2069,24 @@ mod tests {
             pid: "123".to_string(),
             state: "established".to_string(),
             address_type: AddressType::Extern,
+            ..Default::default()
         };
 
         let filter_by_matching_port = FilterOptions {
-            by_local_port: Some("8080".to_string()),
+            by_local_port: Some(PortFilter::Port(8080)),
             ..Default::default()
         };
-        assert_eq!(
-            filter_out_connection(&conn, &filter_by_matching_port),
-            false
-        );
+        assert!(!filter_out_connection(&conn, &filter_by_matching_port, 0));
 
         let filter_by_non_matching_port = FilterOptions {
-            by_local_port: Some("8181".to_string()),
+            by_local_port: Some(PortFilter::Port(8181)),
             ..Default::default()
         };
-        assert_eq!(
-            filter_out_connection(&conn, &filter_by_non_matching_port),
-            true
-        );
+        assert!(filter_out_connection(
+            &conn,
+            &filter_by_non_matching_port,
+            0
+        ));
     }
 
     #[test]
@@ -287,6 +2095,7 @@ mod tests {
 
         let mut conn = Connection {
             proto: "udp".to_string(),
+            local_address: "0.0.0.0".to_string(),
             local_port: "8080".to_string(),
             remote_port: "443".to_string(),
             remote_address: "8.8.8.8".to_string(),
@@ -294,19 +2103,20 @@ mod tests {
             pid: "123".to_string(),
             state: "close".to_string(),
             address_type: AddressType::Extern,
+            ..Default::default()
         };
 
         let filter_by_open_state = FilterOptions {
             by_open: true,
             ..Default::default()
         };
-        assert_eq!(filter_out_connection(&conn, &filter_by_open_state), true);
+        assert!(filter_out_connection(&conn, &filter_by_open_state, 0));
 
         let no_active_open_filter = FilterOptions {
             by_open: false,
             ..Default::default()
         };
-        assert_eq!(filter_out_connection(&conn, &no_active_open_filter), false);
+        assert!(!filter_out_connection(&conn, &no_active_open_filter, 0));
 
         conn.state = "listen".to_string();
 
@@ -314,24 +2124,332 @@ mod tests {
             by_listen: true,
             ..Default::default()
         };
-        assert_eq!(filter_out_connection(&conn, &filter_by_listen_state), false);
+        assert!(!filter_out_connection(&conn, &filter_by_listen_state, 0));
 
         let no_active_listen_filter = FilterOptions {
             by_listen: false,
             ..Default::default()
         };
+        assert!(!filter_out_connection(&conn, &no_active_listen_filter, 0));
+    }
+
+    #[test]
+    fn test_state_category() {
+        assert_eq!(state_category("listen"), Some(StateCategory::Listening));
         assert_eq!(
-            filter_out_connection(&conn, &no_active_listen_filter),
-            false
+            state_category("established"),
+            Some(StateCategory::Established)
+        );
+        assert_eq!(state_category("syn-sent"), Some(StateCategory::Waiting));
+        assert_eq!(state_category("timewait"), Some(StateCategory::Waiting));
+        assert_eq!(state_category("close-wait"), Some(StateCategory::Closing));
+        assert_eq!(state_category("lastack"), Some(StateCategory::Closing));
+        assert_eq!(state_category("close"), None);
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_include() {
+        let listening_and_established = FilterOptions {
+            by_include: vec![StateCategory::Listening, StateCategory::Established],
+            ..Default::default()
+        };
+
+        let mut conn = Connection {
+            state: "listen".to_string(),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &listening_and_established, 0));
+
+        conn.state = "established".to_string();
+        assert!(!filter_out_connection(&conn, &listening_and_established, 0));
+
+        conn.state = "time-wait".to_string();
+        assert!(filter_out_connection(&conn, &listening_and_established, 0));
+
+        conn.state = "close".to_string();
+        assert!(filter_out_connection(&conn, &listening_and_established, 0));
+    }
+
+    #[test]
+    fn test_filter_unresolved_only() {
+        let unresolved_only = FilterOptions {
+            by_unresolved: true,
+            ..Default::default()
+        };
+
+        let mut conn = Connection {
+            program: "-".to_string(),
+            pid: "-".to_string(),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &unresolved_only, 0));
+
+        conn.program = "nginx".to_string();
+        conn.pid = "123".to_string();
+        assert!(filter_out_connection(&conn, &unresolved_only, 0));
+    }
+
+    #[test]
+    fn test_filter_by_since() {
+        assert!(is_recent(Some(1_000), 1_029, 30, false));
+        assert!(!is_recent(Some(1_000), 1_030, 30, false));
+        // a clock which went backwards doesn't make a connection old
+        assert!(is_recent(Some(1_100), 1_000, 30, false));
+        assert!(!is_recent(None, 1_000, 30, false));
+        assert!(is_recent(None, 1_000, 30, true));
+
+        let now = 1_700_000_000;
+        let since = FilterOptions {
+            by_since: Some(300),
+            ..Default::default()
+        };
+        let mut conn = Connection {
+            created: Some(now - 10),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &since, now));
+        conn.created = Some(now - 3600);
+        assert!(filter_out_connection(&conn, &since, now));
+        conn.created = None;
+        assert!(filter_out_connection(&conn, &since, now));
+        assert!(!filter_out_connection(
+            &conn,
+            &FilterOptions {
+                include_unknown_age: true,
+                ..since
+            },
+            now
+        ));
+        assert!(!filter_out_connection(
+            &conn,
+            &FilterOptions::default(),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_filter_by_privileged() {
+        let privileged = FilterOptions {
+            by_privileged: Some(true),
+            by_listen: true,
+            ..Default::default()
+        };
+        let unprivileged = FilterOptions {
+            by_privileged: Some(false),
+            ..Default::default()
+        };
+
+        let mut conn = Connection {
+            local_port: "22".to_string(),
+            state: "listen".to_string(),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &privileged, 0));
+        assert!(filter_out_connection(&conn, &unprivileged, 0));
+
+        conn.local_port = "1024".to_string();
+        assert!(filter_out_connection(&conn, &privileged, 0));
+        assert!(!filter_out_connection(&conn, &unprivileged, 0));
+
+        // only listening sockets on privileged ports are kept
+        conn.local_port = "443".to_string();
+        conn.state = "established".to_string();
+        assert!(filter_out_connection(&conn, &privileged, 0));
+
+        assert!(is_privileged_port("1"));
+        assert!(is_privileged_port("1023"));
+        assert!(!is_privileged_port("0"));
+        assert!(!is_privileged_port("65536"));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_local_address() {
+        use crate::schemas::AddressFilter;
+
+        let mut conn = Connection {
+            local_address: "192.168.1.20".to_string(),
+            ..Default::default()
+        };
+
+        let by_network = FilterOptions {
+            by_local_address: Some("192.168.0.0/16".parse::<AddressFilter>().unwrap()),
+            ..Default::default()
+        };
+        let by_any = FilterOptions {
+            by_local_address: Some(AddressFilter::Any),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &by_network, 0));
+        assert!(filter_out_connection(&conn, &by_any, 0));
+
+        conn.local_address = "[::]".to_string();
+        assert!(filter_out_connection(&conn, &by_network, 0));
+        assert!(!filter_out_connection(&conn, &by_any, 0));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_needs_privilege_hint() {
+        let resolved = || connection_with("80", "42", "nginx");
+        let unresolved = || connection_with("8080", "-", "-");
+        let time_wait = || Connection {
+            state: "timewait".to_string(),
+            ..connection_with("443", "-", "-")
+        };
+
+        assert!(!needs_privilege_hint(&[resolved(), resolved()], false));
+        assert!(!needs_privilege_hint(&[], false));
+        assert!(needs_privilege_hint(&[resolved(), unresolved()], false));
+        assert!(!needs_privilege_hint(&[resolved(), unresolved()], true));
+
+        // below the threshold the hint isn't worth it
+        let mostly_resolved = [resolved(), resolved(), resolved(), resolved(), unresolved()];
+        assert!(!needs_privilege_hint(&mostly_resolved, false));
+
+        // sockets in time-wait never have a process
+        let mut connections = vec![resolved(), resolved(), resolved()];
+        connections.extend((0..10).map(|_| time_wait()));
+        assert!(!needs_privilege_hint(&connections, false));
+
+        assert_eq!(
+            lacks_privileges(&[unresolved()]),
+            !Uid::effective().is_root()
         );
     }
 
+    #[test]
+    fn test_filter_by_container() {
+        let mut connections: Vec<Connection> = [Some("web"), None, Some("3f4e8a1b2c9d")]
+            .iter()
+            .map(|container| Connection {
+                container: container.map(|container| container.to_string()),
+                ..Default::default()
+            })
+            .collect();
+
+        filter_by_container(&mut connections, "WEB");
+        assert_eq!(connections.len(), 1);
+
+        // a container whose name is known still matches the beginning of its ID
+        let mut connections = vec![Connection {
+            container: Some("web".to_string()),
+            container_id: Some("3f4e8a1b2c9d0e7f".to_string()),
+            ..Default::default()
+        }];
+        filter_by_container(&mut connections, "3F4E");
+        assert_eq!(connections.len(), 1);
+        // only the beginning of the ID matches
+        filter_by_container(&mut connections, "8a1b");
+        assert!(connections.is_empty());
+
+        let mut connections = vec![Connection {
+            container: Some("3f4e8a1b2c9d".to_string()),
+            container_id: Some("3f4e8a1b2c9d0e7f".to_string()),
+            ..Default::default()
+        }];
+        filter_by_container(&mut connections, "3f4e8a1b2c9d0e");
+        assert_eq!(connections.len(), 1);
+        filter_by_container(&mut connections, "db");
+        assert!(connections.is_empty());
+    }
+
+    #[test]
+    fn test_deduplicate_connections() {
+        let udp = Connection {
+            proto: "udp".to_string(),
+            local_port: "5353".to_string(),
+            pid: "42".to_string(),
+            state: "close".to_string(),
+            ..Default::default()
+        };
+        let tcp = Connection {
+            proto: "tcp".to_string(),
+            ..udp.clone()
+        };
+
+        let mut connections = vec![udp.clone(), tcp.clone(), udp.clone(), udp.clone()];
+        deduplicate_connections(&mut connections, false);
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].proto, "udp");
+        assert!(connections[0].duplicates.is_none());
+
+        let mut connections = vec![udp.clone(), tcp, udp];
+        deduplicate_connections(&mut connections, true);
+        let duplicates: Vec<Option<usize>> = connections.iter().map(|c| c.duplicates).collect();
+        assert_eq!(duplicates, vec![Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn test_filter_by_unit() {
+        let mut connections: Vec<Connection> = [Some("nginx.service"), None, Some("sshd.service")]
+            .iter()
+            .map(|unit| Connection {
+                unit: unit.map(|unit| unit.to_string()),
+                ..Default::default()
+            })
+            .collect();
+
+        filter_by_unit(&mut connections, "Nginx");
+        assert_eq!(connections.len(), 1);
+        filter_by_unit(&mut connections, "nginx.service");
+        assert_eq!(connections.len(), 1);
+        filter_by_unit(&mut connections, "nginx.socket");
+        assert!(connections.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_and_validate_state() {
+        assert_eq!(normalize_state("TIME_WAIT"), "timewait");
+        assert_eq!(normalize_state("time-wait"), "timewait");
+        assert_eq!(normalize_state("Established"), "established");
+
+        assert!(is_valid_state("time-wait"));
+        assert!(is_valid_state("CLOSE_WAIT"));
+        assert!(is_valid_state("listen"));
+        assert!(!is_valid_state("waiting"));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_state_flag() {
+        use crate::schemas::{AddressType, Connection, FilterOptions};
+
+        let conn = Connection {
+            proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: "8080".to_string(),
+            remote_port: "443".to_string(),
+            remote_address: "8.8.8.8".to_string(),
+            program: "nginx".to_string(),
+            pid: "123".to_string(),
+            state: "timewait".to_string(),
+            address_type: AddressType::Extern,
+            ..Default::default()
+        };
+
+        let filter_by_matching_state = FilterOptions {
+            by_state: Some("TIME-WAIT".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &filter_by_matching_state, 0));
+
+        let filter_by_non_matching_state = FilterOptions {
+            by_state: Some("established".to_string()),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(
+            &conn,
+            &filter_by_non_matching_state,
+            0
+        ));
+    }
+
     #[test]
     fn test_filter_out_connection_by_pid_and_program() {
         use crate::schemas::{AddressType, Connection, FilterOptions};
 
         let conn = Connection {
             proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
             local_port: "8080".to_string(),
             remote_port: "443".to_string(),
             remote_address: "8.8.8.8".to_string(),
@@ -339,27 +2457,161 @@ mod tests {
             pid: "123".to_string(),
             state: "close".to_string(),
             address_type: AddressType::Extern,
+            ..Default::default()
         };
 
         let filter_by_open_state = FilterOptions {
-            by_pid: Some("123".to_string()),
+            by_pid: Some(PidFilter::Pid(123)),
             ..Default::default()
         };
-        assert_eq!(filter_out_connection(&conn, &filter_by_open_state), false);
+        assert!(!filter_out_connection(&conn, &filter_by_open_state, 0));
 
         let no_active_open_filter = FilterOptions {
-            by_program: Some("postgres".to_string()),
+            by_program: Some(ProgramMatcher::Substring("postgres".to_string())),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &no_active_open_filter, 0));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_program_substring() {
+        let conn = Connection {
+            program: "Chrome".to_string(),
+            ..Default::default()
+        };
+
+        let filter_by_substring = FilterOptions {
+            by_program: Some(ProgramMatcher::Substring("chr".to_string())),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &filter_by_substring, 0));
+
+        let filter_by_other_program = FilterOptions {
+            by_program: Some(ProgramMatcher::Substring("firefox".to_string())),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &filter_by_other_program, 0));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_resolve_user_filter() {
+        // with --numeric the users of the connections are their UID
+        assert_eq!(resolve_user_filter("root", true), "0");
+        assert_eq!(resolve_user_filter("0", true), "0");
+        assert_eq!(resolve_user_filter("root", false), "root");
+        assert_eq!(resolve_user_filter("0", false), "root");
+        // unknown users never match, but are kept so that the filter doesn't match everything
+        assert_eq!(resolve_user_filter("no-such-user", true), "no-such-user");
+        assert_eq!(resolve_user_filter("4000000000", false), "4000000000");
+
+        let root = Connection {
+            user: Some("0".to_string()),
+            ..Default::default()
+        };
+        let filter_options = FilterOptions {
+            by_user: Some(resolve_user_filter("root", true)),
+            numeric_users: true,
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&root, &filter_options, 0));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_user() {
+        let conn = Connection {
+            user: Some("postgres".to_string()),
+            ..Default::default()
+        };
+        let unknown_user = Connection::default();
+
+        let filter_by_user = FilterOptions {
+            by_user: Some("postgres".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &filter_by_user, 0));
+        assert!(filter_out_connection(&unknown_user, &filter_by_user, 0));
+
+        let filter_by_other_user = FilterOptions {
+            by_user: Some("root".to_string()),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &filter_by_other_user, 0));
+    }
+
+    #[test]
+    fn test_filter_out_connection_by_exclude_filters() {
+        use crate::schemas::{AddressFilter, PortFilter};
+
+        let conn = Connection {
+            proto: "tcp".to_string(),
+            local_port: "8080".to_string(),
+            remote_address: "10.0.0.5".to_string(),
+            program: "chrome".to_string(),
+            ..Default::default()
+        };
+
+        let exclude_self = FilterOptions {
+            exclude_self: true,
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &exclude_self, 0));
+        let own = Connection {
+            pid: std::process::id().to_string(),
+            ..conn.clone()
+        };
+        assert!(filter_out_connection(&own, &exclude_self, 0));
+        assert!(!filter_out_connection(&own, &FilterOptions::default(), 0));
+
+        let exclude_program = FilterOptions {
+            exclude_program: Some("CHROME".to_string()),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &exclude_program, 0));
+
+        let exclude_other_program = FilterOptions {
+            exclude_program: Some("firefox".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter_out_connection(&conn, &exclude_other_program, 0));
+
+        let exclude_network = FilterOptions {
+            exclude_remote_address: Some("10.0.0.0/8".parse::<AddressFilter>().unwrap()),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &exclude_network, 0));
+
+        let exclude_port = FilterOptions {
+            exclude_local_port: Some(PortFilter::Port(8080)),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &exclude_port, 0));
+    }
+
+    #[test]
+    fn test_filter_out_connection_exclude_wins_over_include() {
+        let conn = Connection {
+            proto: "tcp".to_string(),
+            program: "chrome".to_string(),
             ..Default::default()
         };
-        assert_eq!(filter_out_connection(&conn, &no_active_open_filter), true);
+
+        let include_and_exclude = FilterOptions {
+            by_proto: vec!["tcp".to_string()],
+            by_program: Some(ProgramMatcher::Substring("chrome".to_string())),
+            exclude_program: Some("chrome".to_string()),
+            ..Default::default()
+        };
+        assert!(filter_out_connection(&conn, &include_and_exclude, 0));
     }
 
     #[test]
     fn test_filter_out_connection_by_multiple_conditions() {
-        use crate::schemas::{AddressType, Connection, FilterOptions};
+        use crate::schemas::{AddressType, Connection, FilterOptions, PortFilter};
 
         let mut conn = Connection {
             proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
             local_port: "8080".to_string(),
             remote_port: "443".to_string(),
             remote_address: "8.8.8.8".to_string(),
@@ -367,24 +2619,168 @@ mod tests {
             pid: "123".to_string(),
             state: "listen".to_string(),
             address_type: AddressType::Extern,
+            ..Default::default()
         };
 
         let filter_by_multiple_conditions = FilterOptions {
-            by_local_port: Some("8080".to_string()),
-            by_pid: Some("123".to_string()),
-            by_program: Some("python".to_string()),
+            by_local_port: Some(PortFilter::Port(8080)),
+            by_pid: Some(PidFilter::Pid(123)),
+            by_program: Some(ProgramMatcher::Substring("python".to_string())),
             by_listen: true,
             ..Default::default()
         };
+        assert!(!filter_out_connection(
+            &conn,
+            &filter_by_multiple_conditions,
+            0
+        ));
+
+        conn.state = "close".to_string();
+        assert!(filter_out_connection(
+            &conn,
+            &filter_by_multiple_conditions,
+            0
+        ));
+    }
+
+    fn connection_with(local_port: &str, pid: &str, program: &str) -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: local_port.to_string(),
+            remote_port: "0".to_string(),
+            remote_address: "0.0.0.0".to_string(),
+            program: program.to_string(),
+            pid: pid.to_string(),
+            state: "listen".to_string(),
+            address_type: AddressType::Unspecified,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_connections_numeric() {
+        let mut connections = vec![
+            connection_with("1000", "1", "a"),
+            connection_with("80", "2", "b"),
+            connection_with("8080", "3", "c"),
+        ];
+
+        sort_connections(&mut connections, SortField::LocalPort, false);
+        let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["80", "1000", "8080"]);
+
+        sort_connections(&mut connections, SortField::LocalPort, true);
+        let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["8080", "1000", "80"]);
+    }
+
+    #[test]
+    fn test_sort_connections_unknown_last() {
+        let mut connections = vec![
+            connection_with("22", "-", "-"),
+            connection_with("80", "1000", "nginx"),
+            connection_with("443", "80", "caddy"),
+        ];
+
+        sort_connections(&mut connections, SortField::Pid, false);
+        let pids: Vec<&str> = connections.iter().map(|c| c.pid.as_str()).collect();
+        assert_eq!(pids, vec!["80", "1000", "-"]);
+
+        sort_connections(&mut connections, SortField::Pid, true);
+        let pids: Vec<&str> = connections.iter().map(|c| c.pid.as_str()).collect();
+        assert_eq!(pids, vec!["1000", "80", "-"]);
+
+        sort_connections(&mut connections, SortField::Program, true);
+        let programs: Vec<&str> = connections.iter().map(|c| c.program.as_str()).collect();
+        assert_eq!(programs, vec!["nginx", "caddy", "-"]);
+    }
+
+    #[test]
+    fn test_sort_connections_by_age() {
+        let connection = |local_port: &str, created: Option<u64>| Connection {
+            local_port: local_port.to_string(),
+            created,
+            ..Default::default()
+        };
+        let mut connections = vec![
+            connection("22", None),
+            connection("80", Some(1_700_000_500)),
+            connection("443", Some(1_700_000_000)),
+        ];
+
+        sort_connections(&mut connections, SortField::Age, false);
+        let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["443", "80", "22"]);
+
+        sort_connections(&mut connections, SortField::Age, true);
+        let ports: Vec<&str> = connections.iter().map(|c| c.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["80", "443", "22"]);
+    }
+
+    #[test]
+    fn test_group_connections() {
+        let connection = |program: &str, state: &str| Connection {
+            program: program.to_string(),
+            state: state.to_string(),
+            ..Default::default()
+        };
+        let connections = [
+            connection("sshd", "listen"),
+            connection("chrome", "established"),
+            connection("chrome", "established"),
+            connection("chrome", "timewait"),
+            connection("nginx", "listen"),
+        ];
+
+        let groups = group_connections(&connections, GroupField::Program);
+        let keys: Vec<(&str, usize)> = groups.iter().map(|g| (g.key.as_str(), g.count)).collect();
+        assert_eq!(keys, vec![("chrome", 3), ("nginx", 1), ("sshd", 1)]);
         assert_eq!(
-            filter_out_connection(&conn, &filter_by_multiple_conditions),
-            false
+            groups[0].states,
+            BTreeMap::from([("established".to_string(), 2), ("timewait".to_string(), 1)])
         );
 
-        conn.state = "close".to_string();
+        let groups = group_connections(&connections, GroupField::State);
+        let keys: Vec<(&str, usize)> = groups.iter().map(|g| (g.key.as_str(), g.count)).collect();
+        assert_eq!(
+            keys,
+            vec![("established", 2), ("listen", 2), ("timewait", 1)]
+        );
+    }
+
+    #[test]
+    fn test_group_remote_addresses() {
+        let connection = |remote_address: &str, local_port: &str, program: &str| Connection {
+            remote_address: remote_address.to_string(),
+            local_port: local_port.to_string(),
+            program: program.to_string(),
+            address_type: get_address_type(remote_address),
+            ..Default::default()
+        };
+        let connections = [
+            connection("10.0.0.5", "5432", "postgres"),
+            connection("10.0.0.5", "5432", "postgres"),
+            connection("10.0.0.5", "443", "nginx"),
+            connection("10.0.0.6", "443", "nginx"),
+            connection("0.0.0.0", "22", "sshd"),
+        ];
+
+        let groups = group_remote_addresses(&connections);
+        let keys: Vec<(&str, usize)> = groups.iter().map(|g| (g.key.as_str(), g.count)).collect();
+        assert_eq!(keys, vec![("10.0.0.5", 3), ("10.0.0.6", 1)]);
+        assert_eq!(
+            groups[0].programs,
+            BTreeSet::from(["nginx".to_string(), "postgres".to_string()])
+        );
         assert_eq!(
-            filter_out_connection(&conn, &filter_by_multiple_conditions),
-            true
+            groups[0].local_ports,
+            BTreeSet::from(["443".to_string(), "5432".to_string()])
         );
     }
+
+    #[test]
+    fn test_group_connections_empty() {
+        assert!(group_connections(&[], GroupField::Proto).is_empty());
+    }
 }
@@ -0,0 +1,196 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::get_user_name;
+use crate::schemas::ProcessInfo;
+
+/// The details of a process which don't change while it is running, as read by a `ProcessReader`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessDetails {
+    pub program: String,
+    pub uid: Option<u32>,
+    pub command: String,
+    /// The resolved path of the executable and whether it was deleted, if it is readable.
+    pub exe_path: Option<(String, bool)>,
+    /// The start of the process as a Unix timestamp in seconds.
+    pub started: Option<u64>,
+}
+
+/// Reads the details of a single process, e.g from `/proc/<pid>`.
+/// Tests use their own reader to count how often every process is read.
+pub trait ProcessReader: Sync {
+    /// Reads the details of a process.
+    ///
+    /// # Arguments
+    /// * `pid`: The PID of the process.
+    ///
+    /// # Returns
+    /// The details or `None` if the process exited in the meantime or can't be accessed.
+    fn read_details(&self, pid: i32) -> Option<ProcessDetails>;
+}
+
+/// Caches the processes by their PID, so that every process is only read once even if it owns many sockets.
+/// The cache can be kept across the refreshes of the watch mode, processes which exited are removed on every update.
+/// A PID which is reused between two updates keeps the details of the previous process.
+#[derive(Debug, Default)]
+pub struct ProcessCache {
+    processes: HashMap<i32, Option<ProcessInfo>>,
+    user_names: HashMap<u32, String>,
+    /// Whether the cached processes hold the UID of their user instead of its name.
+    numeric_users: bool,
+}
+
+impl ProcessCache {
+    /// Removes the processes which aren't running anymore and reads all processes which aren't cached yet in parallel.
+    ///
+    /// # Arguments
+    /// * `pids`: The PIDs of all running processes.
+    /// * `reader`: Reads the details of a process which isn't cached.
+    /// * `numeric_users`: Whether the users are their UID instead of their name, the cache is cleared if this changes.
+    ///
+    /// # Returns
+    /// None
+    pub fn update(&mut self, pids: &[i32], reader: &impl ProcessReader, numeric_users: bool) {
+        if self.numeric_users != numeric_users {
+            self.processes.clear();
+            self.numeric_users = numeric_users;
+        }
+        let running: HashSet<i32> = pids.iter().copied().collect();
+        self.processes.retain(|pid, _| running.contains(pid));
+
+        let missing: Vec<i32> = running
+            .into_iter()
+            .filter(|pid| !self.processes.contains_key(pid))
+            .collect();
+        let read: Vec<(i32, Option<ProcessDetails>)> = missing
+            .into_par_iter()
+            .map(|pid| (pid, reader.read_details(pid)))
+            .collect();
+
+        // the user names are looked up afterwards since their cache isn't shared between threads
+        for (pid, details) in read {
+            let process_info = details.map(|details| ProcessInfo {
+                pid,
+                program: details.program,
+                user: details
+                    .uid
+                    .map(|uid| get_user_name(uid, &mut self.user_names, numeric_users)),
+                command: details.command,
+                exe_deleted: details
+                    .exe_path
+                    .as_ref()
+                    .is_some_and(|(_, deleted)| *deleted),
+                exe_path: details.exe_path.map(|(path, _)| path),
+                created: details.started,
+            });
+            self.processes.insert(pid, process_info);
+        }
+    }
+
+    /// Gets a cached process.
+    ///
+    /// # Arguments
+    /// * `pid`: The PID of the process.
+    ///
+    /// # Returns
+    /// The process or `None` if it isn't cached or couldn't be read.
+    pub fn get(&self, pid: i32) -> Option<&ProcessInfo> {
+        self.processes.get(&pid)?.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Counts how often every process is read.
+    #[derive(Default)]
+    struct CountingReader {
+        reads: Mutex<HashMap<i32, usize>>,
+    }
+
+    impl CountingReader {
+        fn reads(&self, pid: i32) -> usize {
+            self.reads.lock().unwrap().get(&pid).copied().unwrap_or(0)
+        }
+
+        fn total_reads(&self) -> usize {
+            self.reads.lock().unwrap().values().sum()
+        }
+    }
+
+    impl ProcessReader for CountingReader {
+        fn read_details(&self, pid: i32) -> Option<ProcessDetails> {
+            *self.reads.lock().unwrap().entry(pid).or_insert(0) += 1;
+            // even PIDs are processes which can't be accessed
+            (pid % 2 == 1).then(|| ProcessDetails {
+                program: format!("program-{}", pid),
+                uid: Some(0),
+                command: format!("/usr/bin/program-{}", pid),
+                exe_path: None,
+                started: Some(1_700_000_000),
+            })
+        }
+    }
+
+    #[test]
+    fn test_update_reads_every_process_once() {
+        let reader = CountingReader::default();
+        let mut cache = ProcessCache::default();
+
+        // a process owning many sockets is listed once per socket
+        cache.update(&[1, 1, 1, 3, 3, 4], &reader, false);
+        assert_eq!(reader.total_reads(), 3);
+        assert_eq!(cache.get(1).unwrap().program, "program-1");
+        assert_eq!(cache.get(3).unwrap().pid, 3);
+        assert!(cache.get(4).is_none());
+
+        cache.update(&[1, 3, 4], &reader, false);
+        assert_eq!(reader.total_reads(), 3);
+        assert_eq!(reader.reads(4), 1);
+    }
+
+    #[test]
+    fn test_update_invalidates_exited_processes() {
+        let reader = CountingReader::default();
+        let mut cache = ProcessCache::default();
+
+        cache.update(&[1, 3], &reader, false);
+        cache.update(&[3, 5], &reader, false);
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(5).is_some());
+        assert_eq!(reader.reads(3), 1);
+
+        // a process which was removed is read again when it comes back
+        cache.update(&[1, 3, 5], &reader, false);
+        assert_eq!(reader.reads(1), 2);
+        assert_eq!(reader.total_reads(), 4);
+    }
+
+    #[test]
+    fn test_update_numeric_users() {
+        let reader = CountingReader::default();
+        let mut cache = ProcessCache::default();
+
+        cache.update(&[1], &reader, true);
+        assert_eq!(cache.get(1).unwrap().user.as_deref(), Some("0"));
+
+        // the cached process has the UID as its user, so it is read again when the names are wanted
+        cache.update(&[1], &reader, false);
+        assert_eq!(reader.reads(1), 2);
+        assert_eq!(cache.get(1).unwrap().user.as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn test_update_many_processes() {
+        let reader = CountingReader::default();
+        let mut cache = ProcessCache::default();
+        let pids: Vec<i32> = (1..=10_000).collect();
+
+        cache.update(&pids, &reader, false);
+        cache.update(&pids, &reader, false);
+        assert_eq!(reader.total_reads(), 10_000);
+        assert_eq!(cache.get(9_999).unwrap().command, "/usr/bin/program-9999");
+    }
+}
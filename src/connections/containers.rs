@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::schemas::Connection;
+
+/// The socket of the Docker daemon, used to look up the names of containers.
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+/// The maximum time to wait for an answer of the Docker daemon.
+const DOCKER_TIMEOUT: Duration = Duration::from_millis(500);
+/// The number of characters of a container ID which are shown if its name is unknown, like `docker ps` does.
+const SHORT_ID_LENGTH: usize = 12;
+
+/// Extracts the ID of a container from the cgroup file of a process.
+/// Both cgroup v1 (e.g "12:memory:/docker/<id>") and cgroup v2 (e.g "0::/system.slice/docker-<id>.scope") are supported,
+/// as well as the layouts of Podman, containerd and CRI-O.
+///
+/// # Arguments
+/// * `cgroup`: The content of `/proc/<pid>/cgroup`.
+///
+/// # Example
+/// ```
+/// # use somo::connections::parse_container_id;
+/// let id = "a".repeat(64);
+/// assert_eq!(parse_container_id(&format!("0::/system.slice/docker-{}.scope", id)), Some(id));
+/// assert_eq!(parse_container_id("0::/user.slice/user-1000.slice"), None);
+/// ```
+///
+/// # Returns
+/// The full container ID or `None` if the process doesn't run in a container.
+pub fn parse_container_id(cgroup: &str) -> Option<String> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.split('/'))
+        .map(|component| {
+            let component = component.trim_end_matches(".scope");
+            ["docker-", "libpod-", "cri-containerd-", "crio-"]
+                .iter()
+                .find_map(|prefix| component.strip_prefix(prefix))
+                .unwrap_or(component)
+        })
+        .rfind(|id| id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|id| id.to_string())
+}
+
+/// Gets the container ID of a process by reading its cgroup file.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+///
+/// # Returns
+/// The full container ID or `None` if the process doesn't run in a container or the file couldn't be read.
+fn get_container_id(pid: &str) -> Option<String> {
+    let cgroup = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    parse_container_id(&cgroup)
+}
+
+/// Extracts the container name from a response of the Docker "inspect" endpoint.
+///
+/// # Arguments
+/// * `response`: The raw HTTP response including its headers.
+///
+/// # Returns
+/// The container name without its leading "/" or `None` if the response has no name.
+fn parse_docker_name(response: &str) -> Option<String> {
+    let (_, body) = response.split_once("\r\n\r\n")?;
+    let body = &body[body.find('{')?..];
+    // the body may be followed by the end of a chunked response, so only the first JSON value is read
+    let inspect: serde_json::Value = serde_json::Deserializer::from_str(body)
+        .into_iter()
+        .next()?
+        .ok()?;
+    inspect["Name"]
+        .as_str()
+        .map(|name| name.trim_start_matches('/').to_string())
+}
+
+/// Asks the Docker daemon for the name of a container.
+///
+/// # Arguments
+/// * `id`: The full container ID.
+///
+/// # Returns
+/// The name of the container or `None` if Docker isn't running, the socket can't be accessed or the container is unknown to Docker.
+fn lookup_docker_name(id: &str) -> Option<String> {
+    let mut stream = UnixStream::connect(DOCKER_SOCKET).ok()?;
+    stream.set_read_timeout(Some(DOCKER_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(DOCKER_TIMEOUT)).ok()?;
+
+    let request = format!(
+        "GET /containers/{}/json HTTP/1.0\r\nHost: docker\r\n\r\n",
+        id
+    );
+    stream.write_all(request.as_bytes()).ok()?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    parse_docker_name(&response)
+}
+
+/// Sets the container and its full ID of all connections whose process runs in a container.
+/// The name is looked up with the Docker daemon, if that's not possible the shortened container ID is used instead.
+/// Every process and container is only looked up once.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `container`.
+///
+/// # Returns
+/// None
+pub fn annotate_containers(connections: &mut [Connection]) {
+    let mut containers: HashMap<String, Option<String>> = HashMap::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    for connection in connections.iter_mut() {
+        if connection.pid == "-" {
+            continue;
+        }
+
+        let id = containers
+            .entry(connection.pid.clone())
+            .or_insert_with(|| get_container_id(&connection.pid));
+        connection.container_id = id.clone();
+        connection.container = id.as_ref().map(|id| {
+            names
+                .entry(id.clone())
+                .or_insert_with(|| {
+                    lookup_docker_name(id).unwrap_or_else(|| id[..SHORT_ID_LENGTH].to_string())
+                })
+                .clone()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4e8a1b2c9d0e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f";
+
+    #[test]
+    fn test_parse_container_id_cgroup_v1() {
+        let cgroup = format!(
+            "12:memory:/docker/{}\n11:cpu,cpuacct:/docker/{}\n1:name=systemd:/docker/{}",
+            ID, ID, ID
+        );
+        assert_eq!(parse_container_id(&cgroup), Some(ID.to_string()));
+
+        let kubernetes = format!("4:pids:/kubepods/besteffort/pod1234/{}", ID);
+        assert_eq!(parse_container_id(&kubernetes), Some(ID.to_string()));
+    }
+
+    #[test]
+    fn test_parse_container_id_cgroup_v2() {
+        let docker = format!("0::/system.slice/docker-{}.scope", ID);
+        assert_eq!(parse_container_id(&docker), Some(ID.to_string()));
+
+        let podman = format!(
+            "0::/user.slice/user-1000.slice/libpod-{}.scope/container",
+            ID
+        );
+        assert_eq!(parse_container_id(&podman), Some(ID.to_string()));
+    }
+
+    #[test]
+    fn test_parse_container_id_host() {
+        assert_eq!(parse_container_id("0::/init.scope"), None);
+        assert_eq!(
+            parse_container_id("0::/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+        assert_eq!(parse_container_id(""), None);
+    }
+
+    #[test]
+    fn test_parse_docker_name() {
+        let response = "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"Id\":\"abc\",\"Name\":\"/web\"}\n";
+        assert_eq!(parse_docker_name(response), Some("web".to_string()));
+
+        let not_found = "HTTP/1.0 404 Not Found\r\n\r\n{\"message\":\"No such container\"}";
+        assert_eq!(parse_docker_name(not_found), None);
+    }
+
+    #[test]
+    fn test_annotate_containers_unknown_pid() {
+        let mut connections = vec![Connection {
+            pid: "-".to_string(),
+            ..Default::default()
+        }];
+        annotate_containers(&mut connections);
+        assert!(connections[0].container.is_none());
+    }
+}
@@ -0,0 +1,1247 @@
+use procfs::net::{TcpNetEntry, UdpNetEntry};
+use procfs::process::{FDTarget, Process};
+use procfs::ProcError;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, Cursor};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+
+use super::cache::{ProcessCache, ProcessDetails, ProcessReader};
+use super::Platform;
+use crate::error::SomoError;
+use crate::schemas::NetEntry;
+use crate::schemas::ProcessInfo;
+use crate::schemas::SocketQueues;
+
+/// Formats the path of a failed procfs read for an error message.
+fn describe_path(path: Option<PathBuf>) -> String {
+    path.map(|path| path.display().to_string())
+        .unwrap_or_else(|| "/proc".to_string())
+}
+
+impl From<ProcError> for SomoError {
+    fn from(error: ProcError) -> Self {
+        match error {
+            ProcError::PermissionDenied(path) => SomoError::PermissionDenied(describe_path(path)),
+            ProcError::NotFound(path) => {
+                SomoError::ProcRead(format!("{} doesn't exist", describe_path(path)))
+            }
+            ProcError::Io(error, path) => {
+                SomoError::ProcRead(format!("{}: {}", describe_path(path), error))
+            }
+            ProcError::Incomplete(path) => {
+                SomoError::Parse(format!("{} is incomplete", describe_path(path)))
+            }
+            ProcError::Other(message) => SomoError::Parse(message),
+            ProcError::InternalError(error) => SomoError::Parse(error.to_string()),
+        }
+    }
+}
+
+/// Joins the arguments of a process' command line, kernel threads have no command line and get the program name in brackets instead.
+///
+/// # Arguments
+/// * `cmdline`: The arguments read from `/proc/<pid>/cmdline`.
+/// * `program`: The program name of the process.
+///
+/// # Example
+/// ```ignore
+/// assert_eq!(get_command(vec![], "kworker"), "[kworker]");
+/// ```
+///
+/// # Returns
+/// The full command line.
+fn get_command(cmdline: Vec<String>, program: &str) -> String {
+    if cmdline.is_empty() {
+        return format!("[{}]", program);
+    }
+    cmdline.join(" ")
+}
+
+/// Splits the " (deleted)" suffix off the executable of a process, Linux appends it when the binary was removed or replaced.
+///
+/// # Arguments
+/// * `exe`: The target of the `/proc/<pid>/exe` link.
+///
+/// # Example
+/// ```ignore
+/// assert_eq!(parse_exe_path(Path::new("/usr/sbin/nginx (deleted)")), ("/usr/sbin/nginx".to_string(), true));
+/// ```
+///
+/// # Returns
+/// The path of the executable and whether it was deleted.
+fn parse_exe_path(exe: &Path) -> (String, bool) {
+    let path = exe.to_string_lossy();
+    match path.strip_suffix(" (deleted)") {
+        Some(path) => (path.to_string(), true),
+        None => (path.into_owned(), false),
+    }
+}
+
+/// Gets the time at which a file descriptor of a process was first listed by the kernel, which is close to the creation of the socket for long-lived connections.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+/// * `fd`: The file descriptor of the socket.
+///
+/// # Returns
+/// The modification time of `/proc/<pid>/fd/<fd>` as a Unix timestamp in seconds or `None` if it can't be read.
+fn get_fd_time(pid: i32, fd: i32) -> Option<u64> {
+    fs::symlink_metadata(format!("/proc/{}/fd/{}", pid, fd))
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// The processes read by previous calls, kept so that the refreshes of the watch mode only read new processes.
+static PROCESS_CACHE: LazyLock<Arc<Mutex<ProcessCache>>> = LazyLock::new(Default::default);
+
+/// The inode and the creation time of a socket.
+type SocketTime = (u64, Option<u64>);
+
+/// The raw data which somo reads from `/proc`.
+/// `ProcFs` reads the real files, tests use fixtures instead so that parsing and filtering can be tested on every platform.
+pub trait ProcSource: Sync {
+    /// Checks if `/proc` can be read at all, it may be missing in minimal containers.
+    fn is_available(&self) -> bool;
+
+    /// Reads the IPv4 and the IPv6 TCP socket tables, ie. the content of `/proc/net/tcp` and `/proc/net/tcp6`.
+    fn tcp_table(&self) -> Result<Vec<String>, SomoError>;
+
+    /// Reads the IPv4 and the IPv6 UDP socket tables, ie. the content of `/proc/net/udp` and `/proc/net/udp6`.
+    fn udp_table(&self) -> Result<Vec<String>, SomoError>;
+
+    /// Lists the PIDs of all running processes.
+    fn pids(&self) -> Result<Vec<i32>, SomoError>;
+
+    /// Checks if a process is still running, ie. if `/proc/<pid>` is still there.
+    fn exists(&self, pid: i32) -> bool;
+
+    /// Reads the sockets of a process from its file descriptors, `None` if the process can't be accessed.
+    fn sockets(&self, pid: i32) -> Option<Vec<SocketTime>>;
+
+    /// Reads the program name and the start time (as a Unix timestamp in seconds) of a process.
+    fn stat(&self, pid: i32) -> Option<(String, Option<u64>)>;
+
+    /// Reads the arguments of the command line of a process.
+    fn cmdline(&self, pid: i32) -> Option<Vec<String>>;
+
+    /// Reads the target of the `/proc/<pid>/exe` link of a process, `None` if it isn't readable, e.g for processes of other users.
+    fn exe(&self, pid: i32) -> Option<PathBuf>;
+
+    /// Reads the UID of the user owning a process.
+    fn uid(&self, pid: i32) -> Option<u32>;
+
+    /// Reads the PID of the parent of a process.
+    fn parent_pid(&self, pid: i32) -> Option<i32>;
+
+    /// Reads the NUL separated environment of a process, ie. the content of `/proc/<pid>/environ`.
+    /// `None` if it isn't readable, which without root is the case for the processes of other users.
+    fn environ(&self, pid: i32) -> Option<Vec<u8>>;
+
+    /// Reads the interface index of every IPv6 socket bound to an interface by the inode of the socket.
+    fn scope_ids(&self) -> HashMap<u64, u32>;
+}
+
+/// Reads the sockets and processes from the real `/proc` file system using the "procfs" crate.
+pub struct ProcFs {
+    /// The boot time of the system as a Unix timestamp in seconds.
+    boot_time: Option<u64>,
+    /// The clock ticks per second, in which the start time of processes is measured.
+    ticks_per_second: u64,
+}
+
+impl Default for ProcFs {
+    fn default() -> Self {
+        ProcFs {
+            boot_time: procfs::boot_time_secs().ok(),
+            ticks_per_second: procfs::ticks_per_second(),
+        }
+    }
+}
+
+/// Reads a file of `/proc`, converting the IO error into a `SomoError` which names the file.
+///
+/// # Arguments
+/// * `path`: The path of the file.
+///
+/// # Returns
+/// The content of the file or the error which prevented reading it.
+fn read_proc_file(path: &str) -> Result<String, SomoError> {
+    fs::read_to_string(path).map_err(|error| match error.kind() {
+        io::ErrorKind::PermissionDenied => SomoError::PermissionDenied(path.to_string()),
+        io::ErrorKind::NotFound => SomoError::ProcRead(format!("{} doesn't exist", path)),
+        _ => SomoError::ProcRead(format!("{}: {}", path, error)),
+    })
+}
+
+impl ProcSource for ProcFs {
+    fn is_available(&self) -> bool {
+        Path::new("/proc/self").is_dir()
+    }
+
+    fn tcp_table(&self) -> Result<Vec<String>, SomoError> {
+        Ok(vec![
+            read_proc_file("/proc/net/tcp")?,
+            read_proc_file("/proc/net/tcp6")?,
+        ])
+    }
+
+    fn udp_table(&self) -> Result<Vec<String>, SomoError> {
+        Ok(vec![
+            read_proc_file("/proc/net/udp")?,
+            read_proc_file("/proc/net/udp6")?,
+        ])
+    }
+
+    fn pids(&self) -> Result<Vec<i32>, SomoError> {
+        Ok(procfs::process::all_processes()?
+            .flatten()
+            .map(|process| process.pid())
+            .collect())
+    }
+
+    fn exists(&self, pid: i32) -> bool {
+        Path::new("/proc").join(pid.to_string()).exists()
+    }
+
+    fn sockets(&self, pid: i32) -> Option<Vec<SocketTime>> {
+        let fds = Process::new(pid).ok()?.fd().ok()?;
+        let sockets = fds
+            .flatten()
+            .filter_map(|fd| match fd.target {
+                FDTarget::Socket(inode) => Some((inode, get_fd_time(pid, fd.fd))),
+                _ => None,
+            })
+            .collect();
+        Some(sockets)
+    }
+
+    fn stat(&self, pid: i32) -> Option<(String, Option<u64>)> {
+        let stat = Process::new(pid).ok()?.stat().ok()?;
+        let started = self
+            .boot_time
+            .map(|boot_time| boot_time + stat.starttime / self.ticks_per_second);
+        Some((stat.comm, started))
+    }
+
+    fn cmdline(&self, pid: i32) -> Option<Vec<String>> {
+        Process::new(pid).ok()?.cmdline().ok()
+    }
+
+    fn exe(&self, pid: i32) -> Option<PathBuf> {
+        Process::new(pid).ok()?.exe().ok()
+    }
+
+    fn uid(&self, pid: i32) -> Option<u32> {
+        Process::new(pid).ok()?.uid().ok()
+    }
+
+    fn parent_pid(&self, pid: i32) -> Option<i32> {
+        read_parent_pid(pid)
+    }
+
+    fn environ(&self, pid: i32) -> Option<Vec<u8>> {
+        fs::read(format!("/proc/{}/environ", pid)).ok()
+    }
+
+    fn scope_ids(&self) -> HashMap<u64, u32> {
+        super::stats::get_scope_ids()
+    }
+}
+
+/// Reads the PID of the parent of a process from `/proc/<pid>/stat`.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+///
+/// # Returns
+/// The PID of the parent or `None` if the process isn't running.
+pub(super) fn read_parent_pid(pid: i32) -> Option<i32> {
+    Some(Process::new(pid).ok()?.stat().ok()?.ppid)
+}
+
+/// Parses the content of `/proc/<pid>/environ`, in which every variable is terminated by a NUL byte.
+/// Values may contain `=` themselves, entries without any `=` are skipped.
+///
+/// # Arguments
+/// * `environ`: The raw content of the file.
+///
+/// # Example
+/// ```ignore
+/// let environment = parse_environ(b"DEPLOY_ID=42\0URL=a=b\0");
+/// assert_eq!(environment["URL"], "a=b");
+/// ```
+///
+/// # Returns
+/// The environment variables keyed by their name.
+fn parse_environ(environ: &[u8]) -> HashMap<String, String> {
+    environ
+        .split(|byte| *byte == 0)
+        .filter_map(|variable| {
+            let variable = String::from_utf8_lossy(variable);
+            let (key, value) = variable.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The program and command of a process which exited while it was read.
+const GONE_PROCESS: &str = "(gone)";
+
+/// Reads a file of a process and retries once if the read fails.
+/// Reads of `/proc/<pid>` fail transiently on busy systems while other processes are forked or exit.
+///
+/// # Arguments
+/// * `read`: Reads the file, `None` if the read failed.
+///
+/// # Returns
+/// The result of the first successful read or `None` if both reads failed.
+fn retry_once<T>(read: impl Fn() -> Option<T>) -> Option<T> {
+    read().or_else(read)
+}
+
+impl<S: ProcSource> ProcessReader for S {
+    /// Processes which exited after their sockets were listed still own these sockets, they are recorded as "(gone)" instead of being dropped.
+    fn read_details(&self, pid: i32) -> Option<ProcessDetails> {
+        let Some((program, started)) = retry_once(|| self.stat(pid)) else {
+            return (!self.exists(pid)).then(|| ProcessDetails {
+                program: GONE_PROCESS.to_string(),
+                uid: None,
+                command: GONE_PROCESS.to_string(),
+                exe_path: None,
+                started: None,
+            });
+        };
+        let command = match retry_once(|| self.cmdline(pid)) {
+            Some(cmdline) => get_command(cmdline, &program),
+            None if !self.exists(pid) => GONE_PROCESS.to_string(),
+            None => get_command(Vec::new(), &program),
+        };
+        // the executable isn't retried since it isn't readable for processes of other users anyway
+        Some(ProcessDetails {
+            uid: retry_once(|| self.uid(pid)),
+            command,
+            exe_path: self.exe(pid).map(|exe| parse_exe_path(&exe)),
+            program,
+            started,
+        })
+    }
+}
+
+/// Reads the sockets and processes of the system from a `ProcSource`, by default from the real `/proc` file system.
+pub struct Linux<S = ProcFs> {
+    source: S,
+    cache: Arc<Mutex<ProcessCache>>,
+}
+
+impl Default for Linux {
+    /// Reads from `/proc` and shares the cache of the processes with all other `Linux` platforms.
+    fn default() -> Self {
+        Linux {
+            source: ProcFs::default(),
+            cache: PROCESS_CACHE.clone(),
+        }
+    }
+}
+
+impl<S: ProcSource> Linux<S> {
+    /// Creates a platform which reads from another source, with its own empty cache of the processes.
+    ///
+    /// # Arguments
+    /// * `source`: The source of the socket tables and processes.
+    ///
+    /// # Returns
+    /// The platform.
+    #[cfg(test)]
+    pub fn with_source(source: S) -> Self {
+        Linux {
+            source,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Fails with a clear error before any file is read if `/proc` isn't there, instead of failing on the first missing file.
+    ///
+    /// # Arguments
+    /// None
+    ///
+    /// # Returns
+    /// `SomoError::ProcUnavailable` if `/proc` can't be read.
+    fn ensure_available(&self) -> Result<(), SomoError> {
+        if self.source.is_available() {
+            Ok(())
+        } else {
+            Err(SomoError::ProcUnavailable)
+        }
+    }
+}
+
+/// Reads many processes in parallel using the "rayon" crate.
+/// The results keep the order of the processes regardless of how the threads are scheduled, so the output stays deterministic.
+///
+/// # Arguments
+/// * `processes`: The processes to read, every process is read exactly once.
+/// * `read`: Reads a single process, returning `None` for processes which should be skipped.
+///
+/// # Returns
+/// The read processes in the order of `processes`.
+fn read_in_parallel<P: Send, R: Send>(
+    processes: Vec<P>,
+    read: impl Fn(P) -> Option<R> + Sync + Send,
+) -> Vec<R> {
+    processes.into_par_iter().filter_map(read).collect()
+}
+
+/// Builds the map from each socket inode to its process.
+/// Sockets shared by multiple processes (e.g after a fork) belong to the last of them, like they did when the processes were read one after another.
+/// The start of the process is used if the time of a socket can't be read.
+///
+/// # Arguments
+/// * `sockets`: The PID and the sockets of every process in the order in which they were listed.
+/// * `cache`: The cached details of the processes.
+///
+/// # Returns
+/// A map from each socket inode to the process which owns the socket.
+fn build_process_map(
+    sockets: Vec<(i32, Vec<SocketTime>)>,
+    cache: &ProcessCache,
+) -> HashMap<u64, ProcessInfo> {
+    let mut map: HashMap<u64, ProcessInfo> = HashMap::new();
+    for (pid, process_sockets) in sockets {
+        let Some(process_info) = cache.get(pid) else {
+            continue;
+        };
+        for (inode, created) in process_sockets {
+            map.insert(
+                inode,
+                ProcessInfo {
+                    created: created.or(process_info.created),
+                    ..process_info.clone()
+                },
+            );
+        }
+    }
+    map
+}
+
+/// Parses the socket tables of a `ProcSource` using the "procfs" crate.
+///
+/// # Arguments
+/// * `tables`: The content of the IPv4 and the IPv6 socket table.
+/// * `read_table`: The "procfs" parser of the table, either `read_tcp_table` or `read_udp_table`.
+///
+/// # Returns
+/// The entries of all tables or the error if a table has an unexpected format.
+fn parse_tables<T>(
+    tables: Vec<String>,
+    read_table: impl Fn(BufReader<Cursor<String>>) -> procfs::ProcResult<Vec<T>>,
+) -> Result<Vec<T>, SomoError> {
+    let mut entries = Vec::new();
+    for table in tables {
+        entries.extend(read_table(BufReader::new(Cursor::new(table)))?);
+    }
+    Ok(entries)
+}
+
+/// Converts the TCP sockets read by the "procfs" crate into socket entries.
+///
+/// # Arguments
+/// * `entries`: The IPv4 and IPv6 TCP sockets.
+///
+/// # Returns
+/// The socket entries, the `owner` of each entry is the socket inode.
+pub(super) fn to_tcp_entries(entries: Vec<TcpNetEntry>) -> Vec<NetEntry> {
+    entries
+        .into_iter()
+        .map(|entry| NetEntry {
+            protocol: "tcp".to_string(),
+            local_address: entry.local_address,
+            remote_address: entry.remote_address,
+            state: format!("{:?}", entry.state).to_ascii_lowercase(),
+            owner: entry.inode,
+            queues: Some(SocketQueues {
+                rx_queue: entry.rx_queue,
+                tx_queue: entry.tx_queue,
+                retransmits: None,
+            }),
+        })
+        .collect()
+}
+
+/// Converts the UDP sockets read by the "procfs" crate into socket entries.
+///
+/// # Arguments
+/// * `entries`: The IPv4 and IPv6 UDP sockets.
+///
+/// # Returns
+/// The socket entries, the `owner` of each entry is the socket inode.
+pub(super) fn to_udp_entries(entries: Vec<UdpNetEntry>) -> Vec<NetEntry> {
+    entries
+        .into_iter()
+        .map(|entry| NetEntry {
+            protocol: "udp".to_string(),
+            local_address: entry.local_address,
+            remote_address: entry.remote_address,
+            state: format!("{:?}", entry.state).to_ascii_lowercase(),
+            owner: entry.inode,
+            queues: Some(SocketQueues {
+                rx_queue: entry.rx_queue,
+                tx_queue: entry.tx_queue,
+                retransmits: None,
+            }),
+        })
+        .collect()
+}
+
+/// Reads the retransmits of every TCP socket by its inode, since the "procfs" crate doesn't parse the `retrnsmt` column.
+/// Lines which don't have the expected columns, e.g the headers, are skipped.
+///
+/// # Arguments
+/// * `tables`: The content of the IPv4 and the IPv6 TCP socket table.
+///
+/// # Returns
+/// The retransmits by the inode of the socket.
+fn parse_retransmits(tables: &[String]) -> HashMap<u64, u32> {
+    tables
+        .iter()
+        .flat_map(|table| table.lines())
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let retransmits = u32::from_str_radix(columns.get(6)?, 16).ok()?;
+            let inode = columns.get(9)?.parse::<u64>().ok()?;
+            Some((inode, retransmits))
+        })
+        .collect()
+}
+
+/// Checks if an address is a link-local IPv6 address (`fe80::/10`) without a scope.
+fn is_unscoped_link_local(address: &SocketAddr) -> bool {
+    match address {
+        SocketAddr::V6(address) => {
+            address.scope_id() == 0 && (address.ip().segments()[0] & 0xffc0) == 0xfe80
+        }
+        SocketAddr::V4(_) => false,
+    }
+}
+
+/// Sets the interface of the link-local IPv6 addresses, since the socket tables of `/proc` don't contain it.
+/// The interfaces are only read if there is at least one link-local address.
+///
+/// # Arguments
+/// * `entries`: The socket entries in which to set the scope id of the link-local addresses.
+/// * `source`: The source to read the interfaces of the sockets from.
+///
+/// # Returns
+/// None
+fn set_scope_ids(entries: &mut [NetEntry], source: &impl ProcSource) {
+    let is_link_local = |entry: &NetEntry| {
+        is_unscoped_link_local(&entry.local_address)
+            || is_unscoped_link_local(&entry.remote_address)
+    };
+    if !entries.iter().any(is_link_local) {
+        return;
+    }
+
+    let scope_ids = source.scope_ids();
+    for entry in entries.iter_mut() {
+        let Some(scope_id) = scope_ids.get(&entry.owner) else {
+            continue;
+        };
+        for address in [&mut entry.local_address, &mut entry.remote_address] {
+            if let SocketAddr::V6(address) = address {
+                if is_unscoped_link_local(&SocketAddr::V6(*address)) {
+                    address.set_scope_id(*scope_id);
+                }
+            }
+        }
+    }
+}
+
+impl<S: ProcSource> Platform for Linux<S> {
+    /// Gets all running processes, the processes are read in parallel since reading `/proc/<pid>` one after another is slow on hosts with many processes.
+    /// The details of every process are only read once and then cached.
+    fn get_processes(&self, numeric_users: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+        self.ensure_available()?;
+        let pids = self.source.pids()?;
+
+        // the sockets are listed first, so that a process which exits meanwhile is still known as their owner
+        let sockets = read_in_parallel(pids.clone(), |pid| {
+            retry_once(|| self.source.sockets(pid)).map(|sockets| (pid, sockets))
+        });
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.update(&pids, &self.source, numeric_users);
+        Ok(build_process_map(sockets, &cache))
+    }
+
+    /// Only reads the file descriptors of the one process instead of every process in `/proc`, which is much faster on busy hosts.
+    fn get_process(
+        &self,
+        pid: i32,
+        numeric_users: bool,
+    ) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+        self.ensure_available()?;
+        if !self.source.exists(pid) {
+            return Ok(HashMap::new());
+        }
+
+        let sockets: Vec<(i32, Vec<SocketTime>)> = retry_once(|| self.source.sockets(pid))
+            .map(|sockets| (pid, sockets))
+            .into_iter()
+            .collect();
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.update(&[pid], &self.source, numeric_users);
+        Ok(build_process_map(sockets, &cache))
+    }
+
+    fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        self.ensure_available()?;
+        let tables = self.source.tcp_table()?;
+        let retransmits = parse_retransmits(&tables);
+        let tcp_entries = parse_tables(tables, procfs::net::read_tcp_table)?;
+        let mut entries = to_tcp_entries(tcp_entries);
+        for entry in entries.iter_mut() {
+            if let Some(queues) = entry.queues.as_mut() {
+                queues.retransmits = retransmits.get(&entry.owner).copied();
+            }
+        }
+        set_scope_ids(&mut entries, &self.source);
+        Ok(entries)
+    }
+
+    /// Reads the parents in parallel, they aren't cached since a process gets a new parent when its parent exits.
+    fn get_parent_pids(&self) -> Result<HashMap<i32, i32>, SomoError> {
+        self.ensure_available()?;
+        let pids = self.source.pids()?;
+        Ok(read_in_parallel(pids, |pid| {
+            self.source.parent_pid(pid).map(|parent| (pid, parent))
+        })
+        .into_iter()
+        .collect())
+    }
+
+    fn get_environment(&self, pid: i32) -> Option<HashMap<String, String>> {
+        self.source
+            .environ(pid)
+            .map(|environ| parse_environ(&environ))
+    }
+
+    fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        self.ensure_available()?;
+        let udp_entries = parse_tables(self.source.udp_table()?, procfs::net::read_udp_table)?;
+        let mut entries = to_udp_entries(udp_entries);
+        set_scope_ids(&mut entries, &self.source);
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{get_connection_data, get_interface_name, get_platform_connections};
+    use super::*;
+    use crate::schemas::{Connection, FilterOptions};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_proc_error_conversion() {
+        let error = SomoError::from(ProcError::PermissionDenied(Some(PathBuf::from(
+            "/proc/1/fd",
+        ))));
+        assert_eq!(error, SomoError::PermissionDenied("/proc/1/fd".to_string()));
+        assert!(error.is_permission_denied());
+
+        let error = SomoError::from(ProcError::NotFound(None));
+        assert_eq!(
+            error,
+            SomoError::ProcRead("/proc doesn't exist".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_exe_path() {
+        assert_eq!(
+            parse_exe_path(Path::new("/usr/bin/python3.12")),
+            ("/usr/bin/python3.12".to_string(), false)
+        );
+        assert_eq!(
+            parse_exe_path(Path::new("/usr/sbin/nginx (deleted)")),
+            ("/usr/sbin/nginx".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_get_command() {
+        assert_eq!(
+            get_command(vec!["python3".to_string(), "app.py".to_string()], "python3"),
+            "python3 app.py"
+        );
+        assert_eq!(get_command(vec![], "kworker/0:1"), "[kworker/0:1]");
+    }
+
+    const TCP_TABLE: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1001 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:D431 01 00000010:00000200 00:00000000 00000003  1000        0 1002 1 0000000000000000 20 4 0 10 -1
+";
+    const TCP6_TABLE: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1003 1 0000000000000000 100 0 0 10 0
+";
+    const UDP_TABLE: &str = "   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  100: 00000000:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 1004 2 0000000000000000 0
+";
+    const UDP6_TABLE: &str = "   sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+";
+
+    /// A process of the fixtures.
+    struct MockProcess {
+        program: &'static str,
+        cmdline: Vec<&'static str>,
+        exe: Option<&'static str>,
+        uid: Option<u32>,
+        parent: i32,
+        sockets: Vec<u64>,
+        /// The content of `/proc/<pid>/environ`, `None` if it isn't readable.
+        environ: Option<&'static [u8]>,
+    }
+
+    const LINK_LOCAL_TCP6_TABLE: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 000080FE000000000000000001000000:0016 000080FE000000000000000002000000:A10E 01 00000000:00000000 00:00000000 00000000     0        0 1005 1 0000000000000000 20 4 0 10 -1
+   1: 000080FE000000000000000001000000:0017 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1006 1 0000000000000000 100 0 0 10 0
+";
+
+    /// Returns the socket tables above and a few processes instead of reading `/proc`.
+    struct MockSource {
+        processes: HashMap<i32, MockProcess>,
+        tcp6_table: &'static str,
+        scope_ids: HashMap<u64, u32>,
+        available: bool,
+        /// The processes whose next read of their stat and command line fails, as if another process raced with them.
+        flaky: Mutex<HashSet<i32>>,
+        /// The processes which exit right after their sockets were listed.
+        gone: HashSet<i32>,
+        /// The processes whose sockets were read, in the order of the reads.
+        socket_reads: Mutex<Vec<i32>>,
+    }
+
+    impl Default for MockSource {
+        fn default() -> Self {
+            MockSource {
+                processes: HashMap::from([
+                    (
+                        42,
+                        MockProcess {
+                            program: "nginx",
+                            cmdline: vec!["nginx", "-g", "daemon off;"],
+                            exe: Some("/usr/sbin/nginx (deleted)"),
+                            uid: Some(0),
+                            parent: 1,
+                            sockets: vec![1001, 1003],
+                            environ: Some(b"PATH=/usr/bin\0DEPLOY_ID=blue\0"),
+                        },
+                    ),
+                    (
+                        99,
+                        MockProcess {
+                            program: "dnsmasq",
+                            cmdline: vec![],
+                            exe: None,
+                            uid: None,
+                            parent: 42,
+                            sockets: vec![1004],
+                            environ: None,
+                        },
+                    ),
+                ]),
+                tcp6_table: TCP6_TABLE,
+                scope_ids: HashMap::new(),
+                available: true,
+                flaky: Mutex::default(),
+                gone: HashSet::new(),
+                socket_reads: Mutex::default(),
+            }
+        }
+    }
+
+    impl ProcSource for MockSource {
+        fn is_available(&self) -> bool {
+            self.available
+        }
+
+        fn tcp_table(&self) -> Result<Vec<String>, SomoError> {
+            Ok(vec![TCP_TABLE.to_string(), self.tcp6_table.to_string()])
+        }
+
+        fn udp_table(&self) -> Result<Vec<String>, SomoError> {
+            Ok(vec![UDP_TABLE.to_string(), UDP6_TABLE.to_string()])
+        }
+
+        fn pids(&self) -> Result<Vec<i32>, SomoError> {
+            let mut pids: Vec<i32> = self.processes.keys().copied().collect();
+            pids.sort();
+            Ok(pids)
+        }
+
+        fn exists(&self, pid: i32) -> bool {
+            self.processes.contains_key(&pid) && !self.gone.contains(&pid)
+        }
+
+        fn sockets(&self, pid: i32) -> Option<Vec<SocketTime>> {
+            self.socket_reads.lock().unwrap().push(pid);
+            let process = self.processes.get(&pid)?;
+            Some(process.sockets.iter().map(|inode| (*inode, None)).collect())
+        }
+
+        fn stat(&self, pid: i32) -> Option<(String, Option<u64>)> {
+            if self.gone.contains(&pid) || self.flaky.lock().unwrap().remove(&pid) {
+                return None;
+            }
+            let process = self.processes.get(&pid)?;
+            Some((process.program.to_string(), Some(1_700_000_000)))
+        }
+
+        fn cmdline(&self, pid: i32) -> Option<Vec<String>> {
+            if self.gone.contains(&pid) {
+                return None;
+            }
+            let process = self.processes.get(&pid)?;
+            Some(process.cmdline.iter().map(|arg| arg.to_string()).collect())
+        }
+
+        fn exe(&self, pid: i32) -> Option<PathBuf> {
+            self.processes.get(&pid)?.exe.map(PathBuf::from)
+        }
+
+        fn uid(&self, pid: i32) -> Option<u32> {
+            self.processes.get(&pid)?.uid
+        }
+
+        fn parent_pid(&self, pid: i32) -> Option<i32> {
+            Some(self.processes.get(&pid)?.parent)
+        }
+
+        fn environ(&self, pid: i32) -> Option<Vec<u8>> {
+            self.processes.get(&pid)?.environ.map(<[u8]>::to_vec)
+        }
+
+        fn scope_ids(&self) -> HashMap<u64, u32> {
+            self.scope_ids.clone()
+        }
+    }
+
+    fn mock_connections(filter_options: &FilterOptions) -> Vec<Connection> {
+        let linux = Linux::with_source(MockSource::default());
+        get_platform_connections(&linux, filter_options).unwrap()
+    }
+
+    #[test]
+    fn test_parse_mock_connections() {
+        let connections = mock_connections(&FilterOptions::default());
+        assert_eq!(connections.len(), 4);
+
+        let nginx = &connections[0];
+        assert_eq!(nginx.proto, "tcp");
+        assert_eq!(nginx.local_address, "0.0.0.0");
+        assert_eq!(nginx.local_port, "80");
+        assert_eq!(nginx.remote_address, "0.0.0.0");
+        assert_eq!(nginx.remote_port, "0");
+        assert_eq!(nginx.state, "listen");
+        assert_eq!(nginx.pid, "42");
+        assert_eq!(nginx.program, "nginx");
+        assert_eq!(nginx.user.as_deref(), Some("root"));
+        assert_eq!(nginx.command.as_deref(), Some("nginx -g daemon off;"));
+        assert_eq!(nginx.created, Some(1_700_000_000));
+        assert_eq!(nginx.exe_path.as_deref(), Some("/usr/sbin/nginx"));
+        assert!(nginx.exe_deleted);
+
+        let unresolved = &connections[1];
+        assert_eq!(unresolved.local_address, "127.0.0.1");
+        assert_eq!(unresolved.local_port, "8080");
+        assert_eq!(unresolved.remote_port, "54321");
+        assert_eq!(unresolved.state, "established");
+        assert_eq!(unresolved.pid, "-");
+        assert_eq!(unresolved.program, "-");
+        assert!(unresolved.user.is_none());
+        assert_eq!(unresolved.rx_queue, Some(512));
+        assert_eq!(unresolved.tx_queue, Some(16));
+        assert_eq!(unresolved.retransmits, Some(3));
+        assert_eq!(nginx.retransmits, Some(0));
+
+        let ipv6 = &connections[2];
+        assert_eq!(ipv6.local_address, "[::1]");
+        assert_eq!(ipv6.local_port, "22");
+        assert_eq!(ipv6.program, "nginx");
+
+        let udp = &connections[3];
+        assert_eq!(udp.proto, "udp");
+        assert_eq!(udp.local_port, "53");
+        assert_eq!(udp.state, "close");
+        assert_eq!(udp.command.as_deref(), Some("[dnsmasq]"));
+        // the link of the executable isn't readable
+        assert!(udp.exe_path.is_none());
+        assert!(!udp.exe_deleted);
+        assert!(udp.user.is_none());
+        assert_eq!(udp.rx_queue, Some(0));
+        // UDP has no retransmits
+        assert!(udp.retransmits.is_none());
+    }
+
+    #[test]
+    fn test_numeric_users() {
+        let connections = mock_connections(&FilterOptions {
+            numeric_users: true,
+            ..Default::default()
+        });
+        assert_eq!(connections[0].user.as_deref(), Some("0"));
+        assert!(connections[3].user.is_none());
+    }
+
+    #[test]
+    fn test_parse_retransmits() {
+        let retransmits = parse_retransmits(&[TCP_TABLE.to_string(), TCP6_TABLE.to_string()]);
+        assert_eq!(retransmits.get(&1001), Some(&0));
+        assert_eq!(retransmits.get(&1002), Some(&3));
+        assert_eq!(retransmits.get(&1003), Some(&0));
+        assert!(parse_retransmits(&["   0: garbage\n".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_filter_mock_connections() {
+        let listening = mock_connections(&FilterOptions {
+            by_listen: true,
+            ..Default::default()
+        });
+        assert_eq!(listening.len(), 2);
+
+        let ipv6 = mock_connections(&FilterOptions {
+            by_proto: vec!["tcp6".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(ipv6.len(), 1);
+        assert_eq!(ipv6[0].local_port, "22");
+
+        let dnsmasq = mock_connections(&FilterOptions {
+            by_program: Some(crate::schemas::ProgramMatcher::Substring("dns".to_string())),
+            ..Default::default()
+        });
+        assert_eq!(dnsmasq.len(), 1);
+
+        let unresolved = mock_connections(&FilterOptions {
+            by_unresolved: true,
+            ..Default::default()
+        });
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].local_port, "8080");
+    }
+
+    #[test]
+    fn test_proc_unavailable() {
+        let linux = Linux::with_source(MockSource {
+            available: false,
+            ..Default::default()
+        });
+        let error = get_platform_connections(&linux, &FilterOptions::default()).unwrap_err();
+        assert_eq!(error, SomoError::ProcUnavailable);
+        assert_eq!(error.to_string(), "Cannot read /proc; is it mounted?");
+        assert_eq!(linux.get_parent_pids(), Err(SomoError::ProcUnavailable));
+    }
+
+    #[test]
+    fn test_parse_ipv6_addresses() {
+        // every 32 bit word of the address is stored in host byte order, e.g fe80:: is "000080FE"
+        let table = format!(
+            "{}{}{}{}",
+            TCP6_TABLE,
+            "   1: 000080FE00000000FF3C2B1A6F5E4DFE:0016 B80D0120000000000000000005000000:01BB 01 00000000:00000000 00:00000000 00000000     0        0 1005 1 0000000000000000 20 4 0 10 -1\n",
+            "   2: 0000000000000000FFFF00000100007F:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1006 1 0000000000000000 100 0 0 10 0\n",
+            "   3: 00000000000000000000000000000000:0050 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1007 1 0000000000000000 100 0 0 10 0\n",
+        );
+        let entries = parse_tables(vec![table], procfs::net::read_tcp_table).unwrap();
+        let connections: Vec<Connection> = to_tcp_entries(entries)
+            .into_iter()
+            .map(|entry| get_connection_data(entry, &HashMap::new()))
+            .collect();
+
+        let addresses: Vec<(&str, &str, &str, &str)> = connections
+            .iter()
+            .map(|c| {
+                (
+                    c.local_address.as_str(),
+                    c.local_port.as_str(),
+                    c.remote_address.as_str(),
+                    c.remote_port.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![
+                ("[::1]", "22", "[::]", "0"),
+                ("[fe80::1a2b:3cff:fe4d:5e6f]", "22", "[2001:db8::5]", "443"),
+                ("[::ffff:127.0.0.1]", "8080", "[::]", "0"),
+                ("[::]", "80", "[::]", "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tables_invalid() {
+        let tables = vec![format!("{}   1: garbage\n", TCP_TABLE)];
+        let result = parse_tables(tables, procfs::net::read_tcp_table);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_read_proc_file_missing() {
+        assert_eq!(
+            read_proc_file("/proc/net/does-not-exist"),
+            Err(SomoError::ProcRead(
+                "/proc/net/does-not-exist doesn't exist".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_read_in_parallel_keeps_order() {
+        let pids: Vec<i32> = (1..=50_000).collect();
+        let read = |pid: i32| (pid % 7 != 0).then(|| (pid, vec![(pid as u64, None)]));
+
+        let parallel = read_in_parallel(pids.clone(), read);
+        let serial: Vec<(i32, Vec<SocketTime>)> = pids.into_iter().filter_map(read).collect();
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn test_filter_mock_connections_by_ppid() {
+        let linux = Linux::with_source(MockSource::default());
+        assert_eq!(
+            linux.get_parent_pids().unwrap(),
+            HashMap::from([(42, 1), (99, 42)])
+        );
+
+        let children = mock_connections(&FilterOptions {
+            by_ppid: Some(42),
+            ..Default::default()
+        });
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].program, "dnsmasq");
+
+        let descendants = mock_connections(&FilterOptions {
+            by_ppid: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(descendants.len(), 3);
+    }
+
+    #[test]
+    fn test_get_single_process() {
+        let linux = Linux::with_source(MockSource::default());
+        let mut processes = linux.get_processes(false).unwrap();
+        processes.retain(|_, process| process.pid == 99);
+        linux.source.socket_reads.lock().unwrap().clear();
+
+        let process = linux.get_process(99, false).unwrap();
+        assert_eq!(process.len(), processes.len());
+        assert_eq!(
+            format!("{:?}", process[&1004]),
+            format!("{:?}", processes[&1004])
+        );
+        // the sockets of the other processes aren't read
+        assert_eq!(*linux.source.socket_reads.lock().unwrap(), vec![99]);
+        assert!(linux.get_process(1234, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_filter_mock_connections_by_single_pid() {
+        let linux = Linux::with_source(MockSource::default());
+        let by_pid = |pid: &str| {
+            get_platform_connections(
+                &linux,
+                &FilterOptions {
+                    by_pid: Some(pid.parse().unwrap()),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        // the fast path of a single PID gives the same connections as the full scan
+        let mut full_scan = mock_connections(&FilterOptions::default());
+        full_scan.retain(|connection| connection.pid == "42");
+        linux.source.socket_reads.lock().unwrap().clear();
+        assert_eq!(format!("{:?}", by_pid("42")), format!("{:?}", full_scan));
+        assert_eq!(*linux.source.socket_reads.lock().unwrap(), vec![42]);
+
+        // a list of PIDs still scans all processes
+        linux.source.socket_reads.lock().unwrap().clear();
+        assert_eq!(by_pid("42,99").len(), 3);
+        assert_eq!(linux.source.socket_reads.lock().unwrap().len(), 2);
+        assert!(by_pid("1234").is_empty());
+    }
+
+    #[test]
+    fn test_parse_environ() {
+        let environment = parse_environ(b"DEPLOY_ID=42\0URL=http://host/?a=b\0EMPTY=\0garbage\0\0");
+        assert_eq!(environment.len(), 3);
+        assert_eq!(environment["DEPLOY_ID"], "42");
+        assert_eq!(environment["URL"], "http://host/?a=b");
+        assert_eq!(environment["EMPTY"], "");
+        assert!(parse_environ(b"").is_empty());
+    }
+
+    #[test]
+    fn test_filter_mock_connections_by_env() {
+        let by_env = |variables: &[&str]| {
+            mock_connections(&FilterOptions {
+                by_env: variables
+                    .iter()
+                    .map(|variable| variable.parse().unwrap())
+                    .collect(),
+                ..Default::default()
+            })
+        };
+        // the environment of dnsmasq isn't readable, so only nginx can match
+        let blue = by_env(&["DEPLOY_ID=blue"]);
+        assert_eq!(blue.len(), 2);
+        assert!(blue.iter().all(|connection| connection.program == "nginx"));
+
+        assert_eq!(by_env(&["DEPLOY_ID=blue", "PATH=/usr/bin"]).len(), 2);
+        assert!(by_env(&["DEPLOY_ID=green"]).is_empty());
+        assert!(by_env(&["DEPLOY_ID=blue", "HOME=/root"]).is_empty());
+    }
+
+    #[test]
+    fn test_link_local_scope() {
+        let source = MockSource {
+            tcp6_table: LINK_LOCAL_TCP6_TABLE,
+            scope_ids: HashMap::from([(1005, 2)]),
+            ..Default::default()
+        };
+        let linux = Linux::with_source(source);
+        let connections = get_platform_connections(&linux, &FilterOptions::default()).unwrap();
+        let zone = get_interface_name(2);
+
+        let scoped = &connections[2];
+        assert_eq!(scoped.local_address, format!("[fe80::1%{}]", zone));
+        assert_eq!(scoped.local_port, "22");
+        assert_eq!(scoped.remote_address, format!("[fe80::2%{}]", zone));
+        assert_eq!(scoped.remote_port, "41230");
+
+        // sockets which aren't bound to an interface keep the address without a zone
+        assert_eq!(connections[3].local_address, "[fe80::1]");
+
+        let filtered = get_platform_connections(
+            &linux,
+            &FilterOptions {
+                by_local_address: Some(format!("fe80::1%{}", zone).parse().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].local_port, "22");
+
+        let all_interfaces = get_platform_connections(
+            &linux,
+            &FilterOptions {
+                by_local_address: Some("fe80::1".parse().unwrap()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(all_interfaces.len(), 2);
+    }
+
+    #[test]
+    fn test_read_in_parallel_reads_every_process_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let reads = AtomicUsize::new(0);
+        let entries = read_in_parallel((1..=10_000).collect(), |pid| {
+            reads.fetch_add(1, Ordering::Relaxed);
+            Some(pid)
+        });
+        assert_eq!(reads.load(Ordering::Relaxed), 10_000);
+        assert_eq!(entries.len(), 10_000);
+    }
+
+    #[test]
+    fn test_build_process_map() {
+        let mut cache = ProcessCache::default();
+        cache.update(&[42, 99], &MockSource::default(), false);
+        let map = build_process_map(
+            vec![
+                (42, vec![(100, Some(1_700_000_100)), (101, None)]),
+                (99, vec![(101, None), (200, None)]),
+                // the process couldn't be read
+                (30, vec![(300, None)]),
+            ],
+            &cache,
+        );
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&100].pid, 42);
+        assert_eq!(map[&100].created, Some(1_700_000_100));
+        // the socket is shared after a fork, the later process wins
+        assert_eq!(map[&101].pid, 99);
+        assert_eq!(map[&200].program, "dnsmasq");
+        assert_eq!(map[&200].created, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_retry_racy_reads() {
+        let source = MockSource {
+            flaky: Mutex::new(HashSet::from([42])),
+            ..Default::default()
+        };
+        // the first read of the stat fails, the second one succeeds
+        let details = source.read_details(42).unwrap();
+        assert_eq!(details.program, "nginx");
+        assert_eq!(details.command, "nginx -g daemon off;");
+        assert!(source.flaky.lock().unwrap().is_empty());
+
+        let connections = get_platform_connections(
+            &Linux::with_source(MockSource {
+                flaky: Mutex::new(HashSet::from([42, 99])),
+                ..Default::default()
+            }),
+            &FilterOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(connections[0].program, "nginx");
+        assert_eq!(connections[3].program, "dnsmasq");
+    }
+
+    #[test]
+    fn test_gone_process() {
+        let linux = Linux::with_source(MockSource {
+            gone: HashSet::from([99]),
+            ..Default::default()
+        });
+        let connections = get_platform_connections(&linux, &FilterOptions::default()).unwrap();
+        let dnsmasq = &connections[3];
+        assert_eq!(dnsmasq.pid, "99");
+        assert_eq!(dnsmasq.program, "(gone)");
+        assert_eq!(dnsmasq.command.as_deref(), Some("(gone)"));
+        assert!(dnsmasq.user.is_none());
+        // processes which can't be accessed but are still running aren't gone
+        assert_eq!(connections[1].program, "-");
+    }
+
+    #[test]
+    fn test_get_fd_time() {
+        use std::os::fd::AsRawFd;
+
+        let file = fs::File::open("/proc/self/stat").unwrap();
+        let pid = std::process::id() as i32;
+        assert!(get_fd_time(pid, file.as_raw_fd()).is_some());
+        assert!(get_fd_time(pid, i32::MAX).is_none());
+    }
+}
@@ -0,0 +1,243 @@
+use libproc::bsd_info::BSDInfo;
+use libproc::file_info::{pidfdinfo, ListFDs, ProcFDType};
+use libproc::net_info::{InSockInfo, SocketFDInfo, SocketInfoKind, TcpSIState};
+use libproc::proc_pid::{listpidinfo, listpids, name, pidinfo, pidpath, ProcType};
+use nix::unistd::geteuid;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use super::get_user_name;
+use super::Platform;
+use crate::error::SomoError;
+use crate::schemas::NetEntry;
+use crate::schemas::ProcessInfo;
+use crate::utils;
+
+/// The `insi_vflag` bit of IPv4 sockets.
+const INI_IPV4: u8 = 0x1;
+/// The protocol number of UDP.
+const IPPROTO_UDP: i32 = 17;
+
+/// All sockets of all processes, collected in a single pass over the file descriptors of every process.
+#[derive(Default)]
+struct Sockets {
+    /// The processes without their user, which is only looked up by `get_processes`.
+    processes: HashMap<u64, ProcessInfo>,
+    /// The UID of every process which owns sockets, keyed by its PID.
+    uids: HashMap<i32, u32>,
+    tcp_entries: Vec<NetEntry>,
+    udp_entries: Vec<NetEntry>,
+}
+
+/// Reads the sockets of every process using `proc_pidinfo` and `proc_pidfdinfo` from the "libproc" crate.
+/// macOS has no global socket table, so the sockets are only read once and shared by all methods.
+#[derive(Default)]
+pub struct MacOs {
+    sockets: OnceCell<Result<Sockets, SomoError>>,
+}
+
+/// Converts a port of a socket, which is stored in network byte order, into a number.
+fn get_port(port: i32) -> u16 {
+    u16::from_be(port as u16)
+}
+
+/// Gets the local and remote address of an internet socket.
+///
+/// # Arguments
+/// * `info`: The internet socket info of the socket.
+///
+/// # Returns
+/// The local and the remote address.
+fn get_addresses(info: &InSockInfo) -> (SocketAddr, SocketAddr) {
+    // SAFETY: `insi_vflag` tells which variant of the address unions is set
+    let (local_ip, remote_ip) = unsafe {
+        if info.insi_vflag & INI_IPV4 != 0 {
+            (
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                    info.insi_laddr.ina_46.i46a_addr4.s_addr,
+                ))),
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                    info.insi_faddr.ina_46.i46a_addr4.s_addr,
+                ))),
+            )
+        } else {
+            (
+                IpAddr::V6(Ipv6Addr::from(info.insi_laddr.ina_6.s6_addr)),
+                IpAddr::V6(Ipv6Addr::from(info.insi_faddr.ina_6.s6_addr)),
+            )
+        }
+    };
+
+    (
+        SocketAddr::new(local_ip, get_port(info.insi_lport)),
+        SocketAddr::new(remote_ip, get_port(info.insi_fport)),
+    )
+}
+
+/// Converts a TCP state into the same names the "procfs" crate uses on Linux.
+///
+/// # Arguments
+/// * `state`: The TCP state of the socket.
+///
+/// # Returns
+/// The lowercase state name, e.g "established" or "timewait".
+fn get_tcp_state(state: TcpSIState) -> String {
+    match state {
+        TcpSIState::Listen => "listen",
+        TcpSIState::SynSent => "synsent",
+        TcpSIState::SynReceived => "synrecv",
+        TcpSIState::Established => "established",
+        TcpSIState::CloseWait => "closewait",
+        TcpSIState::FinWait1 => "finwait1",
+        TcpSIState::Closing => "closing",
+        TcpSIState::LastAck => "lastack",
+        TcpSIState::FinWait2 => "finwait2",
+        TcpSIState::TimeWait => "timewait",
+        _ => "close",
+    }
+    .to_string()
+}
+
+/// Reads the sockets of all processes which can be accessed.
+/// Without root only the processes of the current user can be read, in that case the user is told to use sudo.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// All sockets, the `owner` of each socket entry is the kernel address of the socket, or an error if the processes couldn't be listed.
+fn read_sockets() -> Result<Sockets, SomoError> {
+    let mut sockets = Sockets::default();
+    let mut access_denied = false;
+
+    let pids = listpids(ProcType::ProcAllPIDS)
+        .map_err(|error| SomoError::ProcRead(format!("couldn't list the processes: {}", error)))?;
+    for pid in pids {
+        let pid = pid as i32;
+        let Ok(bsd_info) = pidinfo::<BSDInfo>(pid, 0) else {
+            access_denied = true;
+            continue;
+        };
+        let Ok(fds) = listpidinfo::<ListFDs>(pid, bsd_info.pbi_nfiles as usize) else {
+            access_denied = true;
+            continue;
+        };
+
+        let program = name(pid).unwrap_or_else(|_| "-".to_string());
+        let process_info = ProcessInfo {
+            pid,
+            user: None,
+            command: pidpath(pid).unwrap_or_else(|_| format!("[{}]", program)),
+            exe_path: pidpath(pid).ok(),
+            exe_deleted: false,
+            program,
+            created: None,
+        };
+
+        for fd in fds {
+            if !matches!(ProcFDType::from(fd.proc_fdtype), ProcFDType::Socket) {
+                continue;
+            }
+            let Ok(socket) = pidfdinfo::<SocketFDInfo>(pid, fd.proc_fd) else {
+                continue;
+            };
+
+            let owner = socket.psi.soi_so;
+            match SocketInfoKind::from(socket.psi.soi_kind) {
+                SocketInfoKind::Tcp => {
+                    // SAFETY: the protocol union holds the TCP info for TCP sockets
+                    let tcp_info = unsafe { socket.psi.soi_proto.pri_tcp };
+                    let (local_address, remote_address) = get_addresses(&tcp_info.tcpsi_ini);
+                    sockets.tcp_entries.push(NetEntry {
+                        protocol: "tcp".to_string(),
+                        local_address,
+                        remote_address,
+                        state: get_tcp_state(TcpSIState::from(tcp_info.tcpsi_state)),
+                        owner,
+                        queues: None,
+                    });
+                }
+                SocketInfoKind::In if socket.psi.soi_protocol == IPPROTO_UDP => {
+                    // SAFETY: the protocol union holds the internet info for UDP sockets
+                    let in_info = unsafe { socket.psi.soi_proto.pri_in };
+                    let (local_address, remote_address) = get_addresses(&in_info);
+                    // like on Linux, only connected UDP sockets are "established"
+                    let state = if remote_address.port() == 0 {
+                        "close"
+                    } else {
+                        "established"
+                    };
+                    sockets.udp_entries.push(NetEntry {
+                        protocol: "udp".to_string(),
+                        local_address,
+                        remote_address,
+                        state: state.to_string(),
+                        owner,
+                        queues: None,
+                    });
+                }
+                _ => continue,
+            }
+            sockets.processes.insert(owner, process_info.clone());
+            sockets.uids.insert(pid, bsd_info.pbi_uid);
+        }
+    }
+
+    if access_denied && !geteuid().is_root() {
+        utils::pretty_print_info(
+            "Some processes couldn't be read, run somo with sudo to see the sockets of all users.",
+        );
+    }
+
+    Ok(sockets)
+}
+
+impl MacOs {
+    fn sockets(&self) -> Result<&Sockets, SomoError> {
+        self.sockets
+            .get_or_init(read_sockets)
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+}
+
+impl Platform for MacOs {
+    fn get_processes(&self, numeric_users: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+        let sockets = self.sockets()?;
+        let mut user_names: HashMap<u32, String> = HashMap::new();
+        let mut processes = sockets.processes.clone();
+        for process in processes.values_mut() {
+            process.user = sockets
+                .uids
+                .get(&process.pid)
+                .map(|uid| get_user_name(*uid, &mut user_names, numeric_users));
+        }
+        Ok(processes)
+    }
+
+    fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        Ok(self.sockets()?.tcp_entries.clone())
+    }
+
+    fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        Ok(self.sockets()?.udp_entries.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_port() {
+        assert_eq!(get_port(u16::to_be(8080) as i32), 8080);
+    }
+
+    #[test]
+    fn test_get_tcp_state() {
+        assert_eq!(get_tcp_state(TcpSIState::Listen), "listen");
+        assert_eq!(get_tcp_state(TcpSIState::TimeWait), "timewait");
+        assert_eq!(get_tcp_state(TcpSIState::Closed), "close");
+    }
+}
@@ -0,0 +1,214 @@
+use nix::errno::Errno;
+use nix::sched::{setns, CloneFlags};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use super::linux::{to_tcp_entries, to_udp_entries};
+use crate::error::SomoError;
+use crate::schemas::NetEntry;
+
+/// The directory in which `ip netns` creates the named network namespaces.
+const NAMED_NAMESPACES_DIR: &str = "/var/run/netns";
+
+/// A network namespace, identified by the inode of its namespace file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetNamespace {
+    /// The inode of the namespace, like `ip netns identify` and `lsns` show it.
+    pub id: u64,
+    /// The name of the namespace if it was created by `ip netns`.
+    pub name: Option<String>,
+    /// A file referring to the namespace, either `/proc/<pid>/ns/net` or a file of a named namespace.
+    path: PathBuf,
+}
+
+impl NetNamespace {
+    /// The name of the namespace or its ID if it has no name, used to tag the connections.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.id.to_string())
+    }
+
+    /// Checks if the namespace is the one provided by the user, either by its name or its ID.
+    pub fn matches(&self, netns: &str) -> bool {
+        self.name.as_deref() == Some(netns) || self.id.to_string() == netns
+    }
+}
+
+/// The error for all namespace operations which failed because of missing privileges.
+fn permission_error() -> SomoError {
+    SomoError::PermissionDenied(
+        "reading the sockets of other network namespaces requires root or CAP_SYS_ADMIN"
+            .to_string(),
+    )
+}
+
+/// Parses the target of a network namespace link, e.g "net:[4026531840]".
+///
+/// # Arguments
+/// * `link`: The target of a `/proc/<pid>/ns/net` link.
+///
+/// # Returns
+/// The inode of the namespace or `None` if the link isn't a network namespace.
+fn parse_namespace_link(link: &Path) -> Option<u64> {
+    link.to_str()?
+        .strip_prefix("net:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Gets the network namespace of the current process.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The inode of the namespace somo runs in.
+pub fn get_own_namespace_id() -> Result<u64, SomoError> {
+    fs::read_link("/proc/self/ns/net")
+        .ok()
+        .as_deref()
+        .and_then(parse_namespace_link)
+        .ok_or_else(|| SomoError::ProcRead("/proc/self/ns/net".to_string()))
+}
+
+/// Finds all distinct network namespaces, ie. the namespaces of all processes and the named namespaces created by `ip netns`.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// All network namespaces ordered by their ID or a permission error if the namespaces of other processes can't be read.
+pub fn get_net_namespaces() -> Result<Vec<NetNamespace>, SomoError> {
+    let mut namespaces: BTreeMap<u64, NetNamespace> = BTreeMap::new();
+
+    if let Ok(entries) = fs::read_dir(NAMED_NAMESPACES_DIR) {
+        for entry in entries.flatten() {
+            if let Ok(metadata) = fs::metadata(entry.path()) {
+                namespaces.insert(
+                    metadata.ino(),
+                    NetNamespace {
+                        id: metadata.ino(),
+                        name: Some(entry.file_name().to_string_lossy().to_string()),
+                        path: entry.path(),
+                    },
+                );
+            }
+        }
+    }
+
+    for process in procfs::process::all_processes()?.flatten() {
+        let path = PathBuf::from(format!("/proc/{}/ns/net", process.pid()));
+        match fs::read_link(&path) {
+            Ok(link) => {
+                if let Some(id) = parse_namespace_link(&link) {
+                    namespaces.entry(id).or_insert(NetNamespace {
+                        id,
+                        name: None,
+                        path,
+                    });
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(permission_error());
+            }
+            // the process exited in the meantime
+            Err(_) => continue,
+        }
+    }
+
+    Ok(namespaces.into_values().collect())
+}
+
+/// Opens one of the socket tables of the network namespace of the current thread.
+fn open_table(name: &str) -> Result<BufReader<File>, SomoError> {
+    let path = format!("/proc/thread-self/net/{}", name);
+    File::open(&path)
+        .map(BufReader::new)
+        .map_err(|error| SomoError::ProcRead(format!("{}: {}", path, error)))
+}
+
+/// Reads the TCP and UDP sockets of a network namespace.
+/// Other namespaces are entered with `setns` on a separate thread, so that somo itself stays in its own namespace.
+///
+/// # Arguments
+/// * `namespace`: The namespace to read the sockets of.
+/// * `enter`: Whether the namespace has to be entered, ie. it isn't the namespace of somo itself.
+///
+/// # Returns
+/// The TCP and the UDP socket entries or the error if the namespace couldn't be entered or read.
+pub fn read_namespace_entries(
+    namespace: &NetNamespace,
+    enter: bool,
+) -> Result<(Vec<NetEntry>, Vec<NetEntry>), SomoError> {
+    let path = namespace.path.clone();
+    let reader = thread::spawn(move || {
+        if enter {
+            let file = File::open(&path)
+                .map_err(|error| SomoError::ProcRead(format!("{}: {}", path.display(), error)))?;
+            setns(&file, CloneFlags::CLONE_NEWNET).map_err(|errno| match errno {
+                Errno::EPERM => permission_error(),
+                errno => SomoError::ProcRead(format!("{}: {}", path.display(), errno)),
+            })?;
+        }
+
+        let mut tcp_entries = procfs::net::read_tcp_table(open_table("tcp")?)?;
+        tcp_entries.extend(procfs::net::read_tcp_table(open_table("tcp6")?)?);
+        let mut udp_entries = procfs::net::read_udp_table(open_table("udp")?)?;
+        udp_entries.extend(procfs::net::read_udp_table(open_table("udp6")?)?);
+
+        Ok((to_tcp_entries(tcp_entries), to_udp_entries(udp_entries)))
+    });
+
+    reader
+        .join()
+        .unwrap_or_else(|_| Err(SomoError::ProcRead(namespace.path.display().to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_namespace_link() {
+        assert_eq!(
+            parse_namespace_link(Path::new("net:[4026531840]")),
+            Some(4026531840)
+        );
+        assert_eq!(parse_namespace_link(Path::new("mnt:[4026531841]")), None);
+        assert_eq!(parse_namespace_link(Path::new("net:[abc]")), None);
+    }
+
+    #[test]
+    fn test_namespace_label_and_matches() {
+        let unnamed = NetNamespace {
+            id: 4026532201,
+            name: None,
+            path: PathBuf::from("/proc/42/ns/net"),
+        };
+        assert_eq!(unnamed.label(), "4026532201");
+        assert!(unnamed.matches("4026532201"));
+        assert!(!unnamed.matches("vpn"));
+
+        let named = NetNamespace {
+            name: Some("vpn".to_string()),
+            ..unnamed
+        };
+        assert_eq!(named.label(), "vpn");
+        assert!(named.matches("vpn"));
+        assert!(named.matches("4026532201"));
+    }
+
+    #[test]
+    fn test_read_own_namespace_entries() {
+        let namespace = NetNamespace {
+            id: get_own_namespace_id().unwrap(),
+            name: None,
+            path: PathBuf::from("/proc/self/ns/net"),
+        };
+        assert!(read_namespace_entries(&namespace, false).is_ok());
+    }
+}
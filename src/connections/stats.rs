@@ -0,0 +1,260 @@
+use netlink_packet_core::{
+    NetlinkHeader, NetlinkMessage, NetlinkPayload, NLM_F_DUMP, NLM_F_REQUEST,
+};
+use netlink_packet_sock_diag::constants::{AF_INET, AF_INET6, IPPROTO_TCP, IPPROTO_UDP};
+use netlink_packet_sock_diag::inet::nlas::Nla;
+use netlink_packet_sock_diag::inet::{
+    ExtensionFlags, InetRequest, InetResponse, SocketId, StateFlags,
+};
+use netlink_packet_sock_diag::SockDiagMessage;
+use netlink_sys::protocols::NETLINK_SOCK_DIAG;
+use netlink_sys::{Socket, SocketAddr};
+use std::collections::HashMap;
+use std::io;
+
+use crate::schemas::Connection;
+
+/// The size of the buffer for the answers of the kernel, big enough for many sockets per read.
+const RECEIVE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The byte counters of a TCP socket as reported by the kernel in its `tcp_info`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SocketStats {
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+/// Extracts the byte counters of a socket from an `inet_diag` response.
+///
+/// # Arguments
+/// * `response`: A socket of the `inet_diag` dump.
+///
+/// # Returns
+/// The inode and the counters of the socket or `None` if the kernel didn't include its `tcp_info` or the socket has no inode, e.g in "time-wait".
+fn parse_response(response: &InetResponse) -> Option<(u64, SocketStats)> {
+    let inode = response.header.inode as u64;
+    if inode == 0 {
+        return None;
+    }
+    let stats = response.nlas.iter().find_map(|nla| match nla {
+        Nla::TcpInfo(info) => Some(SocketStats {
+            rx_bytes: info.bytes_received,
+            tx_bytes: info.bytes_acked,
+        }),
+        _ => None,
+    })?;
+    Some((inode, stats))
+}
+
+/// Dumps all sockets of one address family and protocol using a `NETLINK_SOCK_DIAG` socket.
+///
+/// # Arguments
+/// * `family`: Either `AF_INET` or `AF_INET6`.
+/// * `protocol`: Either `IPPROTO_TCP` or `IPPROTO_UDP`.
+/// * `extensions`: The extensions the kernel should add to every socket, e.g `ExtensionFlags::INFO` for the `tcp_info`.
+///
+/// # Returns
+/// All sockets or the error if the netlink socket couldn't be used.
+fn dump_sockets(
+    family: u8,
+    protocol: u8,
+    extensions: ExtensionFlags,
+) -> io::Result<Vec<InetResponse>> {
+    let mut socket = Socket::new(NETLINK_SOCK_DIAG)?;
+    socket.bind_auto()?;
+    socket.connect(&SocketAddr::new(0, 0))?;
+
+    let mut header = NetlinkHeader::default();
+    header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+    let socket_id = if family == AF_INET6 {
+        SocketId::new_v6()
+    } else {
+        SocketId::new_v4()
+    };
+    let mut packet = NetlinkMessage::new(
+        header,
+        SockDiagMessage::InetRequest(InetRequest {
+            family,
+            protocol,
+            extensions,
+            states: StateFlags::all(),
+            socket_id,
+        })
+        .into(),
+    );
+    packet.finalize();
+    let mut request = vec![0; packet.buffer_len()];
+    packet.serialize(&mut request[..]);
+    socket.send(&request[..], 0)?;
+
+    let mut sockets = Vec::new();
+    let mut receive_buffer = vec![0; RECEIVE_BUFFER_SIZE];
+    loop {
+        let size = socket.recv(&mut &mut receive_buffer[..], 0)?;
+        let mut offset = 0;
+        while offset < size {
+            let message =
+                NetlinkMessage::<SockDiagMessage>::deserialize(&receive_buffer[offset..size])
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            match message.payload {
+                NetlinkPayload::InnerMessage(SockDiagMessage::InetResponse(response)) => {
+                    sockets.push(*response);
+                }
+                NetlinkPayload::Done(_) => return Ok(sockets),
+                NetlinkPayload::Error(error) => return Err(error.to_io()),
+                _ => {}
+            }
+
+            if message.header.length == 0 {
+                break;
+            }
+            offset += message.header.length as usize;
+        }
+    }
+}
+
+/// Dumps all TCP sockets of one address family including their `tcp_info`.
+///
+/// # Arguments
+/// * `family`: Either `AF_INET` or `AF_INET6`.
+///
+/// # Returns
+/// The counters of every socket which reported them keyed by the inode of the socket or the error if the netlink socket couldn't be used.
+fn dump_tcp_stats(family: u8) -> io::Result<HashMap<u64, SocketStats>> {
+    Ok(dump_sockets(family, IPPROTO_TCP, ExtensionFlags::INFO)?
+        .iter()
+        .filter_map(parse_response)
+        .collect())
+}
+
+/// Gets the interfaces of all IPv6 TCP and UDP sockets which are bound to one, which `/proc/net/tcp6` and `/proc/net/udp6` don't show.
+/// Only the sockets of the current network namespace are queried, if the query fails no socket has an interface.
+///
+/// # Returns
+/// A map from the inode of each socket to the index of its interface.
+pub fn get_scope_ids() -> HashMap<u64, u32> {
+    let mut scope_ids = HashMap::new();
+    for protocol in [IPPROTO_TCP, IPPROTO_UDP] {
+        let sockets = dump_sockets(AF_INET6, protocol, ExtensionFlags::empty()).unwrap_or_default();
+        scope_ids.extend(
+            sockets
+                .iter()
+                .filter(|socket| socket.header.socket_id.interface_id != 0)
+                .map(|socket| {
+                    (
+                        socket.header.inode as u64,
+                        socket.header.socket_id.interface_id,
+                    )
+                }),
+        );
+    }
+    scope_ids
+}
+
+/// Sets the counters of the connections whose socket has counters.
+/// The sockets are matched by their inode, since their addresses can differ, e.g IPv4 clients of an IPv6 listener are shown with an IPv4 address but reported with an IPv4-mapped IPv6 one.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set `rx_bytes` and `tx_bytes`.
+/// * `stats`: The counters keyed by the inode of their socket.
+///
+/// # Returns
+/// None
+fn apply_stats(connections: &mut [Connection], stats: &HashMap<u64, SocketStats>) {
+    for connection in connections.iter_mut() {
+        if !connection.proto.starts_with("tcp") || connection.netns.is_some() {
+            continue;
+        }
+        if let Some(socket_stats) = connection.inode.and_then(|inode| stats.get(&inode)) {
+            connection.rx_bytes = Some(socket_stats.rx_bytes);
+            connection.tx_bytes = Some(socket_stats.tx_bytes);
+        }
+    }
+}
+
+/// Sets the received and sent bytes of all TCP connections, read from the kernel with an `inet_diag` netlink query.
+/// Only the sockets of somo's own network namespace are queried, UDP sockets have no byte counters.
+/// Connections without counters (and all connections if the query fails) keep `None`, which is shown as "-".
+///
+/// # Arguments
+/// * `connections`: The connections in which to set `rx_bytes` and `tx_bytes`.
+///
+/// # Returns
+/// None
+pub fn annotate_stats(connections: &mut [Connection]) {
+    let mut stats = HashMap::new();
+    for family in [AF_INET, AF_INET6] {
+        stats.extend(dump_tcp_stats(family).unwrap_or_default());
+    }
+    apply_stats(connections, &stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_apply_stats_by_inode() {
+        let stats = HashMap::from([(
+            1001,
+            SocketStats {
+                rx_bytes: 10,
+                tx_bytes: 20,
+            },
+        )]);
+        // an IPv4 client of a dual-stack listener, the kernel reports the socket as "[::ffff:1.2.3.4]:80"
+        let mapped = Connection {
+            proto: "tcp".to_string(),
+            local_address: "1.2.3.4".to_string(),
+            local_port: "80".to_string(),
+            inode: Some(1001),
+            ..Default::default()
+        };
+        // a socket with the same addresses, e.g with SO_REUSEPORT, doesn't get the counters of the other one
+        let reused = Connection {
+            inode: Some(1002),
+            ..mapped.clone()
+        };
+        let unknown = Connection {
+            inode: None,
+            ..mapped.clone()
+        };
+
+        let mut connections = vec![mapped, reused, unknown];
+        apply_stats(&mut connections, &stats);
+        assert_eq!(connections[0].rx_bytes, Some(10));
+        assert_eq!(connections[0].tx_bytes, Some(20));
+        assert!(connections[1].rx_bytes.is_none());
+        assert!(connections[2].rx_bytes.is_none());
+    }
+
+    #[test]
+    fn test_annotate_stats_skips_udp() {
+        let mut connections = vec![Connection {
+            proto: "udp".to_string(),
+            ..Default::default()
+        }];
+        annotate_stats(&mut connections);
+        assert!(connections[0].rx_bytes.is_none());
+        assert!(connections[0].tx_bytes.is_none());
+    }
+
+    #[test]
+    fn test_dump_tcp_stats_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let link = std::fs::read_link(format!("/proc/self/fd/{}", listener.as_raw_fd())).unwrap();
+        let inode: u64 = link
+            .to_string_lossy()
+            .trim_start_matches("socket:[")
+            .trim_end_matches(']')
+            .parse()
+            .unwrap();
+
+        // netlink may not be available in restricted environments, the query must then fail instead of panicking
+        if let Ok(stats) = dump_tcp_stats(AF_INET) {
+            assert!(stats.contains_key(&inode));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::schemas::Connection;
+
+/// Extracts the systemd service of a process from its cgroup file.
+/// The service is the innermost `.service` component of the systemd hierarchy, so nested slices
+/// (e.g "0::/system.slice/system-getty.slice/getty@tty1.service") and the services of user managers are supported.
+/// Processes of user sessions and other scopes aren't part of a service.
+///
+/// # Arguments
+/// * `cgroup`: The content of `/proc/<pid>/cgroup`.
+///
+/// # Example
+/// ```
+/// # use somo::connections::parse_unit;
+/// assert_eq!(parse_unit("0::/system.slice/nginx.service"), Some("nginx.service".to_string()));
+/// assert_eq!(parse_unit("0::/user.slice/user-1000.slice/session-2.scope"), None);
+/// ```
+///
+/// # Returns
+/// The name of the unit or `None` if the process doesn't belong to a service.
+pub fn parse_unit(cgroup: &str) -> Option<String> {
+    cgroup
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+            // cgroup v2 has no controllers, on cgroup v1 only the hierarchy of systemd names the units
+            (controllers.is_empty() || controllers == "name=systemd").then_some(path)
+        })
+        .flat_map(|path| path.split('/'))
+        .rfind(|component| component.len() > ".service".len() && component.ends_with(".service"))
+        .map(|unit| unit.to_string())
+}
+
+/// Gets the systemd unit of a process by reading its cgroup file.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+///
+/// # Returns
+/// The name of the unit or `None` if the process doesn't belong to a service or the file couldn't be read.
+fn get_unit(pid: &str) -> Option<String> {
+    let cgroup = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    parse_unit(&cgroup)
+}
+
+/// Sets the systemd unit of all connections whose process belongs to a service.
+/// Every process is only looked up once.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `unit`.
+///
+/// # Returns
+/// None
+pub fn annotate_units(connections: &mut [Connection]) {
+    let mut units: HashMap<String, Option<String>> = HashMap::new();
+
+    for connection in connections.iter_mut() {
+        if connection.pid == "-" {
+            continue;
+        }
+
+        connection.unit = units
+            .entry(connection.pid.clone())
+            .or_insert_with(|| get_unit(&connection.pid))
+            .clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unit_cgroup_v2() {
+        assert_eq!(
+            parse_unit("0::/system.slice/sshd.service"),
+            Some("sshd.service".to_string())
+        );
+        assert_eq!(
+            parse_unit("0::/system.slice/system-getty.slice/getty@tty1.service"),
+            Some("getty@tty1.service".to_string())
+        );
+        // a service which delegates its cgroup to sub-groups
+        assert_eq!(
+            parse_unit("0::/system.slice/containerd.service/payload"),
+            Some("containerd.service".to_string())
+        );
+        // the services of a user manager win over the manager itself
+        assert_eq!(
+            parse_unit(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/pipewire.service"
+            ),
+            Some("pipewire.service".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_unit_cgroup_v1() {
+        let cgroup = "12:memory:/system.slice/other.service\n1:name=systemd:/system.slice/nginx.service\n0::/";
+        assert_eq!(parse_unit(cgroup), Some("nginx.service".to_string()));
+    }
+
+    #[test]
+    fn test_parse_unit_no_service() {
+        assert_eq!(
+            parse_unit("0::/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+        assert_eq!(parse_unit("0::/init.scope"), None);
+        assert_eq!(parse_unit("0::/system.slice/docker-3f4e8a.scope"), None);
+        assert_eq!(parse_unit(""), None);
+    }
+
+    #[test]
+    fn test_annotate_units_unknown_pid() {
+        let mut connections = vec![Connection {
+            pid: "-".to_string(),
+            ..Default::default()
+        }];
+        annotate_units(&mut connections);
+        assert!(connections[0].unit.is_none());
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_ACCESS_DENIED, ERROR_INSUFFICIENT_BUFFER, NO_ERROR,
+};
+use windows::Win32::NetworkManagement::IpHelper::{
+    GetExtendedTcpTable, GetExtendedUdpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID,
+    MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, TCP_TABLE_OWNER_PID_ALL, UDP_TABLE_OWNER_PID,
+};
+use windows::Win32::Networking::WinSock::{AF_INET, AF_INET6};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+
+use super::Platform;
+use crate::error::SomoError;
+use crate::schemas::NetEntry;
+use crate::schemas::ProcessInfo;
+
+/// Reads the sockets and processes using the IP Helper and ToolHelp APIs of the "windows" crate.
+#[derive(Default)]
+pub struct Windows;
+
+/// Gets all running processes on the system by taking a ToolHelp snapshot.
+/// Windows doesn't expose the owner or command line of other processes cheaply, so only the executable name is known.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// A map from each PID to its process.
+fn get_processes() -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+    let mut map: HashMap<u64, ProcessInfo> = HashMap::new();
+
+    // SAFETY: the snapshot handle is only used while it is open and the entry has the size the API expects
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0).map_err(|error| {
+            SomoError::ProcRead(format!("couldn't take a process snapshot: {}", error))
+        })?;
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut found = Process32FirstW(snapshot, &mut entry).is_ok();
+        while found {
+            let name_length = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let program = String::from_utf16_lossy(&entry.szExeFile[..name_length]);
+
+            map.insert(
+                entry.th32ProcessID as u64,
+                ProcessInfo {
+                    pid: entry.th32ProcessID as i32,
+                    program: program.clone(),
+                    user: None,
+                    command: program,
+                    exe_path: None,
+                    exe_deleted: false,
+                    created: None,
+                },
+            );
+            found = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+
+        let _ = CloseHandle(snapshot);
+    }
+
+    Ok(map)
+}
+
+/// Reads one of the socket tables, growing the buffer until the table fits.
+/// The buffer consists of `u32`s so that it is aligned for the table structs.
+///
+/// # Arguments
+/// * `table_name`: The name of the table, used in the error message.
+/// * `get_table`: Calls `GetExtendedTcpTable` or `GetExtendedUdpTable` with a buffer and its size in bytes.
+///
+/// # Returns
+/// The buffer containing the table or the error if the table couldn't be read.
+fn read_table(
+    table_name: &str,
+    get_table: impl Fn(*mut c_void, &mut u32) -> u32,
+) -> Result<Vec<u32>, SomoError> {
+    let mut size: u32 = 0;
+    let mut buffer: Vec<u32> = Vec::new();
+    loop {
+        let pointer = if buffer.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            buffer.as_mut_ptr() as *mut c_void
+        };
+
+        let result = get_table(pointer, &mut size);
+        if result == NO_ERROR.0 && !buffer.is_empty() {
+            return Ok(buffer);
+        } else if result == ERROR_INSUFFICIENT_BUFFER.0 {
+            buffer = vec![0; (size as usize).div_ceil(std::mem::size_of::<u32>())];
+        } else if result == ERROR_ACCESS_DENIED.0 {
+            return Err(SomoError::PermissionDenied(table_name.to_string()));
+        } else {
+            return Err(SomoError::ProcRead(format!(
+                "{} (error code {})",
+                table_name, result
+            )));
+        }
+    }
+}
+
+/// Gets the rows of a socket table, ie. one of the `MIB_*TABLE_OWNER_PID` structs.
+/// All of them start with the number of rows followed by the rows, which only consist of `u32` and `u8` fields.
+///
+/// # Arguments
+/// * `buffer`: The buffer filled by `read_table`.
+///
+/// # Returns
+/// A copy of all rows of the table.
+///
+/// # Safety
+/// The buffer has to contain a table whose rows are of type `Row`.
+unsafe fn get_rows<Row: Copy>(buffer: &[u32]) -> Vec<Row> {
+    let count = buffer[0] as usize;
+    let rows = buffer[1..].as_ptr() as *const Row;
+    std::slice::from_raw_parts(rows, count).to_vec()
+}
+
+/// Converts a port from the socket tables, which is stored in network byte order, into a number.
+fn get_port(port: u32) -> u16 {
+    u16::from_be(port as u16)
+}
+
+/// Converts a TCP state of the socket tables into the same names the procfs crate uses on Linux.
+///
+/// # Arguments
+/// * `state`: The `MIB_TCP_STATE` value.
+///
+/// # Returns
+/// The lowercase state name, e.g "established" or "timewait".
+fn get_tcp_state(state: u32) -> String {
+    match state {
+        1 => "close",
+        2 => "listen",
+        3 => "synsent",
+        4 => "synrecv",
+        5 => "established",
+        6 => "finwait1",
+        7 => "finwait2",
+        8 => "closewait",
+        9 => "closing",
+        10 => "lastack",
+        11 => "timewait",
+        _ => "close",
+    }
+    .to_string()
+}
+
+impl Platform for Windows {
+    /// Users aren't known on Windows, so `numeric_users` doesn't change anything.
+    fn get_processes(&self, _: bool) -> Result<HashMap<u64, ProcessInfo>, SomoError> {
+        get_processes()
+    }
+
+    fn get_tcp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        let mut entries: Vec<NetEntry> = Vec::new();
+
+        let ipv4_table = read_table("the IPv4 TCP table", |buffer, size| unsafe {
+            GetExtendedTcpTable(
+                Some(buffer),
+                size,
+                false,
+                AF_INET.0 as u32,
+                TCP_TABLE_OWNER_PID_ALL,
+                0,
+            )
+        })?;
+        // SAFETY: the buffer was filled with a table of the requested class and address family
+        let rows = unsafe { get_rows::<MIB_TCPROW_OWNER_PID>(&ipv4_table) };
+        entries.extend(rows.iter().map(|row| NetEntry {
+            protocol: "tcp".to_string(),
+            local_address: SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(row.dwLocalAddr))),
+                get_port(row.dwLocalPort),
+            ),
+            remote_address: SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(row.dwRemoteAddr))),
+                get_port(row.dwRemotePort),
+            ),
+            state: get_tcp_state(row.dwState),
+            owner: row.dwOwningPid as u64,
+            queues: None,
+        }));
+
+        let ipv6_table = read_table("the IPv6 TCP table", |buffer, size| unsafe {
+            GetExtendedTcpTable(
+                Some(buffer),
+                size,
+                false,
+                AF_INET6.0 as u32,
+                TCP_TABLE_OWNER_PID_ALL,
+                0,
+            )
+        })?;
+        // SAFETY: the buffer was filled with a table of the requested class and address family
+        let rows = unsafe { get_rows::<MIB_TCP6ROW_OWNER_PID>(&ipv6_table) };
+        entries.extend(rows.iter().map(|row| NetEntry {
+            protocol: "tcp".to_string(),
+            local_address: SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(row.ucLocalAddr)),
+                get_port(row.dwLocalPort),
+            ),
+            remote_address: SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(row.ucRemoteAddr)),
+                get_port(row.dwRemotePort),
+            ),
+            state: get_tcp_state(row.dwState),
+            owner: row.dwOwningPid as u64,
+            queues: None,
+        }));
+
+        Ok(entries)
+    }
+
+    // UDP sockets have no remote address on Windows, they are shown like unconnected sockets on Linux
+    fn get_udp_entries(&self) -> Result<Vec<NetEntry>, SomoError> {
+        let mut entries: Vec<NetEntry> = Vec::new();
+
+        let ipv4_table = read_table("the IPv4 UDP table", |buffer, size| unsafe {
+            GetExtendedUdpTable(
+                Some(buffer),
+                size,
+                false,
+                AF_INET.0 as u32,
+                UDP_TABLE_OWNER_PID,
+                0,
+            )
+        })?;
+        // SAFETY: the buffer was filled with a table of the requested class and address family
+        let rows = unsafe { get_rows::<MIB_UDPROW_OWNER_PID>(&ipv4_table) };
+        entries.extend(rows.iter().map(|row| NetEntry {
+            protocol: "udp".to_string(),
+            local_address: SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(row.dwLocalAddr))),
+                get_port(row.dwLocalPort),
+            ),
+            remote_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            state: "close".to_string(),
+            owner: row.dwOwningPid as u64,
+            queues: None,
+        }));
+
+        let ipv6_table = read_table("the IPv6 UDP table", |buffer, size| unsafe {
+            GetExtendedUdpTable(
+                Some(buffer),
+                size,
+                false,
+                AF_INET6.0 as u32,
+                UDP_TABLE_OWNER_PID,
+                0,
+            )
+        })?;
+        // SAFETY: the buffer was filled with a table of the requested class and address family
+        let rows = unsafe { get_rows::<MIB_UDP6ROW_OWNER_PID>(&ipv6_table) };
+        entries.extend(rows.iter().map(|row| NetEntry {
+            protocol: "udp".to_string(),
+            local_address: SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(row.ucLocalAddr)),
+                get_port(row.dwLocalPort),
+            ),
+            remote_address: SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+            state: "close".to_string(),
+            owner: row.dwOwningPid as u64,
+            queues: None,
+        }));
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_port() {
+        assert_eq!(get_port(u16::to_be(8080) as u32), 8080);
+    }
+
+    #[test]
+    fn test_get_tcp_state() {
+        assert_eq!(get_tcp_state(2), "listen");
+        assert_eq!(get_tcp_state(5), "established");
+        assert_eq!(get_tcp_state(11), "timewait");
+    }
+}
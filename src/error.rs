@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// Represents the errors which can occur while collecting the connections.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SomoError {
+    /// A socket table or the process list couldn't be read.
+    ProcRead(String),
+    /// `/proc` isn't mounted or can't be read at all, e.g in a minimal container.
+    ProcUnavailable,
+    /// The socket tables or processes couldn't be read without root privileges.
+    PermissionDenied(String),
+    /// A socket table or process file had an unexpected format.
+    Parse(String),
+}
+
+impl SomoError {
+    /// Checks if the error was caused by missing privileges, in that case running somo with sudo may help.
+    ///
+    /// # Arguments
+    /// None
+    ///
+    /// # Example
+    /// ```
+    /// # use somo::SomoError;
+    /// assert!(SomoError::PermissionDenied("/proc/net/tcp".to_string()).is_permission_denied());
+    /// assert!(!SomoError::Parse("/proc/net/tcp".to_string()).is_permission_denied());
+    /// ```
+    ///
+    /// # Returns
+    /// `true` if the permission was denied, `false` if not.
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self, SomoError::PermissionDenied(_))
+    }
+}
+
+impl fmt::Display for SomoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SomoError::ProcRead(details) => write!(f, "Couldn't read the sockets: {}", details),
+            SomoError::ProcUnavailable => write!(f, "Cannot read /proc; is it mounted?"),
+            SomoError::PermissionDenied(details) => write!(f, "Permission denied: {}", details),
+            SomoError::Parse(details) => write!(f, "Couldn't parse the sockets: {}", details),
+        }
+    }
+}
+
+impl std::error::Error for SomoError {}
@@ -0,0 +1,70 @@
+use maxminddb::geoip2;
+use maxminddb::Reader;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::schemas::Connection;
+use crate::utils;
+
+/// Opens a MaxMind GeoIP2/GeoLite2 database using the "maxminddb" crate.
+///
+/// # Arguments
+/// * `path`: The path to the `.mmdb` database file.
+///
+/// # Returns
+/// The database reader or an error message if the file can't be read or isn't a valid database.
+pub fn open_database(path: &Path) -> Result<Reader<Vec<u8>>, String> {
+    Reader::open_readfile(path)
+        .map_err(|error| format!("Couldn't open GeoIP database {}: {}", path.display(), error))
+}
+
+/// Looks up the ISO country code of an IP address.
+///
+/// # Arguments
+/// * `reader`: The GeoIP database.
+/// * `ip`: The IP address to look up.
+///
+/// # Returns
+/// The ISO country code, e.g "DE", or `None` if the address isn't in the database.
+fn lookup_country(reader: &Reader<Vec<u8>>, ip: IpAddr) -> Option<String> {
+    let record: geoip2::Country = reader.lookup(ip).ok()?;
+    record
+        .country
+        .and_then(|country| country.iso_code)
+        .map(|iso_code| iso_code.to_string())
+}
+
+/// Annotates every connection to a public remote address with the country of the address.
+/// Every remote address is only looked up once and non-public addresses are never looked up.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `country`.
+/// * `reader`: The GeoIP database.
+///
+/// # Returns
+/// None
+pub fn annotate_countries(connections: &mut [Connection], reader: &Reader<Vec<u8>>) {
+    let mut countries: HashMap<IpAddr, Option<String>> = HashMap::new();
+
+    for connection in connections.iter_mut() {
+        connection.country = match utils::parse_ip(&connection.remote_address) {
+            Some(ip) if utils::is_public_ip(&ip) => countries
+                .entry(ip)
+                .or_insert_with(|| lookup_country(reader, ip))
+                .clone(),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_database_missing() {
+        let error = open_database(Path::new("/nonexistent/GeoLite2-Country.mmdb")).unwrap_err();
+        assert!(error.contains("/nonexistent/GeoLite2-Country.mmdb"));
+    }
+}
@@ -0,0 +1,738 @@
+use clap::ValueEnum;
+use std::io::{self, stdout, IsTerminal, Write};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use termimad::crossterm::cursor::{Hide, MoveTo, Show};
+use termimad::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use termimad::crossterm::execute;
+use termimad::crossterm::terminal::{
+    self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+
+use crate::cli::{self, KillOptions};
+use crate::connections;
+use crate::schemas::{Connection, SortField};
+use crate::table::{self, HighlightedRows, RenderOptions, TableOptions};
+use crate::utils;
+
+/// How often the connections are refreshed while no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+/// The lines of the screen which aren't rows of the table: the borders, the header and the filling row of the table, the status and the help line.
+const RESERVED_LINES: usize = 7;
+/// The keys which sort the table by a column, pressing the key of the current sort field again reverses the order.
+const SORT_KEYS: [(char, SortField); 8] = [
+    ('1', SortField::Proto),
+    ('2', SortField::LocalPort),
+    ('3', SortField::RemoteAddress),
+    ('4', SortField::RemotePort),
+    ('5', SortField::Pid),
+    ('6', SortField::Program),
+    ('7', SortField::State),
+    ('8', SortField::Age),
+];
+/// The key bindings shown at the bottom of the screen.
+const HELP: &str = "/ filter  ↑↓ select  1-8 sort by proto, local port, remote address, remote port, pid, program, state, age  k kill  q quit";
+
+/// The state of the interactive mode, which is changed by the keys the user presses.
+#[derive(Debug, Default)]
+struct State {
+    /// The text which the shown connections have to contain.
+    filter: String,
+    /// Whether the keys are typed into the filter.
+    typing: bool,
+    sort: Option<SortField>,
+    reverse: bool,
+    /// The index of the selected row.
+    selected: usize,
+    /// The PID and the program of a process which is killed once the user confirms it.
+    pending_kill: Option<(i32, String)>,
+    /// The result of the last action, e.g of a kill.
+    message: Option<String>,
+}
+
+/// What the interactive mode does after a key was handled.
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Redraws the screen with the changed state.
+    Redraw,
+    /// Kills the process with the PID.
+    Kill(i32),
+    /// Exits the interactive mode.
+    Quit,
+}
+
+/// Switches the terminal into raw mode and to the alternate screen, both are restored when the guard is dropped.
+/// The guard is also dropped on a panic, so the terminal of the user is never left in raw mode.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(guard)
+    }
+
+    /// Restores the terminal while `action` runs, e.g so that the messages and prompts of a kill can be read.
+    ///
+    /// # Arguments
+    /// * `action`: What to do with the normal terminal.
+    ///
+    /// # Returns
+    /// The result of the action or the error if the terminal couldn't be switched.
+    fn suspend<T>(&self, action: impl FnOnce() -> T) -> io::Result<T> {
+        execute!(stdout(), Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        let result = action();
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(result)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Checks if a connection contains the filter typed by the user in any of its shown fields, ignoring the case.
+///
+/// # Arguments
+/// * `connection`: The connection to check.
+/// * `filter`: The text typed by the user, an empty filter matches every connection.
+///
+/// # Returns
+/// `true` if the connection should be shown, `false` if not.
+fn matches_filter(connection: &Connection, filter: &str) -> bool {
+    let filter = filter.to_lowercase();
+    [
+        Some(connection.proto.as_str()),
+        Some(connection.local_address.as_str()),
+        Some(connection.local_port.as_str()),
+        Some(connection.remote_address.as_str()),
+        Some(connection.remote_port.as_str()),
+        connection.remote_hostname.as_deref(),
+        Some(connection.program.as_str()),
+        Some(connection.pid.as_str()),
+        connection.user.as_deref(),
+        Some(connection.state.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|field| field.to_lowercase().contains(&filter))
+}
+
+/// Gets the connections which match the filter, sorted by the column the user chose.
+///
+/// # Arguments
+/// * `connections`: All collected connections.
+/// * `state`: The filter and the sort order.
+///
+/// # Returns
+/// The rows of the table.
+fn get_rows(connections: &[Connection], state: &State) -> Vec<Connection> {
+    let mut rows: Vec<Connection> = connections
+        .iter()
+        .filter(|connection| matches_filter(connection, &state.filter))
+        .cloned()
+        .collect();
+    if let Some(sort_field) = state.sort {
+        connections::sort_connections(&mut rows, sort_field, state.reverse);
+    }
+    rows
+}
+
+/// Gets the rows which fit on the screen, scrolled so that the selected row is visible.
+///
+/// # Arguments
+/// * `selected`: The index of the selected row.
+/// * `rows`: The number of rows.
+/// * `visible`: The number of rows which fit on the screen.
+///
+/// # Returns
+/// The range of the visible rows.
+fn get_visible_rows(selected: usize, rows: usize, visible: usize) -> Range<usize> {
+    let start = (selected + 1).saturating_sub(visible);
+    start..rows.min(start + visible)
+}
+
+/// Changes the state according to a key pressed by the user.
+///
+/// # Arguments
+/// * `state`: The state to change.
+/// * `key`: The pressed key.
+/// * `rows`: The rows currently shown, used to find the process of the selected row.
+/// * `kill_options`: Whether kills have to be confirmed.
+///
+/// # Returns
+/// What to do next.
+fn handle_key(
+    state: &mut State,
+    key: KeyEvent,
+    rows: &[Connection],
+    kill_options: &KillOptions,
+) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Action::Quit;
+    }
+    state.message = None;
+
+    if let Some((pid, program)) = state.pending_kill.take() {
+        if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            return Action::Kill(pid);
+        }
+        state.message = Some(format!("Kept {} (PID {}) alive.", program, pid));
+        return Action::Redraw;
+    }
+
+    if state.typing {
+        match key.code {
+            KeyCode::Char(character) => state.filter.push(character),
+            KeyCode::Backspace => {
+                state.filter.pop();
+            }
+            KeyCode::Enter => state.typing = false,
+            KeyCode::Esc => {
+                state.typing = false;
+                state.filter.clear();
+            }
+            _ => {}
+        }
+        state.selected = 0;
+        return Action::Redraw;
+    }
+
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+        KeyCode::Char('/') => state.typing = true,
+        KeyCode::Up => state.selected = state.selected.saturating_sub(1),
+        KeyCode::Down => {
+            state.selected = (state.selected + 1).min(rows.len().saturating_sub(1));
+        }
+        KeyCode::Char('k') => match rows.get(state.selected) {
+            Some(connection) => match connection.pid.parse::<i32>() {
+                Ok(pid) if kill_options.yes => return Action::Kill(pid),
+                Ok(pid) => state.pending_kill = Some((pid, connection.program.clone())),
+                Err(_) => {
+                    state.message = Some("The process of this connection is unknown.".to_string())
+                }
+            },
+            None => state.message = Some("No connection is selected.".to_string()),
+        },
+        KeyCode::Char(character) => {
+            if let Some((_, sort_field)) = SORT_KEYS.iter().find(|(key, _)| *key == character) {
+                state.reverse = state.sort == Some(*sort_field) && !state.reverse;
+                state.sort = Some(*sort_field);
+            }
+        }
+        _ => {}
+    }
+    Action::Redraw
+}
+
+/// Kills a process selected in the interactive mode the same way as `--kill`, including `--force`, `--tree` and `--dry-run`.
+/// It runs while the terminal is suspended, the user presses Enter to get back to the table once the messages are read.
+///
+/// # Arguments
+/// * `pid`: The PID of the process.
+/// * `connection`: A connection of the process, used for its program and command line.
+/// * `kill_options`: The signal to send and how to escalate it, the kill was already confirmed in the table.
+///
+/// # Returns
+/// The message shown in the status line.
+fn kill(pid: i32, connection: &Connection, kill_options: &KillOptions) -> String {
+    let options = KillOptions {
+        yes: true,
+        ..*kill_options
+    };
+    let killed = cli::kill_processes(&[(pid, connection)], &options);
+    utils::pretty_print_info("Press Enter to return to the connections.");
+    let _ = io::stdin().read_line(&mut String::new());
+    describe_kill(pid, connection, kill_options, killed)
+}
+
+/// Describes the result of a kill for the status line, the details were printed while the terminal was suspended.
+///
+/// # Arguments
+/// * `pid`: The PID of the killed process.
+/// * `connection`: A connection of the process, used for its program.
+/// * `kill_options`: Whether it was a dry-run or the children were killed too.
+/// * `killed`: Whether all kills succeeded.
+///
+/// # Returns
+/// The message shown in the status line.
+fn describe_kill(
+    pid: i32,
+    connection: &Connection,
+    kill_options: &KillOptions,
+    killed: bool,
+) -> String {
+    let target = format!("{} (PID {})", connection.program, pid);
+    let children = if kill_options.tree {
+        " and its children"
+    } else {
+        ""
+    };
+    if kill_options.dry_run {
+        format!("Dry-run, nothing was sent to {}{}.", target, children)
+    } else if killed {
+        format!("Killed {}{}.", target, children)
+    } else {
+        format!("Failed to kill {}{}.", target, children)
+    }
+}
+
+/// Gets the status line below the table, which shows the filter while it is typed, the kill confirmation or the number of connections.
+///
+/// # Arguments
+/// * `state`: The state of the interactive mode.
+/// * `rows`: The number of shown connections.
+/// * `kill_options`: The signal which is sent to killed processes.
+///
+/// # Returns
+/// The status line.
+fn get_status_line(state: &State, rows: usize, kill_options: &KillOptions) -> String {
+    if state.typing {
+        return format!("Filter: {}_", state.filter);
+    }
+    if let Some((pid, program)) = &state.pending_kill {
+        let children = if kill_options.tree {
+            " and its children"
+        } else {
+            ""
+        };
+        return format!(
+            "Send {} to {} (PID {}){}? [y/N]",
+            kill_options.signal, program, pid, children
+        );
+    }
+    if let Some(message) = &state.message {
+        return message.clone();
+    }
+
+    let mut status = format!("{} Connections", rows);
+    if !state.filter.is_empty() {
+        status.push_str(&format!(", filtered by \"{}\"", state.filter));
+    }
+    if let Some(sort_field) = state.sort {
+        let order = if state.reverse {
+            "descending"
+        } else {
+            "ascending"
+        };
+        let name = sort_field
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        status.push_str(&format!(", sorted by {} ({})", name, order));
+    }
+    status
+}
+
+/// Renders the whole screen: the visible rows of the table with the selected row in bold, the status and the help line.
+///
+/// # Arguments
+/// * `rows`: The rows of the table.
+/// * `state`: The state of the interactive mode.
+/// * `options`: Which columns to show, whether to use colors and the width of the table.
+/// * `height`: The height of the terminal in lines.
+/// * `kill_options`: The signal which is sent to killed processes.
+///
+/// # Returns
+/// The screen with "\r\n" line breaks, since the terminal is in raw mode.
+fn render_screen(
+    rows: &[Connection],
+    state: &State,
+    options: &RenderOptions,
+    height: u16,
+    kill_options: &KillOptions,
+) -> String {
+    let visible = ((height as usize).saturating_sub(RESERVED_LINES) / 2).max(1);
+    let visible_rows = get_visible_rows(state.selected, rows.len(), visible);
+    let highlighted_rows = HighlightedRows {
+        selected: (!rows.is_empty()).then(|| state.selected - visible_rows.start),
+        ..Default::default()
+    };
+    let table = table::render_highlighted_connections_table(
+        &rows[visible_rows],
+        &highlighted_rows,
+        options,
+    );
+
+    let mut screen = table.trim_end().replace('\n', "\r\n");
+    screen.push_str("\r\n");
+    screen.push_str(&get_status_line(state, rows.len(), kill_options));
+    screen.push_str("\r\n");
+    screen.push_str(HELP);
+    screen
+}
+
+/// Draws the screen into the terminal.
+///
+/// # Arguments
+/// * `screen`: The screen rendered by `render_screen`.
+///
+/// # Returns
+/// The error if the terminal couldn't be written.
+fn draw(screen: &str) -> io::Result<()> {
+    let mut stdout = stdout();
+    execute!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+    write!(stdout, "{}", screen)?;
+    stdout.flush()
+}
+
+/// Runs the loop of the interactive mode until the user presses `q`, `Esc` or `Ctrl-C`.
+///
+/// # Arguments
+/// * `state`: The initial state.
+/// * `options`: Which optional columns to show in the table.
+/// * `kill_options`: The signal which is sent to killed processes and whether kills have to be confirmed.
+/// * `collect_connections`: Collects the (filtered) connections for every refresh.
+///
+/// # Returns
+/// The error if the terminal couldn't be read or written.
+fn run(
+    mut state: State,
+    options: &TableOptions,
+    kill_options: &KillOptions,
+    collect_connections: impl Fn() -> Vec<Connection>,
+) -> io::Result<()> {
+    let guard = TerminalGuard::enter()?;
+
+    let mut connections = collect_connections();
+    let mut refreshed_at = Instant::now();
+    loop {
+        let rows = get_rows(&connections, &state);
+        state.selected = state.selected.min(rows.len().saturating_sub(1));
+
+        let (width, height) = terminal::size()?;
+        let render_options = RenderOptions {
+            table: options.clone(),
+            color: utils::is_color_enabled(),
+            width,
+            border: table::get_border_style(),
+        };
+        draw(&render_screen(
+            &rows,
+            &state,
+            &render_options,
+            height,
+            kill_options,
+        ))?;
+
+        // redraw after every key and terminal resize, refresh the connections once the interval passed
+        if event::poll(REFRESH_INTERVAL.saturating_sub(refreshed_at.elapsed()))? {
+            if let Event::Key(key) = event::read()? {
+                match handle_key(&mut state, key, &rows, kill_options) {
+                    Action::Redraw => {}
+                    Action::Kill(pid) => {
+                        let connection = rows
+                            .iter()
+                            .find(|connection| connection.pid == pid.to_string())
+                            .cloned()
+                            .unwrap_or_default();
+                        state.message =
+                            Some(guard.suspend(|| kill(pid, &connection, kill_options))?);
+                        connections = collect_connections();
+                        refreshed_at = Instant::now();
+                    }
+                    Action::Quit => return Ok(()),
+                }
+            }
+        } else {
+            connections = collect_connections();
+            refreshed_at = Instant::now();
+        }
+    }
+}
+
+/// Shows the connections in a full-screen table, which refreshes every 2 seconds and can be filtered, sorted and used to kill processes.
+/// The terminal is restored when the user exits with `q`, `Esc` or `Ctrl-C`.
+/// The screen is drawn with the crossterm of termimad instead of a TUI library like ratatui,
+/// so that the rows look the same as the normal table and no second terminal library is needed.
+///
+/// # Arguments
+/// * `options`: Which optional columns to show in the table.
+/// * `kill_options`: The signal which is sent to killed processes and whether kills have to be confirmed.
+/// * `sort`: The initial sort field and order provided by the user.
+/// * `collect_connections`: Collects the (filtered) connections for every refresh.
+///
+/// # Returns
+/// `true` if the interactive mode ran, `false` if the terminal couldn't be used.
+pub fn interactive_connections(
+    options: &TableOptions,
+    kill_options: &KillOptions,
+    sort: (Option<SortField>, bool),
+    collect_connections: impl Fn() -> Vec<Connection>,
+) -> bool {
+    if !stdout().is_terminal() {
+        utils::pretty_print_error("The interactive mode needs a terminal.");
+        return false;
+    }
+
+    let state = State {
+        sort: sort.0,
+        reverse: sort.1,
+        ..Default::default()
+    };
+    match run(state, options, kill_options, collect_connections) {
+        Ok(()) => true,
+        Err(error) => {
+            utils::pretty_print_error(&format!("The interactive mode failed: {}.", error));
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection_with(local_port: &str, pid: &str, program: &str) -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: local_port.to_string(),
+            remote_address: "0.0.0.0".to_string(),
+            remote_port: "0".to_string(),
+            program: program.to_string(),
+            pid: pid.to_string(),
+            state: "listen".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn kill_options(yes: bool) -> KillOptions {
+        KillOptions {
+            signal: cli::parse_signal("TERM").unwrap(),
+            yes,
+            dry_run: false,
+            force: false,
+            delay: None,
+            tree: false,
+        }
+    }
+
+    fn press(state: &mut State, code: KeyCode, rows: &[Connection]) -> Action {
+        handle_key(
+            state,
+            KeyEvent::new(code, KeyModifiers::NONE),
+            rows,
+            &kill_options(false),
+        )
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let connection = connection_with("5432", "42", "Postgres");
+        assert!(matches_filter(&connection, ""));
+        assert!(matches_filter(&connection, "postgres"));
+        assert!(matches_filter(&connection, "543"));
+        assert!(matches_filter(&connection, "LISTEN"));
+        assert!(!matches_filter(&connection, "nginx"));
+    }
+
+    #[test]
+    fn test_get_rows() {
+        let connections = vec![
+            connection_with("80", "1", "nginx"),
+            connection_with("443", "2", "nginx"),
+            connection_with("22", "3", "sshd"),
+        ];
+        let state = State {
+            filter: "nginx".to_string(),
+            sort: Some(SortField::LocalPort),
+            reverse: true,
+            ..Default::default()
+        };
+        let rows = get_rows(&connections, &state);
+        let ports: Vec<&str> = rows.iter().map(|row| row.local_port.as_str()).collect();
+        assert_eq!(ports, vec!["443", "80"]);
+    }
+
+    #[test]
+    fn test_get_visible_rows() {
+        assert_eq!(get_visible_rows(0, 100, 10), 0..10);
+        assert_eq!(get_visible_rows(9, 100, 10), 0..10);
+        assert_eq!(get_visible_rows(10, 100, 10), 1..11);
+        assert_eq!(get_visible_rows(2, 3, 10), 0..3);
+        assert_eq!(get_visible_rows(0, 0, 10), 0..0);
+    }
+
+    #[test]
+    fn test_handle_key_filter() {
+        let mut state = State::default();
+        assert_eq!(press(&mut state, KeyCode::Char('/'), &[]), Action::Redraw);
+        assert!(state.typing);
+
+        // while typing, "q" and the sort keys are part of the filter
+        press(&mut state, KeyCode::Char('q'), &[]);
+        press(&mut state, KeyCode::Char('1'), &[]);
+        press(&mut state, KeyCode::Backspace, &[]);
+        assert_eq!(state.filter, "q");
+        assert!(state.sort.is_none());
+
+        press(&mut state, KeyCode::Enter, &[]);
+        assert!(!state.typing);
+        assert_eq!(state.filter, "q");
+
+        press(&mut state, KeyCode::Char('/'), &[]);
+        press(&mut state, KeyCode::Esc, &[]);
+        assert!(state.filter.is_empty());
+    }
+
+    #[test]
+    fn test_handle_key_sort_and_select() {
+        let rows = vec![
+            connection_with("80", "1", "nginx"),
+            connection_with("22", "2", "sshd"),
+        ];
+        let mut state = State::default();
+
+        press(&mut state, KeyCode::Char('2'), &rows);
+        assert_eq!(state.sort, Some(SortField::LocalPort));
+        assert!(!state.reverse);
+        press(&mut state, KeyCode::Char('2'), &rows);
+        assert!(state.reverse);
+        press(&mut state, KeyCode::Char('6'), &rows);
+        assert_eq!(state.sort, Some(SortField::Program));
+        assert!(!state.reverse);
+
+        press(&mut state, KeyCode::Down, &rows);
+        press(&mut state, KeyCode::Down, &rows);
+        assert_eq!(state.selected, 1);
+        press(&mut state, KeyCode::Up, &rows);
+        press(&mut state, KeyCode::Up, &rows);
+        assert_eq!(state.selected, 0);
+    }
+
+    #[test]
+    fn test_handle_key_kill() {
+        let rows = vec![
+            connection_with("80", "42", "nginx"),
+            connection_with("53", "-", "-"),
+        ];
+        let mut state = State::default();
+
+        // the kill has to be confirmed
+        assert_eq!(press(&mut state, KeyCode::Char('k'), &rows), Action::Redraw);
+        assert_eq!(state.pending_kill, Some((42, "nginx".to_string())));
+        assert_eq!(
+            press(&mut state, KeyCode::Char('y'), &rows),
+            Action::Kill(42)
+        );
+
+        press(&mut state, KeyCode::Char('k'), &rows);
+        assert_eq!(press(&mut state, KeyCode::Char('n'), &rows), Action::Redraw);
+        assert!(state.pending_kill.is_none());
+        assert_eq!(state.message.as_deref(), Some("Kept nginx (PID 42) alive."));
+
+        let key = KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE);
+        assert_eq!(
+            handle_key(&mut state, key, &rows, &kill_options(true)),
+            Action::Kill(42)
+        );
+
+        // sockets without a known process can't be killed
+        state.selected = 1;
+        assert_eq!(press(&mut state, KeyCode::Char('k'), &rows), Action::Redraw);
+        assert!(state.pending_kill.is_none());
+        assert!(state.message.is_some());
+    }
+
+    #[test]
+    fn test_describe_kill() {
+        let connection = connection_with("80", "42", "nginx");
+        let options = kill_options(true);
+        assert_eq!(
+            describe_kill(42, &connection, &options, true),
+            "Killed nginx (PID 42)."
+        );
+        assert_eq!(
+            describe_kill(42, &connection, &options, false),
+            "Failed to kill nginx (PID 42)."
+        );
+
+        let dry_run = KillOptions {
+            dry_run: true,
+            tree: true,
+            ..options
+        };
+        assert_eq!(
+            describe_kill(42, &connection, &dry_run, true),
+            "Dry-run, nothing was sent to nginx (PID 42) and its children."
+        );
+    }
+
+    #[test]
+    fn test_handle_key_quit() {
+        let mut state = State::default();
+        assert_eq!(press(&mut state, KeyCode::Char('q'), &[]), Action::Quit);
+
+        // Ctrl-C quits even while typing a filter
+        state.typing = true;
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(
+            handle_key(&mut state, key, &[], &kill_options(false)),
+            Action::Quit
+        );
+    }
+
+    #[test]
+    fn test_render_screen() {
+        let rows: Vec<Connection> = (0..20)
+            .map(|idx| connection_with(&(8000 + idx).to_string(), "1", "nginx"))
+            .collect();
+        let state = State {
+            selected: 15,
+            ..Default::default()
+        };
+        let options = RenderOptions {
+            width: 120,
+            ..Default::default()
+        };
+        let screen = render_screen(&rows, &state, &options, 16, &kill_options(false));
+
+        assert!(!screen.contains("8000"));
+        assert!(screen.contains("8015"));
+        assert!(screen.lines().count() <= 16);
+        assert!(screen.contains("20 Connections"));
+        assert!(screen.ends_with(HELP));
+    }
+
+    #[test]
+    fn test_get_status_line() {
+        let mut state = State {
+            filter: "nginx".to_string(),
+            sort: Some(SortField::LocalPort),
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_status_line(&state, 3, &kill_options(false)),
+            "3 Connections, filtered by \"nginx\", sorted by local_port (descending)"
+        );
+
+        state.pending_kill = Some((42, "nginx".to_string()));
+        assert_eq!(
+            get_status_line(&state, 3, &kill_options(false)),
+            "Send SIGTERM to nginx (PID 42)? [y/N]"
+        );
+        let tree = KillOptions {
+            tree: true,
+            ..kill_options(false)
+        };
+        assert_eq!(
+            get_status_line(&state, 3, &tree),
+            "Send SIGTERM to nginx (PID 42) and its children? [y/N]"
+        );
+
+        state.typing = true;
+        assert_eq!(
+            get_status_line(&state, 3, &kill_options(false)),
+            "Filter: nginx_"
+        );
+    }
+}
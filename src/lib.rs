@@ -0,0 +1,31 @@
+//! Collects the TCP and UDP sockets of the system together with the processes owning them.
+//!
+//! This is the library behind the `somo` binary, the interactive parts (tables, the watch mode and killing processes) only live in the binary.
+//!
+//! # Example
+//! ```no_run
+//! use somo::{get_all_connections, FilterOptions};
+//!
+//! let filter_options = FilterOptions {
+//!     by_listen: true,
+//!     ..Default::default()
+//! };
+//! for connection in get_all_connections(&filter_options)? {
+//!     println!("{} {}:{} {}", connection.proto, connection.local_address, connection.local_port, connection.program);
+//! }
+//!
+//! // without any filters all connections are returned
+//! let all_connections = get_all_connections(&FilterOptions::default())?;
+//! println!("{} connections", all_connections.len());
+//! # Ok::<(), somo::SomoError>(())
+//! ```
+
+pub mod connections;
+pub mod error;
+pub mod schemas;
+pub mod utils;
+
+pub use connections::get_all_connections;
+pub use error::SomoError;
+pub use schemas::Connection;
+pub use schemas::FilterOptions;
@@ -1,32 +1,670 @@
 mod cli;
-mod connections;
-mod schemas;
+mod completions;
+mod config;
+mod geoip;
+mod interactive;
+mod man;
+mod output;
+mod resolve;
+mod services;
+mod snapshot;
 mod table;
-mod utils;
+mod watch;
 
+use maxminddb::Reader;
 use schemas::Connection;
 use schemas::FilterOptions;
+use somo::error::SomoError;
+use somo::{connections, schemas, utils};
+use std::process::ExitCode;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use table::TableOptions;
+use watch::{WatchEnd, WatchLimit};
 
-fn main() {
+/// The exit code if no connection matched the filters and `--exit-nonzero-on-empty` was given.
+const EXIT_NO_MATCHES: u8 = 1;
+/// The exit code if at least one process couldn't be killed.
+const EXIT_KILL_FAILED: u8 = 2;
+/// The exit code if a bounded watch was cut short with Ctrl-C, following the shell convention for `SIGINT`.
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// Gets all connections matching the filter options, finds their containers and systemd units, resolves their remote hosts, looks up their countries and sorts them if requested.
+/// With `--timeout` the sockets are read on a worker thread and the remaining enrichment steps are skipped once the time is up,
+/// so that whatever was collected until then is still shown.
+///
+/// # Arguments
+/// * `filter_options`: The filter options provided by the user.
+/// * `args`: The flag values provided by the user.
+/// * `geoip_database`: The GeoIP database if the user provided one.
+///
+/// # Returns
+/// All filtered and sorted connections, exits with an error message if the connections couldn't be read.
+fn collect_connections(
+    filter_options: &FilterOptions,
+    args: &cli::Flags,
+    geoip_database: Option<&Reader<Vec<u8>>>,
+) -> Vec<Connection> {
+    let deadline = args
+        .timeout
+        .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+    let collected = match deadline {
+        Some(deadline) => {
+            let filter_options = filter_options.clone();
+            let netns = args.netns.clone();
+            let all_netns = args.all_netns;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            utils::run_with_timeout(remaining, move || {
+                read_connections(&filter_options, all_netns, netns.as_deref())
+            })
+        }
+        None => Some(read_connections(
+            filter_options,
+            args.all_netns,
+            args.netns.as_deref(),
+        )),
+    };
+    let mut all_connections: Vec<Connection> = match collected {
+        None => {
+            print_timeout_warning(args.timeout, "reading the sockets");
+            Vec::new()
+        }
+        Some(Ok(all_connections)) => all_connections,
+        Some(Err(error)) => {
+            match error {
+                // the message already ends with a question
+                SomoError::ProcUnavailable => utils::pretty_print_error(&error.to_string()),
+                _ => utils::pretty_print_error(&format!("{}.", error)),
+            }
+            if error.is_permission_denied() {
+                utils::pretty_print_info("Run somo with sudo to read the sockets.");
+            }
+            std::process::exit(1);
+        }
+    };
+
+    // once the deadline passed, the warning is printed and all remaining steps are skipped,
+    // a filter which depends on a skipped step keeps no connection
+    let mut expired = false;
+    let mut in_time = |description: &str| {
+        if !expired && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            print_timeout_warning(args.timeout, description);
+            expired = true;
+        }
+        !expired
+    };
+    if args.containers || args.container.is_some() {
+        let in_time = in_time("finding the containers");
+        run_filtering_step(
+            &mut all_connections,
+            in_time,
+            args.container.is_some(),
+            |all_connections| {
+                connections::annotate_containers(all_connections);
+                if let Some(container) = &args.container {
+                    connections::filter_by_container(all_connections, container);
+                }
+            },
+        );
+    }
+    if args.units || args.unit.is_some() {
+        let in_time = in_time("finding the systemd units");
+        run_filtering_step(
+            &mut all_connections,
+            in_time,
+            args.unit.is_some(),
+            |all_connections| {
+                connections::annotate_units(all_connections);
+                if let Some(unit) = &args.unit {
+                    connections::filter_by_unit(all_connections, unit);
+                }
+            },
+        );
+    }
+    if args.show_exposure {
+        connections::annotate_exposure(&mut all_connections);
+    }
+    connections::annotate_local(&mut all_connections);
+    if let Some(format) = args.timestamps {
+        connections::annotate_timestamps(&mut all_connections, format);
+    }
+    if let Some(local) = args.local {
+        connections::filter_by_local(&mut all_connections, local);
+    }
+    if args.unique {
+        connections::deduplicate_connections(&mut all_connections, args.count_dupes);
+    }
+    if args.stats && in_time("reading the statistics") {
+        connections::annotate_stats(&mut all_connections);
+    }
+    if args.services && in_time("looking up the services") {
+        services::annotate_services(&mut all_connections);
+    }
+    if args.resolve && in_time("resolving the remote hosts") {
+        resolve::resolve_remote_addresses(&mut all_connections);
+    }
+    if let Some(geoip_database) = geoip_database.filter(|_| in_time("looking up the countries")) {
+        geoip::annotate_countries(&mut all_connections, geoip_database);
+    }
+    if let Some(sort_field) = args.sort {
+        connections::sort_connections(&mut all_connections, sort_field, args.reverse);
+    }
+
+    all_connections
+}
+
+/// Whether the sudo hint was already shown, so that the refreshes of the watch mode don't repeat it.
+#[cfg(unix)]
+static PRIVILEGE_HINT_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Tells the user once to run somo with sudo if many connections have no known process, the hint is printed to stderr.
+///
+/// # Arguments
+/// * `connections`: The connections which were read.
+///
+/// # Returns
+/// None
+#[cfg(unix)]
+fn print_privilege_hint(connections: &[Connection]) {
+    if connections::lacks_privileges(connections)
+        && !PRIVILEGE_HINT_SHOWN.swap(true, Ordering::SeqCst)
+    {
+        utils::pretty_print_info(
+            "The processes of many connections are unknown, run 'sudo somo' to see the processes of all users.",
+        );
+    }
+}
+
+/// Reads the connections of somo's own or of other network namespaces.
+/// On Unix a hint to run somo with sudo is printed once if many connections of somo's own namespace have no known process.
+///
+/// # Arguments
+/// * `filter_options`: The filter options provided by the user.
+/// * `all_netns`: Whether the connections of all network namespaces are read.
+/// * `netns`: The network namespace to read if the user selected one.
+///
+/// # Returns
+/// The filtered connections or the error if the sockets couldn't be read.
+fn read_connections(
+    filter_options: &FilterOptions,
+    all_netns: bool,
+    netns: Option<&str>,
+) -> Result<Vec<Connection>, SomoError> {
+    if all_netns || netns.is_some() {
+        connections::get_netns_connections(filter_options, netns)
+    } else {
+        let connections = connections::get_all_connections(filter_options)?;
+        #[cfg(unix)]
+        print_privilege_hint(&connections);
+        Ok(connections)
+    }
+}
+
+/// Runs a step of the collection which a filter may depend on, e.g `--container` needs the containers of the connections.
+/// A step which is skipped because the `--timeout` was reached leaves the connections unknown, so none of them matches its filter.
+///
+/// # Arguments
+/// * `all_connections`: The connections collected so far.
+/// * `in_time`: Whether the deadline hasn't passed yet, the step is skipped if it has.
+/// * `filtered`: Whether the user filters by the result of the step.
+/// * `step`: Annotates the connections and applies the filter.
+///
+/// # Returns
+/// None
+fn run_filtering_step(
+    all_connections: &mut Vec<Connection>,
+    in_time: bool,
+    filtered: bool,
+    step: impl FnOnce(&mut Vec<Connection>),
+) {
+    if in_time {
+        step(all_connections);
+    } else if filtered {
+        all_connections.clear();
+    }
+}
+
+/// Informs the user that the collection hit the `--timeout` and the output is incomplete.
+///
+/// # Arguments
+/// * `timeout`: The timeout in seconds.
+/// * `description`: The step which was skipped or cut short, e.g "resolving the remote hosts".
+///
+/// # Returns
+/// None
+fn print_timeout_warning(timeout: Option<u64>, description: &str) {
+    utils::pretty_print_info(&format!(
+        "Timeout of {}s reached before {} finished, the output may be incomplete.",
+        timeout.unwrap_or_default(),
+        description
+    ));
+}
+
+/// Keeps only the first `limit` connections, a limit of 0 keeps all of them.
+///
+/// # Arguments
+/// * `all_connections`: The filtered and sorted connections to truncate.
+/// * `limit`: The maximum number of connections to keep.
+///
+/// # Returns
+/// The number of connections which were cut off.
+fn limit_connections(all_connections: &mut Vec<Connection>, limit: usize) -> usize {
+    if limit == 0 || all_connections.len() <= limit {
+        return 0;
+    }
+
+    let hidden = all_connections.len() - limit;
+    all_connections.truncate(limit);
+    hidden
+}
+
+/// Chooses the exit code of somo, failed kills win over an empty result.
+///
+/// # Arguments
+/// * `matched`: The number of connections which matched the filters.
+/// * `kills_succeeded`: Whether all processes which should be killed were killed.
+/// * `exit_nonzero_on_empty`: Whether no matching connection should be reported as a failure.
+///
+/// # Returns
+/// 0 on success, otherwise `EXIT_KILL_FAILED` or `EXIT_NO_MATCHES`.
+fn get_exit_code(matched: usize, kills_succeeded: bool, exit_nonzero_on_empty: bool) -> u8 {
+    if !kills_succeeded {
+        EXIT_KILL_FAILED
+    } else if exit_nonzero_on_empty && matched == 0 {
+        EXIT_NO_MATCHES
+    } else {
+        0
+    }
+}
+
+/// Chooses the exit code of a watch, reaching its limit is a success while interrupting a bounded watch isn't.
+/// An unbounded watch can only be stopped with Ctrl-C, which is why it still exits with 0.
+///
+/// # Arguments
+/// * `end`: Why the watch stopped.
+/// * `limit`: The limit of the watch.
+///
+/// # Returns
+/// 0 on success, otherwise `EXIT_INTERRUPTED` or 1 if the watch couldn't be started.
+fn get_watch_exit_code(end: WatchEnd, limit: &WatchLimit) -> u8 {
+    match end {
+        WatchEnd::LimitReached | WatchEnd::Stopped => 0,
+        WatchEnd::Interrupted if limit.is_set() => EXIT_INTERRUPTED,
+        WatchEnd::Interrupted => 0,
+        WatchEnd::Failed => 1,
+    }
+}
+
+fn main() -> ExitCode {
     let args: cli::Flags = cli::cli();
 
+    // the schema doesn't depend on the connections
+    if args.print_schema {
+        println!("{}", output::json_schema());
+        return ExitCode::SUCCESS;
+    }
+    if args.list_fields {
+        print!("{}", table::list_fields());
+        return ExitCode::SUCCESS;
+    }
+
+    // known PIDs are killed right away, without reading any socket
+    if !args.kill_pid.is_empty() {
+        let kill_options = cli::KillOptions {
+            signal: args.signal,
+            yes: true,
+            dry_run: args.dry_run,
+            force: args.force,
+            delay: args.kill_delay,
+            tree: args.tree,
+        };
+        let killed = cli::kill_pids(&args.kill_pid, &kill_options);
+        return ExitCode::from(get_exit_code(args.kill_pid.len(), killed, false));
+    }
+    if let Some(shell) = args.completions {
+        print!("{}", completions::generate(shell, cli::command()));
+        return ExitCode::SUCCESS;
+    }
+    if args.man {
+        print!("{}", man::generate(cli::command()));
+        return ExitCode::SUCCESS;
+    }
+
+    let output_file =
+        args.output
+            .as_ref()
+            .map(|path| match output::OutputFile::open(path, args.append) {
+                Ok(output_file) => output_file,
+                Err(error) => {
+                    utils::pretty_print_error(&format!("{}.", error));
+                    std::process::exit(1);
+                }
+            });
+
     let filter_options: FilterOptions = FilterOptions {
-        by_proto: args.proto,
-        by_remote_address: args.ip,
-        by_remote_port: args.remote_port,
-        by_local_port: args.port,
-        by_program: args.program,
-        by_pid: args.pid,
+        by_proto: args.proto.clone(),
+        by_remote_address: args.ip.clone(),
+        by_local_address: args.local_ip.clone(),
+        by_remote_port: args.remote_port.clone(),
+        by_local_port: args.port.clone(),
+        by_program: args.program.clone(),
+        match_path: args.match_path,
+        by_pid: args.pid.clone(),
+        by_ppid: args.ppid,
+        by_user: args
+            .user
+            .as_deref()
+            .map(|user| connections::resolve_user_filter(user, args.numeric)),
+        by_env: args.env.clone(),
+        by_state: args.state.clone(),
         by_open: args.open,
         by_listen: args.listen,
-        exclude_ipv6: args.exclude_ipv6,
+        by_include: args.include.clone(),
+        by_unresolved: args.unresolved_only,
+        by_privileged: args.privileged,
+        by_since: args.since,
+        include_unknown_age: args.include_unknown_age,
+        raw_addresses: args.raw_addresses,
+        numeric_users: args.numeric,
+        by_family: args.family,
+        by_exposure: args.exposure,
+        exclude_program: args.exclude_program.clone(),
+        exclude_self: args.exclude_self,
+        exclude_remote_address: args.exclude_ip.clone(),
+        exclude_local_port: args.exclude_port.clone(),
+    };
+
+    let contradictions = connections::find_contradictions(&filter_options);
+    for contradiction in &contradictions {
+        if args.strict {
+            utils::pretty_print_error(&format!("{}.", contradiction));
+        } else {
+            utils::pretty_print_info(&format!("{}, no connection can match.", contradiction));
+        }
+    }
+    if args.strict && !contradictions.is_empty() {
+        std::process::exit(1);
+    }
+
+    let geoip_database = args
+        .geoip
+        .as_ref()
+        .map(|path| match geoip::open_database(path) {
+            Ok(reader) => reader,
+            Err(error) => {
+                utils::pretty_print_error(&error);
+                std::process::exit(1);
+            }
+        });
+
+    let table_options = TableOptions {
+        show_command: args.long,
+        show_exe: args.show_exe,
+        show_country: geoip_database.is_some(),
+        show_container: args.containers || args.container.is_some(),
+        show_unit: args.units || args.unit.is_some(),
+        show_exposure: args.show_exposure,
+        show_netns: args.all_netns || args.netns.is_some(),
+        show_inode: args.show_inode,
+        show_stats: args.stats,
+        show_queues: args.queues,
+        show_age: args.show_age,
+        show_duplicates: args.count_dupes,
+        show_diff: args.diff.is_some(),
+        highlight_suspicious: args.highlight_suspicious,
+        highlight: args.highlight.clone(),
+        columns: args.columns.clone(),
+        full: args.full,
+        width: args.width,
+        theme: args.theme,
+        show_summary: !args.no_summary,
+        host: args
+            .show_host
+            .then(|| output::HostInfo::current(args.hostname.clone()).host),
     };
 
-    let all_connections: Vec<Connection> = connections::get_all_connections(&filter_options);
+    let watch_limit = WatchLimit {
+        iterations: args.iterations,
+        duration: args.duration.map(Duration::from_secs),
+    };
+
+    if let (Some(interval), true) = (args.watch, args.ndjson) {
+        let end = watch::stream_connections(Duration::from_secs(interval), watch_limit, || {
+            let mut all_connections =
+                collect_connections(&filter_options, &args, geoip_database.as_ref());
+            limit_connections(&mut all_connections, args.limit);
+            all_connections
+        });
+        return ExitCode::from(get_watch_exit_code(end, &watch_limit));
+    }
+
+    if let Some(interval) = args.watch {
+        let end = watch::watch_connections(
+            Duration::from_secs(interval),
+            watch_limit,
+            &table_options,
+            output_file.as_ref(),
+            || {
+                let mut all_connections =
+                    collect_connections(&filter_options, &args, geoip_database.as_ref());
+                limit_connections(&mut all_connections, args.limit);
+                all_connections
+            },
+        );
+        return ExitCode::from(get_watch_exit_code(end, &watch_limit));
+    }
+
+    let kill_options = cli::KillOptions {
+        signal: args.signal,
+        yes: args.yes,
+        dry_run: args.dry_run,
+        force: args.force,
+        delay: args.kill_delay,
+        tree: args.tree,
+    };
+
+    if args.interactive {
+        let ran = interactive::interactive_connections(
+            &table_options,
+            &kill_options,
+            (args.sort, args.reverse),
+            || {
+                let mut all_connections =
+                    collect_connections(&filter_options, &args, geoip_database.as_ref());
+                limit_connections(&mut all_connections, args.limit);
+                all_connections
+            },
+        );
+        return if ran {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    let mut all_connections: Vec<Connection> =
+        collect_connections(&filter_options, &args, geoip_database.as_ref());
+
+    if let Some(path) = &args.diff {
+        match snapshot::load_snapshot(path) {
+            Ok(snapshot) => all_connections = snapshot::diff_snapshot(all_connections, snapshot),
+            Err(error) => {
+                utils::pretty_print_error(&format!("{}.", error));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if args.top_remotes {
+        // the limit applies to the remote addresses instead of the connections
+        let mut groups = connections::group_remote_addresses(&all_connections);
+        if args.limit > 0 {
+            groups.truncate(args.limit);
+        }
+        table::print_top_remotes_table(&groups);
+        return ExitCode::from(get_exit_code(
+            groups.len(),
+            true,
+            args.exit_nonzero_on_empty,
+        ));
+    }
 
-    table::print_connections_table(&all_connections);
+    let hidden_connections = limit_connections(&mut all_connections, args.limit);
+
+    if args.count {
+        match args.group_by {
+            Some(group_field) => print!(
+                "{}",
+                output::groups_to_counts(&connections::group_connections(
+                    &all_connections,
+                    group_field
+                ))
+            ),
+            None => println!("{}", all_connections.len()),
+        }
+        return ExitCode::from(get_exit_code(
+            all_connections.len(),
+            true,
+            args.exit_nonzero_on_empty,
+        ));
+    }
+
+    let host = output::HostInfo::current(args.hostname.clone());
+    if args.json {
+        println!("{}", output::connections_to_json(&all_connections, &host));
+    } else if args.yaml {
+        print!("{}", output::connections_to_yaml(&all_connections, &host));
+    } else if args.csv {
+        print!("{}", output::connections_to_csv(&all_connections));
+    } else if args.markdown {
+        print!(
+            "{}",
+            output::connections_to_markdown(&all_connections, &table_options)
+        );
+    } else if args.ndjson {
+        // a consumer which exits early, e.g "head", isn't an error
+        let mut stdout = std::io::stdout().lock();
+        let _ = output::write_ndjson_schema(&mut stdout)
+            .and_then(|_| output::write_ndjson(&mut stdout, None, &all_connections));
+    } else if args.prometheus {
+        print!("{}", output::connections_to_prometheus(&all_connections));
+    } else if args.oneline {
+        print!("{}", output::connections_to_oneline(&all_connections));
+    } else if let Some(group_field) = args.group_by {
+        let groups = connections::group_connections(&all_connections, group_field);
+        table::print_grouped_table(&groups, group_field);
+    } else if let Some(port) = args.holding {
+        table::print_port_holders(port, &all_connections, !args.kill);
+        if hidden_connections > 0 {
+            utils::pretty_print_info(&format!("… and {} more", hidden_connections));
+        }
+    } else if args.diff.is_some() {
+        table::print_diff_table(&all_connections, &table_options);
+        if hidden_connections > 0 {
+            utils::pretty_print_info(&format!("… and {} more", hidden_connections));
+        }
+    } else {
+        table::print_connections_table(&all_connections, &table_options);
+        if hidden_connections > 0 {
+            utils::pretty_print_info(&format!("… and {} more", hidden_connections));
+        }
+        if args.unresolved_only && !all_connections.is_empty() {
+            utils::pretty_print_info(
+                "The owning process of these sockets is unknown, running somo as root may resolve more of them.",
+            );
+        }
+    }
+
+    let kills_succeeded = if args.kill && args.all {
+        cli::kill_all_processes(&all_connections, &kill_options)
+    } else if args.kill {
+        cli::interactve_process_kill(&all_connections, &kill_options)
+    } else {
+        true
+    };
+
+    ExitCode::from(get_exit_code(
+        all_connections.len(),
+        kills_succeeded,
+        args.exit_nonzero_on_empty,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_filtering_step() {
+        let keep_first = |connections: &mut Vec<Connection>| connections.truncate(1);
+
+        let mut connections = test_connections(3);
+        run_filtering_step(&mut connections, true, true, keep_first);
+        assert_eq!(connections.len(), 1);
+
+        // after the timeout no connection is known to match the filter
+        let mut connections = test_connections(3);
+        run_filtering_step(&mut connections, false, true, keep_first);
+        assert!(connections.is_empty());
+
+        // without a filter the connections are only left unannotated
+        let mut connections = test_connections(3);
+        run_filtering_step(&mut connections, false, false, keep_first);
+        assert_eq!(connections.len(), 3);
+    }
+
+    fn test_connections(count: usize) -> Vec<Connection> {
+        (0..count)
+            .map(|idx| Connection {
+                local_port: idx.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_limit_connections() {
+        let mut connections = test_connections(5);
+        assert_eq!(limit_connections(&mut connections, 2), 3);
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[1].local_port, "1");
+    }
+
+    #[test]
+    fn test_get_exit_code() {
+        assert_eq!(get_exit_code(3, true, false), 0);
+        assert_eq!(get_exit_code(0, true, false), 0);
+        assert_eq!(get_exit_code(0, true, true), EXIT_NO_MATCHES);
+        assert_eq!(get_exit_code(3, true, true), 0);
+        assert_eq!(get_exit_code(3, false, false), EXIT_KILL_FAILED);
+        assert_eq!(get_exit_code(0, false, true), EXIT_KILL_FAILED);
+    }
+
+    #[test]
+    fn test_get_watch_exit_code() {
+        let bounded = WatchLimit {
+            iterations: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(get_watch_exit_code(WatchEnd::LimitReached, &bounded), 0);
+        assert_eq!(
+            get_watch_exit_code(WatchEnd::Interrupted, &bounded),
+            EXIT_INTERRUPTED
+        );
+        assert_eq!(
+            get_watch_exit_code(WatchEnd::Interrupted, &WatchLimit::default()),
+            0
+        );
+        assert_eq!(get_watch_exit_code(WatchEnd::Stopped, &bounded), 0);
+        assert_eq!(get_watch_exit_code(WatchEnd::Failed, &bounded), 1);
+    }
 
-    if args.kill {
-        cli::interactve_process_kill(&all_connections);
+    #[test]
+    fn test_limit_connections_no_limit() {
+        let mut connections = test_connections(5);
+        assert_eq!(limit_connections(&mut connections, 0), 0);
+        assert_eq!(limit_connections(&mut connections, 10), 0);
+        assert_eq!(connections.len(), 5);
     }
 }
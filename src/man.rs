@@ -0,0 +1,38 @@
+use clap::Command;
+
+/// Generates the man page of the CLI from its clap command using the "clap_mangen" crate, so that it lists every flag without extra work.
+///
+/// # Arguments
+/// * `command`: The clap command of the CLI.
+///
+/// # Returns
+/// The man page in roff format, e.g to be read with `man -l -`.
+pub fn generate(command: Command) -> String {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(command)
+        .render(&mut page)
+        .expect("writing to a vector can't fail");
+    String::from_utf8_lossy(&page).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli;
+
+    #[test]
+    fn test_generate() {
+        let page = generate(cli::command());
+        for section in [".SH NAME", ".SH SYNOPSIS", ".SH DESCRIPTION", ".SH OPTIONS"] {
+            assert!(page.contains(section), "{section} is missing");
+        }
+        for flag in ["kill", "proto", "state", "port", "watch", "json"] {
+            assert!(
+                page.contains(&format!("\\-\\-{flag}")),
+                "--{flag} is missing"
+            );
+        }
+        // hidden flags aren't documented
+        assert!(!page.contains("\\-\\-man"));
+    }
+}
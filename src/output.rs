@@ -0,0 +1,936 @@
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::schemas::{Connection, ConnectionGroup};
+use crate::table::{self, TableOptions};
+
+/// The version of the fields of the `--json`, `--yaml` and `--ndjson` outputs.
+/// It is only bumped when the meaning of a field changes or a field is removed, new optional fields keep the version.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Where and when the connections were read, so that the documents of many hosts can be aggregated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostInfo {
+    /// The hostname of the kernel or the name set with `--hostname`.
+    pub host: String,
+    /// The time the connections were read as a Unix timestamp in seconds.
+    pub collected_at: u64,
+}
+
+impl HostInfo {
+    /// Describes the current host at the current time.
+    ///
+    /// # Arguments
+    /// * `hostname`: The name set with `--hostname`, `None` to use the hostname of the kernel.
+    ///
+    /// # Returns
+    /// The host, named "unknown" if its hostname can't be read.
+    pub fn current(hostname: Option<String>) -> Self {
+        HostInfo {
+            host: hostname
+                .or_else(|| dns_lookup::get_hostname().ok())
+                .unwrap_or_else(|| "unknown".to_string()),
+            collected_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The document printed by `--json` and `--yaml`, the connections together with the version of their fields and their host.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(title = "somo --json")]
+struct Document<'a> {
+    /// The version of the fields, see `SCHEMA_VERSION`.
+    #[schemars(extend("const" = SCHEMA_VERSION))]
+    schema_version: u64,
+    /// The hostname of the kernel or the name set with `--hostname`.
+    host: &'a str,
+    /// The time the connections were read as a Unix timestamp in seconds.
+    collected_at: u64,
+    connections: &'a [Connection],
+}
+
+impl<'a> Document<'a> {
+    /// Wraps the connections into a document of the current schema version.
+    ///
+    /// # Arguments
+    /// * `connections`: The connections of the document.
+    /// * `host`: Where and when the connections were read.
+    ///
+    /// # Returns
+    /// The document.
+    fn new(connections: &'a [Connection], host: &'a HostInfo) -> Self {
+        Document {
+            schema_version: SCHEMA_VERSION,
+            host: &host.host,
+            collected_at: host.collected_at,
+            connections,
+        }
+    }
+}
+
+/// Describes the `--json` output as a JSON Schema, so that consumers can validate it.
+/// The schema is derived from `Document` and `Connection` using the "schemars" crate, fields which are only filled by a flag aren't required.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The pretty-printed JSON Schema (draft 2020-12) of the `--json` document.
+pub fn json_schema() -> String {
+    let schema = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<Document>();
+    serde_json::to_string_pretty(&schema).expect("the schema is always serializable")
+}
+
+/// Serializes all connections into a JSON object using the "serde_json" crate.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `host`: Where and when the connections were read.
+///
+/// # Example
+/// ```
+/// let host = HostInfo { host: "web-1".to_string(), collected_at: 1700000000 };
+/// let json = connections_to_json(&[], &host);
+/// assert_eq!(json, "{\n  \"schema_version\": 1,\n  \"host\": \"web-1\",\n  \"collected_at\": 1700000000,\n  \"connections\": []\n}");
+/// ```
+///
+/// # Returns
+/// A pretty-printed JSON object with the `schema_version`, the `host`, the `collected_at` time and the `connections` array in which every connection is an object.
+pub fn connections_to_json(all_connections: &[Connection], host: &HostInfo) -> String {
+    serde_json::to_string_pretty(&Document::new(all_connections, host))
+        .expect("connections are always serializable")
+}
+
+/// The first line of `--ndjson`, so that consumers can check the version of the fields before reading any connection.
+#[derive(Debug, Serialize)]
+struct NdjsonSchema {
+    /// Always "schema", connections never have a `type` field.
+    #[serde(rename = "type")]
+    kind: &'static str,
+    schema_version: u64,
+}
+
+/// Writes the line with the schema version which starts every `--ndjson` output.
+///
+/// # Arguments
+/// * `writer`: Where to write the line, e.g stdout.
+///
+/// # Returns
+/// The error if the writer failed, e.g because the consumer of a pipe exited.
+pub fn write_ndjson_schema(writer: &mut impl Write) -> io::Result<()> {
+    let schema = NdjsonSchema {
+        kind: "schema",
+        schema_version: SCHEMA_VERSION,
+    };
+    write_ndjson_line(writer, &schema)
+}
+
+/// The first line of every refresh in `--watch --ndjson`, so that consumers can tell the refreshes apart.
+#[derive(Debug, Serialize)]
+pub struct NdjsonEnvelope {
+    /// Always "refresh", connections never have a `type` field.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// The time of the refresh as a Unix timestamp in seconds.
+    pub timestamp: u64,
+    /// The number of connection lines following the envelope.
+    pub connections: usize,
+}
+
+impl NdjsonEnvelope {
+    /// Creates the envelope of a refresh.
+    ///
+    /// # Arguments
+    /// * `timestamp`: The time of the refresh as a Unix timestamp in seconds.
+    /// * `connections`: The number of connections of the refresh.
+    ///
+    /// # Returns
+    /// The envelope.
+    pub fn new(timestamp: u64, connections: usize) -> Self {
+        NdjsonEnvelope {
+            kind: "refresh",
+            timestamp,
+            connections,
+        }
+    }
+}
+
+/// Writes all connections as newline-delimited JSON, ie. one compact JSON object per line without an array around them.
+/// The writer is flushed after every line, so that a consumer receives every connection as soon as it is written.
+///
+/// # Arguments
+/// * `writer`: Where to write the lines to, e.g stdout.
+/// * `envelope`: An optional object written before the connections, used by the watch mode.
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+///
+/// # Returns
+/// The error if the writer failed, e.g because the consumer of a pipe exited.
+pub fn write_ndjson(
+    writer: &mut impl Write,
+    envelope: Option<&NdjsonEnvelope>,
+    all_connections: &[Connection],
+) -> io::Result<()> {
+    if let Some(envelope) = envelope {
+        write_ndjson_line(writer, envelope)?;
+    }
+    for connection in all_connections {
+        write_ndjson_line(writer, connection)?;
+    }
+    Ok(())
+}
+
+/// Writes one value as a compact JSON object followed by a line break and flushes the writer.
+fn write_ndjson_line(writer: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// Serializes all connections into a YAML mapping using the "serde_yaml" crate.
+/// The structure and the field names are the same as in the JSON output.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `host`: Where and when the connections were read.
+///
+/// # Example
+/// ```
+/// let host = HostInfo { host: "web-1".to_string(), collected_at: 1700000000 };
+/// let yaml = connections_to_yaml(&[], &host);
+/// assert_eq!(yaml, "schema_version: 1\nhost: web-1\ncollected_at: 1700000000\nconnections: []\n");
+/// ```
+///
+/// # Returns
+/// A YAML string with the `schema_version`, the `host`, the `collected_at` time and the `connections` sequence in which every connection is a mapping, ending with a line break.
+pub fn connections_to_yaml(all_connections: &[Connection], host: &HostInfo) -> String {
+    serde_yaml::to_string(&Document::new(all_connections, host))
+        .expect("connections are always serializable")
+}
+
+/// Escapes a Markdown table cell, pipes would end the cell and line breaks the row.
+///
+/// # Arguments
+/// * `cell`: The raw cell value.
+///
+/// # Example
+/// ```
+/// assert_eq!(escape_markdown_cell("sh -c a|b"), "sh -c a\\|b");
+/// ```
+///
+/// # Returns
+/// The cell ready to be written into a Markdown table row.
+fn escape_markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Renders all connections as a GitHub flavored Markdown table, e.g to paste them into a ticket.
+/// The columns are the same as in the normal table.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `options`: Which optional columns to add.
+///
+/// # Returns
+/// The Markdown table with a header and a separator row, every line ends with a line break.
+pub fn connections_to_markdown(all_connections: &[Connection], options: &TableOptions) -> String {
+    let (headers, rows) = table::plain_table(all_connections, options);
+    let to_row = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .map(|cell| escape_markdown_cell(cell))
+            .collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut markdown = to_row(headers.clone());
+    markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+    for row in rows {
+        markdown.push_str(&to_row(row));
+    }
+    markdown
+}
+
+/// Quotes a CSV field if it contains a delimiter, a quote or a line break.
+/// Quotes inside of a quoted field are escaped by doubling them.
+///
+/// # Arguments
+/// * `field`: The raw field value.
+///
+/// # Example
+/// ```
+/// assert_eq!(escape_csv_field("nginx"), "nginx");
+/// assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+/// ```
+///
+/// # Returns
+/// The field ready to be written into a CSV row.
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Serializes all connections into CSV with one header row followed by one row per connection.
+/// The columns are named like the ones of `--columns`: the protocol, the endpoints, the PID, the program and the state, plus the address family which the table only shows as part of the protocol.
+/// An unknown PID is written as an empty field.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+///
+/// # Returns
+/// A CSV string in which every line (including the last) ends with a line break.
+pub fn connections_to_csv(all_connections: &[Connection]) -> String {
+    let mut csv = String::from(
+        "proto,family,local_address,local_port,remote_address,remote_port,pid,program,state\n",
+    );
+
+    for connection in all_connections {
+        let pid = if connection.pid == "-" {
+            ""
+        } else {
+            &connection.pid
+        };
+
+        let fields = [
+            connection.proto.as_str(),
+            connection.family.as_str(),
+            connection.local_address.as_str(),
+            connection.local_port.as_str(),
+            connection.remote_address.as_str(),
+            connection.remote_port.as_str(),
+            pid,
+            connection.program.as_str(),
+            connection.state.as_str(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| escape_csv_field(field)).collect();
+
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Makes a field safe for the one-line output: whitespace is replaced with underscores, so that every field stays one word for awk, and empty fields become `-`.
+///
+/// # Arguments
+/// * `field`: The raw field value.
+///
+/// # Example
+/// ```
+/// assert_eq!(oneline_field("Web Content"), "Web_Content");
+/// assert_eq!(oneline_field(""), "-");
+/// ```
+///
+/// # Returns
+/// The field ready to be written into a line.
+fn oneline_field(field: &str) -> String {
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field.replace(char::is_whitespace, "_")
+    }
+}
+
+/// Renders every connection as one line of space-delimited fields, e.g `tcp 0.0.0.0:22 0.0.0.0:0 LISTEN sshd/1234`.
+/// The fields are always proto, local address and port, remote address and port, state and program/PID in this order, so that e.g `awk '{print $4}'` prints the states.
+/// The program/PID field is `-` if the process is unknown, there is no header.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+///
+/// # Returns
+/// The lines, every line (including the last) ends with a line break.
+pub fn connections_to_oneline(all_connections: &[Connection]) -> String {
+    let mut lines = String::new();
+    for connection in all_connections {
+        let process = if connection.pid == "-" {
+            "-".to_string()
+        } else {
+            format!("{}/{}", connection.program, connection.pid)
+        };
+        let fields = [
+            connection.proto.clone(),
+            format!("{}:{}", connection.local_address, connection.local_port),
+            format!("{}:{}", connection.remote_address, connection.remote_port),
+            connection.state.to_uppercase(),
+            process,
+        ];
+        let line: Vec<String> = fields.iter().map(|field| oneline_field(field)).collect();
+
+        lines.push_str(&line.join(" "));
+        lines.push('\n');
+    }
+    lines
+}
+
+/// Escapes a label value according to the Prometheus text exposition format.
+/// Backslashes, double quotes and line breaks have to be escaped.
+///
+/// # Arguments
+/// * `value`: The raw label value.
+///
+/// # Example
+/// ```
+/// assert_eq!(escape_prometheus_label("say \"hi\""), "say \\\"hi\\\"");
+/// ```
+///
+/// # Returns
+/// The label value ready to be written between double quotes.
+fn escape_prometheus_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Aggregates all connections into Prometheus metrics in the text exposition format, e.g for the textfile collector of the node exporter.
+/// Connections are counted per protocol and state, combinations without any connection are omitted.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+///
+/// # Returns
+/// The metrics including their HELP and TYPE lines, every line ends with a line break.
+pub fn connections_to_prometheus(all_connections: &[Connection]) -> String {
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for connection in all_connections {
+        *counts
+            .entry((connection.proto.as_str(), connection.state.as_str()))
+            .or_insert(0) += 1;
+    }
+
+    let mut metrics = String::from(
+        "# HELP somo_connections_total Number of open connections by protocol and state.\n\
+         # TYPE somo_connections_total gauge\n",
+    );
+    for ((proto, state), count) in counts {
+        metrics.push_str(&format!(
+            "somo_connections_total{{proto=\"{}\",state=\"{}\"}} {}\n",
+            escape_prometheus_label(proto),
+            escape_prometheus_label(state),
+            count
+        ));
+    }
+
+    metrics
+}
+
+/// Formats the number of connections per group for `--count`, one group per line.
+///
+/// # Arguments
+/// * `groups`: The groups created by `group_connections`.
+///
+/// # Example
+/// ```
+/// let groups = [ConnectionGroup { key: "nginx".to_string(), count: 3, ..Default::default() }];
+/// assert_eq!(groups_to_counts(&groups), "3 nginx\n");
+/// ```
+///
+/// # Returns
+/// The count followed by the key of every group, every line ends with a line break.
+pub fn groups_to_counts(groups: &[ConnectionGroup]) -> String {
+    groups
+        .iter()
+        .map(|group| format!("{} {}\n", group.count, group.key))
+        .collect()
+}
+
+/// The file set with `--output`. Stdout is redirected into it, so that every format and the watch mode write into the file.
+#[derive(Debug)]
+pub struct OutputFile {
+    file: File,
+    /// Whether the refreshes of `--watch` are appended with `--append` instead of replacing the previous one.
+    append: bool,
+}
+
+impl OutputFile {
+    /// Creates the file, or truncates it unless `append` is set, and redirects stdout into it.
+    ///
+    /// # Arguments
+    /// * `path`: The path provided with `--output`.
+    /// * `append`: Whether to keep the current content of the file.
+    ///
+    /// # Returns
+    /// The opened file or the error message if it couldn't be opened.
+    pub fn open(path: &Path, append: bool) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(|error| format!("Couldn't open output file {}: {}", path.display(), error))?;
+        redirect_stdout(&file).map_err(|error| {
+            format!(
+                "Couldn't write to output file {}: {}",
+                path.display(),
+                error
+            )
+        })?;
+        Ok(OutputFile { file, append })
+    }
+
+    /// Empties the file before a refresh of `--watch`, so that it only contains the latest table.
+    /// With `--append` every refresh is kept.
+    ///
+    /// # Returns
+    /// An error if the file couldn't be truncated.
+    pub fn start_refresh(&self) -> io::Result<()> {
+        if self.append {
+            return Ok(());
+        }
+        io::stdout().flush()?;
+        self.file.set_len(0)?;
+        // stdout shares the offset of the file since it was redirected
+        (&self.file).seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+}
+
+/// Redirects stdout into a file, the file descriptor of stdout then refers to the file.
+///
+/// # Arguments
+/// * `file`: The file to write into.
+///
+/// # Returns
+/// An error if stdout couldn't be redirected.
+#[cfg(unix)]
+fn redirect_stdout(file: &File) -> io::Result<()> {
+    nix::unistd::dup2_stdout(file).map_err(io::Error::from)
+}
+
+/// Stdout can't be redirected on Windows, the output has to be redirected by the shell instead.
+///
+/// # Arguments
+/// * `_file`: The file which stays unused.
+///
+/// # Returns
+/// An unsupported error.
+#[cfg(not(unix))]
+fn redirect_stdout(_file: &File) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--output is only supported on Unix, redirect the output with the shell instead",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::AddressType;
+    use std::collections::BTreeSet;
+
+    fn test_connection() -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            family: "inet".to_string(),
+            local_address: "127.0.0.1".to_string(),
+            local_port: "8080".to_string(),
+            remote_address: "8.8.8.8".to_string(),
+            remote_port: "443".to_string(),
+            program: "nginx".to_string(),
+            pid: "123".to_string(),
+            state: "established".to_string(),
+            address_type: AddressType::Extern,
+            ..Default::default()
+        }
+    }
+
+    fn test_host() -> HostInfo {
+        HostInfo {
+            host: "web-1".to_string(),
+            collected_at: 1700000000,
+        }
+    }
+
+    #[test]
+    fn test_connections_to_json_empty() {
+        let parsed: serde_json::Value =
+            serde_json::from_str(&connections_to_json(&[], &test_host())).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({
+                "schema_version": SCHEMA_VERSION,
+                "host": "web-1",
+                "collected_at": 1700000000,
+                "connections": []
+            })
+        );
+    }
+
+    #[test]
+    fn test_host_info_current() {
+        let host = HostInfo::current(Some("container-7".to_string()));
+        assert_eq!(host.host, "container-7");
+        assert!(host.collected_at > 1700000000);
+
+        let host = HostInfo::current(None);
+        assert!(!host.host.is_empty());
+    }
+
+    #[test]
+    fn test_open_output_file_missing_directory() {
+        let error = OutputFile::open(Path::new("/nonexistent/somo.txt"), false).unwrap_err();
+        assert!(error.contains("Couldn't open output file /nonexistent/somo.txt"));
+    }
+
+    #[test]
+    fn test_output_file_start_refresh() {
+        let path = std::env::temp_dir().join(format!("somo-output-{}.txt", std::process::id()));
+        // stdout isn't redirected here, so the content is written directly
+        let output_file = |append: bool| {
+            let mut file = File::create(&path).unwrap();
+            file.write_all(b"previous refresh\n").unwrap();
+            OutputFile { file, append }
+        };
+
+        output_file(false).start_refresh().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        output_file(true).start_refresh().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "previous refresh\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_json_schema_matches_connection() {
+        let schema: serde_json::Value = serde_json::from_str(&json_schema()).unwrap();
+        let definition = &schema["$defs"]["Connection"];
+        let keys = |value: &serde_json::Value| -> BTreeSet<String> {
+            value.as_object().unwrap().keys().cloned().collect()
+        };
+
+        // every field of a fully populated connection is described
+        let populated = Connection {
+            service: Some("http".to_string()),
+            remote_service: Some("https".to_string()),
+            remote_hostname: Some("dns.google".to_string()),
+            country: Some("US".to_string()),
+            container: Some("web".to_string()),
+            unit: Some("nginx.service".to_string()),
+            exposure: Some(crate::schemas::Exposure::Public),
+            netns: Some("vpn".to_string()),
+            rx_bytes: Some(1),
+            tx_bytes: Some(2),
+            rx_queue: Some(0),
+            tx_queue: Some(0),
+            retransmits: Some(0),
+            created: Some(3),
+            created_formatted: Some("3s".to_string()),
+            user: Some("www".to_string()),
+            command: Some("nginx -g".to_string()),
+            exe_path: Some("/usr/sbin/nginx".to_string()),
+            exe_deleted: true,
+            duplicates: Some(2),
+            diff: Some(crate::schemas::DiffStatus::Added),
+            ..test_connection()
+        };
+        assert_eq!(
+            keys(&definition["properties"]),
+            keys(&serde_json::to_value(&populated).unwrap())
+        );
+
+        // the fields of a connection without any flag are the required ones
+        let required: BTreeSet<String> = definition["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|name| name.as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            required,
+            keys(&serde_json::to_value(test_connection()).unwrap())
+        );
+        assert_eq!(
+            definition["properties"]["user"]["type"],
+            serde_json::json!(["string", "null"])
+        );
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+        assert_eq!(
+            schema["$defs"]["Exposure"]["enum"],
+            serde_json::json!(["loopback", "private", "public", "all-interfaces"])
+        );
+
+        // the document itself has the same fields as the schema
+        let document = serde_json::to_value(Document::new(&[], &test_host())).unwrap();
+        assert_eq!(keys(&schema["properties"]), keys(&document));
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let connections = [test_connection(), test_connection()];
+        let mut output: Vec<u8> = Vec::new();
+        write_ndjson(&mut output, None, &connections).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with('\n'));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            let parsed: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed["program"], "nginx");
+        }
+    }
+
+    #[test]
+    fn test_write_ndjson_schema() {
+        let mut output: Vec<u8> = Vec::new();
+        write_ndjson_schema(&mut output).unwrap();
+        write_ndjson(&mut output, None, &[test_connection()]).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            first,
+            serde_json::json!({"type": "schema", "schema_version": SCHEMA_VERSION})
+        );
+        assert_eq!(output.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_write_ndjson_envelope() {
+        let mut output: Vec<u8> = Vec::new();
+        let envelope = NdjsonEnvelope::new(1_700_000_000, 1);
+        write_ndjson(&mut output, Some(&envelope), &[test_connection()]).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines[0],
+            serde_json::json!({"type": "refresh", "timestamp": 1_700_000_000, "connections": 1})
+        );
+        assert_eq!(lines[1]["local_port"], "8080");
+
+        // an empty refresh only consists of the envelope
+        let mut output: Vec<u8> = Vec::new();
+        write_ndjson(&mut output, Some(&NdjsonEnvelope::new(1, 0)), &[]).unwrap();
+        assert_eq!(output.iter().filter(|byte| **byte == b'\n').count(), 1);
+    }
+
+    #[test]
+    fn test_connections_to_json_field_names() {
+        let json = connections_to_json(&[test_connection()], &test_host());
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["schema_version"], SCHEMA_VERSION);
+        let object = parsed["connections"][0].as_object().unwrap();
+        let keys: Vec<&str> = object.keys().map(|key| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "command",
+                "family",
+                "inode",
+                "is_local",
+                "local_address",
+                "local_port",
+                "pid",
+                "program",
+                "proto",
+                "remote_address",
+                "remote_port",
+                "state",
+                "user"
+            ]
+        );
+        assert_eq!(object["program"], "nginx");
+        assert_eq!(object["remote_port"], "443");
+    }
+
+    #[test]
+    fn test_connections_to_yaml_empty() {
+        assert_eq!(
+            connections_to_yaml(&[], &test_host()),
+            "schema_version: 1\nhost: web-1\ncollected_at: 1700000000\nconnections: []\n"
+        );
+    }
+
+    #[test]
+    fn test_connections_to_yaml_matches_json() {
+        let connections = [test_connection()];
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&connections_to_yaml(&connections, &test_host())).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&connections_to_json(&connections, &test_host())).unwrap();
+        assert_eq!(yaml, json);
+    }
+
+    #[test]
+    fn test_escape_markdown_cell() {
+        assert_eq!(escape_markdown_cell("nginx"), "nginx");
+        assert_eq!(escape_markdown_cell("sh -c a|b"), "sh -c a\\|b");
+        assert_eq!(escape_markdown_cell("a\nb"), "a b");
+    }
+
+    #[test]
+    fn test_connections_to_markdown() {
+        let connection = Connection {
+            command: Some("sh -c 'cat | nc'".to_string()),
+            ..test_connection()
+        };
+        let options = TableOptions {
+            show_command: true,
+            ..Default::default()
+        };
+        let markdown = connections_to_markdown(&[connection], &options);
+        let lines: Vec<&str> = markdown.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("| # | proto | local port | remote address |"));
+        assert!(lines[1].starts_with("| --- | --- |"));
+        assert!(lines[2].contains("| 123 nginx |"));
+        assert!(lines[2].ends_with("| sh -c 'cat \\| nc' |"));
+    }
+
+    #[test]
+    fn test_connections_to_markdown_empty() {
+        let markdown = connections_to_markdown(&[], &TableOptions::default());
+        assert_eq!(markdown.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_groups_to_counts() {
+        let group = |key: &str, count: usize| ConnectionGroup {
+            key: key.to_string(),
+            count,
+            ..Default::default()
+        };
+        assert_eq!(
+            groups_to_counts(&[group("established", 12), group("listen", 3)]),
+            "12 established\n3 listen\n"
+        );
+        assert_eq!(groups_to_counts(&[]), "");
+    }
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("nginx"), "nginx");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("[fe80::1]"), "[fe80::1]");
+    }
+
+    #[test]
+    fn test_connections_to_csv() {
+        let mut unknown_pid = test_connection();
+        unknown_pid.pid = "-".to_string();
+        unknown_pid.program = "my,program".to_string();
+        unknown_pid.local_address = "[::1]".to_string();
+        unknown_pid.remote_address = "[::1]".to_string();
+        unknown_pid.family = "inet6".to_string();
+
+        let csv = connections_to_csv(&[test_connection(), unknown_pid]);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "proto,family,local_address,local_port,remote_address,remote_port,pid,program,state",
+                "tcp,inet,127.0.0.1,8080,8.8.8.8,443,123,nginx,established",
+                "tcp,inet6,[::1],8080,[::1],443,,\"my,program\",established",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_connections_to_csv_empty() {
+        assert_eq!(
+            connections_to_csv(&[]),
+            "proto,family,local_address,local_port,remote_address,remote_port,pid,program,state\n"
+        );
+    }
+
+    #[test]
+    fn test_connections_to_oneline() {
+        let mut listening = test_connection();
+        listening.local_address = "0.0.0.0".to_string();
+        listening.local_port = "22".to_string();
+        listening.remote_address = "0.0.0.0".to_string();
+        listening.remote_port = "0".to_string();
+        listening.state = "listen".to_string();
+        listening.program = "sshd".to_string();
+        listening.pid = "1234".to_string();
+        let mut unknown = test_connection();
+        unknown.remote_address = "[::1]".to_string();
+        unknown.pid = "-".to_string();
+        unknown.program = "-".to_string();
+        let mut spaced = test_connection();
+        spaced.program = "Web Content".to_string();
+        spaced.state = String::new();
+
+        let oneline = connections_to_oneline(&[listening, unknown, spaced]);
+        let lines: Vec<&str> = oneline.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "tcp 0.0.0.0:22 0.0.0.0:0 LISTEN sshd/1234",
+                "tcp 127.0.0.1:8080 [::1]:443 ESTABLISHED -",
+                "tcp 127.0.0.1:8080 8.8.8.8:443 - Web_Content/123",
+            ]
+        );
+        assert!(oneline.ends_with('\n'));
+        assert_eq!(connections_to_oneline(&[]), "");
+    }
+
+    #[test]
+    fn test_escape_prometheus_label() {
+        assert_eq!(escape_prometheus_label("established"), "established");
+        assert_eq!(escape_prometheus_label("a\\b"), "a\\\\b");
+        assert_eq!(escape_prometheus_label("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escape_prometheus_label("a\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn test_connections_to_prometheus() {
+        let mut listening = test_connection();
+        listening.state = "listen".to_string();
+        let mut udp = test_connection();
+        udp.proto = "udp".to_string();
+
+        let metrics =
+            connections_to_prometheus(&[test_connection(), listening, test_connection(), udp]);
+        let lines: Vec<&str> = metrics.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "# HELP somo_connections_total Number of open connections by protocol and state.",
+                "# TYPE somo_connections_total gauge",
+                "somo_connections_total{proto=\"tcp\",state=\"established\"} 2",
+                "somo_connections_total{proto=\"tcp\",state=\"listen\"} 1",
+                "somo_connections_total{proto=\"udp\",state=\"established\"} 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_connections_to_prometheus_empty() {
+        let metrics = connections_to_prometheus(&[]);
+        assert_eq!(metrics.lines().count(), 2);
+        assert!(!metrics.contains("somo_connections_total{"));
+    }
+}
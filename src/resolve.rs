@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::schemas::Connection;
+use crate::utils;
+
+/// The number of threads which perform the reverse DNS lookups, further hosts wait for a free thread.
+const LOOKUP_WORKERS: usize = 8;
+
+/// The maximum time to wait for the reverse DNS lookup of a single host, measured from the start of its lookup.
+const LOOKUP_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// The resolver shared by all refreshes, so that `--watch` never starts more than `LOOKUP_WORKERS` threads.
+static RESOLVER: OnceLock<Arc<Resolver>> = OnceLock::new();
+
+/// Looks up the hostname of an IP address, `dns_lookup::lookup_addr` outside of the tests.
+type LookupFn = fn(&IpAddr) -> io::Result<String>;
+
+/// The state of the reverse DNS lookup of a single host.
+#[derive(Debug, Clone, PartialEq)]
+enum Lookup {
+    /// Waiting for a free worker.
+    Queued,
+    /// Started by a worker at the given time.
+    Running(Instant),
+    /// Finished, with the hostname if the IP address could be resolved.
+    Done(Option<String>),
+}
+
+/// A fixed pool of workers performing reverse DNS lookups, every host is only looked up once.
+/// Lookups which hang can't be cancelled, they keep their worker busy but are no longer waited for after `timeout`.
+struct Resolver {
+    jobs: Sender<IpAddr>,
+    lookups: Mutex<HashMap<IpAddr, Lookup>>,
+    /// Notified whenever a lookup starts or finishes.
+    changed: Condvar,
+    workers: usize,
+    timeout: Duration,
+}
+
+impl Resolver {
+    /// Starts the workers of a resolver, they exit once the resolver is dropped.
+    ///
+    /// # Arguments
+    /// * `workers`: The number of lookups which run at the same time.
+    /// * `timeout`: The maximum time to wait for a single lookup.
+    /// * `lookup`: Looks up the hostname of an IP address.
+    ///
+    /// # Returns
+    /// The resolver.
+    fn start(workers: usize, timeout: Duration, lookup: LookupFn) -> Arc<Self> {
+        let (jobs, receiver) = mpsc::channel();
+        let resolver = Arc::new(Resolver {
+            jobs,
+            lookups: Mutex::new(HashMap::new()),
+            changed: Condvar::new(),
+            workers,
+            timeout,
+        });
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            let resolver = Arc::downgrade(&resolver);
+            thread::spawn(move || Self::work(&receiver, &resolver, lookup));
+        }
+        resolver
+    }
+
+    /// Performs the queued lookups one after another until the resolver is dropped.
+    ///
+    /// # Arguments
+    /// * `receiver`: The queue of IP addresses, shared by all workers.
+    /// * `resolver`: Where to store the state of the lookups.
+    /// * `lookup`: Looks up the hostname of an IP address.
+    ///
+    /// # Returns
+    /// None
+    fn work(receiver: &Mutex<Receiver<IpAddr>>, resolver: &Weak<Self>, lookup: LookupFn) {
+        loop {
+            let job = receiver
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .recv();
+            let (Ok(ip), Some(resolver)) = (job, resolver.upgrade()) else {
+                return;
+            };
+            resolver.update(ip, Lookup::Running(Instant::now()));
+            let hostname = lookup(&ip)
+                .ok()
+                .filter(|hostname| *hostname != ip.to_string());
+            resolver.update(ip, Lookup::Done(hostname));
+        }
+    }
+
+    /// Stores the new state of a lookup and wakes up the waiting callers.
+    fn update(&self, ip: IpAddr, lookup: Lookup) {
+        self.lookups
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(ip, lookup);
+        self.changed.notify_all();
+    }
+
+    /// Resolves IP addresses using the finished lookups, queueing the hosts which weren't looked up yet.
+    /// It waits until every lookup finished or ran for longer than the timeout. Hosts which are still queued are
+    /// given up on once every worker is stuck in an expired lookup, since none of them will be free soon.
+    /// Lookups which finish late are kept, e.g for the next refresh of `--watch`.
+    ///
+    /// # Arguments
+    /// * `ips`: The unique IP addresses to resolve.
+    ///
+    /// # Returns
+    /// A map from each successfully resolved IP address to its hostname.
+    fn resolve(&self, ips: &HashSet<IpAddr>) -> HashMap<IpAddr, String> {
+        let mut lookups = self.lookups.lock().unwrap_or_else(PoisonError::into_inner);
+        for ip in ips {
+            // a host which is still being looked up isn't queued again
+            if !lookups.contains_key(ip) {
+                lookups.insert(*ip, Lookup::Queued);
+                let _ = self.jobs.send(*ip);
+            }
+        }
+
+        loop {
+            let now = Instant::now();
+            let expires = |started: &Instant| *started + self.timeout;
+            let stuck = lookups
+                .values()
+                .filter(
+                    |lookup| matches!(lookup, Lookup::Running(started) if expires(started) <= now),
+                )
+                .count();
+
+            let mut waiting = false;
+            let mut next_expiry: Option<Instant> = None;
+            for ip in ips {
+                match &lookups[ip] {
+                    Lookup::Running(started) if expires(started) > now => {
+                        waiting = true;
+                        next_expiry = Some(
+                            next_expiry
+                                .map_or(expires(started), |expiry| expiry.min(expires(started))),
+                        );
+                    }
+                    Lookup::Queued if stuck < self.workers => waiting = true,
+                    _ => {}
+                }
+            }
+            if !waiting {
+                break;
+            }
+
+            let wait = next_expiry.map_or(self.timeout, |expiry| expiry - now);
+            lookups = self
+                .changed
+                .wait_timeout(lookups, wait)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        ips.iter()
+            .filter_map(|ip| match &lookups[ip] {
+                Lookup::Done(Some(hostname)) => Some((*ip, hostname.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Resolves the remote addresses of all connections to hostnames.
+/// Every remote host is only looked up once, no matter how many connections or refreshes it has.
+/// Lookups which fail or take longer than `LOOKUP_TIMEOUT` fall back to the IP address.
+/// Loopback, unspecified, private and link-local addresses are skipped since they can't be resolved publicly.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `remote_hostname`.
+///
+/// # Returns
+/// None
+pub fn resolve_remote_addresses(connections: &mut [Connection]) {
+    let ips: HashSet<IpAddr> = connections
+        .iter()
+        .filter_map(|connection| utils::parse_ip(&connection.remote_address))
+        .filter(utils::is_public_ip)
+        .collect();
+    if ips.is_empty() {
+        return;
+    }
+
+    let resolver = RESOLVER
+        .get_or_init(|| Resolver::start(LOOKUP_WORKERS, LOOKUP_TIMEOUT, dns_lookup::lookup_addr));
+    let hostnames = resolver.resolve(&ips);
+    for connection in connections.iter_mut() {
+        connection.remote_hostname = utils::parse_ip(&connection.remote_address)
+            .and_then(|ip| hostnames.get(&ip))
+            .cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_remote_addresses_skips_local() {
+        let mut connections = vec![Connection {
+            remote_address: "127.0.0.1".to_string(),
+            ..Default::default()
+        }];
+        resolve_remote_addresses(&mut connections);
+        assert!(connections[0].remote_hostname.is_none());
+    }
+
+    /// Resolves 10.0.0.x to "host-x" after x * 100ms, 10.0.0.99 hangs and 10.0.0.98 fails.
+    fn fake_lookup(ip: &IpAddr) -> io::Result<String> {
+        let IpAddr::V4(ipv4) = ip else {
+            return Err(io::Error::other("not IPv4"));
+        };
+        LOOKUPS.lock().unwrap().push(*ip);
+        let host = ipv4.octets()[3];
+        match host {
+            99 => thread::sleep(Duration::from_secs(60)),
+            98 => return Err(io::Error::other("no PTR record")),
+            _ => thread::sleep(Duration::from_millis(u64::from(host) * 100)),
+        }
+        Ok(format!("host-{}", host))
+    }
+
+    /// The IP addresses passed to `fake_lookup`, in the order of the lookups.
+    static LOOKUPS: Mutex<Vec<IpAddr>> = Mutex::new(Vec::new());
+
+    fn ip(host: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, host])
+    }
+
+    #[test]
+    fn test_resolver() {
+        let resolver = Resolver::start(2, Duration::from_millis(300), fake_lookup);
+
+        // the hanging host only runs into its own timeout, the others are resolved
+        let start = Instant::now();
+        let hostnames = resolver.resolve(&HashSet::from([ip(1), ip(98), ip(99)]));
+        assert_eq!(hostnames, HashMap::from([(ip(1), "host-1".to_string())]));
+        assert!(start.elapsed() < Duration::from_millis(1000));
+
+        // the worker which isn't stuck still resolves new hosts
+        let hostnames = resolver.resolve(&HashSet::from([ip(2)]));
+        assert_eq!(hostnames, HashMap::from([(ip(2), "host-2".to_string())]));
+
+        // once every worker is stuck in an expired lookup the queued host is given up on
+        let start = Instant::now();
+        assert!(resolver.resolve(&HashSet::from([ip(5), ip(6)])).is_empty());
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // the pending and finished hosts aren't looked up again
+        let start = Instant::now();
+        let hostnames = resolver.resolve(&HashSet::from([ip(1), ip(99)]));
+        assert_eq!(hostnames, HashMap::from([(ip(1), "host-1".to_string())]));
+        assert!(start.elapsed() < Duration::from_millis(100));
+        let lookups = LOOKUPS.lock().unwrap();
+        for host in [1, 2, 98, 99] {
+            assert_eq!(
+                lookups
+                    .iter()
+                    .filter(|looked_up| **looked_up == ip(host))
+                    .count(),
+                1
+            );
+        }
+    }
+}
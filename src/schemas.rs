@@ -1,4 +1,12 @@
-use std::net::SocketAddr;
+use clap::ValueEnum;
+use ipnet::IpNet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::utils;
 
 /// Represents the type of an IP address.
 ///
@@ -6,46 +14,933 @@ use std::net::SocketAddr;
 /// * `Localhost`: Represents the localhost/127.0.0.1 address.
 /// * `Unspecified`: Represents an unspecified or wildcard address.
 /// * `Extern`: Represents an external address.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum AddressType {
     Localhost,
+    #[default]
     Unspecified,
     Extern,
 }
 
+/// Represents the columns by which the connections can be sorted.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Proto,
+    #[value(name = "local_port", alias = "local-port")]
+    #[serde(alias = "local-port")]
+    LocalPort,
+    #[value(name = "remote_address", alias = "remote-address")]
+    #[serde(alias = "remote-address")]
+    RemoteAddress,
+    #[value(name = "remote_port", alias = "remote-port")]
+    #[serde(alias = "remote-port")]
+    RemotePort,
+    Pid,
+    Program,
+    State,
+    Age,
+}
+
+/// Represents a high-level category of TCP states which can be combined with `--include`.
+///
+/// # Variants
+/// * `Listening`: Sockets waiting for incoming connections ("listen").
+/// * `Established`: Connections which are transferring data ("established").
+/// * `Waiting`: Connections which are being opened or wait before they are gone ("syn-sent", "syn-recv", "time-wait").
+/// * `Closing`: Connections which are being closed by either side ("fin-wait1", "fin-wait2", "close-wait", "last-ack", "closing").
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum StateCategory {
+    Listening,
+    Established,
+    Waiting,
+    Closing,
+}
+
+/// Represents when the output should be colored.
+///
+/// # Variants
+/// * `Auto`: Colors are used if stdout is a terminal and `NO_COLOR` isn't set.
+/// * `Always`: Colors are always used.
+/// * `Never`: Colors are never used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Represents the colors of the proto and state cells of the table.
+///
+/// # Variants
+/// * `Default`: Each protocol and state category has its own color, e.g listening sockets are green.
+/// * `Mono`: No colors are used at all, like `--color never`.
+/// * `HighContrast`: Bright and bold colors which are easier to tell apart.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Default,
+    Mono,
+    HighContrast,
+}
+
+/// Represents the characters the borders of the tables are drawn with.
+///
+/// # Variants
+/// * `Unicode`: Box-drawing characters, e.g "┌─┬─┐".
+/// * `Ascii`: Plain ASCII for legacy terminals and logs, e.g "+-+-+".
+/// * `None`: No borders at all, the columns are only aligned by whitespace.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorderStyle {
+    #[default]
+    Unicode,
+    Ascii,
+    None,
+}
+
+/// Represents how the creation time of a connection is shown with `--timestamps`.
+///
+/// # Variants
+/// * `Relative`: The age of the connection, e.g "3h12m".
+/// * `Absolute`: The local date and time, e.g "2024-05-01 13:45:10".
+/// * `Epoch`: The Unix timestamp in seconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    #[default]
+    Relative,
+    Absolute,
+    Epoch,
+}
+
+/// Represents the address families of `--family`.
+///
+/// # Variants
+/// * `Inet`: Only IPv4 sockets.
+/// * `Inet6`: Only IPv6 sockets.
+/// * `Any`: Both IPv4 and IPv6 sockets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    Inet,
+    Inet6,
+    #[default]
+    Any,
+}
+
+/// Represents from where a socket can be reached, based on its local address.
+///
+/// # Variants
+/// * `Loopback`: Only reachable from the same host, e.g "127.0.0.1" or "[::1]".
+/// * `Private`: Reachable from a private or link-local network, e.g "10.0.0.5" or "[fe80::1%eth0]".
+/// * `Public`: Bound to a public address.
+/// * `AllInterfaces`: Bound to all interfaces ("0.0.0.0" or "[::]"), so reachable on every address of the host.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Exposure {
+    Loopback,
+    Private,
+    Public,
+    AllInterfaces,
+}
+
+/// Represents the columns by which the connections can be grouped into a summary.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum GroupField {
+    Program,
+    Pid,
+    State,
+    Proto,
+}
+
+/// Represents a group of connections which share the same value in the grouped column.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConnectionGroup {
+    pub key: String,
+    pub count: usize,
+    pub states: BTreeMap<String, usize>,
+    /// The distinct programs of the connections in the group.
+    pub programs: BTreeSet<String>,
+    /// The distinct local ports of the connections in the group.
+    pub local_ports: BTreeSet<String>,
+}
+
+/// Represents how a connection differs from the snapshot loaded with `--diff`.
+///
+/// # Variants
+/// * `Added`: The connection is new since the snapshot.
+/// * `Removed`: The connection is only part of the snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    Added,
+    Removed,
+}
+
 /// Represents a processed socket connection with all its attributes.
-#[derive(Debug)]
+/// Fields which are missing when a connection is deserialized, e.g from a `--diff` snapshot, keep their default.
+/// The JSON Schema of `--print-schema` is derived from this struct, the doc comments of the fields are their descriptions.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct Connection {
+    /// The protocol, "tcp" or "udp".
     pub proto: String,
+    /// The address family of the socket, "inet" for IPv4 or "inet6" for IPv6, so that `proto` never needs a "4" or "6" suffix.
+    pub family: String,
+    /// The local address, IPv6 addresses are in brackets.
+    pub local_address: String,
+    /// The local port, "-" if it is unknown.
     pub local_port: String,
+    /// The service name of the local port, only set by `--services`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// The remote address, IPv6 addresses are in brackets.
     pub remote_address: String,
+    /// The remote port, "-" if it is unknown.
     pub remote_port: String,
+    /// The service name of the remote port, only set by `--services`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_service: Option<String>,
+    /// The reverse DNS name of the remote address, only set by `--resolve`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_hostname: Option<String>,
+    /// The ISO country code of the remote address, only set by `--geoip`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// The container of the process, only set by `--containers`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// The full ID of the container, so that `--container` also matches the beginning of the ID if the name is shown.
+    #[serde(skip)]
+    pub container_id: Option<String>,
+    /// The systemd unit of the process, only set by `--units`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// From where the socket can be reached, only set by `--exposure`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<Exposure>,
+    /// Whether both endpoints are on this host, e.g two local services talking over loopback.
+    pub is_local: bool,
+    /// The network namespace of the socket, only set by `--all-netns`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub netns: Option<String>,
+    /// The inode of the socket, which links it to the file descriptors in `/proc/<pid>/fd`, only known on Linux.
+    pub inode: Option<u64>,
+    /// The received bytes, only set by `--stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rx_bytes: Option<u64>,
+    /// The sent bytes, only set by `--stats`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_bytes: Option<u64>,
+    /// The receive queue in bytes, for a listening socket the connections waiting to be accepted, only known on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rx_queue: Option<u32>,
+    /// The send queue in bytes, only known on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_queue: Option<u32>,
+    /// The retransmits of the unacknowledged data, only known for TCP on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retransmits: Option<u32>,
+    /// The creation time as a Unix timestamp in seconds, if it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    /// The creation time formatted by `--timestamps`, the raw `created` timestamp is always kept next to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_formatted: Option<String>,
+    /// The program of the owning process, "-" if it is unknown.
     pub program: String,
+    /// The resolved path of the executable, if it is readable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exe_path: Option<String>,
+    /// Whether the executable was deleted or replaced since the process started, only present if it was.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub exe_deleted: bool,
+    /// The PID of the owning process, "-" if it is unknown.
     pub pid: String,
+    /// The user of the process, `null` if it is unknown.
+    pub user: Option<String>,
+    /// The full command line of the process, `null` if it is unknown.
+    pub command: Option<String>,
+    /// The lowercase state, e.g "listen".
     pub state: String,
+    /// The number of identical rows this row stands for, only set by `--unique --count-dupes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<usize>,
+    /// Whether the connection was added or removed since the snapshot, only set by `--diff`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<DiffStatus>,
+    #[serde(skip)]
     pub address_type: AddressType,
 }
 
+/// Identifies identical rows for `--unique`: the protocol, both endpoints, the PID and the state of a connection.
+pub type DuplicateKey = (String, String, String, String, String);
+
+/// Identifies a connection across two reads of the sockets: its protocol, both endpoints and the PID of its process.
+pub type ConnectionKey = (String, String, String, String);
+
+impl Connection {
+    /// Gets the identity of the connection, e.g to find the connections which appeared or vanished between two refreshes.
+    /// Two connections with the same key are considered the same socket, even if e.g their state changed.
+    ///
+    /// # Arguments
+    /// None
+    ///
+    /// # Returns
+    /// The protocol, the local and remote endpoint (e.g "127.0.0.1:8080") and the PID.
+    pub fn key(&self) -> ConnectionKey {
+        (
+            self.proto.to_string(),
+            format!("{}:{}", self.local_address, self.local_port),
+            format!("{}:{}", self.remote_address, self.remote_port),
+            self.pid.to_string(),
+        )
+    }
+
+    /// Gets the key under which identical rows are collapsed by `--unique`.
+    /// Unlike `key` the state is included and the protocol and state are compared case-insensitively.
+    ///
+    /// # Arguments
+    /// None
+    ///
+    /// # Returns
+    /// The lowercase protocol, the local and remote endpoint, the PID and the lowercase state.
+    pub fn duplicate_key(&self) -> DuplicateKey {
+        let (proto, local, remote, pid) = self.key();
+        (
+            proto.to_ascii_lowercase(),
+            local,
+            remote,
+            pid,
+            self.state.to_ascii_lowercase(),
+        )
+    }
+}
+
+/// Contains the information about a process which owns a socket.
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub program: String,
+    pub user: Option<String>,
+    pub command: String,
+    /// The resolved path of the executable, if it is readable.
+    pub exe_path: Option<String>,
+    /// Whether the executable was deleted or replaced since the process started, e.g by an upgrade.
+    pub exe_deleted: bool,
+    /// When the socket was created as a Unix timestamp in seconds, if it is known.
+    pub created: Option<u64>,
+}
+
+/// The queues of a socket as listed in the socket tables of `/proc/net`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SocketQueues {
+    /// The bytes received but not read by the program yet, for a listening socket the connections waiting to be accepted.
+    pub rx_queue: u32,
+    /// The bytes sent but not acknowledged by the peer yet.
+    pub tx_queue: u32,
+    /// The retransmits of the unacknowledged data, only known for TCP.
+    pub retransmits: Option<u32>,
+}
+
 /// General struct type for TCP and UDP entries.
-#[derive(Debug)]
+/// The `owner` is the key of the owning process in the map of processes, ie. the socket inode on Linux and the PID on Windows.
+#[derive(Debug, Clone)]
 pub struct NetEntry {
     pub protocol: String,
     pub local_address: SocketAddr,
     pub remote_address: SocketAddr,
     pub state: String,
-    pub inode: u64,
+    pub owner: u64,
+    /// The queues of the socket, only known on Linux.
+    pub queues: Option<SocketQueues>,
+}
+
+/// Represents an address filter provided by the user.
+///
+/// # Variants
+/// * `Ip`: Matches exactly one IP address, e.g "192.168.0.1", link-local addresses match on every interface.
+/// * `Scoped`: Matches exactly one link-local IPv6 address on one interface, e.g "fe80::1%eth0".
+/// * `Network`: Matches every IP address in a subnet given in CIDR notation, e.g "10.0.0.0/8" or "fe80::/10".
+/// * `Any`: Matches the unspecified addresses "0.0.0.0" and "::" of sockets bound to all interfaces, given as "any".
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressFilter {
+    Ip(IpAddr),
+    Scoped(IpAddr, String),
+    Network(IpNet),
+    Any,
+}
+
+impl FromStr for AddressFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("any") {
+            return Ok(AddressFilter::Any);
+        }
+        if value.contains('/') {
+            return value
+                .parse::<IpNet>()
+                .map(AddressFilter::Network)
+                .map_err(|_| format!("'{}' is not a valid CIDR network", value));
+        }
+        let ip = utils::parse_ip(value)
+            .ok_or_else(|| format!("'{}' is not a valid IP address", value))?;
+        match utils::split_zone(value).1 {
+            Some(zone) if ip.is_ipv6() && !zone.is_empty() => {
+                Ok(AddressFilter::Scoped(ip, zone.to_string()))
+            }
+            Some(_) => Err(format!(
+                "'{}' is not a valid IP address, only IPv6 addresses can have an interface",
+                value
+            )),
+            None => Ok(AddressFilter::Ip(ip)),
+        }
+    }
+}
+
+impl AddressFilter {
+    /// Checks if an address of a `Connection` matches the filter.
+    ///
+    /// # Arguments
+    /// * `address`: The address to check, e.g "10.0.0.1" or "[::1]".
+    ///
+    /// # Returns
+    /// `true` if the address matches, `false` if not or if the address isn't a valid IP.
+    pub fn matches(&self, address: &str) -> bool {
+        match (self, utils::parse_ip(address)) {
+            (AddressFilter::Ip(ip), Some(parsed)) => *ip == parsed,
+            (AddressFilter::Scoped(ip, zone), Some(parsed)) => {
+                *ip == parsed && utils::split_zone(address).1 == Some(zone.as_str())
+            }
+            (AddressFilter::Network(network), Some(parsed)) => network.contains(&parsed),
+            (AddressFilter::Any, Some(parsed)) => parsed.is_unspecified(),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Represents how the program filter matches the name of a program.
+///
+/// # Variants
+/// * `Substring`: Matches names containing the text regardless of the case, e.g "gres" matches "postgres" (`--program`).
+/// * `Exact`: Matches only the exact name, e.g "nginx" doesn't match "nginx-debug" (`--program-exact`).
+/// * `Glob`: Matches the whole name against a glob pattern, e.g "python3*" (`--program-glob`).
+/// * `Regex`: Matches names containing a match of a regular expression, e.g "^(nginx|httpd)$" (`--program-regex`).
+#[derive(Debug, Clone)]
+pub enum ProgramMatcher {
+    Substring(String),
+    Exact(String),
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl ProgramMatcher {
+    /// Creates a matcher for a glob pattern.
+    ///
+    /// # Arguments
+    /// * `pattern`: The pattern provided by the user, e.g "python3*".
+    ///
+    /// # Returns
+    /// The matcher or an error message if the pattern isn't valid.
+    pub fn glob(pattern: &str) -> Result<Self, String> {
+        glob::Pattern::new(pattern)
+            .map(ProgramMatcher::Glob)
+            .map_err(|error| format!("'{}' is not a valid glob pattern ({})", pattern, error))
+    }
+
+    /// Creates a matcher for a regular expression.
+    ///
+    /// # Arguments
+    /// * `pattern`: The regular expression provided by the user, e.g "^(nginx|httpd)$".
+    ///
+    /// # Returns
+    /// The matcher or an error message if the regular expression isn't valid.
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        regex::Regex::new(pattern)
+            .map(ProgramMatcher::Regex)
+            .map_err(|_| format!("'{}' is not a valid regular expression", pattern))
+    }
+
+    /// Checks if a program name, or the path of an executable, matches.
+    ///
+    /// # Arguments
+    /// * `name`: The name to check, e.g "postgres".
+    ///
+    /// # Example
+    /// ```
+    /// # use somo::schemas::ProgramMatcher;
+    /// assert!(ProgramMatcher::Substring("Gres".to_string()).matches("postgres"));
+    /// assert!(ProgramMatcher::glob("python3*").unwrap().matches("python3.12"));
+    /// ```
+    ///
+    /// # Returns
+    /// `true` if the name matches, `false` if not.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            ProgramMatcher::Substring(text) => name.to_lowercase().contains(&text.to_lowercase()),
+            ProgramMatcher::Exact(text) => name == text,
+            ProgramMatcher::Glob(pattern) => pattern.matches(name),
+            ProgramMatcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Represents a port filter provided by the user.
+///
+/// # Variants
+/// * `Port`: Matches exactly one port, e.g "443".
+/// * `Range`: Matches every port in an inclusive range, e.g "32768-60999".
+/// * `List`: Matches any of the comma separated ports or ranges, e.g "80,443,8000-8100".
+#[derive(Debug, Clone, PartialEq)]
+pub enum PortFilter {
+    Port(u16),
+    Range(u16, u16),
+    List(Vec<PortFilter>),
+}
+
+impl FromStr for PortFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_port = |port: &str| {
+            port.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("'{}' is not a valid port", port.trim()))
+        };
+
+        if value.contains(',') {
+            return value
+                .split(',')
+                .map(|part| part.parse::<PortFilter>())
+                .collect::<Result<Vec<PortFilter>, String>>()
+                .map(PortFilter::List);
+        }
+        match value.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse_port(start)?, parse_port(end)?);
+                if start > end {
+                    return Err(format!(
+                        "the range '{}' is invalid since {} is greater than {}",
+                        value, start, end
+                    ));
+                }
+                Ok(PortFilter::Range(start, end))
+            }
+            None => parse_port(value).map(PortFilter::Port),
+        }
+    }
+}
+
+impl PortFilter {
+    /// Checks if a port of a `Connection` matches the filter.
+    ///
+    /// # Arguments
+    /// * `port`: The port to check, e.g "8080".
+    ///
+    /// # Returns
+    /// `true` if the port matches, `false` if not or if the port isn't a number.
+    pub fn matches(&self, port: &str) -> bool {
+        let Ok(port) = port.parse::<u16>() else {
+            return false;
+        };
+        self.contains(port)
+    }
+
+    fn contains(&self, port: u16) -> bool {
+        match self {
+            PortFilter::Port(filter_port) => *filter_port == port,
+            PortFilter::Range(start, end) => (*start..=*end).contains(&port),
+            PortFilter::List(filters) => filters.iter().any(|filter| filter.contains(port)),
+        }
+    }
+}
+
+/// Represents a PID filter provided by the user.
+///
+/// # Variants
+/// * `Pid`: Matches exactly one PID, e.g "1234".
+/// * `Range`: Matches every PID in an inclusive range, e.g "1000-2000".
+/// * `List`: Matches any of the comma separated PIDs or ranges, e.g "1234,5678,9000-9100".
+#[derive(Debug, Clone, PartialEq)]
+pub enum PidFilter {
+    Pid(u32),
+    Range(u32, u32),
+    List(Vec<PidFilter>),
+}
+
+impl FromStr for PidFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_pid = |pid: &str| {
+            pid.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' is not a valid PID", pid.trim()))
+        };
+
+        if value.contains(',') {
+            return value
+                .split(',')
+                .map(|part| part.parse::<PidFilter>())
+                .collect::<Result<Vec<PidFilter>, String>>()
+                .map(PidFilter::List);
+        }
+        match value.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse_pid(start)?, parse_pid(end)?);
+                if start > end {
+                    return Err(format!(
+                        "the range '{}' is invalid since {} is greater than {}",
+                        value, start, end
+                    ));
+                }
+                Ok(PidFilter::Range(start, end))
+            }
+            None => parse_pid(value).map(PidFilter::Pid),
+        }
+    }
+}
+
+impl PidFilter {
+    /// Checks if the PID of a `Connection` matches the filter.
+    ///
+    /// # Arguments
+    /// * `pid`: The PID to check, e.g "1234" or "-".
+    ///
+    /// # Returns
+    /// `true` if the PID matches, `false` if not or if the process is unknown.
+    pub fn matches(&self, pid: &str) -> bool {
+        let Ok(pid) = pid.parse::<u32>() else {
+            return false;
+        };
+        self.contains(pid)
+    }
+
+    fn contains(&self, pid: u32) -> bool {
+        match self {
+            PidFilter::Pid(filter_pid) => *filter_pid == pid,
+            PidFilter::Range(start, end) => (*start..=*end).contains(&pid),
+            PidFilter::List(filters) => filters.iter().any(|filter| filter.contains(pid)),
+        }
+    }
+}
+
+/// Represents an environment variable which the process of a connection must have been started with, e.g "DEPLOY_ID=42".
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvFilter {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for EnvFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((key, variable_value)) if !key.is_empty() => Ok(EnvFilter {
+                key: key.to_string(),
+                value: variable_value.to_string(),
+            }),
+            _ => Err(format!("'{}' is not of the form KEY=VALUE", value)),
+        }
+    }
+}
+
+impl EnvFilter {
+    /// Checks if the environment of a process contains the variable with exactly this value.
+    ///
+    /// # Arguments
+    /// * `environment`: The environment variables of the process.
+    ///
+    /// # Returns
+    /// `true` if the variable is set to the value, `false` if it is missing or set to another value.
+    pub fn matches(&self, environment: &HashMap<String, String>) -> bool {
+        environment.get(&self.key) == Some(&self.value)
+    }
 }
 
 /// Contains options for filtering a `Conntection`.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct FilterOptions {
-    pub by_proto: Option<String>,
-    pub by_program: Option<String>,
-    pub by_pid: Option<String>,
-    pub by_remote_address: Option<String>,
-    pub by_remote_port: Option<String>,
-    pub by_local_port: Option<String>,
+    pub by_proto: Vec<String>,
+    pub by_program: Option<ProgramMatcher>,
+    /// Whether `by_program` also matches the path of the executable.
+    pub match_path: bool,
+    pub by_pid: Option<PidFilter>,
+    /// Only keeps the processes which descend from this PID, ie. its children, their children and so on.
+    pub by_ppid: Option<i32>,
+    /// Only keeps the processes of this user, compared with the shown user, see `resolve_user_filter` to match both a name and a UID.
+    pub by_user: Option<String>,
+    /// Only keeps the processes whose environment contains all of these variables, their environment is only readable on Linux.
+    pub by_env: Vec<EnvFilter>,
+    pub by_remote_address: Option<AddressFilter>,
+    pub by_local_address: Option<AddressFilter>,
+    pub by_remote_port: Option<PortFilter>,
+    pub by_local_port: Option<PortFilter>,
+    pub by_state: Option<String>,
     pub by_open: bool,
     pub by_listen: bool,
-    pub exclude_ipv6: bool,
+    pub by_include: Vec<StateCategory>,
+    pub by_unresolved: bool,
+    /// Only keeps the connections which were created less than this many seconds ago.
+    pub by_since: Option<u64>,
+    /// Whether `by_since` keeps the connections whose creation time is unknown instead of removing them.
+    pub include_unknown_age: bool,
+    /// Only keeps the connections whose local port is privileged (ie. below 1024) if `true`, or unprivileged if `false`.
+    pub by_privileged: Option<bool>,
+    pub by_family: AddressFamily,
+    /// Whether IPv4-mapped IPv6 addresses (e.g `::ffff:192.168.1.1`) keep their mapped form instead of being shown and filtered as IPv4.
+    pub raw_addresses: bool,
+    /// Whether the users owning the processes are shown by their UID instead of looking up their names, e.g for `--numeric`.
+    pub numeric_users: bool,
+    pub by_exposure: Option<Exposure>,
+    pub exclude_program: Option<String>,
+    /// Whether to remove the connections of somo itself, e.g the sockets it opens for `--resolve`.
+    pub exclude_self: bool,
+    pub exclude_remote_address: Option<AddressFilter>,
+    pub exclude_local_port: Option<PortFilter>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_filter_exact() {
+        let filter: AddressFilter = "192.168.0.1".parse().unwrap();
+        assert!(filter.matches("192.168.0.1"));
+        assert!(!filter.matches("192.168.0.2"));
+
+        let filter: AddressFilter = "::1".parse().unwrap();
+        assert!(filter.matches("[::1]"));
+    }
+
+    #[test]
+    fn test_address_filter_cidr() {
+        let filter: AddressFilter = "10.0.0.0/8".parse().unwrap();
+        assert!(filter.matches("10.1.2.3"));
+        assert!(!filter.matches("11.0.0.1"));
+        assert!(!filter.matches("[::1]"));
+
+        let filter: AddressFilter = "fe80::/10".parse().unwrap();
+        assert!(filter.matches("[fe80::1]"));
+        assert!(!filter.matches("[2001:db8::1]"));
+        assert!(!filter.matches("10.1.2.3"));
+    }
+
+    #[test]
+    fn test_program_matcher() {
+        let substring = ProgramMatcher::Substring("SQL".to_string());
+        assert!(substring.matches("mysqld"));
+        assert!(!substring.matches("postgres"));
+
+        let exact = ProgramMatcher::Exact("nginx".to_string());
+        assert!(exact.matches("nginx"));
+        assert!(!exact.matches("nginx-debug"));
+        assert!(!exact.matches("Nginx"));
+
+        let glob = ProgramMatcher::glob("python3*").unwrap();
+        assert!(glob.matches("python3.12"));
+        assert!(!glob.matches("ipython3"));
+
+        let regex = ProgramMatcher::regex("^(nginx|httpd)$").unwrap();
+        assert!(regex.matches("httpd"));
+        assert!(!regex.matches("httpd-worker"));
+    }
+
+    #[test]
+    fn test_program_matcher_invalid() {
+        assert!(ProgramMatcher::glob("[python").is_err());
+        let error = ProgramMatcher::regex("(nginx").unwrap_err();
+        assert_eq!(error, "'(nginx' is not a valid regular expression");
+    }
+
+    #[test]
+    fn test_port_filter_parsing() {
+        assert_eq!("80".parse::<PortFilter>(), Ok(PortFilter::Port(80)));
+        assert_eq!(
+            "32768-60999".parse::<PortFilter>(),
+            Ok(PortFilter::Range(32768, 60999))
+        );
+        assert_eq!(
+            "80,443,8000-8100".parse::<PortFilter>(),
+            Ok(PortFilter::List(vec![
+                PortFilter::Port(80),
+                PortFilter::Port(443),
+                PortFilter::Range(8000, 8100)
+            ]))
+        );
+
+        assert!("443-80".parse::<PortFilter>().is_err());
+        assert!("http".parse::<PortFilter>().is_err());
+        assert!("70000".parse::<PortFilter>().is_err());
+        assert!("80,".parse::<PortFilter>().is_err());
+    }
+
+    #[test]
+    fn test_port_filter_matches() {
+        let single: PortFilter = "8080".parse().unwrap();
+        assert!(single.matches("8080"));
+        assert!(!single.matches("8081"));
+        assert!(!single.matches("-"));
+
+        let range: PortFilter = "32768-60999".parse().unwrap();
+        assert!(range.matches("32768"));
+        assert!(range.matches("60999"));
+        assert!(!range.matches("61000"));
+
+        let list: PortFilter = "80,443,8000-8100".parse().unwrap();
+        assert!(list.matches("443"));
+        assert!(list.matches("8050"));
+        assert!(!list.matches("22"));
+    }
+
+    #[test]
+    fn test_connection_key() {
+        let connection = Connection {
+            proto: "tcp".to_string(),
+            local_address: "[::1]".to_string(),
+            local_port: "8080".to_string(),
+            remote_address: "[::1]".to_string(),
+            remote_port: "51234".to_string(),
+            pid: "42".to_string(),
+            state: "established".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            connection.key(),
+            (
+                "tcp".to_string(),
+                "[::1]:8080".to_string(),
+                "[::1]:51234".to_string(),
+                "42".to_string()
+            )
+        );
+
+        // the state isn't part of the identity
+        let closing = Connection {
+            state: "close-wait".to_string(),
+            ..connection
+        };
+        assert_eq!(closing.key().1, "[::1]:8080");
+        let other_port = Connection {
+            local_port: "8081".to_string(),
+            ..Default::default()
+        };
+        assert_ne!(other_port.key(), Connection::default().key());
+    }
+
+    #[test]
+    fn test_connection_duplicate_key() {
+        let connection = Connection {
+            proto: "udp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: "5353".to_string(),
+            pid: "42".to_string(),
+            state: "close".to_string(),
+            ..Default::default()
+        };
+        let upper_case = Connection {
+            proto: "UDP".to_string(),
+            state: "CLOSE".to_string(),
+            program: "avahi-daemon".to_string(),
+            ..connection.clone()
+        };
+        assert_eq!(connection.duplicate_key(), upper_case.duplicate_key());
+
+        // unlike the identity of a connection the state is part of the key
+        let listening = Connection {
+            state: "listen".to_string(),
+            ..connection.clone()
+        };
+        assert_eq!(connection.key(), listening.key());
+        assert_ne!(connection.duplicate_key(), listening.duplicate_key());
+    }
+
+    #[test]
+    fn test_address_filter_any() {
+        assert_eq!("any".parse::<AddressFilter>(), Ok(AddressFilter::Any));
+        assert_eq!("ANY".parse::<AddressFilter>(), Ok(AddressFilter::Any));
+
+        let filter = AddressFilter::Any;
+        assert!(filter.matches("0.0.0.0"));
+        assert!(filter.matches("[::]"));
+        assert!(!filter.matches("127.0.0.1"));
+        assert!(!filter.matches("[::1]"));
+        assert!(!filter.matches("-"));
+    }
+
+    #[test]
+    fn test_address_filter_scoped() {
+        let filter: AddressFilter = "fe80::1%eth0".parse().unwrap();
+        assert_eq!(
+            filter,
+            AddressFilter::Scoped("fe80::1".parse().unwrap(), "eth0".to_string())
+        );
+        assert!(filter.matches("[fe80::1%eth0]"));
+        assert!(!filter.matches("[fe80::1%wlan0]"));
+        assert!(!filter.matches("[fe80::1]"));
+
+        // without an interface the address matches on all of them
+        let filter: AddressFilter = "fe80::1".parse().unwrap();
+        assert!(filter.matches("[fe80::1%eth0]"));
+        assert!(filter.matches("[fe80::1]"));
+
+        assert!("10.0.0.1%eth0".parse::<AddressFilter>().is_err());
+        assert!("fe80::1%".parse::<AddressFilter>().is_err());
+    }
+
+    #[test]
+    fn test_pid_filter() {
+        assert_eq!("1234".parse::<PidFilter>(), Ok(PidFilter::Pid(1234)));
+        let filter: PidFilter = "1234, 5678,9000-9100".parse().unwrap();
+        assert_eq!(
+            filter,
+            PidFilter::List(vec![
+                PidFilter::Pid(1234),
+                PidFilter::Pid(5678),
+                PidFilter::Range(9000, 9100)
+            ])
+        );
+        assert!(filter.matches("5678"));
+        assert!(filter.matches("9050"));
+        assert!(!filter.matches("1235"));
+        assert!(!filter.matches("-"));
+
+        assert!("1234,abc".parse::<PidFilter>().is_err());
+        assert!("200-100".parse::<PidFilter>().is_err());
+        assert!("-5".parse::<PidFilter>().is_err());
+    }
+
+    #[test]
+    fn test_env_filter() {
+        let filter: EnvFilter = "DEPLOY_ID=a=b".parse().unwrap();
+        assert_eq!(filter.key, "DEPLOY_ID");
+        assert_eq!(filter.value, "a=b");
+        assert!(filter.matches(&HashMap::from([(
+            "DEPLOY_ID".to_string(),
+            "a=b".to_string()
+        )])));
+        assert!(!filter.matches(&HashMap::from([("DEPLOY_ID".to_string(), "a".to_string())])));
+        assert!(!filter.matches(&HashMap::new()));
+
+        // an empty value only matches variables which are set but empty
+        let empty: EnvFilter = "DEBUG=".parse().unwrap();
+        assert!(empty.matches(&HashMap::from([("DEBUG".to_string(), String::new())])));
+
+        assert!("DEPLOY_ID".parse::<EnvFilter>().is_err());
+        assert!("=42".parse::<EnvFilter>().is_err());
+    }
+
+    #[test]
+    fn test_address_filter_invalid() {
+        assert!("10.0.0.0/33".parse::<AddressFilter>().is_err());
+        assert!("10.0.0/8".parse::<AddressFilter>().is_err());
+        assert!("example.com".parse::<AddressFilter>().is_err());
+    }
 }
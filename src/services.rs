@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::schemas::Connection;
+
+/// The services database of the system, mapping port numbers to service names.
+const SERVICES_PATH: &str = "/etc/services";
+
+/// The most common services, used if the services database of the system can't be read (e.g on Windows).
+const BUILTIN_SERVICES: [(u16, &str, &str); 24] = [
+    (20, "tcp", "ftp-data"),
+    (21, "tcp", "ftp"),
+    (22, "tcp", "ssh"),
+    (23, "tcp", "telnet"),
+    (25, "tcp", "smtp"),
+    (53, "tcp", "domain"),
+    (53, "udp", "domain"),
+    (67, "udp", "bootps"),
+    (68, "udp", "bootpc"),
+    (80, "tcp", "http"),
+    (110, "tcp", "pop3"),
+    (123, "udp", "ntp"),
+    (143, "tcp", "imap2"),
+    (161, "udp", "snmp"),
+    (389, "tcp", "ldap"),
+    (443, "tcp", "https"),
+    (443, "udp", "https"),
+    (465, "tcp", "submissions"),
+    (587, "tcp", "submission"),
+    (993, "tcp", "imaps"),
+    (995, "tcp", "pop3s"),
+    (3306, "tcp", "mysql"),
+    (5353, "udp", "mdns"),
+    (5432, "tcp", "postgresql"),
+];
+
+/// Maps a port and a protocol ("tcp" or "udp") to the name of its service.
+type Services = HashMap<(u16, String), String>;
+
+/// The services, which are only read once even if the connections are refreshed in watch mode.
+static SERVICES: OnceLock<Services> = OnceLock::new();
+
+/// Parses a services database in the format of `/etc/services`, e.g "https 443/tcp # HTTP over TLS".
+/// Comments, aliases and invalid lines are ignored, if a port is listed multiple times the first name is used.
+///
+/// # Arguments
+/// * `content`: The content of the services database.
+///
+/// # Example
+/// ```
+/// let services = parse_services("ssh\t\t22/tcp\t# SSH Remote Login Protocol\n");
+/// assert_eq!(services[&(22, "tcp".to_string())], "ssh");
+/// ```
+///
+/// # Returns
+/// A map from each port and protocol to its service name.
+fn parse_services(content: &str) -> Services {
+    let mut services = Services::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((port, proto)) = port_proto.split_once('/') else {
+            continue;
+        };
+        if let Ok(port) = port.parse::<u16>() {
+            services
+                .entry((port, proto.to_ascii_lowercase()))
+                .or_insert_with(|| name.to_string());
+        }
+    }
+    services
+}
+
+/// Gets the services of the system or the built-in services if the database can't be read.
+fn get_services() -> &'static Services {
+    SERVICES.get_or_init(|| match std::fs::read_to_string(SERVICES_PATH) {
+        Ok(content) => parse_services(&content),
+        Err(_) => BUILTIN_SERVICES
+            .iter()
+            .map(|(port, proto, name)| ((*port, proto.to_string()), name.to_string()))
+            .collect(),
+    })
+}
+
+/// Looks up the service of a port.
+///
+/// # Arguments
+/// * `services`: The known services.
+/// * `port`: The port of a connection, e.g "443" or "-".
+/// * `proto`: The protocol of the connection.
+///
+/// # Returns
+/// The name of the service or `None` if the port is unknown.
+fn lookup_service(services: &Services, port: &str, proto: &str) -> Option<String> {
+    let port = port.parse::<u16>().ok()?;
+    services.get(&(port, proto.to_string())).cloned()
+}
+
+/// Sets the service names of the local and the remote port of all connections.
+///
+/// # Arguments
+/// * `connections`: The connections in which to set the `service` and the `remote_service`.
+///
+/// # Returns
+/// None
+pub fn annotate_services(connections: &mut [Connection]) {
+    let services = get_services();
+    for connection in connections.iter_mut() {
+        connection.service = lookup_service(services, &connection.local_port, &connection.proto);
+        connection.remote_service =
+            lookup_service(services, &connection.remote_port, &connection.proto);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICES: &str = "# Network services, Internet style
+tcpmux\t\t1/tcp\t\t\t\t# TCP port service multiplexer
+ssh\t\t22/tcp\t\t\t\t# SSH Remote Login Protocol
+domain\t\t53/tcp\t\t\t\t# Domain Name Server
+domain\t\t53/udp
+http\t\t80/tcp\t\twww\t\t# WorldWideWeb HTTP
+www-alt\t\t80/tcp
+invalid\t\tabc/tcp
+";
+
+    #[test]
+    fn test_parse_services() {
+        let services = parse_services(SERVICES);
+        assert_eq!(services.len(), 5);
+        assert_eq!(services[&(22, "tcp".to_string())], "ssh");
+        assert_eq!(services[&(53, "udp".to_string())], "domain");
+        // the first name of a port wins over later names
+        assert_eq!(services[&(80, "tcp".to_string())], "http");
+        assert!(!services.contains_key(&(22, "udp".to_string())));
+    }
+
+    #[test]
+    fn test_lookup_service() {
+        let services = parse_services(SERVICES);
+        assert_eq!(
+            lookup_service(&services, "80", "tcp"),
+            Some("http".to_string())
+        );
+        assert_eq!(lookup_service(&services, "80", "udp"), None);
+        assert_eq!(lookup_service(&services, "8080", "tcp"), None);
+        assert_eq!(lookup_service(&services, "-", "tcp"), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::connections;
+use crate::output::SCHEMA_VERSION;
+use crate::schemas::{Connection, ConnectionKey, DiffStatus};
+
+/// Parses a snapshot saved with `--json`.
+/// Snapshots of older versions of somo, which are a plain array of connections, are read as well.
+/// Fields which are missing in the snapshot, e.g because it was saved with fewer flags, stay unset.
+///
+/// # Arguments
+/// * `content`: The JSON content of the snapshot.
+///
+/// # Example
+/// ```
+/// let snapshot = parse_snapshot("{\"schema_version\": 1, \"connections\": [{\"local_port\": \"22\"}]}").unwrap();
+/// assert_eq!(snapshot[0].local_port, "22");
+/// ```
+///
+/// # Returns
+/// The connections of the snapshot or an error message if it isn't valid or was saved with another schema version.
+fn parse_snapshot(content: &str) -> Result<Vec<Connection>, String> {
+    let invalid = |error: serde_json::Error| {
+        format!(
+            "it isn't a JSON snapshot of somo, save a new one with --json ({})",
+            error
+        )
+    };
+
+    let connections = match serde_json::from_str(content).map_err(invalid)? {
+        Value::Object(mut document) => {
+            match document.get("schema_version").and_then(Value::as_u64) {
+                Some(SCHEMA_VERSION) => {}
+                Some(version) => {
+                    return Err(format!(
+                        "it was saved with schema version {}, but this version of somo uses schema version {}",
+                        version, SCHEMA_VERSION
+                    ))
+                }
+                None => return Err("it has no schema version, save a new one with --json".to_string()),
+            }
+            document.remove("connections").unwrap_or_default()
+        }
+        connections => connections,
+    };
+
+    let mut snapshot: Vec<Connection> = serde_json::from_value(connections).map_err(invalid)?;
+    for connection in snapshot.iter_mut() {
+        connection.address_type = connections::get_address_type(&connection.remote_address);
+        // a saved diff is compared by its connections only
+        connection.diff = None;
+    }
+    Ok(snapshot)
+}
+
+/// Loads a snapshot saved with `--json`.
+///
+/// # Arguments
+/// * `path`: The path of the snapshot provided by the user.
+///
+/// # Returns
+/// The connections of the snapshot or an error message if the file is unreadable, invalid or of another schema version.
+pub fn load_snapshot(path: &Path) -> Result<Vec<Connection>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Couldn't read snapshot {}: {}", path.display(), error))?;
+    parse_snapshot(&content)
+        .map_err(|error| format!("Can't compare with {}: {}", path.display(), error))
+}
+
+/// Compares the current connections with a snapshot, connections are matched by their protocol, endpoints and PID.
+///
+/// # Arguments
+/// * `connections`: The current connections.
+/// * `snapshot`: The connections of the snapshot.
+///
+/// # Returns
+/// The connections which are new since the snapshot followed by the connections which are gone, marked by their `diff`.
+pub fn diff_snapshot(connections: Vec<Connection>, snapshot: Vec<Connection>) -> Vec<Connection> {
+    let snapshot_keys: HashSet<ConnectionKey> = snapshot.iter().map(Connection::key).collect();
+    let current_keys: HashSet<ConnectionKey> = connections.iter().map(Connection::key).collect();
+
+    let added = connections
+        .into_iter()
+        .filter(|connection| !snapshot_keys.contains(&connection.key()))
+        .map(|connection| Connection {
+            diff: Some(DiffStatus::Added),
+            ..connection
+        });
+    let removed = snapshot
+        .into_iter()
+        .filter(|connection| !current_keys.contains(&connection.key()))
+        .map(|connection| Connection {
+            diff: Some(DiffStatus::Removed),
+            ..connection
+        });
+    added.chain(removed).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output;
+    use crate::schemas::AddressType;
+
+    fn connection(local_port: &str, pid: &str) -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: local_port.to_string(),
+            remote_address: "127.0.0.1".to_string(),
+            remote_port: "0".to_string(),
+            pid: pid.to_string(),
+            program: "nginx".to_string(),
+            state: "listen".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_snapshot_roundtrip() {
+        let connections = vec![connection("80", "42"), connection("443", "42")];
+        let snapshot = parse_snapshot(&output::connections_to_json(
+            &connections,
+            &output::HostInfo::current(None),
+        ))
+        .unwrap();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[1].key(), connections[1].key());
+        assert_eq!(snapshot[0].address_type, AddressType::Localhost);
+    }
+
+    #[test]
+    fn test_parse_snapshot_without_version() {
+        let snapshot = parse_snapshot("[{\"proto\": \"udp\", \"local_port\": \"53\"}]").unwrap();
+        assert_eq!(snapshot[0].proto, "udp");
+    }
+
+    #[test]
+    fn test_parse_snapshot_invalid() {
+        assert!(parse_snapshot("{\"connections\": []}").is_err());
+        assert!(parse_snapshot("[{\"local_port\": 22}]").is_err());
+        assert!(parse_snapshot("proto,local_port").is_err());
+
+        let error = parse_snapshot("{\"schema_version\": 99, \"connections\": []}").unwrap_err();
+        assert!(error.contains("schema version 99"));
+    }
+
+    #[test]
+    fn test_diff_snapshot() {
+        let snapshot = vec![connection("80", "42"), connection("8080", "43")];
+        let current = vec![connection("80", "42"), connection("443", "42")];
+        let diff = diff_snapshot(current, snapshot);
+
+        let ports: Vec<(&str, Option<DiffStatus>)> = diff
+            .iter()
+            .map(|connection| (connection.local_port.as_str(), connection.diff))
+            .collect();
+        assert_eq!(
+            ports,
+            vec![
+                ("443", Some(DiffStatus::Added)),
+                ("8080", Some(DiffStatus::Removed))
+            ]
+        );
+        assert!(
+            diff_snapshot(vec![connection("80", "42")], vec![connection("80", "42")]).is_empty()
+        );
+    }
+}
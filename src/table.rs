@@ -1,9 +1,211 @@
-use termimad::crossterm::style::{Attribute::*, Color::*};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use termimad::crossterm::style::{Attribute, Attribute::*, Color, Color::*};
+use termimad::crossterm::style::{SetAttribute, SetForegroundColor};
 use termimad::*;
 
-use crate::schemas::{AddressType, Connection};
+use crate::connections;
+use crate::schemas::{
+    AddressType, BorderStyle, Connection, ConnectionGroup, DiffStatus, GroupField, SortField,
+    StateCategory, Theme,
+};
 use crate::utils;
 
+/// The maximum number of characters of a command in outputs without a width, e.g `--markdown`.
+const MAX_COMMAND_LENGTH: usize = 40;
+/// The minimum number of characters a shortened program or command keeps, even in very narrow terminals.
+const MIN_TRUNCATED_LENGTH: usize = 8;
+
+/// Listening ports from this port on are considered unusual by `--highlight-suspicious`.
+const SUSPICIOUS_MIN_PORT: u16 = 1024;
+/// Program names with at most this many characters are considered unusual by `--highlight-suspicious`, e.g "nc" or a dropped binary called "x".
+const SUSPICIOUS_MAX_PROGRAM_LENGTH: usize = 2;
+/// The number of matched heuristics from which on a listening socket is highlighted yellow.
+const SUSPICIOUS_WARNING_SCORE: usize = 2;
+/// The number of matched heuristics from which on a listening socket is highlighted red.
+const SUSPICIOUS_DANGER_SCORE: usize = 3;
+/// Marks the start of a colored cell in the Markdown of the table, it is replaced by the color once the table is rendered.
+/// termimad has no styles for single cells, but zero width characters don't change the width of a cell.
+const COLOR_START: char = '\u{200B}';
+/// Marks the end of a colored cell, it is replaced by resetting the style.
+const COLOR_END: char = '\u{200C}';
+/// The width of output which isn't a terminal, every column then gets the space it prefers and nothing is dropped or shortened.
+pub const UNLIMITED_WIDTH: u16 = u16::MAX;
+/// The columns dropped first, in this order, if the table doesn't fit into the terminal.
+const DROPPABLE_COLUMNS: [Column; 2] = [Column::State, Column::Proto];
+/// The colors of the `--highlight` terms, cycled if there are more terms.
+const HIGHLIGHT_COLORS: [Color; 4] = [Yellow, Cyan, Magenta, Green];
+/// The vertical border between two cells, a highlighted match never spans multiple cells.
+const CELL_BORDER: char = '│';
+/// The box-drawing characters termimad draws the tables with, and their ASCII replacements for `--border ascii`.
+const ASCII_BORDERS: [(char, char); 11] = [
+    ('┌', '+'),
+    ('┬', '+'),
+    ('┐', '+'),
+    ('├', '+'),
+    ('┼', '+'),
+    ('┤', '+'),
+    ('└', '+'),
+    ('┴', '+'),
+    ('┘', '+'),
+    ('─', '-'),
+    (CELL_BORDER, '|'),
+];
+/// The invisible character the filling row of a table is made of, see `fill_terminal_width`.
+const FILLING_CHARACTER: char = '\u{2800}';
+
+/// The border style of all tables, decided once at startup by `--border` and the locale.
+static BORDER_STYLE: OnceLock<BorderStyle> = OnceLock::new();
+
+/// Options which control which optional columns are shown in the table.
+#[derive(Debug, Default, Clone)]
+pub struct TableOptions {
+    /// Whether to add a column with the command line of each process.
+    pub show_command: bool,
+    /// Whether to add a column with the GeoIP country of each remote address.
+    pub show_country: bool,
+    /// Whether to add a column with the container of each process.
+    pub show_container: bool,
+    /// Whether to add a column with the executable of each process.
+    pub show_exe: bool,
+    /// Whether to add a column with the systemd unit of each process.
+    pub show_unit: bool,
+    /// Whether to add a column with the exposure of each socket.
+    pub show_exposure: bool,
+    /// Whether to add a column with the network namespace of each connection.
+    pub show_netns: bool,
+    /// Whether to add a column with the socket inode of each connection.
+    pub show_inode: bool,
+    /// Whether to add columns with the received and sent bytes of each connection.
+    pub show_stats: bool,
+    /// Whether to add columns with the receive and send queues and the retransmits of each socket.
+    pub show_queues: bool,
+    /// Whether to add a column with the age of each connection.
+    pub show_age: bool,
+    /// Whether to add a column with the number of identical rows collapsed into each row.
+    pub show_duplicates: bool,
+    /// Whether to add a column which marks the connections added or removed since a `--diff` snapshot.
+    pub show_diff: bool,
+    /// Whether to highlight listening sockets which look suspicious.
+    pub highlight_suspicious: bool,
+    /// The columns selected with `--columns`, replacing or changing the columns chosen by the other options.
+    pub columns: Option<ColumnSelection>,
+    /// Whether to show long programs and commands in full instead of shortening them.
+    pub full: bool,
+    /// The colors of the proto and state cells.
+    pub theme: Theme,
+    /// The terms highlighted wherever they appear in a cell, without hiding any row.
+    pub highlight: Vec<String>,
+    /// The width set with `--width`, overriding the width of the terminal.
+    pub width: Option<u16>,
+    /// Whether to print the totals per protocol below the table, turned off with `--no-summary`.
+    pub show_summary: bool,
+    /// The host printed below the table with `--show-host`, `None` to leave it out.
+    pub host: Option<String>,
+}
+
+/// Options which control how a table is rendered into a string.
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    /// Which columns to show and which rows to highlight.
+    pub table: TableOptions,
+    /// Whether to use colors and other text styles.
+    pub color: bool,
+    /// The width of the table in characters, `UNLIMITED_WIDTH` if the output isn't a terminal.
+    pub width: u16,
+    /// The characters the borders are drawn with.
+    pub border: BorderStyle,
+}
+
+impl RenderOptions {
+    /// Creates the options to render a table for the current terminal, using its width and the color setting of the user.
+    /// The width is taken from `--width`, the `COLUMNS` environment variable or the terminal, in this order.
+    /// Output which isn't a terminal, e.g a pipe, has no width limit.
+    ///
+    /// # Arguments
+    /// * `table`: Which columns to show and which rows to highlight.
+    ///
+    /// # Returns
+    /// The render options.
+    pub fn for_terminal(table: TableOptions) -> Self {
+        let columns = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.trim().parse::<u16>().ok())
+            .filter(|columns| *columns > 0);
+        let width = match table.width.or(columns) {
+            Some(width) => width,
+            None if std::io::stdout().is_terminal() => terminal_size().0,
+            None => UNLIMITED_WIDTH,
+        };
+        RenderOptions {
+            table,
+            color: utils::is_color_enabled(),
+            width,
+            border: get_border_style(),
+        }
+    }
+
+    /// Checks whether the table has no width limit, because the output isn't a terminal.
+    ///
+    /// # Returns
+    /// `true` if the width is unlimited, `false` if not.
+    pub fn is_unlimited(&self) -> bool {
+        self.width == UNLIMITED_WIDTH
+    }
+
+    /// The width over which the columns of a table are spread, without a limit every column gets the space it prefers.
+    ///
+    /// # Arguments
+    /// * `max_column_spaces`: The preferred space of every column.
+    ///
+    /// # Returns
+    /// The width to fill.
+    fn fill_width(&self, max_column_spaces: &[u16]) -> u16 {
+        if self.is_unlimited() {
+            max_column_spaces.iter().sum()
+        } else {
+            self.width
+        }
+    }
+}
+
+/// The rows of the table which are highlighted, e.g by the watch mode.
+#[derive(Debug, Default)]
+pub struct HighlightedRows {
+    /// The indices of the connections which are new, highlighted yellow.
+    pub new: HashSet<usize>,
+    /// The indices of the connections which are gone, dimmed and not counted.
+    pub closed: HashSet<usize>,
+    /// The index of the connection selected in the interactive mode, shown in bold.
+    pub selected: Option<usize>,
+}
+
+/// How a row of the table is formatted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowStyle {
+    Normal,
+    /// `inline code` formatted, ie. yellow.
+    Highlighted,
+    /// ~~strikeout~~ formatted, ie. red.
+    Danger,
+    /// *italic* formatted, ie. gray, for connections which are gone.
+    Closed,
+    /// **bold** formatted, ie. cyan, for the selected connection.
+    Selected,
+}
+
+/// Gets the current time as a Unix timestamp in seconds, used to calculate the age of connections.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
 /// Uses the termimad crate to create a custom appearence for Mardown text in the console.
 ///
 /// # Appearence
@@ -12,25 +214,324 @@ use crate::utils;
 /// * ~~strikeout~~ text -> not striked out, red and blinking
 /// * `inline code` text -> not code formatted, yellow
 ///
+/// Without colors, a skin without any styles is used.
+/// Tables are centered in the terminal, without a width limit they are aligned left instead of being padded.
+///
 /// # Arguments
-/// None
+/// * `options`: Whether colors and other text styles should be used and the width of the output.
 ///
 /// # Returns
 /// A custom markdow "skin".
-fn create_table_style() -> MadSkin {
+fn create_table_style(options: &RenderOptions) -> MadSkin {
+    let table_align = if options.is_unlimited() {
+        Alignment::Left
+    } else {
+        Alignment::Center
+    };
+    if !options.color {
+        let mut skin = MadSkin::no_style();
+        skin.paragraph.align = Alignment::Left;
+        skin.table.align = table_align;
+        return skin;
+    }
+
     let mut skin = MadSkin::default();
     skin.bold.set_fg(Cyan);
     skin.italic.set_fg(gray(11));
     skin.strikeout = CompoundStyle::new(Some(Red), None, RapidBlink.into());
     skin.paragraph.align = Alignment::Left;
-    skin.table.align = Alignment::Center;
+    skin.table.align = table_align;
     skin.inline_code = CompoundStyle::new(Some(Yellow), None, Encircled.into());
 
-    return skin;
+    skin
 }
 
-/// Marks localhost and unspecified IP addresses (ie. 0.0.0.0) using Markdown formatting.
+/// Sets the border style of all tables, e.g from `--border`. Only the first call has an effect.
+///
+/// # Arguments
+/// * `border`: The border style.
+///
+/// # Returns
+/// None
+pub fn set_border_style(border: BorderStyle) {
+    let _ = BORDER_STYLE.set(border);
+}
+
+/// Gets the border style of all tables, Unicode unless `set_border_style` chose another one.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// The border style.
+pub fn get_border_style() -> BorderStyle {
+    BORDER_STYLE.get().copied().unwrap_or_default()
+}
+
+/// Chooses the border style if `--border` isn't given: box-drawing characters, unless the locale isn't UTF-8.
+/// The locale is the first set variable of `LC_ALL`, `LC_CTYPE` and `LANG`, if none is set the terminal is assumed to handle UTF-8.
+///
+/// # Arguments
+/// * `locale`: The value of the locale variable, e.g "en_US.UTF-8" or "C".
+///
+/// # Example
+/// ```ignore
+/// assert_eq!(default_border_style(Some("de_DE.UTF-8")), BorderStyle::Unicode);
+/// assert_eq!(default_border_style(Some("POSIX")), BorderStyle::Ascii);
+/// ```
+///
+/// # Returns
+/// `BorderStyle::Ascii` if the locale isn't UTF-8, `BorderStyle::Unicode` if it is or if there is no locale.
+pub fn default_border_style(locale: Option<&str>) -> BorderStyle {
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_ascii_lowercase();
+            if locale.contains("utf-8") || locale.contains("utf8") {
+                BorderStyle::Unicode
+            } else {
+                BorderStyle::Ascii
+            }
+        }
+        None => BorderStyle::Unicode,
+    }
+}
+
+/// Redraws the borders of a rendered table with the characters of a border style.
+/// Without borders, the lines which only drew borders or the filling row are removed and the columns stay aligned by whitespace.
+///
+/// # Arguments
+/// * `table`: The table rendered by termimad, it may contain terminal codes.
+/// * `border`: The border style to use.
+///
+/// # Returns
+/// The table with the new borders.
+fn apply_border_style(table: &str, border: BorderStyle) -> String {
+    if border == BorderStyle::Unicode {
+        return table.to_string();
+    }
+
+    let replace = |character: char| {
+        if character == FILLING_CHARACTER {
+            return ' ';
+        }
+        match ASCII_BORDERS
+            .iter()
+            .find(|(unicode, _)| *unicode == character)
+        {
+            Some(_) if border == BorderStyle::None => ' ',
+            Some((_, ascii)) => *ascii,
+            None => character,
+        }
+    };
+    let lines: Vec<String> = table
+        .split('\n')
+        .map(|line| line.chars().map(replace).collect())
+        .filter(|line: &String| border != BorderStyle::None || !is_blank_line(line))
+        .collect();
+    lines.join("\n")
+}
+
+/// Checks if a line has no visible characters besides whitespace, terminal codes are ignored.
+///
+/// # Arguments
+/// * `line`: The line to check.
+///
+/// # Returns
+/// `true` if nothing visible is left on the line.
+fn is_blank_line(line: &str) -> bool {
+    let mut in_code = false;
+    for character in line.chars() {
+        match character {
+            '\x1b' => in_code = true,
+            // a terminal code ends with its first letter
+            _ if in_code => in_code = !character.is_ascii_alphabetic(),
+            _ if !character.is_whitespace() => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Gets the color of a proto or state cell.
+/// This is the only mapping from protocols and states to colors, new states or themes only need to be added here.
+///
+/// # Arguments
+/// * `column`: The column of the cell, only the proto and state columns are colored.
+/// * `connection`: The connection of the row.
+/// * `theme`: The theme selected by the user.
+///
+/// # Returns
+/// The color of the cell or `None` if the cell isn't colored.
+fn get_cell_color(column: Column, connection: &Connection, theme: Theme) -> Option<Color> {
+    let (default, high_contrast) = match column {
+        Column::Proto if connection.proto.starts_with("tcp") => (DarkCyan, Cyan),
+        Column::Proto if connection.proto.starts_with("udp") => (DarkMagenta, Magenta),
+        Column::State => match connections::state_category(&connection.state)? {
+            StateCategory::Listening => (DarkGreen, Green),
+            StateCategory::Established => (DarkBlue, White),
+            StateCategory::Waiting => (DarkYellow, Yellow),
+            StateCategory::Closing => (DarkRed, Red),
+        },
+        _ => return None,
+    };
+
+    match theme {
+        Theme::Default => Some(default),
+        Theme::HighContrast => Some(high_contrast),
+        Theme::Mono => None,
+    }
+}
+
+/// Replaces the markers of the colored cells in a rendered table with the terminal codes of their colors.
+///
+/// # Arguments
+/// * `table`: The rendered table.
+/// * `colors`: The color of every marked cell, in the order of the cells in the table.
+/// * `theme`: The theme selected by the user, the high contrast theme also uses bold text.
+///
+/// # Returns
+/// The table with colored cells.
+fn apply_cell_colors(table: &str, colors: &[Color], theme: Theme) -> String {
+    let mut colors = colors.iter();
+    let mut colored = String::with_capacity(table.len());
+    for character in table.chars() {
+        match character {
+            COLOR_START => {
+                if let Some(color) = colors.next() {
+                    colored.push_str(&SetForegroundColor(*color).to_string());
+                }
+                if theme == Theme::HighContrast {
+                    colored.push_str(&SetAttribute(Bold).to_string());
+                }
+            }
+            COLOR_END => colored.push_str(&SetAttribute(Attribute::Reset).to_string()),
+            _ => colored.push(character),
+        }
+    }
+    colored
+}
+
+/// Finds the matches of the `--highlight` terms in a line of a rendered table, ignoring the case.
+/// The terms are matched from left to right and the first term wins if several match at the same position.
+///
+/// # Arguments
+/// * `visible`: The visible characters of the line.
+/// * `terms`: The terms to highlight.
+///
+/// # Returns
+/// The start, end and term index of every match, measured in visible characters.
+fn find_highlights(visible: &[char], terms: &[Vec<char>]) -> Vec<(usize, usize, usize)> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < visible.len() {
+        let found = terms.iter().enumerate().find(|(_, term)| {
+            let window = visible.get(start..start + term.len());
+            window.is_some_and(|window| {
+                window
+                    .iter()
+                    .zip(term.iter())
+                    .all(|(character, term_character)| {
+                        *character != CELL_BORDER && character.eq_ignore_ascii_case(term_character)
+                    })
+            })
+        });
+
+        match found {
+            Some((term_idx, term)) => {
+                matches.push((start, start + term.len(), term_idx));
+                start += term.len();
+            }
+            None => start += 1,
+        }
+    }
+    matches
+}
+
+/// Highlights the `--highlight` terms in a rendered table, like `grep --color`.
+/// Every term gets its own color which is shown inverted, after a match the style of the surrounding text is restored.
+///
+/// # Arguments
+/// * `table`: The rendered table, including the terminal codes of its styles.
+/// * `terms`: The terms to highlight, empty terms are ignored.
+///
+/// # Returns
+/// The table with highlighted matches.
+fn apply_highlights(table: &str, terms: &[String]) -> String {
+    let terms: Vec<Vec<char>> = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .map(|term| term.chars().collect())
+        .collect();
+    if terms.is_empty() {
+        return table.to_string();
+    }
+
+    let reset = SetAttribute(Attribute::Reset).to_string();
+    let mut highlighted = String::with_capacity(table.len());
+    for (line_idx, line) in table.split('\n').enumerate() {
+        if line_idx > 0 {
+            highlighted.push('\n');
+        }
+
+        // split the line into terminal codes and visible characters
+        let mut segments: Vec<(bool, &str)> = Vec::new();
+        let mut rest = line;
+        while let Some(character) = rest.chars().next() {
+            let length = match rest.strip_prefix("\x1b[") {
+                Some(code) => code
+                    .find(|c: char| ('@'..='~').contains(&c))
+                    .map_or(rest.len(), |end| end + 3),
+                None => character.len_utf8(),
+            };
+            segments.push((rest.starts_with('\x1b'), &rest[..length]));
+            rest = &rest[length..];
+        }
+
+        let visible: Vec<char> = segments
+            .iter()
+            .filter(|(is_code, _)| !is_code)
+            .filter_map(|(_, segment)| segment.chars().next())
+            .collect();
+        let mut matches = find_highlights(&visible, &terms).into_iter().peekable();
+
+        // the styles since the last reset, replayed after a match
+        let mut active_styles: Vec<&str> = Vec::new();
+        let mut visible_idx = 0;
+        for (is_code, segment) in segments {
+            if is_code {
+                if segment == reset || segment == "\x1b[m" {
+                    active_styles.clear();
+                } else if segment.ends_with('m') {
+                    active_styles.push(segment);
+                }
+                highlighted.push_str(segment);
+                continue;
+            }
+
+            if let Some((start, _, term_idx)) = matches.peek() {
+                if *start == visible_idx {
+                    let color = HIGHLIGHT_COLORS[term_idx % HIGHLIGHT_COLORS.len()];
+                    highlighted.push_str(&SetForegroundColor(color).to_string());
+                    highlighted.push_str(&SetAttribute(Bold).to_string());
+                    highlighted.push_str(&SetAttribute(Reverse).to_string());
+                }
+            }
+            highlighted.push_str(segment);
+            visible_idx += 1;
+            if let Some((_, end, _)) = matches.peek() {
+                if *end == visible_idx {
+                    matches.next();
+                    highlighted.push_str(&reset);
+                    highlighted.extend(active_styles.iter().copied());
+                }
+            }
+        }
+    }
+    highlighted
+}
 
+/// Marks localhost and unspecified IP addresses (ie. 0.0.0.0) using Markdown formatting.
+///
 /// * `address_type` == Localhost -> *italic* + "localhost"
 /// * `address_type` == Unspecified -> *italic*
 /// * `address_type` == Extern -> not formatted
@@ -61,111 +562,2674 @@ fn format_known_address(remote_address: &String, address_type: &AddressType) ->
     }
 }
 
-/// Creates a Markdown table row with just empty characters with the width of the terminal window.
+/// Formats a port together with the name of its service, if it is known.
 ///
-/// # Argument
-/// * `terminal_width`: The current width of the terminal.
-/// * `max_column_spaces`: An array in which the values represent the max-width of each of the 7 Markdown table rows.
+/// # Arguments
+/// * `port`: The port number.
+/// * `service`: The service name set by `--services`.
+///
+/// # Example
+/// ```
+/// assert_eq!(format_port("443", &Some("https".to_string())), "443 (https)");
+/// assert_eq!(format_port("8080", &None), "8080");
+/// ```
 ///
 /// # Returns
-/// A Markdown table row string in which each column is filled with as much empty characters needed to fit in content and as well fill out the terminal width.
-fn fill_terminal_width(terminal_width: u16, max_column_spaces: [u16; 7]) -> String {
-    let total_column_spaces: u16 = max_column_spaces.iter().sum();
-
-    let calculate_column_width = |column_space: u16| {
-        (column_space as f64 / total_column_spaces as f64) * (terminal_width as f64)
-    };
-    let empty_character = "\u{2800}";
+/// The port followed by the service in parentheses or just the port.
+fn format_port(port: &str, service: &Option<String>) -> String {
+    match service {
+        Some(service) => format!("{} ({})", port, service),
+        None => port.to_string(),
+    }
+}
 
-    let mut row: String = String::new();
-    for &max_column_space in &max_column_spaces {
-        row.push_str(&format!(
-            "| {} ",
-            empty_character.repeat(calculate_column_width(max_column_space) as usize)
-        ));
+/// Formats the protocol of a socket the way the table shows it, with a "6" suffix for IPv6 sockets.
+/// The structured output keeps the protocol and the family apart, the table combines them to save a column.
+///
+/// # Arguments
+/// * `proto`: The protocol, e.g "tcp".
+/// * `family`: The address family, "inet" or "inet6".
+///
+/// # Example
+/// ```
+/// assert_eq!(format_protocol("tcp", "inet6"), "tcp6");
+/// assert_eq!(format_protocol("udp", "inet"), "udp");
+/// ```
+///
+/// # Returns
+/// The protocol followed by "6" for IPv6 or just the protocol.
+fn format_protocol(proto: &str, family: &str) -> String {
+    if family == "inet6" && !proto.ends_with('6') {
+        format!("{}6", proto)
+    } else {
+        proto.to_string()
     }
-    row.push_str("|\n");
+}
 
-    return row;
+/// Represents a column of the connections table.
+/// `PidProgram` is the combined column of the default table, `Pid` and `Program` can only be selected with `--columns`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Index,
+    Diff,
+    Proto,
+    LocalAddress,
+    LocalPort,
+    Exposure,
+    RemoteAddress,
+    RemotePort,
+    Country,
+    PidProgram,
+    Pid,
+    Program,
+    Exe,
+    Container,
+    Unit,
+    Netns,
+    Inode,
+    State,
+    RxBytes,
+    TxBytes,
+    RecvQueue,
+    SendQueue,
+    Retransmits,
+    Age,
+    Duplicates,
+    User,
+    Command,
 }
 
-/// Prints all current connections in a pretty Markdown table.
+/// The names of all columns which can be selected with `--columns`, in the order of the default table.
+const COLUMN_NAMES: [(&str, Column); 26] = [
+    ("index", Column::Index),
+    ("diff", Column::Diff),
+    ("proto", Column::Proto),
+    ("local_address", Column::LocalAddress),
+    ("local_port", Column::LocalPort),
+    ("exposure", Column::Exposure),
+    ("remote_address", Column::RemoteAddress),
+    ("remote_port", Column::RemotePort),
+    ("country", Column::Country),
+    ("pid", Column::Pid),
+    ("program", Column::Program),
+    ("exe", Column::Exe),
+    ("container", Column::Container),
+    ("unit", Column::Unit),
+    ("netns", Column::Netns),
+    ("inode", Column::Inode),
+    ("user", Column::User),
+    ("state", Column::State),
+    ("rx", Column::RxBytes),
+    ("tx", Column::TxBytes),
+    ("recv_q", Column::RecvQueue),
+    ("send_q", Column::SendQueue),
+    ("retrans", Column::Retransmits),
+    ("age", Column::Age),
+    ("dupes", Column::Duplicates),
+    ("command", Column::Command),
+];
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = value.trim().to_ascii_lowercase().replace('-', "_");
+        COLUMN_NAMES
+            .iter()
+            .find(|(column_name, _)| *column_name == name)
+            .map(|(_, column)| *column)
+            .ok_or_else(|| {
+                let valid_names: Vec<&str> = COLUMN_NAMES.iter().map(|(name, _)| *name).collect();
+                format!(
+                    "'{}' is not a valid column, valid columns are: {}",
+                    value,
+                    valid_names.join(", ")
+                )
+            })
+    }
+}
+
+/// Lists every column which can be selected with `--columns` together with what it shows, whether it can be used with `--sort` and what it needs, e.g for `--list-fields`.
+/// The list is generated from the columns of the table, so that new columns show up without extra work.
 ///
 /// # Arguments
-/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// None
 ///
 /// # Returns
-/// None
-pub fn print_connections_table(all_connections: &Vec<Connection>) {
-    let skin: MadSkin = create_table_style();
-    let (terminal_width, _) = terminal_size();
+/// One line per column below a header, every line ends with a line break.
+pub fn list_fields() -> String {
+    let mut lines = format!("{:<16}{:<6}{}\n", "FIELD", "SORT", "DESCRIPTION");
+    for (name, column) in COLUMN_NAMES {
+        let sortable = SortField::from_str(name, true).is_ok();
+        let description = match column.requirement() {
+            Some(requirement) => format!("{} ({})", column.description(), requirement),
+            None => column.description().to_string(),
+        };
+        lines.push_str(&format!(
+            "{:<16}{:<6}{}\n",
+            name,
+            if sortable { "yes" } else { "-" },
+            description
+        ));
+    }
+    lines
+}
 
-    // Add table headers
-    static CENTER_MARKDOWN_ROW: &str = "| :-: | :-: | :-: | :-: | :-: | :-: | :-: |\n";
-    let mut markdown = CENTER_MARKDOWN_ROW.to_string();
-    markdown.push_str("| **#** | **proto** | **local port** | **remote address** | **remote port** | **pid** *program* | **state** |\n");
+/// Represents the columns selected by the user with `--columns`.
+///
+/// # Variants
+/// * `Exact`: Shows exactly these columns in this order, e.g "local_port,program,command".
+/// * `Relative`: Adds (`true`) or removes (`false`) columns from the default columns, e.g "+command,-proto".
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSelection {
+    Exact(Vec<Column>),
+    Relative(Vec<(bool, Column)>),
+}
 
-    // iterate over all connections to build the table
-    for (idx, connection) in all_connections.iter().enumerate() {
-        markdown.push_str(CENTER_MARKDOWN_ROW);
+impl FromStr for ColumnSelection {
+    type Err = String;
 
-        let formatted_remote_address: String =
-            format_known_address(&connection.remote_address, &connection.address_type);
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let names: Vec<&str> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        if names.is_empty() {
+            return Err("at least one column is required".to_string());
+        }
 
-        markdown.push_str(&format!(
-            "| *{}* | {} | {} | {} | {} | {} *{}* | {} |\n",
-            idx + 1,
-            connection.proto,
-            connection.local_port,
-            &formatted_remote_address,
-            connection.remote_port,
-            connection.pid,
-            connection.program,
-            connection.state
-        ));
+        let relative = |name: &str| name.starts_with('+') || name.starts_with('-');
+        if names.iter().all(|name| relative(name)) {
+            names
+                .iter()
+                .map(|name| Ok((name.starts_with('+'), name[1..].parse::<Column>()?)))
+                .collect::<Result<Vec<(bool, Column)>, String>>()
+                .map(ColumnSelection::Relative)
+        } else if names.iter().any(|name| relative(name)) {
+            Err(format!(
+                "'{}' mixes columns with added (+) or removed (-) columns",
+                value
+            ))
+        } else {
+            names
+                .iter()
+                .map(|name| name.parse::<Column>())
+                .collect::<Result<Vec<Column>, String>>()
+                .map(ColumnSelection::Exact)
+        }
+    }
+}
+
+impl Column {
+    /// A one-line description of what the column shows, for `--list-fields`.
+    fn description(&self) -> &'static str {
+        match self {
+            Column::Index => "the number of the row",
+            Column::Diff => "whether the connection was added or removed since the snapshot",
+            Column::Proto => "the protocol of the socket, e.g tcp or udp6",
+            Column::LocalAddress => "the local IP address",
+            Column::LocalPort => "the local port",
+            Column::Exposure => "who can reach a listening socket, e.g public or loopback",
+            Column::RemoteAddress => "the remote IP address",
+            Column::RemotePort => "the remote port",
+            Column::Country => "the country of the remote address",
+            Column::PidProgram => "the PID and the program of the owning process",
+            Column::Pid => "the PID of the owning process",
+            Column::Program => "the program of the owning process",
+            Column::Exe => "the path of the executable of the process",
+            Column::Container => "the container of the process",
+            Column::Unit => "the systemd unit of the process",
+            Column::Netns => "the network namespace of the socket",
+            Column::Inode => "the inode of the socket",
+            Column::State => "the state of the connection, e.g listen or established",
+            Column::RxBytes => "the bytes received over the connection",
+            Column::TxBytes => "the bytes sent over the connection",
+            Column::RecvQueue => {
+                "the bytes not read by the program yet, or the connections waiting to be accepted"
+            }
+            Column::SendQueue => "the bytes not acknowledged by the peer yet",
+            Column::Retransmits => "the retransmits of the unacknowledged data of a TCP connection",
+            Column::Age => "how long the connection or its process exists",
+            Column::Duplicates => "how many identical rows were collapsed into the row",
+            Column::User => "the user owning the process",
+            Column::Command => "the command line of the process",
+        }
     }
 
-    // Create an empty row that forces the table to fit the terminal with respect to how much space ...
-    // ... each column should receive based on the max length of each column (in the array below)
-    let max_column_spaces: [u16; 7] = [5, 8, 8, 28, 7, 24, 13];
-    let terminal_filling_row: String = fill_terminal_width(terminal_width, max_column_spaces);
-    markdown.push_str(&terminal_filling_row);
-    markdown.push_str(CENTER_MARKDOWN_ROW);
+    /// What the column needs to show anything but `-`, e.g a flag or privileges, for `--list-fields`.
+    fn requirement(&self) -> Option<&'static str> {
+        match self {
+            Column::Diff => Some("needs --diff"),
+            Column::Exposure => Some("needs --exposure"),
+            Column::Country => Some("needs --geoip"),
+            Column::PidProgram
+            | Column::Pid
+            | Column::Program
+            | Column::User
+            | Column::Command
+            | Column::Exe
+            | Column::Age => Some("needs root for the processes of other users"),
+            Column::Container => Some("needs --containers, Linux only"),
+            Column::Unit => Some("needs --units, Linux only"),
+            Column::Netns => Some("needs --all-netns or --netns, Linux only"),
+            Column::Inode => Some("Linux only"),
+            Column::RxBytes | Column::TxBytes => Some("needs --stats, Linux only"),
+            Column::RecvQueue | Column::SendQueue | Column::Retransmits => Some("Linux only"),
+            Column::Duplicates => Some("needs --count-dupes"),
+            _ => None,
+        }
+    }
+
+    /// The Markdown formatted header of the column.
+    fn header(&self) -> &'static str {
+        match self {
+            Column::Index => "**#**",
+            Column::Diff => "**diff**",
+            Column::Proto => "**proto**",
+            Column::LocalAddress => "**local address**",
+            Column::LocalPort => "**local port**",
+            Column::Exposure => "**exposure**",
+            Column::RemoteAddress => "**remote address**",
+            Column::RemotePort => "**remote port**",
+            Column::Country => "**country**",
+            Column::PidProgram => "**pid** *program*",
+            Column::Pid => "**pid**",
+            Column::Program => "**program**",
+            Column::Exe => "**exe**",
+            Column::Container => "**container**",
+            Column::Unit => "**unit**",
+            Column::Netns => "**netns**",
+            Column::Inode => "**inode**",
+            Column::State => "**state**",
+            Column::RxBytes => "**rx**",
+            Column::TxBytes => "**tx**",
+            Column::RecvQueue => "**recv-q**",
+            Column::SendQueue => "**send-q**",
+            Column::Retransmits => "**retrans**",
+            Column::Age => "**age**",
+            Column::Duplicates => "**dupes**",
+            Column::User => "**user**",
+            Column::Command => "**command**",
+        }
+    }
+
+    /// The share of the terminal width the column should receive, based on the max length of its content.
+    fn max_space(&self) -> u16 {
+        match self {
+            Column::Index => 5,
+            Column::Diff => 5,
+            Column::Proto => 8,
+            Column::LocalAddress => 28,
+            Column::LocalPort => 8,
+            Column::Exposure => 14,
+            Column::RemoteAddress => 28,
+            Column::RemotePort => 7,
+            Column::Country => 7,
+            Column::PidProgram => 24,
+            Column::Pid => 8,
+            Column::Program => 18,
+            Column::Exe => 30,
+            Column::Container => 14,
+            Column::Unit => 20,
+            Column::Netns => 12,
+            Column::Inode => 10,
+            Column::State => 13,
+            Column::RxBytes => 10,
+            Column::TxBytes => 10,
+            Column::RecvQueue => 10,
+            Column::SendQueue => 10,
+            Column::Retransmits => 8,
+            Column::Age => 19,
+            Column::Duplicates => 6,
+            Column::User => 10,
+            Column::Command => 40,
+        }
+    }
+
+    /// Whether long values of the column are shortened, addresses and ports are always shown in full.
+    fn is_truncated(&self) -> bool {
+        matches!(
+            self,
+            Column::PidProgram | Column::Program | Column::Exe | Column::Command
+        )
+    }
+
+    /// The unformatted text of the column for a connection.
+    /// Programs are shortened in the middle to keep the name of the binary, commands at the end.
+    ///
+    /// # Arguments
+    /// * `idx`: The index of the connection in the table.
+    /// * `connection`: The connection to get the text for.
+    /// * `max_length`: The maximum number of characters of a truncated column, `None` to show it in full.
+    fn text(&self, idx: usize, connection: &Connection, max_length: Option<usize>) -> String {
+        match self {
+            Column::Index => (idx + 1).to_string(),
+            Column::Diff => match connection.diff {
+                Some(DiffStatus::Added) => "+".to_string(),
+                Some(DiffStatus::Removed) => "-".to_string(),
+                None => " ".to_string(),
+            },
+            Column::Proto => format_protocol(&connection.proto, &connection.family),
+            Column::LocalAddress => connection.local_address.to_string(),
+            Column::LocalPort => format_port(&connection.local_port, &connection.service),
+            Column::Exposure => connection
+                .exposure
+                .and_then(|exposure| exposure.to_possible_value())
+                .map_or("-".to_string(), |value| value.get_name().to_string()),
+            Column::RemoteAddress => connection
+                .remote_hostname
+                .as_ref()
+                .unwrap_or(&connection.remote_address)
+                .to_string(),
+            Column::RemotePort => format_port(&connection.remote_port, &connection.remote_service),
+            Column::Country => connection.country.as_deref().unwrap_or("-").to_string(),
+            Column::PidProgram => format!(
+                "{} {}",
+                connection.pid,
+                shorten_program(connection, max_length)
+            ),
+            Column::Pid => connection.pid.to_string(),
+            Column::Program => shorten(&connection.program, max_length, utils::truncate_middle),
+            Column::Exe => match &connection.exe_path {
+                Some(path) if connection.exe_deleted => shorten(
+                    &format!("{} (deleted)", path),
+                    max_length,
+                    utils::truncate_middle,
+                ),
+                Some(path) => shorten(path, max_length, utils::truncate_middle),
+                None => "-".to_string(),
+            },
+            Column::Container => connection.container.as_deref().unwrap_or("-").to_string(),
+            Column::Unit => connection.unit.as_deref().unwrap_or("-").to_string(),
+            Column::Netns => connection.netns.as_deref().unwrap_or("-").to_string(),
+            Column::Inode => connection
+                .inode
+                .map(|inode| inode.to_string())
+                .unwrap_or("-".to_string()),
+            Column::State => connection.state.to_string(),
+            Column::RxBytes => connection
+                .rx_bytes
+                .map(utils::format_bytes)
+                .unwrap_or("-".to_string()),
+            Column::TxBytes => connection
+                .tx_bytes
+                .map(utils::format_bytes)
+                .unwrap_or("-".to_string()),
+            // the receive queue of a listening socket counts connections, not bytes
+            Column::RecvQueue if connection.state == "listen" => connection
+                .rx_queue
+                .map(|queue| queue.to_string())
+                .unwrap_or("-".to_string()),
+            Column::RecvQueue => connection
+                .rx_queue
+                .map(|queue| utils::format_bytes(queue.into()))
+                .unwrap_or("-".to_string()),
+            Column::SendQueue => connection
+                .tx_queue
+                .map(|queue| utils::format_bytes(queue.into()))
+                .unwrap_or("-".to_string()),
+            Column::Retransmits => connection
+                .retransmits
+                .map(|retransmits| retransmits.to_string())
+                .unwrap_or("-".to_string()),
+            Column::Age => match (&connection.created_formatted, connection.created) {
+                (Some(formatted), _) => formatted.to_string(),
+                (None, Some(created)) => utils::format_duration(unix_now().saturating_sub(created)),
+                (None, None) => "-".to_string(),
+            },
+            Column::Duplicates => connection
+                .duplicates
+                .map(|duplicates| duplicates.to_string())
+                .unwrap_or("-".to_string()),
+            Column::User => connection.user.as_deref().unwrap_or("-").to_string(),
+            // arguments can contain line breaks which would break the table
+            Column::Command => shorten(
+                &connection
+                    .command
+                    .as_deref()
+                    .unwrap_or("-")
+                    .replace(char::is_control, " "),
+                max_length,
+                utils::truncate_with_ellipsis,
+            ),
+        }
+    }
 
-    println!("{}", skin.term_text(&markdown));
+    /// The Markdown formatted cell of the column for a connection.
+    ///
+    /// # Arguments
+    /// * `idx`: The index of the connection in the table.
+    /// * `connection`: The connection to create the cell for.
+    /// * `style`: How the row is formatted, highlighted rows format the whole cell.
+    /// * `max_length`: The maximum number of characters of a truncated column, `None` to show it in full.
+    fn cell(
+        &self,
+        idx: usize,
+        connection: &Connection,
+        style: RowStyle,
+        max_length: Option<usize>,
+    ) -> String {
+        match style {
+            RowStyle::Highlighted => {
+                return format!("`{}`", self.text(idx, connection, max_length))
+            }
+            RowStyle::Danger => {
+                return format!(
+                    "~~{}~~",
+                    utils::escape_markdown(&self.text(idx, connection, max_length))
+                )
+            }
+            // closed connections have no index, so they can be told apart without colors
+            RowStyle::Closed if *self == Column::Index => return "*×*".to_string(),
+            RowStyle::Closed => {
+                return format!(
+                    "*{}*",
+                    utils::escape_markdown(&self.text(idx, connection, max_length))
+                )
+            }
+            // the selected row is marked in the index column, so it can be found without colors
+            RowStyle::Selected if *self == Column::Index => return "**>**".to_string(),
+            RowStyle::Selected => {
+                return format!(
+                    "**{}**",
+                    utils::escape_markdown(&self.text(idx, connection, max_length))
+                )
+            }
+            RowStyle::Normal => {}
+        }
 
-    utils::pretty_print_info(&format!("**{} Connections**", all_connections.len()));
+        match self {
+            Column::Index => format!("*{}*", idx + 1),
+            // loopback addresses are already marked as localhost, addresses of the own interfaces aren't
+            Column::RemoteAddress
+                if connection.is_local && connection.address_type == AddressType::Extern =>
+            {
+                format!(
+                    "*{} local*",
+                    utils::escape_markdown(&self.text(idx, connection, max_length))
+                )
+            }
+            Column::RemoteAddress if connection.remote_hostname.is_none() => {
+                format_known_address(&connection.remote_address, &connection.address_type)
+            }
+            Column::PidProgram => format!(
+                "{} *{}*",
+                connection.pid,
+                utils::escape_markdown(&shorten_program(connection, max_length))
+            ),
+            Column::Program => format!(
+                "*{}*",
+                utils::escape_markdown(&self.text(idx, connection, max_length))
+            ),
+            _ => utils::escape_markdown(&self.text(idx, connection, max_length)),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Estimates how many characters a column needs at least: its longest text or the longest word of its header, but at most its preferred space.
+/// Shortened columns only need `MIN_TRUNCATED_LENGTH` characters.
+///
+/// # Arguments
+/// * `column`: The column to measure.
+/// * `all_connections`: The rows of the table.
+/// * `full`: Whether long programs and commands are shown in full.
+///
+/// # Returns
+/// The minimum width of the column's content.
+fn get_min_width(column: Column, all_connections: &[Connection], full: bool) -> usize {
+    if column.is_truncated() && !full {
+        return MIN_TRUNCATED_LENGTH;
+    }
 
-    #[test]
-    fn test_format_known_address_localhost() {
-        let addr = "127.0.0.1".to_string();
-        let result = format_known_address(&addr, &AddressType::Localhost);
-        assert_eq!(result, "*127.0.0.1 localhost*");
+    let header = column
+        .header()
+        .replace('*', "")
+        .split(' ')
+        .map(|word| word.chars().count())
+        .max()
+        .unwrap_or_default();
+    all_connections
+        .iter()
+        .enumerate()
+        .map(|(idx, connection)| column.text(idx, connection, None).chars().count())
+        .fold(header, usize::max)
+        .min(column.max_space() as usize)
+}
+
+/// Drops the `DROPPABLE_COLUMNS` one after another until the table fits into the width.
+/// Columns selected with `--columns` and tables without a width limit are kept as they are.
+///
+/// # Arguments
+/// * `columns`: The columns of the table.
+/// * `all_connections`: The rows of the table.
+/// * `options`: The width and the table options selected by the user.
+///
+/// # Returns
+/// The columns which are shown and the columns which were dropped.
+fn fit_columns(
+    mut columns: Vec<Column>,
+    all_connections: &[Connection],
+    options: &RenderOptions,
+) -> (Vec<Column>, Vec<Column>) {
+    let mut dropped = Vec::new();
+    if options.is_unlimited() || options.table.columns.is_some() {
+        return (columns, dropped);
     }
 
-    #[test]
-    fn test_format_known_address_unspecified() {
-        let addr = "0.0.0.0".to_string();
-        let result = format_known_address(&addr, &AddressType::Unspecified);
-        assert_eq!(result, "*0.0.0.0*");
+    // every cell has a border on its left, the last one also on its right
+    let required_width = |columns: &[Column]| {
+        columns
+            .iter()
+            .map(|column| get_min_width(*column, all_connections, options.table.full) + 1)
+            .sum::<usize>()
+            + 1
+    };
+    for droppable in DROPPABLE_COLUMNS {
+        if required_width(&columns) <= options.width as usize {
+            break;
+        }
+        if let Some(position) = columns.iter().position(|column| *column == droppable) {
+            dropped.push(columns.remove(position));
+        }
     }
+    (columns, dropped)
+}
 
-    #[test]
-    fn test_format_known_address_extern() {
-        let addr = "123.123.123".to_string();
-        let result = format_known_address(&addr, &AddressType::Extern);
-        assert_eq!(result, "123.123.123");
+/// Shortens a text with the given truncation if a maximum length is set.
+///
+/// # Arguments
+/// * `text`: The text to shorten.
+/// * `max_length`: The maximum number of characters, `None` to keep the text in full.
+/// * `truncate`: Either `utils::truncate_with_ellipsis` or `utils::truncate_middle`.
+///
+/// # Returns
+/// The (shortened) text.
+fn shorten(text: &str, max_length: Option<usize>, truncate: fn(&str, usize) -> String) -> String {
+    match max_length {
+        Some(max_length) => truncate(text, max_length),
+        None => text.to_string(),
     }
+}
 
-    #[test]
-    fn test_fill_terminal_width() {
-        let row = fill_terminal_width(80, [5, 8, 8, 28, 7, 24, 13]);
-        let columns = row.matches('|').count();
-        assert_eq!(columns, 8); // 7 columns + final pipe
+/// Shortens the program of a connection so that it fits into the combined `PidProgram` column together with its PID.
+///
+/// # Arguments
+/// * `connection`: The connection of the program.
+/// * `max_length`: The maximum number of characters of the whole column, `None` to show it in full.
+///
+/// # Returns
+/// The (shortened) program.
+fn shorten_program(connection: &Connection, max_length: Option<usize>) -> String {
+    let max_length = max_length.map(|max_length| {
+        max_length
+            .saturating_sub(connection.pid.chars().count() + 1)
+            .max(MIN_TRUNCATED_LENGTH)
+    });
+    shorten(&connection.program, max_length, utils::truncate_middle)
+}
+
+/// Calculates how many characters every column can show, based on the share of the table width it receives.
+/// Only programs and commands are limited, so narrow terminals shorten them more than wide ones.
+///
+/// # Arguments
+/// * `columns`: The columns of the table.
+/// * `width`: The width of the table.
+/// * `full`: Whether nothing should be shortened.
+///
+/// # Returns
+/// The maximum number of characters of every column, `None` for columns which are shown in full.
+fn get_max_lengths(columns: &[Column], width: u16, full: bool) -> Vec<Option<usize>> {
+    let total_column_spaces: u16 = columns.iter().map(|column| column.max_space()).sum();
+    // the borders between the cells aren't available for the content
+    let available_width = (width as usize).saturating_sub(columns.len() + 1);
+    columns
+        .iter()
+        .map(|column| {
+            if full || !column.is_truncated() {
+                return None;
+            }
+            let share = column.max_space() as f64 / total_column_spaces as f64;
+            let max_length = (share * available_width as f64) as usize;
+            Some(max_length.max(MIN_TRUNCATED_LENGTH))
+        })
+        .collect()
+}
+
+/// Applies the columns selected with `--columns` to the default columns.
+/// A `Pid` column directly followed by a `Program` column is shown as the combined `PidProgram` column,
+/// removing one of them from the combined column keeps the other one.
+///
+/// # Arguments
+/// * `default_columns`: The columns chosen by the other options.
+/// * `selection`: The columns selected by the user.
+///
+/// # Returns
+/// The columns in the order in which they should be shown.
+fn select_columns(default_columns: Vec<Column>, selection: &ColumnSelection) -> Vec<Column> {
+    let mut columns = match selection {
+        ColumnSelection::Exact(columns) => columns.clone(),
+        ColumnSelection::Relative(changes) => {
+            let mut columns: Vec<Column> = default_columns
+                .into_iter()
+                .flat_map(|column| match column {
+                    Column::PidProgram => vec![Column::Pid, Column::Program],
+                    column => vec![column],
+                })
+                .collect();
+            for (add, column) in changes {
+                if !add {
+                    columns.retain(|shown| shown != column);
+                } else if !columns.contains(column) {
+                    columns.push(*column);
+                }
+            }
+            columns
+        }
+    };
+
+    if let Some(idx) = columns
+        .windows(2)
+        .position(|pair| pair == [Column::Pid, Column::Program])
+    {
+        columns.splice(idx..idx + 2, [Column::PidProgram]);
+    }
+    columns
+}
+
+/// Chooses the columns to show in the table, optional columns are only shown if at least one connection has a value for them.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `options`: Which of the columns requested by the user to add.
+///
+/// # Returns
+/// The columns in the order in which they should be shown.
+fn get_columns(all_connections: &[Connection], options: &TableOptions) -> Vec<Column> {
+    let mut columns = vec![Column::Index];
+    if options.show_diff {
+        columns.push(Column::Diff);
+    }
+    columns.extend([Column::Proto, Column::LocalPort]);
+    if options.show_exposure {
+        columns.push(Column::Exposure);
+    }
+    columns.extend([Column::RemoteAddress, Column::RemotePort]);
+    if options.show_country {
+        columns.push(Column::Country);
+    }
+    columns.push(Column::PidProgram);
+    if options.show_exe {
+        columns.push(Column::Exe);
+    }
+    if options.show_container {
+        columns.push(Column::Container);
+    }
+    if options.show_unit {
+        columns.push(Column::Unit);
+    }
+    if options.show_netns {
+        columns.push(Column::Netns);
+    }
+    if options.show_inode {
+        columns.push(Column::Inode);
+    }
+    if all_connections.iter().any(|c| c.user.is_some()) {
+        columns.push(Column::User);
+    }
+    columns.push(Column::State);
+    if options.show_stats {
+        columns.push(Column::RxBytes);
+        columns.push(Column::TxBytes);
+    }
+    if options.show_queues {
+        columns.push(Column::RecvQueue);
+        columns.push(Column::SendQueue);
+        columns.push(Column::Retransmits);
+    }
+    if options.show_age {
+        columns.push(Column::Age);
+    }
+    if options.show_duplicates {
+        columns.push(Column::Duplicates);
+    }
+    if options.show_command {
+        columns.push(Column::Command);
+    }
+
+    match &options.columns {
+        Some(selection) => select_columns(columns, selection),
+        None => columns,
+    }
+}
+
+/// Creates a Markdown table row with just empty characters with the width of the terminal window.
+///
+/// # Argument
+/// * `terminal_width`: The current width of the terminal.
+/// * `max_column_spaces`: A list in which the values represent the max-width of each of the Markdown table columns.
+///
+/// # Returns
+/// A Markdown table row string in which each column is filled with as much empty characters needed to fit in content and as well fill out the terminal width.
+fn fill_terminal_width(terminal_width: u16, max_column_spaces: &[u16]) -> String {
+    let total_column_spaces: u16 = max_column_spaces.iter().sum();
+
+    let calculate_column_width = |column_space: u16| {
+        (column_space as f64 / total_column_spaces as f64) * (terminal_width as f64)
+    };
+    let empty_character = FILLING_CHARACTER.to_string();
+
+    let mut row: String = String::new();
+    for &max_column_space in max_column_spaces {
+        row.push_str(&format!(
+            "| {} ",
+            empty_character.repeat(calculate_column_width(max_column_space) as usize)
+        ));
+    }
+    row.push_str("|\n");
+
+    row
+}
+
+/// Counts how many of the heuristics for suspicious sockets a connection matches.
+/// Only listening sockets can be suspicious, they score one point each for:
+/// * listening on all interfaces (`0.0.0.0` or `[::]`)
+/// * listening on a high port (from `SUSPICIOUS_MIN_PORT` on)
+/// * having no known program or a very short program name (up to `SUSPICIOUS_MAX_PROGRAM_LENGTH` characters)
+///
+/// # Arguments
+/// * `connection`: The connection to check.
+///
+/// # Returns
+/// The number of matched heuristics, 0 if the socket isn't listening.
+fn suspicion_score(connection: &Connection) -> usize {
+    let listening =
+        connection.state == "listen" || (connection.proto == "udp" && connection.state == "close");
+    if !listening {
+        return 0;
+    }
+
+    let all_interfaces =
+        connection.local_address == "0.0.0.0" || connection.local_address == "[::]";
+    let high_port = connection
+        .local_port
+        .parse::<u16>()
+        .is_ok_and(|port| port >= SUSPICIOUS_MIN_PORT);
+    let unusual_program = connection.pid == "-"
+        || connection.program.chars().count() <= SUSPICIOUS_MAX_PROGRAM_LENGTH;
+
+    [all_interfaces, high_port, unusual_program]
+        .iter()
+        .filter(|matched| **matched)
+        .count()
+}
+
+/// Chooses how a row is formatted, the selected row wins over closed connections, which win over suspicious sockets, which win over the highlighted rows.
+///
+/// # Arguments
+/// * `connection`: The connection of the row.
+/// * `idx`: The index of the row.
+/// * `highlighted_rows`: The rows which are highlighted, e.g because they are new or closed in watch mode.
+/// * `options`: Whether suspicious sockets should be highlighted at all.
+///
+/// # Returns
+/// The style of the row.
+fn get_row_style(
+    connection: &Connection,
+    idx: usize,
+    highlighted_rows: &HighlightedRows,
+    options: &TableOptions,
+) -> RowStyle {
+    if highlighted_rows.selected == Some(idx) {
+        return RowStyle::Selected;
+    }
+    if highlighted_rows.closed.contains(&idx) {
+        return RowStyle::Closed;
+    }
+
+    let highlighted = highlighted_rows.new.contains(&idx);
+    let score = if options.highlight_suspicious {
+        suspicion_score(connection)
+    } else {
+        0
+    };
+
+    if score >= SUSPICIOUS_DANGER_SCORE {
+        RowStyle::Danger
+    } else if highlighted || score >= SUSPICIOUS_WARNING_SCORE {
+        RowStyle::Highlighted
+    } else {
+        RowStyle::Normal
+    }
+}
+
+/// Gets the headers and cells of the connections table without any formatting, so that other outputs can use the same columns.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `options`: Which optional columns to show.
+///
+/// # Returns
+/// The plain header of every column and one row of plain cells per connection, in the order of the table.
+pub fn plain_table(
+    all_connections: &[Connection],
+    options: &TableOptions,
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let columns = get_columns(all_connections, options);
+    let headers = columns
+        .iter()
+        .map(|column| column.header().replace('*', ""))
+        .collect();
+    let max_length = (!options.full).then_some(MAX_COMMAND_LENGTH);
+    let rows = all_connections
+        .iter()
+        .enumerate()
+        .map(|(idx, connection)| {
+            columns
+                .iter()
+                .map(|column| match column {
+                    Column::Command => column.text(idx, connection, max_length),
+                    _ => column.text(idx, connection, None),
+                })
+                .collect()
+        })
+        .collect();
+
+    (headers, rows)
+}
+
+/// Renders all current connections into a pretty table.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `options`: Which optional columns to show, whether to use colors and the width of the table.
+///
+/// # Returns
+/// The rendered table, ready to be printed.
+pub fn render_connections_table(all_connections: &[Connection], options: &RenderOptions) -> String {
+    render_highlighted_connections_table(all_connections, &HighlightedRows::default(), options)
+}
+
+/// Renders all current connections into a pretty table and highlights some of the rows.
+///
+/// # Appearence
+/// * highlighted rows -> `inline code` formatted, ie. yellow
+/// * very suspicious rows -> ~~strikeout~~ formatted, ie. red
+/// * closed rows -> *italic* formatted, ie. gray and without an index
+/// * the selected row -> **bold** formatted, ie. cyan and marked with a ">"
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `highlighted_rows`: The connections which should be highlighted, e.g new and closed connections in watch mode.
+/// * `options`: Which optional columns to show, whether to use colors and the width of the table.
+///
+/// # Returns
+/// The rendered table, ready to be printed.
+pub fn render_highlighted_connections_table(
+    all_connections: &[Connection],
+    highlighted_rows: &HighlightedRows,
+    options: &RenderOptions,
+) -> String {
+    let skin: MadSkin = create_table_style(options);
+    let (columns, _) = fit_columns(
+        get_columns(all_connections, &options.table),
+        all_connections,
+        options,
+    );
+    let max_lengths = get_max_lengths(
+        &columns,
+        options.width,
+        options.table.full || options.is_unlimited(),
+    );
+
+    // Add table headers
+    let center_markdown_row = format!("{}|\n", "| :-: ".repeat(columns.len()));
+    let mut markdown = center_markdown_row.clone();
+    let headers: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+    markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+
+    // iterate over all connections to build the table
+    let mut cell_colors: Vec<Color> = Vec::new();
+    for (idx, connection) in all_connections.iter().enumerate() {
+        markdown.push_str(&center_markdown_row);
+
+        let style = get_row_style(connection, idx, highlighted_rows, &options.table);
+        let cells: Vec<String> = columns
+            .iter()
+            .zip(&max_lengths)
+            .map(|(column, max_length)| {
+                let cell = column.cell(idx, connection, style, *max_length);
+                // highlighted rows keep the color of their highlight
+                match get_cell_color(*column, connection, options.table.theme) {
+                    Some(color) if options.color && style == RowStyle::Normal => {
+                        cell_colors.push(color);
+                        format!("{}{}{}", COLOR_START, cell, COLOR_END)
+                    }
+                    _ => cell,
+                }
+            })
+            .collect();
+        markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+
+    // Create an empty row that forces the table to fit the terminal with respect to how much space ...
+    // ... each column should receive based on the max length of each column
+    let max_column_spaces: Vec<u16> = columns.iter().map(|column| column.max_space()).collect();
+    let terminal_filling_row: String =
+        fill_terminal_width(options.fill_width(&max_column_spaces), &max_column_spaces);
+    markdown.push_str(&terminal_filling_row);
+    markdown.push_str(&center_markdown_row);
+
+    let table = skin
+        .text(&markdown, Some(options.width as usize))
+        .to_string();
+    let table = apply_cell_colors(&table, &cell_colors, options.table.theme);
+    // highlights are a display aid only, without colors the table stays unchanged
+    let table = if options.color {
+        apply_highlights(&table, &options.table.highlight)
+    } else {
+        table
+    };
+    apply_border_style(&table, options.border)
+}
+
+/// Prints all current connections in a pretty Markdown table.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `options`: Which optional columns to show.
+///
+/// # Returns
+/// None
+pub fn print_connections_table(all_connections: &[Connection], options: &TableOptions) {
+    let render_options = RenderOptions::for_terminal(options.clone());
+    println!(
+        "{}",
+        render_connections_table(all_connections, &render_options)
+    );
+
+    let count = format!("**{} Connections**", all_connections.len());
+    print_footer(&footer_lines(count, Some(all_connections), options));
+    print_dropped_columns(all_connections, &render_options);
+}
+
+/// Summarizes the connections per protocol, e.g "TCP: 42 (12 listen, 30 established), UDP: 8".
+/// The IPv4 and IPv6 variants of a protocol are counted together, only TCP is broken down by state since UDP sockets have none.
+///
+/// # Arguments
+/// * `connections`: The connections to count.
+///
+/// # Returns
+/// The comma separated totals, empty if there are no connections.
+fn summarize_protocols(connections: &[Connection]) -> String {
+    let mut protocols: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for connection in connections {
+        let protocol = connection.proto.trim_end_matches(['4', '6']).to_lowercase();
+        *protocols
+            .entry(protocol)
+            .or_default()
+            .entry(connection.state.clone())
+            .or_default() += 1;
+    }
+
+    protocols
+        .iter()
+        .map(|(protocol, states)| {
+            let total: usize = states.values().sum();
+            let states: Vec<String> = states
+                .iter()
+                .map(|(state, count)| format!("{} {}", count, state))
+                .collect();
+            if protocol == "tcp" {
+                format!(
+                    "{}: {} ({})",
+                    protocol.to_uppercase(),
+                    total,
+                    states.join(", ")
+                )
+            } else {
+                format!("{}: {}", protocol.to_uppercase(), total)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Builds the lines below a table, shared by the plain, the watch and the diff table: the number of connections,
+/// the totals per protocol (unless `--no-summary` is set or there is nothing to count) and the host if `--show-host` is set, e.g to tell the tables of many hosts apart.
+///
+/// # Arguments
+/// * `count`: The line with the number of connections.
+/// * `summarized`: The connections to count per protocol, `None` if the table has no summary.
+/// * `options`: Whether to show the summary and the host to show, if any.
+///
+/// # Returns
+/// The lines in the order in which they are printed.
+fn footer_lines(
+    count: String,
+    summarized: Option<&[Connection]>,
+    options: &TableOptions,
+) -> Vec<String> {
+    let mut lines = vec![count];
+    let summary = summarized.map(summarize_protocols).unwrap_or_default();
+    if options.show_summary && !summary.is_empty() {
+        lines.push(summary);
+    }
+    if let Some(host) = &options.host {
+        lines.push(format!("Host: {}", host));
+    }
+    lines
+}
+
+/// Prints the lines below a table to stderr.
+///
+/// # Arguments
+/// * `lines`: The lines built by `footer_lines`.
+///
+/// # Returns
+/// None
+fn print_footer(lines: &[String]) {
+    for line in lines {
+        utils::pretty_print_info(line);
+    }
+}
+
+/// Builds the lines below the table of the watch mode, closed connections aren't counted.
+///
+/// # Arguments
+/// * `all_connections`: The rows of the table.
+/// * `highlighted_rows`: The highlighted rows, including the closed ones.
+/// * `options`: Whether to show the summary and the host.
+///
+/// # Returns
+/// The lines in the order in which they are printed.
+fn highlighted_footer_lines(
+    all_connections: &[Connection],
+    highlighted_rows: &HighlightedRows,
+    options: &TableOptions,
+) -> Vec<String> {
+    let open_rows: Vec<Connection> = all_connections
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !highlighted_rows.closed.contains(idx))
+        .map(|(_, connection)| connection.clone())
+        .collect();
+    let count = format!("**{} Connections**", open_rows.len());
+    footer_lines(count, Some(&open_rows), options)
+}
+
+/// Prints which columns were dropped because the table didn't fit into the terminal.
+///
+/// # Arguments
+/// * `all_connections`: The rows of the table.
+/// * `options`: The options the table was rendered with.
+///
+/// # Returns
+/// None
+fn print_dropped_columns(all_connections: &[Connection], options: &RenderOptions) {
+    let (_, dropped) = fit_columns(
+        get_columns(all_connections, &options.table),
+        all_connections,
+        options,
+    );
+    if dropped.is_empty() {
+        return;
+    }
+
+    let names: Vec<String> = dropped
+        .iter()
+        .map(|column| column.header().replace('*', ""))
+        .collect();
+    utils::pretty_print_info(&format!(
+        "Hidden columns: {} (too narrow, see --width)",
+        names.join(", ")
+    ));
+}
+
+/// Prints all current connections in a pretty Markdown table for the current terminal and highlights some of the rows.
+/// Closed connections aren't included in the number of connections.
+///
+/// # Arguments
+/// * `all_connections`: A list containing all current connections as a `Connection` struct.
+/// * `highlighted_rows`: The connections which should be highlighted, e.g new and closed connections in watch mode.
+/// * `options`: Which optional columns to show.
+///
+/// # Returns
+/// None
+pub fn print_highlighted_connections_table(
+    all_connections: &[Connection],
+    highlighted_rows: &HighlightedRows,
+    options: &TableOptions,
+) {
+    let render_options = RenderOptions::for_terminal(options.clone());
+    println!(
+        "{}",
+        render_highlighted_connections_table(all_connections, highlighted_rows, &render_options)
+    );
+
+    print_footer(&highlighted_footer_lines(
+        all_connections,
+        highlighted_rows,
+        options,
+    ));
+    print_dropped_columns(all_connections, &render_options);
+}
+
+/// Prints the connections added and removed since a `--diff` snapshot in a pretty Markdown table.
+/// Added connections are highlighted like new connections in watch mode, removed ones are dimmed like closed ones.
+///
+/// # Arguments
+/// * `diff`: The connections created by `snapshot::diff_snapshot`.
+/// * `options`: Which optional columns to show.
+///
+/// # Returns
+/// None
+pub fn print_diff_table(diff: &[Connection], options: &TableOptions) {
+    let rows_with = |status: DiffStatus| -> HashSet<usize> {
+        diff.iter()
+            .enumerate()
+            .filter(|(_, connection)| connection.diff == Some(status))
+            .map(|(idx, _)| idx)
+            .collect()
+    };
+    let highlighted_rows = HighlightedRows {
+        new: rows_with(DiffStatus::Added),
+        closed: rows_with(DiffStatus::Removed),
+        ..Default::default()
+    };
+
+    let render_options = RenderOptions::for_terminal(options.clone());
+    println!(
+        "{}",
+        render_highlighted_connections_table(diff, &highlighted_rows, &render_options)
+    );
+
+    let count = format!(
+        "**{} added, {} removed**",
+        highlighted_rows.new.len(),
+        highlighted_rows.closed.len()
+    );
+    print_footer(&footer_lines(count, None, options));
+    print_dropped_columns(diff, &render_options);
+}
+
+/// Formats the state counts of a group, e.g "established 3, listen 1".
+///
+/// # Arguments
+/// * `states`: The number of connections per state.
+///
+/// # Returns
+/// The comma separated state counts.
+fn format_states(states: &BTreeMap<String, usize>) -> String {
+    states
+        .iter()
+        .map(|(state, count)| format!("{} {}", state, count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Renders a summary table with one row per group of connections and a row with the totals at the bottom.
+///
+/// # Arguments
+/// * `groups`: The groups created by `connections::group_connections`.
+/// * `group_field`: The column the connections were grouped by, used as the header of the first column.
+/// * `options`: Whether to use colors and the width of the table, the columns are fixed.
+///
+/// # Returns
+/// The rendered table, ready to be printed.
+pub fn render_grouped_table(
+    groups: &[ConnectionGroup],
+    group_field: GroupField,
+    options: &RenderOptions,
+) -> String {
+    let skin: MadSkin = create_table_style(options);
+
+    let key_header = match group_field {
+        GroupField::Program => "**program**",
+        GroupField::Pid => "**pid** *program*",
+        GroupField::State => "**state**",
+        GroupField::Proto => "**proto**",
+    };
+
+    let center_markdown_row = "| :-: | :-: | :-: |\n";
+    let mut markdown = center_markdown_row.to_string();
+    markdown.push_str(&format!(
+        "| {} | **connections** | **states** |\n",
+        key_header
+    ));
+
+    let mut total_states: BTreeMap<String, usize> = BTreeMap::new();
+    for group in groups {
+        markdown.push_str(center_markdown_row);
+        markdown.push_str(&format!(
+            "| {} | {} | {} |\n",
+            utils::escape_markdown(&group.key),
+            group.count,
+            format_states(&group.states)
+        ));
+
+        for (state, count) in &group.states {
+            *total_states.entry(state.to_string()).or_insert(0) += count;
+        }
+    }
+
+    let total: usize = groups.iter().map(|group| group.count).sum();
+    markdown.push_str(center_markdown_row);
+    markdown.push_str(&format!(
+        "| **total** | **{}** | {} |\n",
+        total,
+        format_states(&total_states)
+    ));
+
+    markdown.push_str(&fill_terminal_width(
+        options.fill_width(&[24, 12, 40]),
+        &[24, 12, 40],
+    ));
+    markdown.push_str(center_markdown_row);
+
+    let table = skin
+        .text(&markdown, Some(options.width as usize))
+        .to_string();
+    apply_border_style(&table, options.border)
+}
+
+/// Formats the distinct values of a group as a comma separated list, e.g "nginx, postgres".
+///
+/// # Arguments
+/// * `values`: The distinct values.
+///
+/// # Returns
+/// The escaped and comma separated values.
+fn format_values(values: &BTreeSet<String>) -> String {
+    values
+        .iter()
+        .map(|value| utils::escape_markdown(value))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Renders a summary table with one row per remote peer, its number of connections and the local ports and programs talking to it.
+///
+/// # Arguments
+/// * `groups`: The groups created by `connections::group_remote_addresses`.
+/// * `options`: Whether to use colors and the width of the table, the columns are fixed.
+///
+/// # Returns
+/// The rendered table, ready to be printed.
+pub fn render_top_remotes_table(groups: &[ConnectionGroup], options: &RenderOptions) -> String {
+    let skin: MadSkin = create_table_style(options);
+
+    let center_markdown_row = "| :-: | :-: | :-: | :-: |\n";
+    let mut markdown = center_markdown_row.to_string();
+    markdown
+        .push_str("| **remote address** | **connections** | **local ports** | **programs** |\n");
+
+    for group in groups {
+        markdown.push_str(center_markdown_row);
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            utils::escape_markdown(&group.key),
+            group.count,
+            format_values(&group.local_ports),
+            format_values(&group.programs)
+        ));
+    }
+
+    markdown.push_str(&fill_terminal_width(
+        options.fill_width(&[28, 12, 20, 30]),
+        &[28, 12, 20, 30],
+    ));
+    markdown.push_str(center_markdown_row);
+
+    let table = skin
+        .text(&markdown, Some(options.width as usize))
+        .to_string();
+    apply_border_style(&table, options.border)
+}
+
+/// Prints a summary table with one row per remote peer for the current terminal.
+///
+/// # Arguments
+/// * `groups`: The groups created by `connections::group_remote_addresses`.
+///
+/// # Returns
+/// None
+pub fn print_top_remotes_table(groups: &[ConnectionGroup]) {
+    let render_options = RenderOptions::for_terminal(TableOptions::default());
+    println!("{}", render_top_remotes_table(groups, &render_options));
+    utils::pretty_print_info(&format!("**{} Remote addresses**", groups.len()));
+}
+
+/// Formats the local endpoint of a connection, IPv6 addresses are put in brackets.
+///
+/// # Arguments
+/// * `connection`: The connection of the endpoint.
+///
+/// # Returns
+/// The local address and port, e.g "0.0.0.0:8080" or "[::]:8080".
+fn format_local_endpoint(connection: &Connection) -> String {
+    if connection.local_address.contains(':') {
+        format!("[{}]:{}", connection.local_address, connection.local_port)
+    } else {
+        format!("{}:{}", connection.local_address, connection.local_port)
+    }
+}
+
+/// Renders which processes hold a local port for `--holding`, each process is listed once with all of its sockets on the port.
+/// Several processes can hold the same port, e.g with `SO_REUSEPORT` or after a fork.
+///
+/// # Arguments
+/// * `port`: The port provided by the user.
+/// * `connections`: The connections on the port.
+/// * `options`: Whether to use colors and the width of the text.
+/// * `suggest_kill`: Whether to show how to kill the holders, not needed if they are about to be killed.
+///
+/// # Returns
+/// The rendered text, ready to be printed.
+pub fn render_port_holders(
+    port: u16,
+    connections: &[Connection],
+    options: &RenderOptions,
+    suggest_kill: bool,
+) -> String {
+    let skin: MadSkin = create_table_style(options);
+    if connections.is_empty() {
+        return skin
+            .text(
+                &format!("Nothing holds port **{}**.\n", port),
+                Some(options.width as usize),
+            )
+            .to_string();
+    }
+
+    let mut holders: Vec<(&str, Vec<&Connection>)> = Vec::new();
+    for connection in connections {
+        match holders.iter_mut().find(|(pid, _)| *pid == connection.pid) {
+            Some((_, sockets)) => sockets.push(connection),
+            None => holders.push((&connection.pid, vec![connection])),
+        }
+    }
+
+    let processes = if holders.len() == 1 {
+        "1 process".to_string()
+    } else {
+        format!("{} processes", holders.len())
+    };
+    let mut markdown = format!("Port **{}** is held by **{}**:\n", port, processes);
+    for (pid, sockets) in &holders {
+        let owner = sockets[0];
+        let holder = if *pid == "-" {
+            "*an unknown process*".to_string()
+        } else {
+            let user = owner
+                .user
+                .as_ref()
+                .map(|user| format!(", user {}", utils::escape_markdown(user)))
+                .unwrap_or_default();
+            format!(
+                "**{}** (PID {}{})",
+                utils::escape_markdown(&owner.program),
+                pid,
+                user
+            )
+        };
+        let sockets: Vec<String> = sockets
+            .iter()
+            .map(|socket| {
+                utils::escape_markdown(&format!(
+                    "{} {} {}",
+                    socket.proto,
+                    format_local_endpoint(socket),
+                    socket.state
+                ))
+            })
+            .collect();
+        markdown.push_str(&format!("* {}: {}\n", holder, sockets.join(", ")));
+    }
+    if suggest_kill {
+        markdown.push_str(&format!(
+            "\nFree the port with `somo --holding {} --kill`\n",
+            port
+        ));
+    }
+
+    skin.text(&markdown, Some(options.width as usize))
+        .to_string()
+}
+
+/// Prints which processes hold a local port.
+///
+/// # Arguments
+/// * `port`: The port provided by the user.
+/// * `connections`: The connections on the port.
+/// * `suggest_kill`: Whether to show how to kill the holders.
+///
+/// # Returns
+/// None
+pub fn print_port_holders(port: u16, connections: &[Connection], suggest_kill: bool) {
+    let render_options = RenderOptions::for_terminal(TableOptions::default());
+    print!(
+        "{}",
+        render_port_holders(port, connections, &render_options, suggest_kill)
+    );
+}
+
+/// Prints a summary table with one row per group of connections for the current terminal.
+///
+/// # Arguments
+/// * `groups`: The groups created by `connections::group_connections`.
+/// * `group_field`: The column the connections were grouped by, used as the header of the first column.
+///
+/// # Returns
+/// None
+pub fn print_grouped_table(groups: &[ConnectionGroup], group_field: GroupField) {
+    let render_options = RenderOptions::for_terminal(TableOptions::default());
+    println!(
+        "{}",
+        render_grouped_table(groups, group_field, &render_options)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_known_address_localhost() {
+        let addr = "127.0.0.1".to_string();
+        let result = format_known_address(&addr, &AddressType::Localhost);
+        assert_eq!(result, "*127.0.0.1 localhost*");
+    }
+
+    #[test]
+    fn test_format_known_address_unspecified() {
+        let addr = "0.0.0.0".to_string();
+        let result = format_known_address(&addr, &AddressType::Unspecified);
+        assert_eq!(result, "*0.0.0.0*");
+    }
+
+    #[test]
+    fn test_format_known_address_extern() {
+        let addr = "123.123.123".to_string();
+        let result = format_known_address(&addr, &AddressType::Extern);
+        assert_eq!(result, "123.123.123");
+    }
+
+    #[test]
+    fn test_fill_terminal_width() {
+        let row = fill_terminal_width(80, &[5, 8, 8, 28, 7, 24, 13]);
+        let columns = row.matches('|').count();
+        assert_eq!(columns, 8); // 7 columns + final pipe
+    }
+
+    #[test]
+    fn test_get_columns_user_only_when_known() {
+        let unknown_user = Connection::default();
+        let known_user = Connection {
+            user: Some("root".to_string()),
+            ..Default::default()
+        };
+
+        let options = TableOptions::default();
+        assert!(!get_columns(&[], &options).contains(&Column::User));
+        assert!(!get_columns(&[unknown_user], &options).contains(&Column::User));
+        assert!(get_columns(&[Connection::default(), known_user], &options).contains(&Column::User));
+    }
+
+    #[test]
+    fn test_get_columns_command() {
+        let options = TableOptions {
+            show_command: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Command));
+        assert!(get_columns(&[], &options).contains(&Column::Command));
+    }
+
+    #[test]
+    fn test_get_columns_country() {
+        let options = TableOptions {
+            show_country: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Country));
+        assert!(get_columns(&[], &options).contains(&Column::Country));
+    }
+
+    #[test]
+    fn test_command_cell_truncated() {
+        let connection = Connection {
+            command: Some(format!("python3 {}", "a".repeat(100))),
+            ..Default::default()
+        };
+        let cell = Column::Command.cell(0, &connection, RowStyle::Normal, Some(MAX_COMMAND_LENGTH));
+        assert_eq!(cell.chars().count(), MAX_COMMAND_LENGTH);
+        assert!(cell.ends_with('…'));
+
+        let connection = Connection {
+            command: Some("python3 -c\nimport os".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Command.cell(0, &connection, RowStyle::Normal, None),
+            "python3 -c import os"
+        );
+    }
+
+    #[test]
+    fn test_proto_cell_with_family() {
+        let mut connection = Connection {
+            proto: "tcp".to_string(),
+            family: "inet".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(Column::Proto.text(0, &connection, None), "tcp");
+
+        connection.family = "inet6".to_string();
+        assert_eq!(Column::Proto.text(0, &connection, None), "tcp6");
+
+        // protocols which already carry the suffix aren't changed
+        connection.proto = "udp6".to_string();
+        assert_eq!(Column::Proto.text(0, &connection, None), "udp6");
+    }
+
+    #[test]
+    fn test_program_cell_truncated() {
+        let connection = Connection {
+            pid: "1234".to_string(),
+            program: "/opt/vendor/agent/bin/telemetry-collector".to_string(),
+            ..Default::default()
+        };
+        let cell = Column::PidProgram.cell(0, &connection, RowStyle::Normal, Some(20));
+        assert_eq!(cell, "1234 */opt/ve…llector*");
+        assert_eq!(
+            Column::Program.cell(0, &connection, RowStyle::Normal, Some(12)),
+            "*/opt/v…ector*"
+        );
+        assert_eq!(
+            Column::PidProgram.cell(0, &connection, RowStyle::Normal, None),
+            "1234 */opt/vendor/agent/bin/telemetry-collector*"
+        );
+    }
+
+    #[test]
+    fn test_get_max_lengths() {
+        let columns = [Column::RemoteAddress, Column::PidProgram, Column::Command];
+        let narrow = get_max_lengths(&columns, 46, false);
+        let wide = get_max_lengths(&columns, 184, false);
+
+        // addresses are never shortened
+        assert_eq!(narrow[0], None);
+        assert_eq!(narrow[1], Some(10));
+        assert_eq!(wide[1], Some(46));
+        assert_eq!(narrow[2], Some(18));
+        assert_eq!(wide[2], Some(78));
+
+        assert_eq!(
+            get_max_lengths(&columns, 10, false)[1],
+            Some(MIN_TRUNCATED_LENGTH)
+        );
+        assert_eq!(get_max_lengths(&columns, 46, true), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_country_cell() {
+        let connection = Connection {
+            country: Some("DE".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Country.cell(0, &connection, RowStyle::Normal, None),
+            "DE"
+        );
+        assert_eq!(
+            Column::Country.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_exe_column() {
+        let options = TableOptions {
+            show_exe: true,
+            ..Default::default()
+        };
+        let columns = get_columns(&[], &options);
+        assert_eq!(
+            columns.iter().position(|column| *column == Column::Exe),
+            columns
+                .iter()
+                .position(|column| *column == Column::PidProgram)
+                .map(|position| position + 1)
+        );
+
+        let mut connection = Connection {
+            exe_path: Some("/usr/sbin/nginx".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(Column::Exe.text(0, &connection, None), "/usr/sbin/nginx");
+        connection.exe_deleted = true;
+        assert_eq!(
+            Column::Exe.text(0, &connection, None),
+            "/usr/sbin/nginx (deleted)"
+        );
+        assert_eq!(Column::Exe.text(0, &Connection::default(), None), "-");
+    }
+
+    #[test]
+    fn test_local_remote_address_cell() {
+        let connection = Connection {
+            remote_address: "192.168.0.10".to_string(),
+            address_type: AddressType::Extern,
+            is_local: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::RemoteAddress.cell(0, &connection, RowStyle::Normal, None),
+            "*192.168.0.10 local*"
+        );
+        let connection = Connection {
+            is_local: false,
+            ..connection
+        };
+        assert_eq!(
+            Column::RemoteAddress.cell(0, &connection, RowStyle::Normal, None),
+            "192.168.0.10"
+        );
+    }
+
+    #[test]
+    fn test_exposure_column() {
+        let options = TableOptions {
+            show_exposure: true,
+            ..Default::default()
+        };
+        let columns = get_columns(&[], &options);
+        assert_eq!(&columns[2..4], &[Column::LocalPort, Column::Exposure]);
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Exposure));
+
+        let connection = Connection {
+            exposure: Some(crate::schemas::Exposure::AllInterfaces),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Exposure.cell(0, &connection, RowStyle::Normal, None),
+            "all-interfaces"
+        );
+        assert_eq!(
+            Column::Exposure.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_container_column() {
+        let options = TableOptions {
+            show_container: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Container));
+        assert!(get_columns(&[], &options).contains(&Column::Container));
+
+        let connection = Connection {
+            container: Some("web".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Container.cell(0, &connection, RowStyle::Normal, None),
+            "web"
+        );
+        assert_eq!(
+            Column::Container.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_unit_column() {
+        let options = TableOptions {
+            show_unit: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Unit));
+        assert!(get_columns(&[], &options).contains(&Column::Unit));
+
+        let connection = Connection {
+            unit: Some("nginx.service".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Unit.cell(0, &connection, RowStyle::Normal, None),
+            "nginx.service"
+        );
+        assert_eq!(
+            Column::Unit.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_inode_column() {
+        let options = TableOptions {
+            show_inode: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Inode));
+        assert!(get_columns(&[], &options).contains(&Column::Inode));
+
+        let connection = Connection {
+            inode: Some(123456),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Inode.cell(0, &connection, RowStyle::Normal, None),
+            "123456"
+        );
+        assert_eq!(
+            Column::Inode.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_netns_column() {
+        let options = TableOptions {
+            show_netns: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Netns));
+        assert!(get_columns(&[], &options).contains(&Column::Netns));
+
+        let connection = Connection {
+            netns: Some("vpn".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Netns.cell(0, &connection, RowStyle::Normal, None),
+            "vpn"
+        );
+    }
+
+    #[test]
+    fn test_queue_columns() {
+        let options = TableOptions {
+            show_queues: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::RecvQueue));
+        let columns = get_columns(&[], &options);
+        assert!(columns.ends_with(&[Column::RecvQueue, Column::SendQueue, Column::Retransmits]));
+
+        let mut connection = Connection {
+            proto: "tcp".to_string(),
+            state: "established".to_string(),
+            rx_queue: Some(4096),
+            tx_queue: Some(0),
+            retransmits: Some(3),
+            ..Default::default()
+        };
+        let text = |column: Column, connection: &Connection| {
+            column.cell(0, connection, RowStyle::Normal, None)
+        };
+        assert_eq!(text(Column::RecvQueue, &connection), "4.0 KiB");
+        assert_eq!(text(Column::SendQueue, &connection), "0 B");
+        assert_eq!(text(Column::Retransmits, &connection), "3");
+
+        // a listening socket counts the connections waiting to be accepted
+        connection.state = "listen".to_string();
+        connection.rx_queue = Some(12);
+        assert_eq!(text(Column::RecvQueue, &connection), "12");
+
+        // UDP sockets have no retransmits, other platforms no queues at all
+        connection.retransmits = None;
+        assert_eq!(text(Column::Retransmits, &connection), "-");
+        assert_eq!(text(Column::SendQueue, &Connection::default()), "-");
+    }
+
+    #[test]
+    fn test_stats_columns() {
+        let options = TableOptions {
+            show_stats: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::RxBytes));
+        let columns = get_columns(&[], &options);
+        assert!(columns.contains(&Column::RxBytes));
+        assert!(columns.contains(&Column::TxBytes));
+
+        let connection = Connection {
+            rx_bytes: Some(2048),
+            tx_bytes: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::RxBytes.cell(0, &connection, RowStyle::Normal, None),
+            "2.0 KiB"
+        );
+        assert_eq!(
+            Column::TxBytes.cell(0, &connection, RowStyle::Normal, None),
+            "100 B"
+        );
+        assert_eq!(
+            Column::RxBytes.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_age_column() {
+        let options = TableOptions {
+            show_age: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Age));
+        assert!(get_columns(&[], &options).contains(&Column::Age));
+
+        let connection = Connection {
+            created: Some(unix_now() - 2 * 3600 - 5 * 60 - 30),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Age.cell(0, &connection, RowStyle::Normal, None),
+            "2h5m"
+        );
+        assert_eq!(
+            Column::Age.cell(0, &Connection::default(), RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_diff_column() {
+        let options = TableOptions {
+            show_diff: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Diff));
+        assert_eq!(get_columns(&[], &options)[1], Column::Diff);
+
+        let removed = Connection {
+            diff: Some(DiffStatus::Removed),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Diff.cell(0, &removed, RowStyle::Closed, None),
+            "*-*"
+        );
+        let added = Connection {
+            diff: Some(DiffStatus::Added),
+            ..Default::default()
+        };
+        assert_eq!(Column::Diff.cell(0, &added, RowStyle::Normal, None), "+");
+    }
+
+    #[test]
+    fn test_duplicates_column() {
+        let options = TableOptions {
+            show_duplicates: true,
+            ..Default::default()
+        };
+        assert!(!get_columns(&[], &TableOptions::default()).contains(&Column::Duplicates));
+        assert!(get_columns(&[], &options).contains(&Column::Duplicates));
+
+        let connection = Connection {
+            duplicates: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::Duplicates.cell(0, &connection, RowStyle::Normal, None),
+            "3"
+        );
+    }
+
+    fn listening(local_address: &str, local_port: &str, program: &str) -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_address: local_address.to_string(),
+            local_port: local_port.to_string(),
+            program: program.to_string(),
+            pid: if program == "-" { "-" } else { "42" }.to_string(),
+            state: "listen".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_list_fields() {
+        let fields = list_fields();
+        let lines: Vec<&str> = fields.lines().collect();
+        assert_eq!(lines.len(), COLUMN_NAMES.len() + 1);
+        assert!(lines[0].starts_with("FIELD"));
+        assert!(
+            lines.contains(&"proto           yes   the protocol of the socket, e.g tcp or udp6")
+        );
+        assert!(lines
+            .contains(&"country         -     the country of the remote address (needs --geoip)"));
+        // every listed field can be selected with --columns
+        for line in &lines[1..] {
+            let name = line.split_whitespace().next().unwrap();
+            assert!(name.parse::<Column>().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_parse_column_selection() {
+        assert_eq!(
+            "local_port, program,COMMAND".parse::<ColumnSelection>(),
+            Ok(ColumnSelection::Exact(vec![
+                Column::LocalPort,
+                Column::Program,
+                Column::Command
+            ]))
+        );
+        assert_eq!(
+            "+command,-proto,-remote-port".parse::<ColumnSelection>(),
+            Ok(ColumnSelection::Relative(vec![
+                (true, Column::Command),
+                (false, Column::Proto),
+                (false, Column::RemotePort)
+            ]))
+        );
+
+        let error = "proto,hostname".parse::<ColumnSelection>().unwrap_err();
+        assert!(error.starts_with("'hostname' is not a valid column"));
+        assert!(error.contains("local_port, exposure, remote_address"));
+        assert!("proto,+command".parse::<ColumnSelection>().is_err());
+        assert!("".parse::<ColumnSelection>().is_err());
+    }
+
+    #[test]
+    fn test_get_columns_exact_selection() {
+        let options = TableOptions {
+            columns: "state,pid,program,command".parse().ok(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_columns(&[], &options),
+            vec![Column::State, Column::PidProgram, Column::Command]
+        );
+
+        let options = TableOptions {
+            columns: "program,local_port,pid".parse().ok(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_columns(&[], &options),
+            vec![Column::Program, Column::LocalPort, Column::Pid]
+        );
+    }
+
+    #[test]
+    fn test_get_columns_relative_selection() {
+        let options = TableOptions {
+            columns: "-proto,+command,-program".parse().ok(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_columns(&[], &options),
+            vec![
+                Column::Index,
+                Column::LocalPort,
+                Column::RemoteAddress,
+                Column::RemotePort,
+                Column::Pid,
+                Column::State,
+                Column::Command
+            ]
+        );
+
+        // adding a column which is already shown keeps its position
+        let options = TableOptions {
+            columns: "+proto,+pid".parse().ok(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_columns(&[], &options),
+            get_columns(&[], &TableOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_local_address_and_program_cells() {
+        let connection = Connection {
+            local_address: "[::1]".to_string(),
+            program: "a*b".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::LocalAddress.cell(0, &connection, RowStyle::Normal, None),
+            "[::1]"
+        );
+        assert_eq!(
+            Column::Program.cell(0, &connection, RowStyle::Normal, None),
+            "*a\\*b*"
+        );
+    }
+
+    #[test]
+    fn test_suspicion_score() {
+        assert_eq!(suspicion_score(&listening("127.0.0.1", "80", "nginx")), 0);
+        assert_eq!(suspicion_score(&listening("0.0.0.0", "80", "nginx")), 1);
+        assert_eq!(suspicion_score(&listening("[::]", "31337", "nginx")), 2);
+        assert_eq!(suspicion_score(&listening("0.0.0.0", "31337", "nc")), 3);
+        assert_eq!(suspicion_score(&listening("0.0.0.0", "4444", "-")), 3);
+
+        let mut established = listening("0.0.0.0", "31337", "nc");
+        established.state = "established".to_string();
+        assert_eq!(suspicion_score(&established), 0);
+    }
+
+    #[test]
+    fn test_get_row_style() {
+        let suspicious = listening("0.0.0.0", "31337", "nc");
+        let options = TableOptions {
+            highlight_suspicious: true,
+            ..Default::default()
+        };
+        let none = HighlightedRows::default();
+        let new = HighlightedRows {
+            new: HashSet::from([0]),
+            ..Default::default()
+        };
+        let closed = HighlightedRows {
+            closed: HashSet::from([0]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_row_style(&suspicious, 0, &none, &TableOptions::default()),
+            RowStyle::Normal
+        );
+        assert_eq!(
+            get_row_style(&suspicious, 0, &none, &options),
+            RowStyle::Danger
+        );
+        assert_eq!(
+            get_row_style(&listening("[::]", "8080", "java"), 0, &none, &options),
+            RowStyle::Highlighted
+        );
+        assert_eq!(
+            get_row_style(&Connection::default(), 0, &new, &options),
+            RowStyle::Highlighted
+        );
+        assert_eq!(
+            get_row_style(&Connection::default(), 1, &new, &options),
+            RowStyle::Normal
+        );
+        assert_eq!(
+            get_row_style(&suspicious, 0, &closed, &options),
+            RowStyle::Closed
+        );
+        assert_eq!(
+            Column::Proto.cell(0, &suspicious, RowStyle::Danger, None),
+            "~~tcp~~"
+        );
+        assert_eq!(
+            Column::Proto.cell(0, &suspicious, RowStyle::Closed, None),
+            "*tcp*"
+        );
+        assert_eq!(
+            Column::Index.cell(0, &suspicious, RowStyle::Closed, None),
+            "*×*"
+        );
+    }
+
+    #[test]
+    fn test_plain_table() {
+        let connection = Connection {
+            proto: "tcp".to_string(),
+            pid: "42".to_string(),
+            program: "nginx".to_string(),
+            ..Default::default()
+        };
+        let (headers, rows) = plain_table(&[connection], &TableOptions::default());
+        assert_eq!(headers[0], "#");
+        assert!(headers.contains(&"pid program".to_string()));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), headers.len());
+        assert_eq!(rows[0][0], "1");
+        assert_eq!(rows[0][1], "tcp");
+    }
+
+    #[test]
+    fn test_port_cells_with_services() {
+        let connection = Connection {
+            local_port: "443".to_string(),
+            service: Some("https".to_string()),
+            remote_port: "51234".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::LocalPort.cell(0, &connection, RowStyle::Normal, None),
+            "443 (https)"
+        );
+        assert_eq!(
+            Column::RemotePort.cell(0, &connection, RowStyle::Normal, None),
+            "51234"
+        );
+    }
+
+    #[test]
+    fn test_ipv6_remote_address_cell() {
+        let connection = Connection {
+            remote_address: "[2001:db8::5]".to_string(),
+            address_type: AddressType::Extern,
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::RemoteAddress.cell(0, &connection, RowStyle::Normal, None),
+            "[2001:db8::5]"
+        );
+
+        let unspecified = Connection {
+            remote_address: "[::]".to_string(),
+            address_type: AddressType::Unspecified,
+            ..Default::default()
+        };
+        assert_eq!(
+            Column::RemoteAddress.cell(0, &unspecified, RowStyle::Normal, None),
+            "*[::]*"
+        );
+    }
+
+    #[test]
+    fn test_user_cell_unknown() {
+        let connection = Connection::default();
+        assert_eq!(
+            Column::User.cell(0, &connection, RowStyle::Normal, None),
+            "-"
+        );
+    }
+
+    #[test]
+    fn test_format_states() {
+        let states = BTreeMap::from([("listen".to_string(), 1), ("established".to_string(), 3)]);
+        assert_eq!(format_states(&states), "established 3, listen 1");
+        assert_eq!(format_states(&BTreeMap::new()), "");
+    }
+
+    #[test]
+    fn test_summarize_protocols() {
+        let connection = |proto: &str, state: &str| Connection {
+            proto: proto.to_string(),
+            state: state.to_string(),
+            ..Default::default()
+        };
+        let connections = vec![
+            connection("udp", "close"),
+            connection("tcp", "listen"),
+            connection("tcp6", "established"),
+            connection("tcp", "established"),
+            connection("udp6", "close"),
+        ];
+        assert_eq!(
+            summarize_protocols(&connections),
+            "TCP: 3 (2 established, 1 listen), UDP: 2"
+        );
+        assert_eq!(summarize_protocols(&[]), "");
+    }
+
+    #[test]
+    fn test_highlighted_footer_lines() {
+        let connection = |state: &str| Connection {
+            proto: "tcp".to_string(),
+            state: state.to_string(),
+            ..Default::default()
+        };
+        let connections = vec![connection("listen"), connection("established")];
+        let highlighted_rows = HighlightedRows {
+            closed: HashSet::from([1]),
+            ..Default::default()
+        };
+        let options = TableOptions {
+            show_summary: true,
+            host: Some("web-1".to_string()),
+            ..Default::default()
+        };
+
+        // the watch mode shows the host like the plain table, closed connections aren't counted
+        assert_eq!(
+            highlighted_footer_lines(&connections, &highlighted_rows, &options),
+            vec![
+                "**1 Connections**".to_string(),
+                "TCP: 1 (1 listen)".to_string(),
+                "Host: web-1".to_string(),
+            ]
+        );
+        assert_eq!(
+            footer_lines(
+                "**2 Connections**".to_string(),
+                Some(&connections),
+                &options
+            ),
+            vec![
+                "**2 Connections**".to_string(),
+                "TCP: 2 (1 established, 1 listen)".to_string(),
+                "Host: web-1".to_string(),
+            ]
+        );
+
+        // without a summary, e.g for --diff, only the host is added
+        let options = TableOptions {
+            show_summary: false,
+            ..options
+        };
+        assert_eq!(
+            footer_lines("**1 added, 0 removed**".to_string(), None, &options),
+            vec![
+                "**1 added, 0 removed**".to_string(),
+                "Host: web-1".to_string()
+            ]
+        );
+    }
+
+    fn render_options(width: u16, show_command: bool) -> RenderOptions {
+        RenderOptions {
+            table: TableOptions {
+                show_command,
+                ..Default::default()
+            },
+            color: false,
+            width,
+            border: BorderStyle::Unicode,
+        }
+    }
+
+    fn unknown_listener() -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_port: "22".to_string(),
+            remote_address: "0.0.0.0".to_string(),
+            remote_port: "0".to_string(),
+            pid: "-".to_string(),
+            program: "-".to_string(),
+            state: "listen".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn line_widths(table: &str) -> Vec<usize> {
+        table.lines().map(|line| line.chars().count()).collect()
+    }
+
+    #[test]
+    fn test_render_empty_table() {
+        let table = render_connections_table(&[], &render_options(60, false));
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert!(lines[0].starts_with('┌'));
+        assert!(lines[1].contains("proto") && lines[1].contains("state"));
+        assert!(lines.last().unwrap().starts_with('└'));
+        // without connections there is no separator between the header and the rows
+        assert!(!table.contains('├'));
+        assert!(line_widths(&table).iter().all(|width| *width == 60));
+    }
+
+    #[test]
+    fn test_render_unknown_fields() {
+        let table = render_connections_table(&[unknown_listener()], &render_options(60, true));
+
+        let row = table.lines().find(|line| line.contains("listen")).unwrap();
+        assert_eq!(
+            row.split('│')
+                .map(str::trim)
+                .filter(|cell| !cell.is_empty())
+                .collect::<Vec<&str>>(),
+            vec!["1", "tcp", "22", "0.0.0.0", "0", "- -", "listen", "-"]
+        );
+        assert!(!table.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_render_colors() {
+        let options = RenderOptions {
+            color: true,
+            ..render_options(60, false)
+        };
+        let table = render_connections_table(&[unknown_listener()], &options);
+        assert!(table.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_get_cell_color() {
+        let listener = unknown_listener();
+        assert_eq!(
+            get_cell_color(Column::State, &listener, Theme::Default),
+            Some(DarkGreen)
+        );
+        assert_eq!(
+            get_cell_color(Column::Proto, &listener, Theme::HighContrast),
+            Some(Cyan)
+        );
+        assert_eq!(get_cell_color(Column::State, &listener, Theme::Mono), None);
+        assert_eq!(get_cell_color(Column::Pid, &listener, Theme::Default), None);
+
+        let time_wait = Connection {
+            proto: "udp6".to_string(),
+            state: "time_wait".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_cell_color(Column::State, &time_wait, Theme::Default),
+            Some(DarkYellow)
+        );
+        assert_eq!(
+            get_cell_color(Column::Proto, &time_wait, Theme::Default),
+            Some(DarkMagenta)
+        );
+        let closed = Connection {
+            state: "close".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(get_cell_color(Column::State, &closed, Theme::Default), None);
+    }
+
+    #[test]
+    fn test_render_cell_colors() {
+        let options = RenderOptions {
+            color: true,
+            ..render_options(60, false)
+        };
+        let table = render_connections_table(&[unknown_listener()], &options);
+        assert!(table.contains(&format!("{}listen", SetForegroundColor(DarkGreen))));
+        assert!(!table.contains([COLOR_START, COLOR_END]));
+
+        let mono_options = RenderOptions {
+            table: TableOptions {
+                theme: Theme::Mono,
+                ..Default::default()
+            },
+            ..options
+        };
+        let mono_table = render_connections_table(&[unknown_listener()], &mono_options);
+        assert!(!mono_table.contains(&SetForegroundColor(DarkGreen).to_string()));
+
+        // the colors don't change the layout of the table
+        let strip_colors = |table: &str| {
+            [DarkGreen, DarkCyan]
+                .iter()
+                .fold(table.to_string(), |table, color| {
+                    table.replace(&SetForegroundColor(*color).to_string(), "")
+                })
+                .replace(&SetAttribute(Attribute::Reset).to_string(), "")
+        };
+        assert_eq!(strip_colors(&table), strip_colors(&mono_table));
+    }
+
+    #[test]
+    fn test_fit_columns() {
+        let columns = vec![
+            Column::Index,
+            Column::Proto,
+            Column::LocalPort,
+            Column::RemoteAddress,
+            Column::State,
+        ];
+        let connections = [unknown_listener()];
+        // index 1, proto 5, local port 5, remote address 7, state 6 and 6 borders
+        let fit = |width: u16, selection: Option<ColumnSelection>| {
+            let options = RenderOptions {
+                table: TableOptions {
+                    columns: selection,
+                    ..Default::default()
+                },
+                width,
+                ..Default::default()
+            };
+            fit_columns(columns.clone(), &connections, &options)
+        };
+
+        assert_eq!(fit(30, None), (columns.clone(), vec![]));
+        assert_eq!(fit(29, None).1, vec![Column::State]);
+        assert_eq!(fit(20, None).1, vec![Column::State, Column::Proto]);
+        assert_eq!(fit(UNLIMITED_WIDTH, None).1, vec![]);
+        // explicitly selected columns are never dropped
+        let selection = ColumnSelection::Exact(columns.clone());
+        assert_eq!(fit(20, Some(selection)).1, vec![]);
+    }
+
+    #[test]
+    fn test_render_unlimited_width() {
+        let wide = Connection {
+            program: "a-very-long-program-name-which-does-not-fit".to_string(),
+            ..unknown_listener()
+        };
+        let table = render_connections_table(
+            &[wide],
+            &RenderOptions {
+                width: UNLIMITED_WIDTH,
+                ..render_options(60, true)
+            },
+        );
+        // nothing is shortened or dropped and the table isn't padded to the width
+        assert!(table.contains("a-very-long-program-name-which-does-not-fit"));
+        assert!(table.contains("state"));
+        assert!(table
+            .lines()
+            .all(|line| line.starts_with(['┌', '│', '├', '└'])));
+        assert!(line_widths(&table).iter().all(|width| *width < 200));
+    }
+
+    #[test]
+    fn test_render_port_holders() {
+        let holder = |pid: &str, program: &str, local_address: &str| Connection {
+            proto: "tcp".to_string(),
+            local_address: local_address.to_string(),
+            local_port: "8080".to_string(),
+            pid: pid.to_string(),
+            program: program.to_string(),
+            user: Some("www".to_string()),
+            state: "listen".to_string(),
+            ..Default::default()
+        };
+        let connections = vec![
+            holder("42", "nginx", "0.0.0.0"),
+            holder("42", "nginx", "::"),
+            holder("43", "node", "127.0.0.1"),
+        ];
+
+        let text = render_port_holders(8080, &connections, &render_options(80, false), true);
+        assert!(text.contains("Port 8080 is held by 2 processes:"));
+        assert!(text
+            .contains("nginx (PID 42, user www): tcp 0.0.0.0:8080 listen, tcp [::]:8080 listen"));
+        assert!(text.contains("node (PID 43, user www): tcp 127.0.0.1:8080 listen"));
+        assert!(text.contains("somo --holding 8080 --kill"));
+
+        let text = render_port_holders(8080, &connections[2..], &render_options(80, false), false);
+        assert!(text.contains("held by 1 process:"));
+        assert!(!text.contains("--kill"));
+
+        let text = render_port_holders(8080, &[], &render_options(80, false), true);
+        assert_eq!(text.trim(), "Nothing holds port 8080.");
+    }
+
+    #[test]
+    fn test_find_highlights() {
+        let visible: Vec<char> = "│ nginx │ NGINX-worker │ 80 │".chars().collect();
+        let terms: Vec<Vec<char>> = ["nginx", "worker", "│ 8"]
+            .iter()
+            .map(|term| term.chars().collect())
+            .collect();
+        assert_eq!(
+            find_highlights(&visible, &terms),
+            vec![(2, 7, 0), (10, 15, 0), (16, 22, 1)]
+        );
+    }
+
+    #[test]
+    fn test_render_highlights() {
+        let options = RenderOptions {
+            table: TableOptions {
+                highlight: vec!["LISTEN".to_string(), "tcp".to_string(), String::new()],
+                ..Default::default()
+            },
+            color: true,
+            width: 60,
+            border: BorderStyle::Unicode,
+        };
+        let plain_options = RenderOptions {
+            table: TableOptions::default(),
+            ..options.clone()
+        };
+
+        let table = render_connections_table(&[unknown_listener()], &options);
+        let plain_table = render_connections_table(&[unknown_listener()], &plain_options);
+        let listen_highlight = format!(
+            "{}{}{}listen",
+            SetForegroundColor(HIGHLIGHT_COLORS[0]),
+            SetAttribute(Bold),
+            SetAttribute(Reverse)
+        );
+        assert!(table.contains(&listen_highlight));
+        assert!(table.contains(&format!("{}tcp", SetAttribute(Reverse))));
+        assert!(table.contains(&SetForegroundColor(HIGHLIGHT_COLORS[1]).to_string()));
+        // highlighting doesn't hide rows or change the text
+        assert_eq!(table.lines().count(), plain_table.lines().count());
+
+        let no_color = RenderOptions {
+            color: false,
+            ..options
+        };
+        assert_eq!(
+            render_connections_table(&[unknown_listener()], &no_color),
+            render_connections_table(
+                &[unknown_listener()],
+                &RenderOptions {
+                    color: false,
+                    ..plain_options
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_wide_columns() {
+        let wide = Connection {
+            remote_address: "2001:db8:85a3::8a2e:370:7334".to_string(),
+            program: "a-very-long-program-name-which-does-not-fit".to_string(),
+            ..unknown_listener()
+        };
+        let table =
+            render_connections_table(std::slice::from_ref(&wide), &render_options(60, true));
+
+        // long programs are shortened, addresses are wrapped onto multiple lines instead of widening the table
+        assert!(line_widths(&table).iter().all(|width| *width == 60));
+        assert!(table.contains('…'));
+        // the table is too narrow for the state and proto columns
+        assert!(!table.contains("state") && !table.contains("proto"));
+        let address: String = table
+            .lines()
+            .skip_while(|line| !line.starts_with('├'))
+            .skip(1)
+            .take_while(|line| !line.contains('\u{2800}'))
+            .map(|line| line.split('│').nth(3).unwrap().trim())
+            .collect();
+        assert_eq!(address, wide.remote_address);
+
+        let full_options = RenderOptions {
+            table: TableOptions {
+                full: true,
+                ..Default::default()
+            },
+            ..render_options(60, true)
+        };
+        let full_table = render_connections_table(&[wide], &full_options);
+        assert!(!full_table.contains('…'));
+        // the program is wrapped onto multiple lines instead of being shortened
+        assert!(full_table.contains("a-very-long-prog"));
+    }
+
+    #[test]
+    fn test_border_styles() {
+        let render = |border: BorderStyle, color: bool| {
+            render_connections_table(
+                &[unknown_listener()],
+                &RenderOptions {
+                    border,
+                    color,
+                    ..render_options(60, false)
+                },
+            )
+        };
+        let unicode = render(BorderStyle::Unicode, false);
+        assert!(unicode.starts_with('┌'));
+        assert!(unicode.contains(FILLING_CHARACTER));
+
+        let ascii = render(BorderStyle::Ascii, false);
+        assert!(ascii.is_ascii());
+        assert!(ascii.starts_with("+-"));
+        assert!(ascii
+            .lines()
+            .any(|line| line.starts_with('|') && line.contains("22")));
+        // every character is replaced by one character, so the columns stay aligned
+        assert_eq!(ascii.lines().count(), unicode.lines().count());
+
+        for color in [false, true] {
+            let none = render(BorderStyle::None, color);
+            assert!(!none.contains(['│', '─', '┼', '|', '+', FILLING_CHARACTER]));
+            // only the header, which is wrapped at this width, and the row are left
+            assert!(none.lines().all(|line| !is_blank_line(line)), "{}", none);
+            assert!(none.lines().last().unwrap().contains("22"));
+        }
+
+        let groups = [ConnectionGroup {
+            key: "nginx".to_string(),
+            count: 2,
+            ..Default::default()
+        }];
+        let options = RenderOptions {
+            border: BorderStyle::Ascii,
+            ..render_options(60, false)
+        };
+        assert!(render_grouped_table(&groups, GroupField::Program, &options).is_ascii());
+        assert!(render_top_remotes_table(&groups, &options).is_ascii());
+    }
+
+    #[test]
+    fn test_default_border_style() {
+        assert_eq!(
+            default_border_style(Some("en_US.UTF-8")),
+            BorderStyle::Unicode
+        );
+        assert_eq!(default_border_style(Some("C.utf8")), BorderStyle::Unicode);
+        assert_eq!(default_border_style(Some("C")), BorderStyle::Ascii);
+        assert_eq!(
+            default_border_style(Some("de_DE.ISO-8859-1")),
+            BorderStyle::Ascii
+        );
+        assert_eq!(default_border_style(None), BorderStyle::Unicode);
+    }
+
+    #[test]
+    fn test_render_grouped_table() {
+        let groups = [ConnectionGroup {
+            key: "nginx".to_string(),
+            count: 2,
+            states: BTreeMap::from([("listen".to_string(), 2)]),
+            ..Default::default()
+        }];
+        let table = render_grouped_table(&groups, GroupField::Program, &render_options(60, false));
+        assert!(table.contains("nginx"));
+        assert!(table.contains("listen 2"));
+        assert!(table.contains("total"));
+    }
+
+    #[test]
+    fn test_render_top_remotes_table() {
+        let groups = [ConnectionGroup {
+            key: "10.0.0.5".to_string(),
+            count: 3,
+            programs: BTreeSet::from(["nginx".to_string(), "postgres".to_string()]),
+            local_ports: BTreeSet::from(["443".to_string(), "5432".to_string()]),
+            ..Default::default()
+        }];
+        let table = render_top_remotes_table(&groups, &render_options(100, false));
+        let row = table
+            .lines()
+            .find(|line| line.contains("10.0.0.5"))
+            .unwrap();
+        assert!(row.contains("443, 5432"));
+        assert!(row.contains("nginx, postgres"));
+        assert!(row.contains(" 3 "));
     }
 }
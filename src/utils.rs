@@ -1,6 +1,18 @@
+use chrono::TimeZone;
+use std::ffi::OsString;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 use termimad::crossterm::style::{Attribute::*, Color::*};
 use termimad::*;
 
+use crate::schemas::{ColorMode, TimestampFormat};
+
+/// Whether colors and other text styles are used in the output, decided once at startup.
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
 /// Splits a string combined of an IP address and port with a ":" delimiter into two parts.
 ///
 /// # Arguments
@@ -8,11 +20,9 @@ use termimad::*;
 ///
 /// # Example
 /// ```
-/// let address_port_1 = "127.0.0.1:5432".to_string();
-/// assert_eq!(split_address(address_port_1), Some(("5432", "127.0.0.1")));
-///
-/// let address_port_2 = "fails.com".to_string();
-/// assert_eq!(split_address(address_port_2), None);
+/// # use somo::utils::split_address;
+/// assert_eq!(split_address("127.0.0.1:5432"), Some(("127.0.0.1", "5432")));
+/// assert_eq!(split_address("fails.com"), None);
 /// ```
 ///
 /// # Retunrs
@@ -35,19 +45,366 @@ pub fn split_address(address: &str) -> Option<(&str, &str)> {
 ///
 /// # Example
 /// ```
-/// let address_port_1 = "127.0.0.1:5432".to_string();
-/// assert_eq!(get_address_parts(address_port_1), ("5432", "127.0.0.1"));
-///
-/// let address_port_2 = "fails.com".to_string();
-/// assert_eq!(get_address_parts(address_port_1), ("-", "127.0.0.1"));
+/// # use somo::utils::get_address_parts;
+/// assert_eq!(get_address_parts("127.0.0.1:5432"), ("127.0.0.1".to_string(), "5432".to_string()));
+/// assert_eq!(get_address_parts("fails.com"), ("fails.com".to_string(), "-".to_string()));
 /// ```
 ///
 /// # Returns
 /// A tuple containing the address and port or just the address and a "-" if there wasn't a port.
 pub fn get_address_parts(address: &str) -> (String, String) {
-    return split_address(address)
+    split_address(address)
         .map(|(a, p)| (a.to_string(), p.to_string()))
-        .unwrap_or((address.to_string(), "-".to_string()));
+        .unwrap_or((address.to_string(), "-".to_string()))
+}
+
+/// Splits the zone (the interface) off a link-local IPv6 address, e.g "[fe80::1%eth0]" -> ("fe80::1", Some("eth0")).
+/// The brackets of an IPv6 address are removed.
+///
+/// # Arguments
+/// * `address`: The address to split, e.g "fe80::1%eth0" or "[::1]".
+///
+/// # Example
+/// ```
+/// # use somo::utils::split_zone;
+/// assert_eq!(split_zone("[fe80::1%eth0]"), ("fe80::1", Some("eth0")));
+/// assert_eq!(split_zone("10.0.0.1"), ("10.0.0.1", None));
+/// ```
+///
+/// # Returns
+/// The address without its zone and the zone if there was one.
+pub fn split_zone(address: &str) -> (&str, Option<&str>) {
+    let address = address.trim_start_matches('[').trim_end_matches(']');
+    match address.split_once('%') {
+        Some((ip, zone)) => (ip, Some(zone)),
+        None => (address, None),
+    }
+}
+
+/// Parses an address as it is stored in a `Connection`, ie. IPv6 addresses are wrapped in brackets.
+/// The zone of a link-local address is ignored.
+///
+/// # Arguments
+/// * `address`: The address to parse, e.g "127.0.0.1" or "[::1]".
+///
+/// # Example
+/// ```
+/// # use somo::utils::parse_ip;
+/// # use std::net::{IpAddr, Ipv6Addr};
+/// assert_eq!(parse_ip("[::1]"), Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+/// assert_eq!(parse_ip("[fe80::1%eth0]"), "fe80::1".parse().ok());
+/// assert_eq!(parse_ip("-"), None);
+/// ```
+///
+/// # Returns
+/// The parsed IP address or `None` if the address isn't valid.
+pub fn parse_ip(address: &str) -> Option<IpAddr> {
+    split_zone(address).0.parse::<IpAddr>().ok()
+}
+
+/// Checks if an IP address is a public address, ie. not a loopback, unspecified, private or link-local address.
+///
+/// # Arguments
+/// * `ip`: The IP address to check.
+///
+/// # Example
+/// ```
+/// # use somo::utils::is_public_ip;
+/// assert!(is_public_ip(&"8.8.8.8".parse().unwrap()));
+/// assert!(!is_public_ip(&"192.168.0.1".parse().unwrap()));
+/// ```
+///
+/// # Returns
+/// `true` if the address is public, `false` if not.
+pub fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_private() || ip.is_link_local())
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local())
+        }
+    }
+}
+
+/// Escapes all characters which have a meaning in Markdown, so that text like a command line is printed as is.
+///
+/// # Arguments
+/// * `text`: The text to escape.
+///
+/// # Example
+/// ```
+/// # use somo::utils::escape_markdown;
+/// assert_eq!(escape_markdown("ls *.rs | wc"), "ls \\*.rs \\| wc");
+/// ```
+///
+/// # Returns
+/// The escaped text.
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '~' | '|' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Shortens a text to a maximum number of characters, replacing the end with an ellipsis if it is too long.
+///
+/// # Arguments
+/// * `text`: The text to shorten.
+/// * `max_length`: The maximum number of characters including the ellipsis.
+///
+/// # Example
+/// ```
+/// # use somo::utils::truncate_with_ellipsis;
+/// assert_eq!(truncate_with_ellipsis("python3 -m http.server", 10), "python3 -…");
+/// ```
+///
+/// # Returns
+/// The text itself if it is short enough, otherwise the shortened text.
+pub fn truncate_with_ellipsis(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+    let shortened: String = text.chars().take(max_length.saturating_sub(1)).collect();
+    format!("{}…", shortened)
+}
+
+/// Shortens a text to a maximum number of characters, replacing its middle with an ellipsis if it is too long.
+/// Unlike `truncate_with_ellipsis` the start and the end are kept, e.g the directory and the name of a binary.
+///
+/// # Arguments
+/// * `text`: The text to shorten.
+/// * `max_length`: The maximum number of characters including the ellipsis.
+///
+/// # Example
+/// ```
+/// # use somo::utils::truncate_middle;
+/// assert_eq!(truncate_middle("/usr/libexec/gnome-session", 16), "/usr/lib…session");
+/// ```
+///
+/// # Returns
+/// The text itself if it is short enough, otherwise the shortened text.
+pub fn truncate_middle(text: &str, max_length: usize) -> String {
+    let length = text.chars().count();
+    if length <= max_length {
+        return text.to_string();
+    }
+    let kept = max_length.saturating_sub(1);
+    let start: String = text.chars().take(kept - kept / 2).collect();
+    let end: String = text.chars().skip(length - kept / 2).collect();
+    format!("{}…{}", start, end)
+}
+
+/// Formats a number of bytes human-readably using binary units.
+///
+/// # Arguments
+/// * `bytes`: The number of bytes.
+///
+/// # Example
+/// ```
+/// # use somo::utils::format_bytes;
+/// assert_eq!(format_bytes(512), "512 B");
+/// assert_eq!(format_bytes(1536), "1.5 KiB");
+/// assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+/// ```
+///
+/// # Returns
+/// The bytes with one decimal place and the largest fitting unit, plain bytes are shown without decimals.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats a duration as a short relative age using its two largest units.
+///
+/// # Arguments
+/// * `seconds`: The duration in seconds.
+///
+/// # Example
+/// ```
+/// # use somo::utils::format_duration;
+/// assert_eq!(format_duration(42), "42s");
+/// assert_eq!(format_duration(3 * 3600 + 12 * 60 + 5), "3h12m");
+/// assert_eq!(format_duration(2 * 86400 + 3600), "2d1h");
+/// ```
+///
+/// # Returns
+/// The formatted duration, e.g "5m3s", "3h12m" or "2d1h".
+pub fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Parses a duration given by the user, e.g with `--since`, a number without a unit is taken as seconds.
+///
+/// # Arguments
+/// * `duration`: The duration with a unit of seconds (`s`), minutes (`m`) or hours (`h`), e.g "30s" or "5m".
+///
+/// # Example
+/// ```
+/// # use somo::utils::parse_duration;
+/// assert_eq!(parse_duration("30s"), Ok(30));
+/// assert_eq!(parse_duration("5m"), Ok(300));
+/// assert_eq!(parse_duration("2h"), Ok(7200));
+/// assert!(parse_duration("5d").is_err());
+/// ```
+///
+/// # Returns
+/// The duration in seconds or an error message if it is invalid.
+pub fn parse_duration(duration: &str) -> Result<u64, String> {
+    let duration = duration.trim();
+    let (number, factor) = match duration.char_indices().last() {
+        Some((idx, 's')) => (&duration[..idx], 1),
+        Some((idx, 'm')) => (&duration[..idx], 60),
+        Some((idx, 'h')) => (&duration[..idx], 3600),
+        _ => (duration, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+        .filter(|seconds| *seconds > 0)
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a valid duration, use e.g 30s, 5m or 2h",
+                duration
+            )
+        })
+}
+
+/// Formats a creation time as selected with `--timestamps`.
+///
+/// # Arguments
+/// * `created`: The creation time as a Unix timestamp in seconds.
+/// * `now`: The current time as a Unix timestamp in seconds, used for the relative age.
+/// * `format`: How to show the time.
+///
+/// # Example
+/// ```
+/// # use somo::utils::format_timestamp;
+/// # use somo::schemas::TimestampFormat;
+/// assert_eq!(format_timestamp(1_700_000_000, 1_700_000_042, TimestampFormat::Relative), "42s");
+/// assert_eq!(format_timestamp(1_700_000_000, 1_700_000_042, TimestampFormat::Epoch), "1700000000");
+/// ```
+///
+/// # Returns
+/// The age (e.g "3h12m"), the local date and time (e.g "2024-05-01 13:45:10") or the Unix timestamp.
+pub fn format_timestamp(created: u64, now: u64, format: TimestampFormat) -> String {
+    match format {
+        TimestampFormat::Relative => format_duration(now.saturating_sub(created)),
+        TimestampFormat::Absolute => i64::try_from(created)
+            .ok()
+            .and_then(|created| chrono::Local.timestamp_opt(created, 0).single())
+            .map_or(created.to_string(), |time| {
+                time.format("%Y-%m-%d %H:%M:%S").to_string()
+            }),
+        TimestampFormat::Epoch => created.to_string(),
+    }
+}
+
+/// Runs a function on a worker thread and waits at most `timeout` for its result.
+/// If the function takes longer it keeps running in the background, its result is discarded.
+///
+/// # Arguments
+/// * `timeout`: The maximum time to wait for the result.
+/// * `function`: The function to run.
+///
+/// # Example
+/// ```
+/// # use somo::utils::run_with_timeout;
+/// # use std::time::Duration;
+/// assert_eq!(run_with_timeout(Duration::from_secs(1), || 42), Some(42));
+/// ```
+///
+/// # Returns
+/// The result of the function or `None` if it didn't finish in time (or panicked).
+pub fn run_with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    function: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // the receiver is gone if the timeout was reached, the result is then dropped
+        let _ = sender.send(function());
+    });
+    receiver.recv_timeout(timeout).ok()
+}
+
+/// Decides whether the output should be colored, following the `NO_COLOR` convention (https://no-color.org).
+///
+/// # Arguments
+/// * `color_mode`: The color mode provided by the user.
+/// * `is_terminal`: Whether stdout is a terminal.
+/// * `no_color`: The value of the `NO_COLOR` environment variable.
+///
+/// # Example
+/// ```
+/// # use somo::schemas::ColorMode;
+/// # use somo::utils::should_use_color;
+/// assert!(should_use_color(ColorMode::Auto, true, None));
+/// assert!(!should_use_color(ColorMode::Auto, false, None));
+/// assert!(should_use_color(ColorMode::Always, false, None));
+/// ```
+///
+/// # Returns
+/// `true` if colors should be used, `false` if not.
+pub fn should_use_color(
+    color_mode: ColorMode,
+    is_terminal: bool,
+    no_color: Option<OsString>,
+) -> bool {
+    match color_mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => is_terminal && no_color.is_none_or(|no_color| no_color.is_empty()),
+    }
+}
+
+/// Enables or disables colors and other text styles for all output.
+///
+/// # Arguments
+/// * `enabled`: Whether to use colors.
+///
+/// # Returns
+/// None
+pub fn set_color_enabled(enabled: bool) {
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks if colors and other text styles should be used in the output.
+///
+/// # Arguments
+/// None
+///
+/// # Returns
+/// `true` if colors are enabled, `false` if not.
+pub fn is_color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
 }
 
 /// Prints out Markdown formatted text using a custom appearence / termimad "skin".
@@ -57,19 +414,37 @@ pub fn get_address_parts(address: &str) -> (String, String) {
 /// * *italic* text -> not italic and gray
 /// * ~~strikeout~~ text -> not striked out and green
 ///
+/// The message is printed to stderr, so that only the connections are written to stdout, e.g for `somo --json | jq`.
+///
 /// # Arguments
 /// * `text`: The text to print to the console.
 ///
 /// # Returns
 /// None
 pub fn pretty_print_info(text: &str) {
-    let mut skin: MadSkin = MadSkin::default();
-    skin.bold.set_fg(White);
-    skin.italic = CompoundStyle::new(Some(gray(11)), None, Encircled.into());
-    skin.strikeout = CompoundStyle::new(Some(Cyan), None, Encircled.into());
+    eprint!("{}", format_info(text));
+}
+
+/// Formats an info message with the appearence of `pretty_print_info`.
+///
+/// # Arguments
+/// * `text`: The text of the message.
+///
+/// # Returns
+/// The formatted message, ready to be printed.
+fn format_info(text: &str) -> String {
+    let skin: MadSkin = if is_color_enabled() {
+        let mut skin: MadSkin = MadSkin::default();
+        skin.bold.set_fg(White);
+        skin.italic = CompoundStyle::new(Some(gray(11)), None, Encircled.into());
+        skin.strikeout = CompoundStyle::new(Some(Cyan), None, Encircled.into());
+        skin
+    } else {
+        MadSkin::no_style()
+    };
 
     let markdown: String = format!("~~Info~~: *{}*", text);
-    print!("{}", skin.term_text(&markdown));
+    skin.term_text(&markdown).to_string()
 }
 
 /// Prints out Markdown formatted text using a custom appearence / termimad "skin".
@@ -79,19 +454,26 @@ pub fn pretty_print_info(text: &str) {
 /// * *italic* text -> not italic and gray
 /// * ~~strikeout~~ text -> not striked out and red
 ///
+/// Like `pretty_print_info` the message is printed to stderr.
+///
 /// # Arguments
 /// * `text`: The text to print to the console.
 ///
 /// # Returns
 /// None
 pub fn pretty_print_error(text: &str) {
-    let mut skin = MadSkin::default();
-    skin.bold.set_fg(White);
-    skin.italic = CompoundStyle::new(Some(gray(11)), None, Encircled.into());
-    skin.strikeout = CompoundStyle::new(Some(Red), None, Encircled.into());
+    let skin: MadSkin = if is_color_enabled() {
+        let mut skin: MadSkin = MadSkin::default();
+        skin.bold.set_fg(White);
+        skin.italic = CompoundStyle::new(Some(gray(11)), None, Encircled.into());
+        skin.strikeout = CompoundStyle::new(Some(Red), None, Encircled.into());
+        skin
+    } else {
+        MadSkin::no_style()
+    };
 
     let markdown: String = format!("~~Error~~: *{}*", text);
-    print!("{}", skin.term_text(&markdown));
+    eprint!("{}", skin.term_text(&markdown));
 }
 
 #[cfg(test)]
@@ -123,6 +505,160 @@ mod tests {
         assert_eq!(port, "80");
     }
 
+    #[test]
+    fn test_parse_ip() {
+        use std::net::{Ipv4Addr, Ipv6Addr};
+
+        assert_eq!(
+            parse_ip("192.168.0.1"),
+            Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)))
+        );
+        assert_eq!(parse_ip("[::1]"), Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(parse_ip("::1"), Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(parse_ip("example.com"), None);
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        let public = ["8.8.8.8", "2001:4860:4860::8888"];
+        for ip in public {
+            assert!(is_public_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+
+        let non_public = [
+            "127.0.0.1",
+            "0.0.0.0",
+            "10.1.2.3",
+            "192.168.0.1",
+            "169.254.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+        ];
+        for ip in non_public {
+            assert!(!is_public_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_should_use_color() {
+        assert!(should_use_color(ColorMode::Auto, true, None));
+        assert!(should_use_color(
+            ColorMode::Auto,
+            true,
+            Some(OsString::new())
+        ));
+        assert!(!should_use_color(
+            ColorMode::Auto,
+            true,
+            Some(OsString::from("1"))
+        ));
+        assert!(!should_use_color(ColorMode::Auto, false, None));
+
+        assert!(should_use_color(
+            ColorMode::Always,
+            false,
+            Some(OsString::from("1"))
+        ));
+        assert!(!should_use_color(ColorMode::Never, true, None));
+    }
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("nginx"), "nginx");
+        assert_eq!(escape_markdown("ls *.rs | wc"), "ls \\*.rs \\| wc");
+        assert_eq!(escape_markdown("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn test_truncate_with_ellipsis() {
+        assert_eq!(truncate_with_ellipsis("nginx", 10), "nginx");
+        assert_eq!(truncate_with_ellipsis("0123456789", 10), "0123456789");
+        assert_eq!(
+            truncate_with_ellipsis("python3 -m http.server", 10),
+            "python3 -…"
+        );
+        assert_eq!(truncate_with_ellipsis("ünïcödé", 4), "ünï…");
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("nginx", 10), "nginx");
+        assert_eq!(truncate_middle("/usr/sbin/sshd", 9), "/usr…sshd");
+        assert_eq!(truncate_middle("/usr/bin/python3", 10), "/usr/…hon3");
+        assert_eq!(truncate_middle("ünïcödé", 4), "ün…é");
+    }
+
+    #[test]
+    fn test_run_with_timeout() {
+        assert_eq!(
+            run_with_timeout(Duration::from_millis(500), || "done"),
+            Some("done")
+        );
+        assert_eq!(
+            run_with_timeout(Duration::from_millis(20), || {
+                thread::sleep(Duration::from_secs(2));
+                "too late"
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(10 * 1024 * 1024 + 512 * 1024), "10.5 MiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let relative =
+            format_timestamp(1_000, 1_000 + 3 * 3600 + 12 * 60, TimestampFormat::Relative);
+        assert_eq!(relative, "3h12m");
+        assert_eq!(
+            format_timestamp(2_000, 1_000, TimestampFormat::Relative),
+            "0s"
+        );
+        assert_eq!(
+            format_timestamp(1_000, 5_000, TimestampFormat::Epoch),
+            "1000"
+        );
+
+        // the local time depends on the time zone, so only its shape is checked
+        let absolute = format_timestamp(1_700_000_000, 0, TimestampFormat::Absolute);
+        assert_eq!(absolute.len(), 19);
+        assert!(absolute.starts_with("2023-11-1"));
+        assert_eq!(&absolute[10..11], " ");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(60), "1m0s");
+        assert_eq!(format_duration(3599), "59m59s");
+        assert_eq!(format_duration(3600), "1h0m");
+        assert_eq!(format_duration(86399), "23h59m");
+        assert_eq!(format_duration(86400 * 40 + 7200), "40d2h");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45"), Ok(45));
+        assert_eq!(parse_duration(" 90s "), Ok(90));
+        assert_eq!(parse_duration("15m"), Ok(900));
+        assert_eq!(parse_duration("1h"), Ok(3600));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("1h30m").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
     #[test]
     fn test_get_address_parts_invalid() {
         let addr = "example.com";
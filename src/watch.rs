@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::io::stdout;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::time::{SystemTime, UNIX_EPOCH};
+use termimad::crossterm::cursor::{Hide, MoveTo, Show};
+use termimad::crossterm::execute;
+use termimad::crossterm::terminal::{Clear, ClearType};
+
+use crate::output::{self, NdjsonEnvelope, OutputFile};
+use crate::schemas::{Connection, ConnectionKey};
+use crate::table::{self, HighlightedRows, TableOptions};
+use crate::utils;
+
+/// How often the watch loop checks for a Ctrl-C while waiting for the next refresh.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The maximum number of closed connections shown after a refresh, so that a mass-disconnect doesn't flood the screen.
+const MAX_CLOSED_ROWS: usize = 10;
+
+/// Bounds a watch for scripted captures, without a limit it runs until Ctrl-C is pressed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WatchLimit {
+    /// The number of refreshes after which the watch stops.
+    pub iterations: Option<u64>,
+    /// The total time after which the watch stops.
+    pub duration: Option<Duration>,
+}
+
+impl WatchLimit {
+    /// Checks whether the watch is bounded by a number of refreshes or a duration.
+    ///
+    /// # Returns
+    /// `true` if at least one limit was set, `false` if not.
+    pub fn is_set(&self) -> bool {
+        self.iterations.is_some() || self.duration.is_some()
+    }
+}
+
+/// Why a watch stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEnd {
+    /// The number of refreshes or the duration of the `WatchLimit` was reached.
+    LimitReached,
+    /// Ctrl-C was pressed.
+    Interrupted,
+    /// The output was closed, e.g because the consumer of the pipe exited.
+    Stopped,
+    /// The Ctrl-C handler couldn't be registered.
+    Failed,
+}
+
+/// Compares the connections of a refresh with the previous refresh, to highlight the new ones and show the closed ones one last time.
+///
+/// # Arguments
+/// * `connections`: The connections of the current refresh.
+/// * `previous`: The connections of the previous refresh, `None` on the first refresh.
+///
+/// # Returns
+/// The current connections followed by at most `MAX_CLOSED_ROWS` closed connections, and which of the rows are new or closed.
+fn diff_connections(
+    connections: Vec<Connection>,
+    previous: Option<Vec<Connection>>,
+) -> (Vec<Connection>, HighlightedRows) {
+    let Some(previous) = previous else {
+        return (connections, HighlightedRows::default());
+    };
+
+    let previous_keys: HashSet<ConnectionKey> = previous.iter().map(Connection::key).collect();
+    let current_keys: HashSet<ConnectionKey> = connections.iter().map(Connection::key).collect();
+    let new: HashSet<usize> = connections
+        .iter()
+        .enumerate()
+        .filter(|(_, connection)| !previous_keys.contains(&connection.key()))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut rows = connections;
+    let first_closed = rows.len();
+    rows.extend(
+        previous
+            .into_iter()
+            .filter(|connection| !current_keys.contains(&connection.key()))
+            .take(MAX_CLOSED_ROWS),
+    );
+    let closed = (first_closed..rows.len()).collect();
+
+    (
+        rows,
+        HighlightedRows {
+            new,
+            closed,
+            ..Default::default()
+        },
+    )
+}
+
+/// Calls `refresh` every `interval` while `running` is set, until `refresh` returns `false` or the limit is reached.
+/// There is always at least one refresh, the duration is counted from its start.
+///
+/// # Arguments
+/// * `interval`: The time to wait between two refreshes.
+/// * `limit`: After how many refreshes or how much time to stop.
+/// * `running`: Cleared once Ctrl-C is pressed.
+/// * `refresh`: Refreshes the output, returns whether to keep going.
+///
+/// # Returns
+/// Why the refreshes stopped.
+fn repeat_while_running(
+    interval: Duration,
+    limit: WatchLimit,
+    running: &AtomicBool,
+    mut refresh: impl FnMut() -> bool,
+) -> WatchEnd {
+    let deadline = limit.duration.map(|duration| Instant::now() + duration);
+    let mut iterations: u64 = 0;
+
+    while running.load(Ordering::SeqCst) {
+        if !refresh() {
+            return WatchEnd::Stopped;
+        }
+        iterations += 1;
+        if limit.iterations.is_some_and(|max| iterations >= max) {
+            return WatchEnd::LimitReached;
+        }
+
+        let mut next_refresh = Instant::now() + interval;
+        if let Some(deadline) = deadline {
+            next_refresh = next_refresh.min(deadline);
+        }
+        while running.load(Ordering::SeqCst) && Instant::now() < next_refresh {
+            thread::sleep(POLL_INTERVAL.min(next_refresh - Instant::now()));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return WatchEnd::LimitReached;
+        }
+    }
+    WatchEnd::Interrupted
+}
+
+/// Calls `refresh` every `interval` until Ctrl-C is pressed, `refresh` returns `false` or the limit is reached.
+///
+/// # Arguments
+/// * `interval`: The time to wait between two refreshes.
+/// * `limit`: After how many refreshes or how much time to stop.
+/// * `refresh`: Refreshes the output, returns whether to keep going.
+///
+/// # Returns
+/// Why the refreshes stopped, `WatchEnd::Failed` if the Ctrl-C handler couldn't be registered.
+fn repeat_until_interrupted(
+    interval: Duration,
+    limit: WatchLimit,
+    refresh: impl FnMut() -> bool,
+) -> WatchEnd {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    if ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst)).is_err() {
+        utils::pretty_print_error("Couldn't register the Ctrl-C handler.");
+        return WatchEnd::Failed;
+    }
+
+    repeat_while_running(interval, limit, &running, refresh)
+}
+
+/// Clears the screen and re-renders the connections table every `interval` until Ctrl-C is pressed or the limit is reached.
+/// Connections which appeared since the previous refresh are highlighted, connections which are gone are dimmed for one refresh.
+/// With `--output` the file is truncated instead of clearing the screen, or every refresh is appended with `--append`.
+///
+/// # Arguments
+/// * `interval`: The time to wait between two refreshes.
+/// * `limit`: After how many refreshes or how much time to stop.
+/// * `options`: Which optional columns to show in the table.
+/// * `output_file`: The file set with `--output`, `None` to render into the terminal.
+/// * `collect_connections`: Collects the (filtered and sorted) connections for every refresh.
+///
+/// # Returns
+/// Why the watch stopped.
+pub fn watch_connections(
+    interval: Duration,
+    limit: WatchLimit,
+    options: &TableOptions,
+    output_file: Option<&OutputFile>,
+    collect_connections: impl Fn() -> Vec<Connection>,
+) -> WatchEnd {
+    if output_file.is_none() {
+        let _ = execute!(stdout(), Hide);
+    }
+
+    let mut previous: Option<Vec<Connection>> = None;
+    let end = repeat_until_interrupted(interval, limit, || {
+        let (mut rows, highlighted_rows) = diff_connections(collect_connections(), previous.take());
+
+        match output_file {
+            Some(output_file) => {
+                if let Err(error) = output_file.start_refresh() {
+                    utils::pretty_print_error(&format!(
+                        "Couldn't truncate the output file: {}.",
+                        error
+                    ));
+                    return false;
+                }
+            }
+            None => {
+                let _ = execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0));
+            }
+        }
+        table::print_highlighted_connections_table(&rows, &highlighted_rows, options);
+
+        // closed connections are only shown once
+        rows.truncate(rows.len() - highlighted_rows.closed.len());
+        previous = Some(rows);
+        true
+    });
+
+    if output_file.is_none() {
+        let _ = execute!(stdout(), Show);
+        if end != WatchEnd::Failed {
+            println!();
+        }
+    }
+    end
+}
+
+/// Writes the connections as newline-delimited JSON every `interval` until Ctrl-C is pressed, stdout is closed or the limit is reached.
+/// The stream starts with the schema version, every refresh starts with an envelope carrying its timestamp and the number of connections which follow.
+///
+/// # Arguments
+/// * `interval`: The time to wait between two refreshes.
+/// * `limit`: After how many refreshes or how much time to stop.
+/// * `collect_connections`: Collects the (filtered and sorted) connections for every refresh.
+///
+/// # Returns
+/// Why the stream stopped.
+pub fn stream_connections(
+    interval: Duration,
+    limit: WatchLimit,
+    collect_connections: impl Fn() -> Vec<Connection>,
+) -> WatchEnd {
+    if output::write_ndjson_schema(&mut stdout().lock()).is_err() {
+        return WatchEnd::Stopped;
+    }
+    repeat_until_interrupted(interval, limit, || {
+        let connections = collect_connections();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let envelope = NdjsonEnvelope::new(timestamp, connections.len());
+        // stop once the consumer of the pipe is gone
+        output::write_ndjson(&mut stdout().lock(), Some(&envelope), &connections).is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::AddressType;
+
+    fn connection_with(local_port: &str) -> Connection {
+        Connection {
+            proto: "tcp".to_string(),
+            local_address: "0.0.0.0".to_string(),
+            local_port: local_port.to_string(),
+            remote_address: "0.0.0.0".to_string(),
+            remote_port: "0".to_string(),
+            program: "nginx".to_string(),
+            pid: "123".to_string(),
+            state: "listen".to_string(),
+            address_type: AddressType::Unspecified,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_connections() {
+        let previous = vec![connection_with("80"), connection_with("443")];
+        let current = vec![
+            connection_with("80"),
+            connection_with("8080"),
+            connection_with("22"),
+        ];
+
+        let (rows, highlighted_rows) = diff_connections(current, Some(previous));
+        assert_eq!(highlighted_rows.new, HashSet::from([1, 2]));
+        assert_eq!(highlighted_rows.closed, HashSet::from([3]));
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].local_port, "443");
+    }
+
+    #[test]
+    fn test_diff_connections_first_refresh() {
+        let current = vec![connection_with("80")];
+        let (rows, highlighted_rows) = diff_connections(current, None);
+        assert_eq!(rows.len(), 1);
+        assert!(highlighted_rows.new.is_empty());
+        assert!(highlighted_rows.closed.is_empty());
+    }
+
+    #[test]
+    fn test_diff_connections_caps_closed_rows() {
+        let previous: Vec<Connection> = (1000..1100)
+            .map(|port| connection_with(&port.to_string()))
+            .collect();
+
+        let (rows, highlighted_rows) = diff_connections(Vec::new(), Some(previous));
+        assert_eq!(rows.len(), MAX_CLOSED_ROWS);
+        assert_eq!(highlighted_rows.closed.len(), MAX_CLOSED_ROWS);
+    }
+
+    #[test]
+    fn test_repeat_iterations() {
+        let limit = WatchLimit {
+            iterations: Some(3),
+            ..Default::default()
+        };
+        let running = AtomicBool::new(true);
+        let mut refreshes = 0;
+        let end = repeat_while_running(Duration::ZERO, limit, &running, || {
+            refreshes += 1;
+            true
+        });
+        assert_eq!(end, WatchEnd::LimitReached);
+        assert_eq!(refreshes, 3);
+    }
+
+    #[test]
+    fn test_repeat_duration() {
+        let limit = WatchLimit {
+            duration: Some(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        let running = AtomicBool::new(true);
+        let mut refreshes = 0;
+        let started = Instant::now();
+        let end = repeat_while_running(Duration::from_secs(60), limit, &running, || {
+            refreshes += 1;
+            true
+        });
+        assert_eq!(end, WatchEnd::LimitReached);
+        // the deadline ends the wait for the second refresh early, the bound leaves room for slow machines
+        assert_eq!(refreshes, 1);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(250));
+        assert!(elapsed < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_repeat_interrupted_and_stopped() {
+        let running = AtomicBool::new(true);
+        let end = repeat_while_running(Duration::ZERO, WatchLimit::default(), &running, || {
+            // pressing Ctrl-C during the first refresh
+            running.store(false, Ordering::SeqCst);
+            true
+        });
+        assert_eq!(end, WatchEnd::Interrupted);
+
+        let running = AtomicBool::new(true);
+        let end = repeat_while_running(Duration::ZERO, WatchLimit::default(), &running, || false);
+        assert_eq!(end, WatchEnd::Stopped);
+    }
+}
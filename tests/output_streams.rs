@@ -0,0 +1,32 @@
+use std::process::{Command, Output};
+
+/// Runs the somo binary with the given flags and returns both of its streams.
+fn run_somo(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_somo"))
+        .args(args)
+        .output()
+        .expect("somo couldn't be started")
+}
+
+#[test]
+fn test_messages_go_to_stderr() {
+    // the contradicting filters print a warning, which must not break the JSON
+    let output = run_somo(&["--json", "--listen", "--state", "established"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["connections"], serde_json::json!([]));
+    assert!(stderr.contains("no connection can match"));
+}
+
+#[test]
+fn test_summary_goes_to_stderr() {
+    let output = run_somo(&["--listen", "--state", "established"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(stdout.contains("proto"));
+    assert!(!stdout.contains("Info"));
+    assert!(stderr.contains("0 Connections"));
+}